@@ -0,0 +1,20 @@
+#version 450
+layout (location = 0) in vec2 position;
+layout (location = 1) in vec2 uv;
+layout (location = 2) in vec4 color;
+
+layout(push_constant) uniform PushConstant {
+    vec2 size;
+} push_constant;
+
+layout (location = 0) out vec2 outuv;
+layout (location = 1) out vec4 outcolor;
+
+out gl_PerVertex {
+    vec4 gl_Position;
+};
+void main() {
+    gl_Position = vec4(position / push_constant.size * 2.0 - 1.0, 0.0, 1.0);
+    outuv = uv;
+    outcolor = color;
+}
@@ -0,0 +1,33 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+layout(location = 0) in vec4 v_pos01;
+layout(location = 1) in vec4 v_pos23;
+layout(location = 2) in vec4 v_uv01;
+layout(location = 3) in vec4 v_uv23;
+layout(location = 4) in vec2 v_dxdy;
+layout(location = 5) in float rotation;
+layout(location = 6) in float scale;
+layout(location = 7) in vec4 opacity;
+
+layout(location = 0) out vec2 f_uv;
+layout(location = 1) out float f_opacity;
+
+layout(push_constant) uniform PushConstant {
+    mat4 view;
+} push_constant;
+
+out gl_PerVertex {
+    vec4 gl_Position;
+};
+
+void main() {
+    vec2 positions[4] = vec2[4](v_pos01.xy, v_pos01.zw, v_pos23.xy, v_pos23.zw);
+    vec2 uvs[4] = vec2[4](v_uv01.xy, v_uv01.zw, v_uv23.xy, v_uv23.zw);
+    int corner = gl_VertexIndex;
+    mat2 rotmatrix = mat2(cos(rotation), -sin(rotation), sin(rotation), cos(rotation));
+    vec2 pos = rotmatrix * scale * positions[corner];
+    f_uv = uvs[corner];
+    f_opacity = opacity[corner];
+    gl_Position = push_constant.view * vec4(pos + v_dxdy, 0.0, 1.0);
+}
@@ -0,0 +1,167 @@
+#version 450
+
+layout (location = 0) in vec2 texpos;
+layout (location = 0) out vec4 Color;
+
+layout(push_constant) uniform PushConstant {
+    vec4 seed_frequency;
+    vec4 region;
+    // lacunarity, gain, threshold
+    vec4 params;
+    // octaves, noise type, mapping
+    ivec4 kinds;
+    vec4 low;
+    vec4 high;
+} push;
+
+// Hash function: http://amindforeverprogramming.blogspot.com/2013/07/random-floats-in-glsl-330.html
+uint hash( uint x ) {
+    x += ( x << 10u );
+    x ^= ( x >>  6u );
+    x += ( x <<  3u );
+    x ^= ( x >> 11u );
+    x += ( x << 15u );
+    return x;
+}
+uint hash(uvec3 v) {
+    return hash( v.x ^ hash(v.y) ^ hash(v.z) );
+}
+// Random value in [0, 1)
+float random(uvec3 pos) {
+    const uint mantissaMask = 0x007FFFFFu;
+    const uint one          = 0x3F800000u;
+
+    uint h = hash( pos );
+    h &= mantissaMask;
+    h |= one;
+
+    return uintBitsToFloat( h ) - 1.0;
+}
+float random(vec3 cell) {
+    return random(uvec3(ivec3(cell)));
+}
+vec3 gradient(vec3 cell) {
+    uvec3 c = uvec3(ivec3(cell));
+    vec3 g = vec3(random(c), random(c ^ uvec3(0x9e3779b9u)), random(c ^ uvec3(0x85ebca6bu)));
+    return normalize(g * 2.0 - 1.0 + 1e-6);
+}
+vec3 fade(vec3 t) {
+    return t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+}
+
+float value_noise(vec3 pos) {
+    vec3 i = floor(pos);
+    vec3 u = fade(pos - i);
+    return mix(
+        mix(mix(random(i + vec3(0, 0, 0)), random(i + vec3(1, 0, 0)), u.x),
+            mix(random(i + vec3(0, 1, 0)), random(i + vec3(1, 1, 0)), u.x), u.y),
+        mix(mix(random(i + vec3(0, 0, 1)), random(i + vec3(1, 0, 1)), u.x),
+            mix(random(i + vec3(0, 1, 1)), random(i + vec3(1, 1, 1)), u.x), u.y),
+        u.z);
+}
+
+float perlin_corner(vec3 i, vec3 f, vec3 corner) {
+    return dot(gradient(i + corner), f - corner);
+}
+float perlin_noise(vec3 pos) {
+    vec3 i = floor(pos);
+    vec3 f = pos - i;
+    vec3 u = fade(f);
+    float n = mix(
+        mix(mix(perlin_corner(i, f, vec3(0, 0, 0)), perlin_corner(i, f, vec3(1, 0, 0)), u.x),
+            mix(perlin_corner(i, f, vec3(0, 1, 0)), perlin_corner(i, f, vec3(1, 1, 0)), u.x), u.y),
+        mix(mix(perlin_corner(i, f, vec3(0, 0, 1)), perlin_corner(i, f, vec3(1, 0, 1)), u.x),
+            mix(perlin_corner(i, f, vec3(0, 1, 1)), perlin_corner(i, f, vec3(1, 1, 1)), u.x), u.y),
+        u.z);
+    return 0.5 + 0.5 * n;
+}
+
+// 2D simplex noise, the z coordinate selects an independent lattice
+float simplex_corner(vec2 x, vec2 cell, float layer) {
+    float t = 0.5 - dot(x, x);
+    if (t < 0.0) {
+        return 0.0;
+    }
+    float angle = random(vec3(cell, layer)) * 6.28318530718;
+    t *= t;
+    return t * t * dot(vec2(cos(angle), sin(angle)), x);
+}
+float simplex_noise(vec3 pos) {
+    const float F2 = 0.366025403784;
+    const float G2 = 0.211324865405;
+    float layer = floor(pos.z);
+    vec2 i = floor(pos.xy + dot(pos.xy, vec2(F2)));
+    vec2 x0 = pos.xy - i + dot(i, vec2(G2));
+    vec2 i1 = x0.x > x0.y ? vec2(1, 0) : vec2(0, 1);
+    vec2 x1 = x0 - i1 + G2;
+    vec2 x2 = x0 - 1.0 + 2.0 * G2;
+    float n = simplex_corner(x0, i, layer)
+        + simplex_corner(x1, i + i1, layer)
+        + simplex_corner(x2, i + 1.0, layer);
+    return 0.5 + 35.0 * n;
+}
+
+// Distance to the closest feature point, one point per cell, the z coordinate selects the layout
+float worley_noise(vec3 pos) {
+    float layer = floor(pos.z);
+    vec2 i = floor(pos.xy);
+    vec2 f = pos.xy - i;
+    float closest = 1.0;
+    for (int y = -1; y <= 1; y ++) {
+        for (int x = -1; x <= 1; x ++) {
+            vec2 cell = vec2(x, y);
+            vec2 point = vec2(random(vec3(i + cell, layer)), random(vec3(i + cell, layer + 65536.0)));
+            closest = min(closest, length(cell + point - f));
+        }
+    }
+    return closest;
+}
+
+float noise(vec3 pos) {
+    if (push.kinds.y == 1) {
+        return simplex_noise(pos);
+    } else if (push.kinds.y == 2) {
+        return worley_noise(pos);
+    } else if (push.kinds.y == 3) {
+        return value_noise(pos);
+    }
+    return perlin_noise(pos);
+}
+
+// Fractal sum of octaves, normalized to the range of a single octave
+float fbm(vec2 pixel) {
+    float frequency = push.seed_frequency.w;
+    float amplitude = 1.0;
+    float total = 0.0;
+    float sum = 0.0;
+    for (int i = 0; i < push.kinds.x && i < 16; i ++) {
+        vec3 pos = vec3(pixel * frequency, 0) + push.seed_frequency.xyz + float(i) * 17.0;
+        total += amplitude * noise(pos);
+        sum += amplitude;
+        amplitude *= push.params.y;
+        frequency *= push.params.x;
+    }
+    return total / max(sum, 1e-6);
+}
+
+float to_linear(float value) {
+    return value <= 0.04045 ? value / 12.92 : pow((value + 0.055) / 1.055, 2.4);
+}
+
+void main()
+{
+    vec2 pixel = push.region.xy + texpos * push.region.zw;
+    float r = clamp(fbm(pixel), 0.0, 1.0);
+    switch (push.kinds.z) {
+        case 1:
+            Color = vec4(vec3(step(push.params.z, r)), 1);
+            break;
+        case 2:
+            Color = mix(push.low, push.high, r);
+            break;
+        default:
+            // The texture is sRGB, store the value itself as the gray level
+            Color = vec4(vec3(to_linear(r)), 1);
+            break;
+    }
+}
@@ -0,0 +1,17 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+layout(location = 0) in vec2 uv;
+
+layout(location = 0) out vec4 color;
+
+layout(set = 0, binding = 0) uniform texture2D frame;
+layout(set = 0, binding = 1) uniform sampler frame_sampler;
+
+layout(push_constant) uniform PushConstant {
+    vec4 tint;
+} push;
+
+void main() {
+    color = texture(sampler2D(frame, frame_sampler), uv) * push.tint;
+}
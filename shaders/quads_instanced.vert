@@ -0,0 +1,29 @@
+#version 450
+layout (location = 0) in vec4 pos01;
+layout (location = 1) in vec4 pos23;
+layout (location = 2) in vec4 color0;
+layout (location = 3) in vec4 color1;
+layout (location = 4) in vec4 color2;
+layout (location = 5) in vec4 color3;
+layout (location = 6) in vec2 dxdy;
+layout (location = 7) in float rotation;
+layout (location = 8) in float scale;
+
+layout(push_constant) uniform PushConstant {
+    mat4 view;
+} push_constant;
+
+layout (location = 0) out vec4 outcolor;
+
+out gl_PerVertex {
+    vec4 gl_Position;
+};
+void main() {
+    vec2 positions[4] = vec2[4](pos01.xy, pos01.zw, pos23.xy, pos23.zw);
+    vec4 colors[4] = vec4[4](color0, color1, color2, color3);
+    int corner = gl_VertexIndex;
+    mat2 rotmatrix = mat2(cos(rotation), -sin(rotation), sin(rotation), cos(rotation));
+    vec2 pos = rotmatrix * scale * positions[corner];
+    gl_Position = push_constant.view * vec4(pos + dxdy, 0.0, 1.0);
+    outcolor = colors[corner];
+}
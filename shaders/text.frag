@@ -3,6 +3,7 @@
 
 layout(location = 0) in vec2 f_uv;
 layout(location = 1) in float f_opacity;
+layout(location = 2) in vec4 f_color;
 
 layout(location = 0) out vec4 color;
 
@@ -10,6 +11,6 @@ layout(set = 0, binding = 0) uniform texture2D f_texture;
 layout(set = 0, binding = 1) uniform sampler f_sampler;
 
 void main() {
-    color = texture(sampler2D(f_texture, f_sampler), f_uv);
+    color = texture(sampler2D(f_texture, f_sampler), f_uv) * f_color;
     color.a *= f_opacity;
 }
@@ -7,9 +7,11 @@ layout(location = 2) in vec2 v_dxdy;
 layout(location = 3) in float rotation;
 layout(location = 4) in float scale;
 layout(location = 5) in float opacity;
+layout(location = 6) in vec4 color;
 
 layout(location = 0) out vec2 f_uv;
 layout(location = 1) out float f_opacity;
+layout(location = 2) out vec4 f_color;
 
 layout(push_constant) uniform PushConstant {
     mat4 view;
@@ -24,5 +26,6 @@ void main() {
     vec2 pos = rotmatrix * scale * v_pos;
     f_uv = v_uv;
     f_opacity = opacity;
+    f_color = color;
     gl_Position = push_constant.view * vec4(pos + v_dxdy, 0.0, 1.0);
 }
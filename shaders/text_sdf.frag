@@ -0,0 +1,53 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+layout(location = 0) in vec2 f_uv;
+layout(location = 1) in float f_opacity;
+layout(location = 2) in vec4 f_color;
+
+layout(location = 0) out vec4 color;
+
+layout(set = 0, binding = 0) uniform texture2D f_texture;
+layout(set = 0, binding = 1) uniform sampler f_sampler;
+
+layout(push_constant) uniform PushConstant {
+    layout(offset = 64) vec4 outline_color;
+    vec4 glow_color;
+    vec4 shadow_color;
+    // Outline width and glow radius as a fraction of the spread, shadow offset in texels
+    vec4 sizes;
+} effects;
+
+// Place a color with straight alpha over another
+vec4 over(vec4 top, vec4 bottom) {
+    float alpha = top.a + bottom.a * (1.0 - top.a);
+    vec3 rgb = top.rgb * top.a + bottom.rgb * bottom.a * (1.0 - top.a);
+    return vec4(rgb / max(alpha, 0.00001), alpha);
+}
+
+// Coverage of the area whose distance value lies above the edge
+float inside(float dist, float edge, float smoothing) {
+    return smoothstep(edge - smoothing, edge + smoothing, dist);
+}
+
+void main() {
+    float dist = texture(sampler2D(f_texture, f_sampler), f_uv).a;
+    float smoothing = max(fwidth(dist) * 0.5, 0.00001);
+
+    vec2 shadow_uv = f_uv - effects.sizes.zw / vec2(textureSize(sampler2D(f_texture, f_sampler), 0));
+    float shadow_dist = texture(sampler2D(f_texture, f_sampler), shadow_uv).a;
+
+    float outline_edge = 0.5 - effects.sizes.x * 0.5;
+    float glow_edge = 0.5 - effects.sizes.y * 0.5;
+    float glow = clamp((dist - glow_edge) / max(0.5 - glow_edge, 0.00001), 0.0, 1.0);
+
+    vec4 fill = vec4(f_color.rgb, f_color.a * inside(dist, 0.5, smoothing));
+    vec4 outline = vec4(effects.outline_color.rgb,
+                        effects.outline_color.a * inside(dist, outline_edge, smoothing));
+    vec4 halo = vec4(effects.glow_color.rgb, effects.glow_color.a * glow);
+    vec4 shadow = vec4(effects.shadow_color.rgb,
+                       effects.shadow_color.a * inside(shadow_dist, 0.5, smoothing));
+
+    color = over(fill, over(outline, over(halo, shadow)));
+    color.a *= f_opacity;
+}
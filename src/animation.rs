@@ -0,0 +1,551 @@
+//! Methods and types to animate dyntex sprites using sprite sheets
+//!
+//! A clip is a sequence of cells in a grid-based sprite sheet, each cell shown for a given
+//! duration. Clips are played on [crate::dyntex::Handle]s, and a single call to
+//! [Animations::tick] advances every playing animation and writes the changed UV coordinates.
+//!
+//! # Example - Animating a fireball #
+//! ```
+//! use vxdraw::{animation::{ClipOptions, Mode}, dyntex::{ImgData, LayerOptions, Sprite}, void_logger, ShowWindow, VxDraw};
+//! use winit::platform::unix::EventLoopExtUnix;
+//! use winit::event_loop::EventLoop;
+//!
+//! // Create an event loop
+//! let event_loop = EventLoop::new_any_thread();
+//!
+//! static FIREBALL: &ImgData = &ImgData::PNGBytes(include_bytes!["../images/Fireball_68x9.png"]);
+//! #[cfg(feature = "doctest-headless")]
+//! let mut vx = VxDraw::new(void_logger(), ShowWindow::Headless1k, &event_loop);
+//! #[cfg(not(feature = "doctest-headless"))]
+//! let mut vx = VxDraw::new(void_logger(), ShowWindow::Enable, &event_loop);
+//!
+//! let layer = vx.dyntex().add_layer(FIREBALL, &LayerOptions::new());
+//! let fireball = vx.dyntex().add(&layer, Sprite::new().width(0.68).height(0.09));
+//!
+//! // The sheet has 10 columns and 6 rows, show each cell for 1/60th of a second
+//! let clip = vx.animation().add_clip(&ClipOptions::new(10, 6).duration(1.0 / 60.0).mode(Mode::Loop));
+//! vx.animation().play(&fireball, &clip);
+//!
+//! #[cfg(not(feature = "doctest-headless"))]
+//! for _ in 0..180 {
+//!     vx.animation().tick(1.0 / 60.0);
+//!     vx.draw_frame();
+//! }
+//! #[cfg(feature = "doctest-headless")]
+//! vx.draw_frame();
+//! ```
+use crate::data::{AnimationClip, SpriteAnimation, VxDraw};
+use crate::dyntex::{self, Region};
+
+// ---
+
+/// Handle to an animation clip
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Clip(usize);
+
+/// How a clip continues after its last frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Start over from the first frame
+    Loop,
+    /// Play the frames backwards, then forwards again, and so on
+    PingPong,
+    /// Stop at the last frame
+    Once,
+}
+
+/// Options for creating a clip from a grid-based sprite sheet
+#[derive(Clone)]
+pub struct ClipOptions {
+    columns: usize,
+    rows: usize,
+    region: Option<Region>,
+    frames: Option<Vec<usize>>,
+    durations: Vec<f32>,
+    mode: Mode,
+}
+
+impl ClipOptions {
+    /// Create options for a sheet divided into `columns` by `rows` equally sized cells
+    ///
+    /// By default all cells are played, row by row, each for 0.1 seconds, in a loop.
+    pub fn new(columns: usize, rows: usize) -> Self {
+        assert![
+            columns > 0 && rows > 0,
+            "A sprite sheet must have at least one cell"
+        ];
+        Self {
+            columns,
+            rows,
+            region: None,
+            frames: None,
+            durations: vec![0.1],
+            mode: Mode::Loop,
+        }
+    }
+
+    /// Only use a region of the texture as the sheet, useful when the sheet is part of an atlas
+    pub fn region(mut self, region: Region) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    /// Set which cells to show, cells are counted row by row starting at the topleft
+    pub fn frames(mut self, frames: &[usize]) -> Self {
+        self.frames = Some(frames.to_vec());
+        self
+    }
+
+    /// Show every frame for the same duration in seconds
+    pub fn duration(mut self, duration: f32) -> Self {
+        self.durations = vec![duration];
+        self
+    }
+
+    /// Set the duration of each frame in seconds
+    ///
+    /// There must be as many durations as frames.
+    pub fn durations(mut self, durations: &[f32]) -> Self {
+        self.durations = durations.to_vec();
+        self
+    }
+
+    /// Set what happens after the last frame
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+// ---
+
+/// Accessor object to all sprite animations
+///
+/// See [crate::animation] for examples.
+pub struct Animations<'a> {
+    vx: &'a mut VxDraw,
+}
+
+impl<'a> Animations<'a> {
+    /// Prepare to edit animations
+    ///
+    /// You're not supposed to use this function directly (although you can).
+    /// The recommended way of spawning an animations accessor is via [VxDraw::animation()].
+    pub(crate) fn new(s: &'a mut VxDraw) -> Self {
+        Self { vx: s }
+    }
+
+    /// Create a clip that can be played on any sprite
+    pub fn add_clip(&mut self, options: &ClipOptions) -> Clip {
+        let cells = options.columns * options.rows;
+        let frames = options
+            .frames
+            .clone()
+            .unwrap_or_else(|| (0..cells).collect());
+        assert![!frames.is_empty(), "A clip must have at least one frame"];
+        assert![
+            frames.iter().all(|frame| *frame < cells),
+            "Frame is outside of the sprite sheet"
+        ];
+        let durations = if options.durations.len() == 1 {
+            vec![options.durations[0]; frames.len()]
+        } else {
+            options.durations.clone()
+        };
+        assert_eq![
+            frames.len(),
+            durations.len(),
+            "There must be one duration per frame"
+        ];
+        assert![
+            durations.iter().all(|duration| *duration > 0.0),
+            "Frame durations must be positive"
+        ];
+        let (uv_begin, uv_end) = match options.region {
+            Some(region) => (region.uv_begin, region.uv_end),
+            None => ((0.0, 0.0), (1.0, 1.0)),
+        };
+        self.vx.clips.push(AnimationClip {
+            columns: options.columns,
+            rows: options.rows,
+            uv_begin,
+            uv_end,
+            frames,
+            durations,
+            mode: options.mode,
+        });
+        Clip(self.vx.clips.len() - 1)
+    }
+
+    /// Play a clip on a sprite from its first frame, replacing any clip that is playing
+    ///
+    /// The sprite's UV coordinates are set to the first frame immediately.
+    pub fn play(&mut self, sprite: &dyntex::Handle, clip: &Clip) {
+        if !dyntex::check_sprite(self.vx, sprite) {
+            return;
+        }
+        let key = key(sprite);
+        self.vx.animations.insert(
+            key,
+            SpriteAnimation {
+                clip: clip.0,
+                frame: 0,
+                elapsed: 0.0,
+                forward: true,
+                finished: false,
+            },
+        );
+        let uv = uv_quad(cell_uv(&self.vx.clips[clip.0], 0));
+        self.vx.dyntexs[key.0].uvbuffer[key.1].copy_from_slice(&uv);
+        self.vx.dyntexs[key.0].uvbuf_touch = self.vx.swapconfig.image_count;
+    }
+
+    /// Stop animating a sprite, the sprite keeps showing its current frame
+    pub fn stop(&mut self, sprite: &dyntex::Handle) {
+        if !dyntex::check_sprite(self.vx, sprite) {
+            return;
+        }
+        self.vx.animations.remove(&key(sprite));
+    }
+
+    /// Check if a sprite is animating, clips played in [Mode::Once] stop after their last frame
+    ///
+    /// Returns false for a stale handle.
+    pub fn is_playing(&self, sprite: &dyntex::Handle) -> bool {
+        if !dyntex::check_sprite(self.vx, sprite) {
+            return false;
+        }
+        self.vx
+            .animations
+            .get(&key(sprite))
+            .map_or(false, |animation| !animation.finished)
+    }
+
+    /// Get the index of the frame a sprite is currently showing
+    ///
+    /// Returns `None` for a stale handle.
+    pub fn current_frame(&self, sprite: &dyntex::Handle) -> Option<usize> {
+        if !dyntex::check_sprite(self.vx, sprite) {
+            return None;
+        }
+        self.vx
+            .animations
+            .get(&key(sprite))
+            .map(|animation| animation.frame)
+    }
+
+    /// Advance all animations by `dt` seconds
+    ///
+    /// The UV coordinates of every sprite that changed frame are written, and each affected layer
+    /// is marked for upload once.
+    pub fn tick(&mut self, dt: f32) {
+        let s = &mut *self.vx;
+        let mut touched = vec![];
+        for (key, animation) in s.animations.iter_mut() {
+            let clip = &s.clips[animation.clip];
+            if !advance(clip, animation, dt) {
+                continue;
+            }
+            let uv = uv_quad(cell_uv(clip, animation.frame));
+            s.dyntexs[key.0].uvbuffer[key.1].copy_from_slice(&uv);
+            if !touched.contains(&key.0) {
+                touched.push(key.0);
+            }
+        }
+        for layer in touched {
+            s.dyntexs[layer].uvbuf_touch = s.swapconfig.image_count;
+        }
+    }
+}
+
+// ---
+
+/// Move the animations of sprites that were relocated by a layer compaction
+///
+/// Compaction keeps the generation of a moved sprite, so it must already be in its new slot.
+pub(crate) fn remap(s: &mut VxDraw, layer: usize, moves: &[(usize, usize)]) {
+    let moved = moves
+        .iter()
+        .filter_map(|(from, to)| {
+            let generation = s.dyntexs[layer].generations[*to];
+            s.animations
+                .remove(&(layer, *from, generation))
+                .map(|x| ((layer, *to, generation), x))
+        })
+        .collect::<Vec<_>>();
+    for (key, animation) in moved {
+        s.animations.insert(key, animation);
+    }
+}
+
+/// Forget the animation of a sprite when it is removed
+pub(crate) fn forget(s: &mut VxDraw, sprite: &dyntex::Handle) {
+    s.animations.remove(&key(sprite));
+}
+
+/// Forget the animations of an entire layer when it is removed
+pub(crate) fn forget_layer(s: &mut VxDraw, layer: usize) {
+    s.animations.retain(|key, _| key.0 != layer);
+}
+
+fn key(sprite: &dyntex::Handle) -> (usize, usize, u64) {
+    (sprite.0, sprite.1, sprite.2)
+}
+
+/// Move an animation forward in time, returns true if the frame changed
+///
+/// Whole cycles of looping clips are skipped at once, so a large `dt` costs no more than a cycle.
+fn advance(clip: &AnimationClip, animation: &mut SpriteAnimation, dt: f32) -> bool {
+    if animation.finished {
+        return false;
+    }
+    let last = clip.frames.len() - 1;
+    let start = animation.frame;
+    animation.elapsed += dt;
+    // Every frame shows once per loop, a ping-pong shows all but the first and last twice
+    let cycle = match clip.mode {
+        Mode::Loop => clip.durations.iter().sum::<f32>(),
+        Mode::PingPong if last == 0 => clip.durations[0],
+        Mode::PingPong => {
+            2.0 * clip.durations.iter().sum::<f32>() - clip.durations[0] - clip.durations[last]
+        }
+        Mode::Once => std::f32::INFINITY,
+    };
+    if animation.elapsed >= cycle {
+        animation.elapsed %= cycle;
+    }
+    while animation.elapsed >= clip.durations[animation.frame] {
+        animation.elapsed -= clip.durations[animation.frame];
+        match clip.mode {
+            Mode::Loop => {
+                animation.frame = if animation.frame == last {
+                    0
+                } else {
+                    animation.frame + 1
+                };
+            }
+            Mode::PingPong => {
+                if last == 0 {
+                    continue;
+                }
+                if animation.forward && animation.frame == last {
+                    animation.forward = false;
+                } else if !animation.forward && animation.frame == 0 {
+                    animation.forward = true;
+                }
+                if animation.forward {
+                    animation.frame += 1;
+                } else {
+                    animation.frame -= 1;
+                }
+            }
+            Mode::Once => {
+                if animation.frame == last {
+                    animation.finished = true;
+                    animation.elapsed = 0.0;
+                    break;
+                }
+                animation.frame += 1;
+            }
+        }
+    }
+    animation.frame != start
+}
+
+/// Compute the UV coordinates of a frame in a clip
+fn cell_uv(clip: &AnimationClip, frame: usize) -> ((f32, f32), (f32, f32)) {
+    let cell = clip.frames[frame];
+    let (column, row) = (cell % clip.columns, cell / clip.columns);
+    let width = (clip.uv_end.0 - clip.uv_begin.0) / clip.columns as f32;
+    let height = (clip.uv_end.1 - clip.uv_begin.1) / clip.rows as f32;
+    let begin = (
+        clip.uv_begin.0 + column as f32 * width,
+        clip.uv_begin.1 + row as f32 * height,
+    );
+    (begin, (begin.0 + width, begin.1 + height))
+}
+
+/// Expand a UV rectangle into the per-vertex layout of the uv buffer
+fn uv_quad((uv_begin, uv_end): ((f32, f32), (f32, f32))) -> [f32; 8] {
+    [
+        uv_begin.0, uv_begin.1, uv_begin.0, uv_end.1, uv_end.0, uv_end.1, uv_end.0, uv_begin.1,
+    ]
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dyntex::{Handle, ImgData, LayerOptions, Sprite};
+    use slog::{o, Discard, Logger};
+
+    static FIREBALL: &ImgData = &ImgData::PNGBytes(include_bytes!["../images/Fireball_68x9.png"]);
+
+    fn clip(frames: usize, mode: Mode) -> AnimationClip {
+        AnimationClip {
+            columns: frames,
+            rows: 1,
+            uv_begin: (0.0, 0.0),
+            uv_end: (1.0, 1.0),
+            frames: (0..frames).collect(),
+            durations: vec![1.0; frames],
+            mode,
+        }
+    }
+
+    fn frames_over_time(clip: &AnimationClip, steps: usize) -> Vec<usize> {
+        let mut animation = SpriteAnimation {
+            clip: 0,
+            frame: 0,
+            elapsed: 0.0,
+            forward: true,
+            finished: false,
+        };
+        (0..steps)
+            .map(|_| {
+                advance(clip, &mut animation, 1.0);
+                animation.frame
+            })
+            .collect()
+    }
+
+    #[test]
+    fn playback_modes() {
+        assert_eq![
+            vec![1, 2, 0, 1, 2, 0],
+            frames_over_time(&clip(3, Mode::Loop), 6)
+        ];
+        assert_eq![
+            vec![1, 2, 1, 0, 1, 2],
+            frames_over_time(&clip(3, Mode::PingPong), 6)
+        ];
+        assert_eq![
+            vec![1, 2, 2, 2, 2, 2],
+            frames_over_time(&clip(3, Mode::Once), 6)
+        ];
+        assert_eq![vec![0, 0], frames_over_time(&clip(1, Mode::PingPong), 2)];
+    }
+
+    #[test]
+    fn large_steps_skip_frames() {
+        let clip = clip(4, Mode::Loop);
+        let mut animation = SpriteAnimation {
+            clip: 0,
+            frame: 0,
+            elapsed: 0.0,
+            forward: true,
+            finished: false,
+        };
+        assert![advance(&clip, &mut animation, 2.5)];
+        assert_eq![2, animation.frame];
+        assert![!advance(&clip, &mut animation, 0.25)];
+        assert![advance(&clip, &mut animation, 0.25)];
+        assert_eq![3, animation.frame];
+    }
+
+    #[test]
+    fn huge_steps_skip_whole_cycles() {
+        let mut animation = SpriteAnimation {
+            clip: 0,
+            frame: 0,
+            elapsed: 0.0,
+            forward: true,
+            finished: false,
+        };
+        assert![advance(&clip(3, Mode::Loop), &mut animation, 1.0e9)];
+        assert_eq![1, animation.frame];
+
+        let mut animation = SpriteAnimation {
+            clip: 0,
+            frame: 0,
+            elapsed: 0.0,
+            forward: true,
+            finished: false,
+        };
+        assert![advance(
+            &clip(3, Mode::PingPong),
+            &mut animation,
+            1.0e6 + 3.0
+        )];
+        assert_eq![1, animation.frame];
+        assert![!animation.forward];
+    }
+
+    #[test]
+    fn cell_uv_in_region() {
+        let mut clip = clip(4, Mode::Loop);
+        clip.columns = 2;
+        clip.rows = 2;
+        clip.uv_begin = (0.5, 0.0);
+        clip.uv_end = (1.0, 0.5);
+        assert_eq![((0.5, 0.0), (0.75, 0.25)), cell_uv(&clip, 0)];
+        assert_eq![((0.75, 0.25), (1.0, 0.5)), cell_uv(&clip, 3)];
+    }
+
+    #[test]
+    fn tick_updates_sprite_uvs() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let layer = vx.dyntex().add_layer(FIREBALL, &LayerOptions::new());
+        let sprite = vx.dyntex().add(&layer, Sprite::new());
+        let other = vx.dyntex().add(&layer, Sprite::new());
+
+        let clip = vx.animation().add_clip(
+            &ClipOptions::new(10, 6)
+                .frames(&[0, 11])
+                .durations(&[0.5, 0.25])
+                .mode(Mode::Once),
+        );
+        vx.animation().play(&sprite, &clip);
+        assert_eq![[0.0, 0.0, 0.0, 1.0 / 6.0], {
+            let uv = vx.dyntexs[0].uvbuffer[0];
+            [uv[0], uv[1], uv[2], uv[3]]
+        }];
+
+        vx.animation().tick(0.5);
+        assert_eq![Some(1), vx.animation().current_frame(&sprite)];
+        assert_eq![[0.1, 1.0 / 6.0], {
+            let uv = vx.dyntexs[0].uvbuffer[0];
+            [uv[0], uv[1]]
+        }];
+        assert_eq![None, vx.animation().current_frame(&other)];
+        vx.draw_frame();
+
+        vx.animation().tick(0.25);
+        assert![!vx.animation().is_playing(&sprite)];
+
+        vx.dyntex().remove(sprite);
+        assert![vx.animations.is_empty()];
+        vx.draw_frame();
+    }
+
+    #[test]
+    fn sprite_in_reused_slot_is_not_animated() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let layer = vx.dyntex().add_layer(FIREBALL, &LayerOptions::new());
+        let sprite = vx.dyntex().add(&layer, Sprite::new());
+        let clip = vx.animation().add_clip(&ClipOptions::new(10, 6));
+        vx.animation().play(&sprite, &clip);
+
+        vx.dyntex().remove(sprite);
+        let reused = vx.dyntex().add(&layer, Sprite::new());
+        assert_eq![0, reused.1];
+        assert![!vx.animation().is_playing(&reused)];
+        assert_eq![None, vx.animation().current_frame(&reused)];
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "Stale handle")]
+    fn stale_handle_panics_in_debug() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let layer = vx.dyntex().add_layer(FIREBALL, &LayerOptions::new());
+        let sprite = vx.dyntex().add(&layer, Sprite::new());
+        let stale = Handle(sprite.0, sprite.1, sprite.2);
+        vx.dyntex().remove(sprite);
+        vx.animation().is_playing(&stale);
+    }
+}
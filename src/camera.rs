@@ -0,0 +1,368 @@
+//! Two-dimensional camera
+//!
+//! A [Camera2D] describes which part of the world is visible, and produces the matrix passed to
+//! [VxDraw::set_perspective] or to the `set_perspective` function of a layer.
+//!
+//! # Example #
+//! ```
+//! use vxdraw::{camera::{Camera2D, Fit}, debtri::DebugTriangle, void_logger, VxDraw};
+//! let mut vx = VxDraw::new_offscreen(void_logger(), (200, 100));
+//!
+//! let camera = Camera2D::new()
+//!     .position((1.0, 0.0))
+//!     .zoom(2.0)
+//!     .fit(Fit::Letterbox);
+//! vx.set_camera(&camera);
+//! vx.debtri().add(DebugTriangle::default());
+//! vx.draw_frame();
+//!
+//! let size = vx.get_window_size_in_pixels_float();
+//! assert_eq![(100.0, 50.0), camera.world_to_screen(size, (1.0, 0.0))];
+//! ```
+use cgmath::{prelude::*, Matrix4, Rad, Vector3};
+
+/// Smallest zoom a camera accepts, smaller, zero, negative, and NaN zooms are raised to this
+pub const MIN_ZOOM: f32 = 1e-6;
+
+/// How the view is fitted to a window whose aspect ratio differs from the view's
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fit {
+    /// Stretch the view to the window, distorting the image when the aspect ratios differ
+    Stretch,
+    /// Keep the aspect ratio and show the entire view, more of the world becomes visible along the
+    /// longer window axis
+    Letterbox,
+    /// Keep the aspect ratio and expand the view until it covers the entire window, part of the
+    /// view along the longer window axis is cut off
+    Expand,
+}
+
+/// A camera with a position, zoom, and rotation
+///
+/// At zoom 1 the camera shows `view_size` world units centered on its position. The default view
+/// size of `(2.0, 2.0)` with [Fit::Stretch] gives the identity matrix, the default perspective.
+#[derive(Clone, Copy, Debug)]
+pub struct Camera2D {
+    position: (f32, f32),
+    zoom: f32,
+    rotation: f32,
+    view_size: (f32, f32),
+    fit: Fit,
+    bounds: Option<((f32, f32), (f32, f32))>,
+}
+
+impl Default for Camera2D {
+    fn default() -> Self {
+        Self {
+            position: (0.0, 0.0),
+            zoom: 1.0,
+            rotation: 0.0,
+            view_size: (2.0, 2.0),
+            fit: Fit::Stretch,
+            bounds: None,
+        }
+    }
+}
+
+impl Camera2D {
+    /// Same as default
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the world position at the center of the view
+    pub fn position(mut self, position: (f32, f32)) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Set the zoom, values larger than 1 magnify the world
+    ///
+    /// The zoom is kept at or above [MIN_ZOOM].
+    pub fn zoom(mut self, zoom: f32) -> Self {
+        self.set_zoom(zoom);
+        self
+    }
+
+    /// Set the rotation of the view, in the same direction as sprites rotate
+    pub fn rotation<T: Copy + Into<Rad<f32>>>(mut self, angle: T) -> Self {
+        self.rotation = angle.into().0;
+        self
+    }
+
+    /// Set the size of the world area visible at zoom 1
+    pub fn view_size(mut self, size: (f32, f32)) -> Self {
+        self.view_size = size;
+        self
+    }
+
+    /// Set how the view is fitted to the window (default is [Fit::Stretch])
+    pub fn fit(mut self, fit: Fit) -> Self {
+        self.fit = fit;
+        self
+    }
+
+    /// Keep the visible area inside the rectangle from `min` to `max`
+    ///
+    /// When the visible area is larger than the bounds along an axis, the view is centered on the
+    /// bounds along that axis.
+    pub fn bounds(mut self, min: (f32, f32), max: (f32, f32)) -> Self {
+        self.bounds = Some((min, max));
+        self
+    }
+
+    // ---
+
+    /// Get the position of the camera, before any bounds clamping
+    pub fn get_position(&self) -> (f32, f32) {
+        self.position
+    }
+
+    /// Get the zoom of the camera
+    pub fn get_zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Get the rotation of the camera
+    pub fn get_rotation(&self) -> Rad<f32> {
+        Rad(self.rotation)
+    }
+
+    /// Set the world position at the center of the view
+    pub fn set_position(&mut self, position: (f32, f32)) {
+        self.position = position;
+    }
+
+    /// Move the camera by a world-space offset
+    pub fn pan(&mut self, delta: (f32, f32)) {
+        self.position.0 += delta.0;
+        self.position.1 += delta.1;
+    }
+
+    /// Set the zoom, values larger than 1 magnify the world
+    ///
+    /// The zoom is kept at or above [MIN_ZOOM].
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.max(MIN_ZOOM);
+    }
+
+    /// Multiply the current zoom by a factor
+    ///
+    /// The zoom is kept at or above [MIN_ZOOM].
+    pub fn zoom_by(&mut self, factor: f32) {
+        self.set_zoom(self.zoom * factor);
+    }
+
+    /// Set the rotation of the view
+    pub fn set_rotation<T: Copy + Into<Rad<f32>>>(&mut self, angle: T) {
+        self.rotation = angle.into().0;
+    }
+
+    /// Rotate the view, in the same direction as sprites rotate
+    pub fn rotate<T: Copy + Into<Rad<f32>>>(&mut self, angle: T) {
+        self.rotation += angle.into().0;
+    }
+
+    // ---
+
+    /// Compute the perspective matrix of this camera for a window of the given size in pixels
+    pub fn matrix(&self, window_size: (f32, f32)) -> Matrix4<f32> {
+        let half = self.half_extents(window_size);
+        let position = self.clamped_position(half);
+        Matrix4::from_nonuniform_scale(1.0 / half.0, 1.0 / half.1, 1.0)
+            * Matrix4::from_angle_z(Rad(self.rotation))
+            * Matrix4::from_translation(Vector3::new(-position.0, -position.1, 0.0))
+    }
+
+    /// Convert a world position to a pixel position in a window of the given size
+    pub fn world_to_screen(&self, window_size: (f32, f32), world: (f32, f32)) -> (f32, f32) {
+        let pos = self.matrix(window_size) * cgmath::vec4(world.0, world.1, 0.0, 1.0);
+        (
+            (pos.x + 1.0) * window_size.0 / 2.0,
+            (pos.y + 1.0) * window_size.1 / 2.0,
+        )
+    }
+
+    /// Convert a pixel position in a window of the given size to a world position
+    ///
+    /// Returns `None` when no world position maps to the pixel, which happens when the window or
+    /// the view size is 0 along an axis.
+    pub fn screen_to_world(
+        &self,
+        window_size: (f32, f32),
+        screen: (f32, f32),
+    ) -> Option<(f32, f32)> {
+        if !(window_size.0 > 0.0 && window_size.1 > 0.0) {
+            return None;
+        }
+        let inverse = self.matrix(window_size).invert()?;
+        let pos = inverse
+            * cgmath::vec4(
+                screen.0 / (window_size.0 / 2.0) - 1.0,
+                screen.1 / (window_size.1 / 2.0) - 1.0,
+                0.0,
+                1.0,
+            );
+        if pos.x.is_finite() && pos.y.is_finite() {
+            Some((pos.x, pos.y))
+        } else {
+            None
+        }
+    }
+
+    /// Half of the visible world area after zooming and fitting to the window
+    fn half_extents(&self, window_size: (f32, f32)) -> (f32, f32) {
+        let half = (
+            self.view_size.0 / 2.0 / self.zoom,
+            self.view_size.1 / 2.0 / self.zoom,
+        );
+        let window_aspect = window_size.0 / window_size.1;
+        let wider = window_aspect > self.view_size.0 / self.view_size.1;
+        match self.fit {
+            Fit::Stretch => half,
+            Fit::Letterbox if wider => (half.1 * window_aspect, half.1),
+            Fit::Letterbox => (half.0, half.0 / window_aspect),
+            Fit::Expand if wider => (half.0, half.0 / window_aspect),
+            Fit::Expand => (half.1 * window_aspect, half.1),
+        }
+    }
+
+    /// Position of the camera after keeping the (rotated) visible area inside the bounds
+    fn clamped_position(&self, half: (f32, f32)) -> (f32, f32) {
+        let ((min_x, min_y), (max_x, max_y)) = match self.bounds {
+            Some(bounds) => bounds,
+            None => return self.position,
+        };
+        let (sin, cos) = (self.rotation.sin().abs(), self.rotation.cos().abs());
+        let extent = (cos * half.0 + sin * half.1, sin * half.0 + cos * half.1);
+        let clamp = |pos: f32, min: f32, max: f32, extent: f32| {
+            if max - min <= 2.0 * extent {
+                (min + max) / 2.0
+            } else {
+                pos.max(min + extent).min(max - extent)
+            }
+        };
+        (
+            clamp(self.position.0, min_x, max_x, extent.0),
+            clamp(self.position.1, min_y, max_y, extent.1),
+        )
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Deg;
+
+    fn assert_close(expected: (f32, f32), actual: (f32, f32)) {
+        assert![
+            (expected.0 - actual.0).abs() < 1e-4 && (expected.1 - actual.1).abs() < 1e-4,
+            "expected {:?}, got {:?}",
+            expected,
+            actual
+        ];
+    }
+
+    #[test]
+    fn default_camera_is_identity() {
+        assert_eq![
+            Matrix4::identity(),
+            Camera2D::new().matrix((1000.0, 1000.0))
+        ];
+        assert_eq![Matrix4::identity(), Camera2D::new().matrix((300.0, 100.0))];
+    }
+
+    #[test]
+    fn letterbox_matches_perspective_projection() {
+        let camera = Camera2D::new().fit(Fit::Letterbox);
+        assert_eq![
+            Matrix4::from_nonuniform_scale(0.5, 1.0, 1.0),
+            camera.matrix((2000.0, 1000.0))
+        ];
+        assert_eq![
+            Matrix4::from_nonuniform_scale(1.0, 0.5, 1.0),
+            camera.matrix((1000.0, 2000.0))
+        ];
+    }
+
+    #[test]
+    fn expand_covers_the_window() {
+        let camera = Camera2D::new().fit(Fit::Expand);
+        assert_eq![
+            Matrix4::from_nonuniform_scale(1.0, 2.0, 1.0),
+            camera.matrix((2000.0, 1000.0))
+        ];
+    }
+
+    #[test]
+    fn screen_and_world_conversions_are_inverses() {
+        let size = (640.0, 480.0);
+        let camera = Camera2D::new()
+            .position((3.0, -2.0))
+            .zoom(1.5)
+            .rotation(Deg(30.0))
+            .view_size((16.0, 9.0))
+            .fit(Fit::Letterbox);
+
+        assert_close((320.0, 240.0), camera.world_to_screen(size, (3.0, -2.0)));
+        for &point in &[(0.0, 0.0), (5.0, 1.0), (-4.0, 7.5)] {
+            let screen = camera.world_to_screen(size, point);
+            assert_close(point, camera.screen_to_world(size, screen).unwrap());
+        }
+    }
+
+    #[test]
+    fn zoom_and_pan() {
+        let size = (100.0, 100.0);
+        let mut camera = Camera2D::new();
+        camera.zoom_by(2.0);
+        assert_close(
+            (0.5, 0.5),
+            camera.screen_to_world(size, (100.0, 100.0)).unwrap(),
+        );
+
+        camera.pan((1.0, 0.0));
+        assert_eq![(1.0, 0.0), camera.get_position()];
+        assert_close(
+            (1.5, 0.5),
+            camera.screen_to_world(size, (100.0, 100.0)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn degenerate_cameras_have_no_world_positions() {
+        let camera = Camera2D::new().zoom(0.0);
+        assert_eq![MIN_ZOOM, camera.get_zoom()];
+        let mut camera = Camera2D::new();
+        camera.set_zoom(-2.0);
+        assert_eq![MIN_ZOOM, camera.get_zoom()];
+        camera.zoom_by(std::f32::NAN);
+        assert_eq![MIN_ZOOM, camera.get_zoom()];
+
+        let camera = Camera2D::new();
+        assert_eq![None, camera.screen_to_world((0.0, 100.0), (0.0, 0.0))];
+        assert_eq![None, camera.screen_to_world((100.0, 0.0), (0.0, 0.0))];
+        let camera = Camera2D::new().view_size((0.0, 2.0));
+        assert_eq![None, camera.screen_to_world((100.0, 100.0), (50.0, 50.0))];
+    }
+
+    #[test]
+    fn bounds_keep_the_view_inside() {
+        let size = (100.0, 100.0);
+        let camera = Camera2D::new()
+            .position((9.5, -20.0))
+            .bounds((-10.0, -10.0), (10.0, 10.0));
+        assert_close(
+            (9.0, -9.0),
+            camera.screen_to_world(size, (50.0, 50.0)).unwrap(),
+        );
+
+        let camera = camera.bounds((0.0, 0.0), (1.0, 10.0));
+        assert_close(
+            (0.5, 1.0),
+            camera.screen_to_world(size, (50.0, 50.0)).unwrap(),
+        );
+    }
+}
@@ -10,7 +10,7 @@ use gfx_backend_metal as back;
 use gfx_backend_vulkan as back;
 use gfx_hal::{adapter::Adapter, command::ClearColor, device::Device, Backend, Instance};
 use smallvec::SmallVec;
-use std::mem::ManuallyDrop;
+use std::{collections::HashMap, mem::ManuallyDrop};
 
 #[derive(Clone, Copy, Debug, Default)]
 pub(crate) struct SData {
@@ -18,22 +18,172 @@ pub(crate) struct SData {
     pub uv_end: (f32, f32),
     pub topleft: (i32, i32),
     pub bottomright: (i32, i32),
+    /// Id of the text the glyph belongs to
+    pub text: usize,
+    pub color: [u8; 4],
+}
+
+/// Layout of a single text, kept so the text can be laid out again
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct TextLayout {
+    pub(crate) max_width: Option<f32>,
+    pub(crate) wrap: crate::text::Wrap,
+    pub(crate) align: crate::text::Align,
+    pub(crate) line_spacing: f32,
+    /// Color of the text outside of any colored span
+    pub(crate) color: [u8; 4],
+    /// Font of the text outside of any font span
+    pub(crate) font: usize,
+}
+
+/// Style of a byte range of a text
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Span {
+    Color([u8; 4]),
+    Font(usize),
+}
+
+/// Area taken up by a character of a text, in model space
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct CharacterCell {
+    /// Byte index of the character in the string of the text
+    pub(crate) index: usize,
+    /// Left, top, right, and bottom edge of the cell
+    pub(crate) rect: [f32; 4],
+    /// Offset of the character's glyph within the glyphs of the text, if it is drawn
+    pub(crate) glyph: Option<usize>,
+}
+
+/// Transform of a text as a whole, reapplied to its glyphs when the text is laid out again
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct TextPlacement {
+    pub(crate) translation: (f32, f32),
+    pub(crate) rotation: f32,
+    pub(crate) scale: f32,
+    pub(crate) opacity: u8,
+}
+
+impl TextPlacement {
+    pub(crate) fn opacities(&self, count: usize) -> impl Iterator<Item = [u8; 4]> {
+        std::iter::repeat_n([self.opacity; 4], count)
+    }
+
+    pub(crate) fn translations(&self, count: usize) -> impl Iterator<Item = [f32; 8]> {
+        let (x, y) = self.translation;
+        std::iter::repeat_n([x, y, x, y, x, y, x, y], count)
+    }
+
+    pub(crate) fn rotations(&self, count: usize) -> impl Iterator<Item = [f32; 4]> {
+        std::iter::repeat_n([self.rotation; 4], count)
+    }
+
+    pub(crate) fn scales(&self, count: usize) -> impl Iterator<Item = [f32; 4]> {
+        std::iter::repeat_n([self.scale; 4], count)
+    }
+}
+
+/// Glyphs of a text layer that renders signed distance fields
+///
+/// The glyph brush caches glyphs tightly packed, so each distance field, which needs `spread`
+/// texels of room around its glyph, is given its own slot in the glyph texture.
+#[derive(Debug)]
+pub(crate) struct DistanceFieldGlyphs {
+    /// Font size all glyphs are rasterized at
+    pub(crate) size: f32,
+    pub(crate) spread: u32,
+    /// Fragment shader push constants: outline, glow, and shadow colors, then the outline width,
+    /// glow radius, and shadow offset
+    pub(crate) effects: [f32; 16],
+    /// Top-left corner of each slot, keyed by the top-left corner of the glyph in the glyph brush
+    /// cache
+    pub(crate) slots: HashMap<(u32, u32), (u32, u32)>,
+    /// Top-left corner and height of the row slots are being added to
+    pub(crate) shelf: (u32, u32, u32),
+}
+
+impl DistanceFieldGlyphs {
+    /// Find room for a slot in a texture of the given size, filling it row by row
+    pub(crate) fn allocate(&mut self, size: (u32, u32), texture: (u32, u32)) -> Option<(u32, u32)> {
+        let (mut x, mut y, mut height) = self.shelf;
+        if x + size.0 > texture.0 {
+            x = 0;
+            y += height + 1;
+            height = 0;
+        }
+        if x + size.0 > texture.0 || y + size.1 > texture.1 {
+            return None;
+        }
+        self.shelf = (x + size.0 + 1, y, height.max(size.1));
+        Some((x, y))
+    }
+
+    /// Forget all slots, for when the glyph texture is replaced
+    pub(crate) fn clear(&mut self) {
+        self.slots.clear();
+        self.shelf = (0, 0, 0);
+    }
+
+    /// Make glyph vertices cover their distance fields instead of their glyph brush cache entries
+    pub(crate) fn expand(&self, texture: (u32, u32), vertices: &mut [SData]) {
+        let (width, height) = (texture.0 as f32, texture.1 as f32);
+        let spread = self.spread as i32;
+        for vtx in vertices.iter_mut() {
+            let begin = (
+                (vtx.uv_begin.0 * width).round() as u32,
+                (vtx.uv_begin.1 * height).round() as u32,
+            );
+            let end = (
+                (vtx.uv_end.0 * width).round() as u32,
+                (vtx.uv_end.1 * height).round() as u32,
+            );
+            // The cache pads each glyph by a single texel
+            let slot = self.slots[&(begin.0 - 1, begin.1 - 1)];
+            let slot_begin = (slot.0 + 1, slot.1 + 1);
+            let slot_end = (
+                slot_begin.0 + end.0 - begin.0 + 2 * self.spread,
+                slot_begin.1 + end.1 - begin.1 + 2 * self.spread,
+            );
+            vtx.uv_begin = (slot_begin.0 as f32 / width, slot_begin.1 as f32 / height);
+            vtx.uv_end = (slot_end.0 as f32 / width, slot_end.1 as f32 / height);
+            vtx.topleft = (vtx.topleft.0 - spread, vtx.topleft.1 - spread);
+            vtx.bottomright = (vtx.bottomright.0 + spread, vtx.bottomright.1 + spread);
+        }
+    }
 }
 
 #[derive(Debug)]
 pub(crate) struct Text {
     pub(crate) hidden: bool,
-    pub(crate) removed: Vec<std::ops::Range<usize>>,
+    /// Ids of removed texts, their slots are reused by new texts
+    pub(crate) removed: Vec<usize>,
+    /// Generation of the layer, 0 once removed
+    pub(crate) generation: u64,
+    /// Generation of each text, 0 when the text is removed
+    pub(crate) generations: Vec<u64>,
     pub(crate) glyph_brush: glyph_brush::GlyphBrush<'static, SData>,
 
     pub(crate) texts: Vec<String>,
     pub(crate) font_sizes: Vec<(f32, f32)>,
     pub(crate) origin: Vec<(f32, f32)>,
+    pub(crate) layouts: Vec<TextLayout>,
+    /// Byte ranges of each text that are drawn in their own style, later spans take precedence
+    pub(crate) spans: Vec<Vec<(std::ops::Range<usize>, Span)>>,
+    /// Fonts searched in order for glyphs missing from the font of a text
+    pub(crate) fallbacks: Vec<usize>,
 
     pub(crate) width: Vec<i32>,
     pub(crate) height: Vec<i32>,
+    /// Vertex range of each text, texts are stored contiguously in the order they were added
+    pub(crate) ranges: Vec<std::ops::Range<usize>>,
+    pub(crate) placements: Vec<TextPlacement>,
+    /// Cell of every character of each text, followed by the cell after its last character
+    pub(crate) cells: Vec<Vec<CharacterCell>>,
 
     pub(crate) fixed_perspective: Option<Matrix4<f32>>,
+    pub(crate) distance_field: Option<DistanceFieldGlyphs>,
+    /// Glyph cache entries written to the glyph texture, the cache only writes over one of them
+    /// after evicting it
+    pub(crate) glyph_rects: Vec<glyph_brush::rusttype::Rect<u32>>,
 
     pub(crate) posbuf_touch: u32,
     pub(crate) opacbuf_touch: u32,
@@ -41,6 +191,7 @@ pub(crate) struct Text {
     pub(crate) tranbuf_touch: u32,
     pub(crate) rotbuf_touch: u32,
     pub(crate) scalebuf_touch: u32,
+    pub(crate) colbuf_touch: u32,
 
     pub(crate) posbuffer: Vec<[f32; 8]>,   // 8 per quad
     pub(crate) opacbuffer: Vec<[u8; 4]>,   // 4per quad
@@ -48,6 +199,7 @@ pub(crate) struct Text {
     pub(crate) tranbuffer: Vec<[f32; 8]>,  // 8 per quad
     pub(crate) rotbuffer: Vec<[f32; 4]>,   // 4 per quad
     pub(crate) scalebuffer: Vec<[f32; 4]>, // 4 per quad
+    pub(crate) colbuffer: Vec<[u8; 16]>,   // 16 per quad
 
     pub(crate) posbuf: Vec<super::utils::ResizBuf>,
     pub(crate) opacbuf: Vec<super::utils::ResizBuf>,
@@ -55,6 +207,7 @@ pub(crate) struct Text {
     pub(crate) tranbuf: Vec<super::utils::ResizBuf>,
     pub(crate) rotbuf: Vec<super::utils::ResizBuf>,
     pub(crate) scalebuf: Vec<super::utils::ResizBuf>,
+    pub(crate) colbuf: Vec<super::utils::ResizBuf>,
 
     pub(crate) indices: Vec<super::utils::ResizBufIdx4>,
 
@@ -96,6 +249,9 @@ impl Text {
         for mut scalebuf in self.scalebuf.drain(..) {
             scalebuf.destroy(&device);
         }
+        for mut colbuf in self.colbuf.drain(..) {
+            colbuf.destroy(&device);
+        }
         unsafe {
             device.destroy_image(ManuallyDrop::into_inner(read(&self.image_buffer)));
             device.free_memory(ManuallyDrop::into_inner(read(&self.image_memory)));
@@ -112,17 +268,125 @@ impl Text {
     }
 }
 
+/// A grid of tiles drawn from a tileset texture, see [crate::tilemap]
+///
+/// The quads of the tiles are stored chunk by chunk, and the chunks row by row, so that a row of
+/// chunks is a contiguous range of quads.
+#[derive(Debug)]
+pub(crate) struct Tilemap {
+    pub(crate) hidden: bool,
+    /// Generation of the layer, 0 once removed
+    pub(crate) generation: u64,
+    pub(crate) fixed_perspective: Option<Matrix4<f32>>,
+
+    /// Size of the map in tiles
+    pub(crate) size: (usize, usize),
+    /// Size of the map in chunks
+    pub(crate) chunks: (usize, usize),
+    /// Size of a tile in the tileset, in pixels
+    pub(crate) tile_pixels: (u32, u32),
+    /// Size of the tileset in pixels
+    pub(crate) tileset_size: (u32, u32),
+    /// Size of a tile in the world
+    pub(crate) tile_size: (f32, f32),
+    /// World position of the top left corner of the map
+    pub(crate) translation: (f32, f32),
+    /// Tileset index of every tile, row by row
+    pub(crate) tiles: Vec<Option<u32>>,
+    /// Frames and frame duration of animated tileset indices
+    pub(crate) animations: HashMap<u32, (Vec<u32>, f32)>,
+    /// Time the animations have advanced
+    pub(crate) time: f32,
+
+    /// Remaining uploads of each chunk, counted down once per frame like the `*_touch` fields
+    pub(crate) chunk_touch: Vec<u32>,
+
+    pub(crate) posbuffer: Vec<[f32; 8]>, // 8 per quad
+    pub(crate) uvbuffer: Vec<[f32; 8]>,  // 8 per quad
+
+    pub(crate) posbuf: Vec<super::utils::ResizBuf>,
+    pub(crate) uvbuf: Vec<super::utils::ResizBuf>,
+
+    pub(crate) indices: Vec<super::utils::ResizBufIdx4>,
+
+    pub(crate) texture_image_buffer: ManuallyDrop<<back::Backend as Backend>::Image>,
+    pub(crate) texture_image_memory: ManuallyDrop<<back::Backend as Backend>::Memory>,
+    pub(crate) image_view: ManuallyDrop<<back::Backend as Backend>::ImageView>,
+    pub(crate) sampler: ManuallyDrop<<back::Backend as Backend>::Sampler>,
+    pub(crate) descriptor_pool: ManuallyDrop<<back::Backend as Backend>::DescriptorPool>,
+
+    pub(crate) descriptor_set: ManuallyDrop<<back::Backend as Backend>::DescriptorSet>,
+    pub(crate) descriptor_set_layouts: Vec<<back::Backend as Backend>::DescriptorSetLayout>,
+    pub(crate) pipeline: ManuallyDrop<<back::Backend as Backend>::GraphicsPipeline>,
+    pub(crate) pipeline_layout: ManuallyDrop<<back::Backend as Backend>::PipelineLayout>,
+    pub(crate) render_pass: ManuallyDrop<<back::Backend as Backend>::RenderPass>,
+}
+
+impl Tilemap {
+    pub(crate) fn destroy(mut self, device: &back::Device) {
+        for mut indices in self.indices.drain(..) {
+            indices.destroy(&device);
+        }
+        for mut posbuf in self.posbuf.drain(..) {
+            posbuf.destroy(&device);
+        }
+        for mut uvbuf in self.uvbuf.drain(..) {
+            uvbuf.destroy(&device);
+        }
+        unsafe {
+            device.destroy_image(ManuallyDrop::into_inner(read(&self.texture_image_buffer)));
+            device.free_memory(ManuallyDrop::into_inner(read(&self.texture_image_memory)));
+            device.destroy_render_pass(ManuallyDrop::into_inner(read(&self.render_pass)));
+            device.destroy_pipeline_layout(ManuallyDrop::into_inner(read(&self.pipeline_layout)));
+            device.destroy_graphics_pipeline(ManuallyDrop::into_inner(read(&self.pipeline)));
+            for dsl in self.descriptor_set_layouts.drain(..) {
+                device.destroy_descriptor_set_layout(dsl);
+            }
+            device.destroy_descriptor_pool(ManuallyDrop::into_inner(read(&self.descriptor_pool)));
+            device.destroy_sampler(ManuallyDrop::into_inner(read(&self.sampler)));
+            device.destroy_image_view(ManuallyDrop::into_inner(read(&self.image_view)));
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub(crate) enum StreamingTextureWrite {
     Single((u32, u32), (u8, u8, u8, u8)),
     Block((u32, u32), (u32, u32), (u8, u8, u8, u8)),
 }
 
+/// A pending copy of a region of a streaming texture into a host-visible buffer
+#[derive(Debug)]
+pub(crate) struct StreamingTextureRead {
+    pub(crate) ticket: u64,
+    pub(crate) start: (u32, u32),
+    pub(crate) size: (u32, u32),
+    pub(crate) buffer: <back::Backend as Backend>::Buffer,
+    pub(crate) memory: <back::Backend as Backend>::Memory,
+    /// Frame in flight whose command buffer contains the copy
+    pub(crate) recorded: Option<usize>,
+    /// The fence of the recording frame has been waited on since
+    pub(crate) done: bool,
+}
+
+impl StreamingTextureRead {
+    pub(crate) fn destroy(self, device: &back::Device) {
+        unsafe {
+            device.destroy_buffer(self.buffer);
+            device.free_memory(self.memory);
+        }
+    }
+}
+
 /// A texture that host can read/write into directly, functions similarly to a sprite
 #[derive(Debug)]
 pub(crate) struct StreamingTexture {
     pub(crate) hidden: bool,
     pub(crate) removed: Vec<usize>,
+    /// Generation of the layer, 0 once removed
+    pub(crate) generation: u64,
+    /// Generation of each slot, 0 when the slot is removed
+    pub(crate) generations: Vec<u64>,
 
     pub(crate) fixed_perspective: Option<Matrix4<f32>>,
 
@@ -159,6 +423,7 @@ pub(crate) struct StreamingTexture {
     pub(crate) descriptor_sets: SmallVec<[<back::Backend as Backend>::DescriptorSet; 1]>,
 
     pub(crate) circular_writes: Vec<Vec<StreamingTextureWrite>>,
+    pub(crate) reads: Vec<StreamingTextureRead>,
 
     pub(crate) sampler: ManuallyDrop<<back::Backend as Backend>::Sampler>,
     pub(crate) descriptor_pool: ManuallyDrop<<back::Backend as Backend>::DescriptorPool>,
@@ -174,8 +439,18 @@ pub(crate) struct StreamingTexture {
 pub(crate) struct DynamicTexture {
     pub(crate) hidden: bool,
     pub(crate) removed: Vec<usize>,
+    /// Generation of the layer, 0 once removed
+    pub(crate) generation: u64,
+    /// Generation of each slot, 0 when the slot is removed
+    pub(crate) generations: Vec<u64>,
 
     pub(crate) fixed_perspective: Option<Matrix4<f32>>,
+    /// Translation, rotation, and scale are uploaded once per sprite
+    pub(crate) instanced: bool,
+    /// Size of the sampled texture in pixels
+    pub(crate) texture_size: (u32, u32),
+    /// Render texture sampled by the layer
+    pub(crate) rentex: Option<usize>,
 
     pub(crate) posbuf_touch: u32,
     pub(crate) opacbuf_touch: u32,
@@ -200,8 +475,9 @@ pub(crate) struct DynamicTexture {
 
     pub(crate) indices: Vec<super::utils::ResizBufIdx4>,
 
-    pub(crate) texture_image_buffer: ManuallyDrop<<back::Backend as Backend>::Image>,
-    pub(crate) texture_image_memory: ManuallyDrop<<back::Backend as Backend>::Memory>,
+    /// `None` when the layer samples a render texture, which owns the image instead
+    pub(crate) texture_image_buffer: Option<<back::Backend as Backend>::Image>,
+    pub(crate) texture_image_memory: Option<<back::Backend as Backend>::Memory>,
 
     pub(crate) sampler: ManuallyDrop<<back::Backend as Backend>::Sampler>,
     pub(crate) image_view: ManuallyDrop<<back::Backend as Backend>::ImageView>,
@@ -214,11 +490,152 @@ pub(crate) struct DynamicTexture {
     pub(crate) descriptor_set: ManuallyDrop<<back::Backend as Backend>::DescriptorSet>,
 }
 
+/// An image that layers are drawn into before the main render pass, sampled by dyntex layers
+#[derive(Debug)]
+pub(crate) struct RenderTexture {
+    /// Generation of the render texture
+    pub(crate) generation: u64,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+
+    pub(crate) fixed_perspective: Option<Matrix4<f32>>,
+    pub(crate) clear_color: ClearColor,
+
+    pub(crate) draw_order: Vec<DrawType>,
+
+    pub(crate) image: ManuallyDrop<<back::Backend as Backend>::Image>,
+    pub(crate) image_memory: ManuallyDrop<<back::Backend as Backend>::Memory>,
+    pub(crate) image_view: ManuallyDrop<<back::Backend as Backend>::ImageView>,
+
+    pub(crate) depth_image: ManuallyDrop<<back::Backend as Backend>::Image>,
+    pub(crate) depth_image_memory: ManuallyDrop<<back::Backend as Backend>::Memory>,
+    pub(crate) depth_image_view: ManuallyDrop<<back::Backend as Backend>::ImageView>,
+
+    pub(crate) framebuffer: ManuallyDrop<<back::Backend as Backend>::Framebuffer>,
+    pub(crate) render_pass: ManuallyDrop<<back::Backend as Backend>::RenderPass>,
+}
+
+impl RenderTexture {
+    pub(crate) fn destroy(self, device: &back::Device) {
+        unsafe {
+            device.destroy_framebuffer(ManuallyDrop::into_inner(read(&self.framebuffer)));
+            device.destroy_render_pass(ManuallyDrop::into_inner(read(&self.render_pass)));
+            device.destroy_image_view(ManuallyDrop::into_inner(read(&self.image_view)));
+            device.destroy_image(ManuallyDrop::into_inner(read(&self.image)));
+            device.free_memory(ManuallyDrop::into_inner(read(&self.image_memory)));
+            device.destroy_image_view(ManuallyDrop::into_inner(read(&self.depth_image_view)));
+            device.destroy_image(ManuallyDrop::into_inner(read(&self.depth_image)));
+            device.free_memory(ManuallyDrop::into_inner(read(&self.depth_image_memory)));
+        }
+    }
+}
+
+/// A full-screen pass run on the composed frame
+#[derive(Debug)]
+pub(crate) struct PostProcPass {
+    /// Generation of the pass
+    pub(crate) generation: u64,
+    pub(crate) hidden: bool,
+    pub(crate) push_constants: Vec<f32>,
+
+    pub(crate) pipeline: ManuallyDrop<<back::Backend as Backend>::GraphicsPipeline>,
+    pub(crate) pipeline_layout: ManuallyDrop<<back::Backend as Backend>::PipelineLayout>,
+}
+
+impl PostProcPass {
+    pub(crate) fn destroy(self, device: &back::Device) {
+        unsafe {
+            device.destroy_graphics_pipeline(ManuallyDrop::into_inner(read(&self.pipeline)));
+            device.destroy_pipeline_layout(ManuallyDrop::into_inner(read(&self.pipeline_layout)));
+        }
+    }
+}
+
+/// Intermediate images that the frame is drawn into when post-processing is enabled
+///
+/// The passes ping-pong between the two images, the last pass writes to the swapchain image.
+#[derive(Debug)]
+pub(crate) struct PostProcTargets {
+    pub(crate) images: Vec<<back::Backend as Backend>::Image>,
+    pub(crate) image_memories: Vec<<back::Backend as Backend>::Memory>,
+    pub(crate) image_views: Vec<<back::Backend as Backend>::ImageView>,
+    pub(crate) framebuffers: Vec<<back::Backend as Backend>::Framebuffer>,
+
+    pub(crate) depth_image: ManuallyDrop<<back::Backend as Backend>::Image>,
+    pub(crate) depth_image_memory: ManuallyDrop<<back::Backend as Backend>::Memory>,
+    pub(crate) depth_image_view: ManuallyDrop<<back::Backend as Backend>::ImageView>,
+
+    pub(crate) sampler: ManuallyDrop<<back::Backend as Backend>::Sampler>,
+    pub(crate) descriptor_pool: ManuallyDrop<<back::Backend as Backend>::DescriptorPool>,
+    pub(crate) descriptor_sets: Vec<<back::Backend as Backend>::DescriptorSet>,
+    pub(crate) descriptor_set_layouts: Vec<<back::Backend as Backend>::DescriptorSetLayout>,
+    pub(crate) render_pass: ManuallyDrop<<back::Backend as Backend>::RenderPass>,
+}
+
+impl PostProcTargets {
+    /// Destroy the resources that depend on the frame size, they must be recreated before use
+    pub(crate) fn destroy_images(&mut self, device: &back::Device) {
+        unsafe {
+            for fb in self.framebuffers.drain(..) {
+                device.destroy_framebuffer(fb);
+            }
+            for iv in self.image_views.drain(..) {
+                device.destroy_image_view(iv);
+            }
+            for image in self.images.drain(..) {
+                device.destroy_image(image);
+            }
+            for memory in self.image_memories.drain(..) {
+                device.free_memory(memory);
+            }
+            device.destroy_image_view(ManuallyDrop::into_inner(read(&self.depth_image_view)));
+            device.destroy_image(ManuallyDrop::into_inner(read(&self.depth_image)));
+            device.free_memory(ManuallyDrop::into_inner(read(&self.depth_image_memory)));
+        }
+    }
+
+    pub(crate) fn destroy(mut self, device: &back::Device) {
+        self.destroy_images(device);
+        unsafe {
+            device.destroy_descriptor_pool(ManuallyDrop::into_inner(read(&self.descriptor_pool)));
+            for dsl in self.descriptor_set_layouts.drain(..) {
+                device.destroy_descriptor_set_layout(dsl);
+            }
+            device.destroy_sampler(ManuallyDrop::into_inner(read(&self.sampler)));
+            device.destroy_render_pass(ManuallyDrop::into_inner(read(&self.render_pass)));
+        }
+    }
+}
+
+/// A sequence of cells in a grid-based sprite sheet
+#[derive(Debug)]
+pub(crate) struct AnimationClip {
+    pub(crate) columns: usize,
+    pub(crate) rows: usize,
+    pub(crate) uv_begin: (f32, f32),
+    pub(crate) uv_end: (f32, f32),
+    pub(crate) frames: Vec<usize>,
+    pub(crate) durations: Vec<f32>,
+    pub(crate) mode: crate::animation::Mode,
+}
+
+/// Playback state of a clip on a single sprite
+#[derive(Debug)]
+pub(crate) struct SpriteAnimation {
+    pub(crate) clip: usize,
+    pub(crate) frame: usize,
+    pub(crate) elapsed: f32,
+    pub(crate) forward: bool,
+    pub(crate) finished: bool,
+}
+
 #[derive(Debug)]
 pub(crate) struct DebugTriangleData {
     pub(crate) hidden: bool,
 
     pub(crate) holes: Vec<usize>,
+    /// Generation of each slot, 0 when the slot is removed
+    pub(crate) generations: Vec<u64>,
 
     pub(crate) posbuf_touch: u32,
     pub(crate) colbuf_touch: u32,
@@ -249,7 +666,13 @@ pub(crate) struct QuadsData {
     pub(crate) hidden: bool,
 
     pub(crate) fixed_perspective: Option<Matrix4<f32>>,
+    /// Translation, rotation, and scale are uploaded once per quad
+    pub(crate) instanced: bool,
     pub(crate) holes: Vec<usize>,
+    /// Generation of the layer, 0 once removed
+    pub(crate) generation: u64,
+    /// Generation of each slot, 0 when the slot is removed
+    pub(crate) generations: Vec<u64>,
 
     pub(crate) posbuf_touch: u32,
     pub(crate) colbuf_touch: u32,
@@ -277,12 +700,83 @@ pub(crate) struct QuadsData {
     pub(crate) render_pass: ManuallyDrop<<back::Backend as Backend>::RenderPass>,
 }
 
+/// A layer of arbitrary shapes, see [crate::shapes]
+///
+/// Every shape is a list of triangles stored contiguously in the buffers, one entry per vertex.
+#[derive(Debug)]
+pub(crate) struct ShapesData {
+    pub(crate) hidden: bool,
+
+    pub(crate) fixed_perspective: Option<Matrix4<f32>>,
+    /// Slots of removed shapes, reused by later shapes
+    pub(crate) removed: Vec<usize>,
+    /// Generation of the layer, 0 once removed
+    pub(crate) generation: u64,
+    /// Generation of each slot, 0 when the slot is removed
+    pub(crate) generations: Vec<u64>,
+    /// Vertices of each slot in the buffers, empty when the slot is removed
+    pub(crate) ranges: Vec<std::ops::Range<usize>>,
+
+    pub(crate) posbuf_touch: u32,
+    pub(crate) colbuf_touch: u32,
+    pub(crate) tranbuf_touch: u32,
+    pub(crate) rotbuf_touch: u32,
+    pub(crate) scalebuf_touch: u32,
+
+    pub(crate) posbuffer: Vec<[f32; 2]>,  // 2 per vertex
+    pub(crate) colbuffer: Vec<[u8; 4]>,   // 4 per vertex
+    pub(crate) tranbuffer: Vec<[f32; 2]>, // 2 per vertex
+    pub(crate) rotbuffer: Vec<f32>,       // 1 per vertex
+    pub(crate) scalebuffer: Vec<f32>,     // 1 per vertex
+
+    pub(crate) posbuf: Vec<super::utils::ResizBuf>,
+    pub(crate) colbuf: Vec<super::utils::ResizBuf>,
+    pub(crate) tranbuf: Vec<super::utils::ResizBuf>,
+    pub(crate) rotbuf: Vec<super::utils::ResizBuf>,
+    pub(crate) scalebuf: Vec<super::utils::ResizBuf>,
+
+    pub(crate) descriptor_set: Vec<<back::Backend as Backend>::DescriptorSetLayout>,
+    pub(crate) pipeline: ManuallyDrop<<back::Backend as Backend>::GraphicsPipeline>,
+    pub(crate) pipeline_layout: ManuallyDrop<<back::Backend as Backend>::PipelineLayout>,
+    pub(crate) render_pass: ManuallyDrop<<back::Backend as Backend>::RenderPass>,
+}
+
+impl ShapesData {
+    pub(crate) fn destroy(mut self, device: &back::Device) {
+        for mut posbuf in self.posbuf.drain(..) {
+            posbuf.destroy(&device);
+        }
+        for mut colbuf in self.colbuf.drain(..) {
+            colbuf.destroy(&device);
+        }
+        for mut tranbuf in self.tranbuf.drain(..) {
+            tranbuf.destroy(&device);
+        }
+        for mut rotbuf in self.rotbuf.drain(..) {
+            rotbuf.destroy(&device);
+        }
+        for mut scalebuf in self.scalebuf.drain(..) {
+            scalebuf.destroy(&device);
+        }
+        unsafe {
+            for dsl in self.descriptor_set.drain(..) {
+                device.destroy_descriptor_set_layout(dsl);
+            }
+            device.destroy_graphics_pipeline(ManuallyDrop::into_inner(read(&self.pipeline)));
+            device.destroy_pipeline_layout(ManuallyDrop::into_inner(read(&self.pipeline_layout)));
+            device.destroy_render_pass(ManuallyDrop::into_inner(read(&self.render_pass)));
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub(crate) enum DrawType {
     StreamingTexture { id: usize },
     DynamicTexture { id: usize },
     Quad { id: usize },
     Text { id: usize },
+    Tilemap { id: usize },
+    Shapes { id: usize },
 }
 
 pub(crate) struct LayerHoles {
@@ -334,6 +828,16 @@ pub struct VxDraw {
     pub(crate) strtexs: Vec<StreamingTexture>,
     pub(crate) dyntexs: Vec<DynamicTexture>,
     pub(crate) quads: Vec<QuadsData>,
+    pub(crate) tilemaps: Vec<Tilemap>,
+    pub(crate) shapes: Vec<ShapesData>,
+    pub(crate) rentexs: Vec<Option<RenderTexture>>,
+    pub(crate) postprocs: Vec<Option<PostProcPass>>,
+    pub(crate) postproc_targets: Option<PostProcTargets>,
+    pub(crate) clips: Vec<AnimationClip>,
+    /// Animations keyed by dyntex layer, sprite, and sprite generation
+    pub(crate) animations: HashMap<(usize, usize, u64), SpriteAnimation>,
+    /// Last generation handed out to a layer or object, see `utils::is_current`
+    pub(crate) generation: u64,
     pub(crate) debtris: DebugTriangleData,
     //
     pub(crate) current_frame: usize,
@@ -353,9 +857,10 @@ pub struct VxDraw {
     pub(crate) framebuffers: Vec<<back::Backend as Backend>::Framebuffer>,
     pub(crate) command_buffers: Vec<<back::Backend as Backend>::CommandBuffer>,
     pub(crate) images: Vec<<back::Backend as Backend>::Image>,
+    pub(crate) image_memories: Vec<<back::Backend as Backend>::Memory>,
     pub(crate) image_views: Vec<<back::Backend as Backend>::ImageView>,
     pub(crate) render_pass: ManuallyDrop<<back::Backend as Backend>::RenderPass>,
-    pub(crate) swapchain: ManuallyDrop<<back::Backend as Backend>::Swapchain>,
+    pub(crate) swapchain: Option<<back::Backend as Backend>::Swapchain>,
     pub(crate) swapconfig: gfx_hal::window::SwapchainConfig,
     pub(crate) format: gfx_hal::format::Format,
 
@@ -375,12 +880,12 @@ pub struct VxDraw {
     pub(crate) device: back::Device,
     pub(crate) adapter: Adapter<back::Backend>,
 
-    pub(crate) surf: ManuallyDrop<<back::Backend as Backend>::Surface>,
+    pub(crate) surf: Option<<back::Backend as Backend>::Surface>,
     #[allow(dead_code)]
     #[cfg(not(feature = "gl"))]
     pub(crate) vk_inst: back::Instance,
     #[cfg(not(feature = "gl"))]
-    pub(crate) window: winit::window::Window,
+    pub(crate) window: Option<winit::window::Window>,
 }
 
 // ---
@@ -406,6 +911,14 @@ impl Drop for VxDraw {
             for iv in self.image_views.drain(..) {
                 self.device.destroy_image_view(iv);
             }
+            if self.swapchain.is_none() {
+                for image in self.images.drain(..) {
+                    self.device.destroy_image(image);
+                }
+            }
+            for memory in self.image_memories.drain(..) {
+                self.device.free_memory(memory);
+            }
             self.device.destroy_semaphore(ManuallyDrop::into_inner(read(
                 &self.acquire_image_semaphore_free,
             )));
@@ -486,8 +999,9 @@ impl Drop for VxDraw {
                 .destroy_command_pool(ManuallyDrop::into_inner(read(&self.command_pool)));
             self.device
                 .destroy_render_pass(ManuallyDrop::into_inner(read(&self.render_pass)));
-            self.device
-                .destroy_swapchain(ManuallyDrop::into_inner(read(&self.swapchain)));
+            if let Some(swapchain) = self.swapchain.take() {
+                self.device.destroy_swapchain(swapchain);
+            }
 
             for mut simple_tex in self.dyntexs.drain(..) {
                 for mut indices in simple_tex.indices.drain(..) {
@@ -511,12 +1025,12 @@ impl Drop for VxDraw {
                 for mut scalebuf in simple_tex.scalebuf.drain(..) {
                     scalebuf.destroy(&self.device);
                 }
-                self.device.destroy_image(ManuallyDrop::into_inner(read(
-                    &simple_tex.texture_image_buffer,
-                )));
-                self.device.free_memory(ManuallyDrop::into_inner(read(
-                    &simple_tex.texture_image_memory,
-                )));
+                if let Some(image) = simple_tex.texture_image_buffer.take() {
+                    self.device.destroy_image(image);
+                }
+                if let Some(memory) = simple_tex.texture_image_memory.take() {
+                    self.device.free_memory(memory);
+                }
                 self.device
                     .destroy_render_pass(ManuallyDrop::into_inner(read(&simple_tex.render_pass)));
                 self.device
@@ -588,14 +1102,37 @@ impl Drop for VxDraw {
                 for image_view in strtex.image_view.drain(..) {
                     self.device.destroy_image_view(image_view);
                 }
+                for read in strtex.reads.drain(..) {
+                    read.destroy(&self.device);
+                }
             }
 
             for text in self.texts.drain(..) {
                 text.destroy(&self.device);
             }
 
-            self.vk_inst
-                .destroy_surface(ManuallyDrop::into_inner(read(&self.surf)));
+            for tilemap in self.tilemaps.drain(..) {
+                tilemap.destroy(&self.device);
+            }
+
+            for shapes in self.shapes.drain(..) {
+                shapes.destroy(&self.device);
+            }
+
+            for rentex in self.rentexs.drain(..).flatten() {
+                rentex.destroy(&self.device);
+            }
+
+            for pass in self.postprocs.drain(..).flatten() {
+                pass.destroy(&self.device);
+            }
+            if let Some(targets) = self.postproc_targets.take() {
+                targets.destroy(&self.device);
+            }
+
+            if let Some(surf) = self.surf.take() {
+                self.vk_inst.destroy_surface(surf);
+            }
         }
     }
 }
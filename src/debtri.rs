@@ -36,7 +36,7 @@
 //! #[cfg(not(feature = "doctest-headless"))]
 //! std::thread::sleep(std::time::Duration::new(3, 0));
 //! ```
-use super::{utils::*, Color};
+use super::{utils::*, Color, Error};
 use crate::data::{DebugTriangleData, VxDraw};
 use cgmath::Rad;
 #[cfg(feature = "dx12")]
@@ -56,9 +56,10 @@ use std::{io::Cursor, mem::ManuallyDrop};
 
 /// Handle to a debug triangle
 ///
-/// Used to update/remove a debug triangle.
+/// Used to update/remove a debug triangle. The handle becomes stale when the triangle is removed,
+/// using a stale handle panics in debug builds and is ignored in release builds.
 #[derive(Debug)]
-pub struct Handle(usize);
+pub struct Handle(usize, u64);
 
 /// Information used when creating/updating a debug triangle
 #[derive(Clone, Copy, Debug)]
@@ -206,6 +207,9 @@ impl<'a> Debtri<'a> {
     /// and so on). The effect of this is that the draw order is swapped too, meaning that the
     /// triangles reverse order (one drawn on top of the other).
     pub fn swap_draw_order(&mut self, left: &mut Handle, right: &mut Handle) {
+        if !self.check(left) || !self.check(right) {
+            return;
+        }
         let debtris = &mut self.vx.debtris;
 
         debtris.posbuffer.swap(left.0, right.0);
@@ -220,7 +224,7 @@ impl<'a> Debtri<'a> {
         debtris.rotbuf_touch = self.vx.swapconfig.image_count;
         debtris.scalebuf_touch = self.vx.swapconfig.image_count;
 
-        std::mem::swap(&mut left.0, &mut right.0);
+        std::mem::swap(left, right);
     }
 
     /// Enable drawing of the debug triangles
@@ -247,6 +251,7 @@ impl<'a> Debtri<'a> {
     /// [Debtri::remove] has been called earlier. In general, use [Debtri::compare_draw_order]
     /// and [Debtri::swap_draw_order] to enforce drawing order if that's needed.
     pub fn add(&mut self, triangle: DebugTriangle) -> Handle {
+        let generation = self.vx.next_generation();
         let debtris = &mut self.vx.debtris;
 
         let handle = if let Some(hole) = debtris.holes.pop() {
@@ -292,7 +297,8 @@ impl<'a> Debtri<'a> {
                 triangle.scale,
                 triangle.scale,
             ]);
-            Handle(hole)
+            debtris.generations[hole] = generation;
+            Handle(hole, generation)
         } else {
             debtris.posbuffer.push([
                 triangle.origin[0].0,
@@ -331,7 +337,8 @@ impl<'a> Debtri<'a> {
             debtris
                 .scalebuffer
                 .push([triangle.scale, triangle.scale, triangle.scale]);
-            Handle(debtris.posbuffer.len() - 1)
+            debtris.generations.push(generation);
+            Handle(debtris.posbuffer.len() - 1, generation)
         };
 
         debtris.posbuf_touch = self.vx.swapconfig.image_count;
@@ -358,6 +365,7 @@ impl<'a> Debtri<'a> {
         debtris.tranbuffer.pop();
         debtris.rotbuffer.pop();
         debtris.scalebuffer.pop();
+        debtris.generations.pop();
     }
 
     /// Remove the last N added debug triangle from rendering
@@ -374,6 +382,7 @@ impl<'a> Debtri<'a> {
         debtris.tranbuffer.drain(begin..end);
         debtris.rotbuffer.drain(begin..end);
         debtris.scalebuffer.drain(begin..end);
+        debtris.generations.drain(begin..end);
     }
 
     /// Remove a debug triangle
@@ -382,8 +391,28 @@ impl<'a> Debtri<'a> {
     /// `holes`. Calling [Debtri::add] with available holes will fill the first available hole
     /// with the new triangle.
     pub fn remove(&mut self, handle: Handle) {
+        if !self.check(&handle) {
+            return;
+        }
         self.vx.debtris.holes.push(handle.0);
         self.set_scale(&handle, 0.0);
+        self.vx.debtris.generations[handle.0] = 0;
+    }
+
+    /// Check if a handle still refers to a triangle that has not been removed or popped
+    pub fn is_alive(&self, handle: &Handle) -> bool {
+        self.vx.debtris.generations.get(handle.0) == Some(&handle.1)
+    }
+
+    fn check(&self, handle: &Handle) -> bool {
+        let current = self
+            .vx
+            .debtris
+            .generations
+            .get(handle.0)
+            .cloned()
+            .unwrap_or(0);
+        is_current(&self.vx.log, current, handle.1)
     }
 
     // ---
@@ -394,6 +423,9 @@ impl<'a> Debtri<'a> {
     /// What this function does is just setting absolute vertex positions for each vertex in the
     /// triangle.
     pub fn set_deform(&mut self, handle: &Handle, points: [(f32, f32); 3]) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.debtris.posbuf_touch = self.vx.swapconfig.image_count;
         let vertex = &mut self.vx.debtris.posbuffer[handle.0];
         vertex[0] = points[0].0;
@@ -406,6 +438,9 @@ impl<'a> Debtri<'a> {
 
     /// Set a solid color of a debug triangle
     pub fn set_color(&mut self, handle: &Handle, rgba: Color) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.debtris.colbuf_touch = self.vx.swapconfig.image_count;
         for vtx in 0..3 {
             let Color::Rgba(r, g, b, a) = rgba;
@@ -422,6 +457,9 @@ impl<'a> Debtri<'a> {
     /// `translate_all` consistent. This function can purely be thought of as setting the position
     /// of the triangle with respect to the model-space's origin.
     pub fn set_translation(&mut self, handle: &Handle, pos: (f32, f32)) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.debtris.tranbuf_touch = self.vx.swapconfig.image_count;
         for vtx in 0..3 {
             self.vx.debtris.tranbuffer[handle.0][vtx * 2] = pos.0;
@@ -433,6 +471,9 @@ impl<'a> Debtri<'a> {
     ///
     /// The rotation is about the model space origin.
     pub fn set_rotation<T: Copy + Into<Rad<f32>>>(&mut self, handle: &Handle, deg: T) {
+        if !self.check(handle) {
+            return;
+        }
         let angle = deg.into().0;
         self.vx.debtris.rotbuf_touch = self.vx.swapconfig.image_count;
         self.vx.debtris.rotbuffer[handle.0].copy_from_slice(&[angle, angle, angle]);
@@ -440,6 +481,9 @@ impl<'a> Debtri<'a> {
 
     /// Set the scale of a debug triangle
     pub fn set_scale(&mut self, handle: &Handle, scale: f32) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.debtris.scalebuf_touch = self.vx.swapconfig.image_count;
         for sc in self.vx.debtris.scalebuffer[handle.0].iter_mut() {
             *sc = scale;
@@ -452,6 +496,9 @@ impl<'a> Debtri<'a> {
     ///
     /// Adds the delta vertices to the debug triangle. Beware: This changes model space form.
     pub fn deform(&mut self, handle: &Handle, delta: [(f32, f32); 3]) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.debtris.posbuf_touch = self.vx.swapconfig.image_count;
         let trn = &mut self.vx.debtris.posbuffer[handle.0];
         trn[0] += delta[0].0;
@@ -468,6 +515,9 @@ impl<'a> Debtri<'a> {
     /// needs to be able to add and subtract the color components. Internally the RGBA u8 color
     /// values are converted to [i16] and then cast back to [u8] using clamping.
     pub fn color(&mut self, handle: &Handle, color: [i16; 4]) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.debtris.tranbuf_touch = self.vx.swapconfig.image_count;
         for cols in self.vx.debtris.colbuffer[handle.0].chunks_exact_mut(4) {
             for (idx, color) in color.iter().enumerate() {
@@ -481,6 +531,9 @@ impl<'a> Debtri<'a> {
     ///
     /// Translation does not mutate the model-space of a triangle.
     pub fn translate(&mut self, handle: &Handle, delta: (f32, f32)) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.debtris.tranbuf_touch = self.vx.swapconfig.image_count;
         for stride in 0..3 {
             self.vx.debtris.tranbuffer[handle.0][stride * 2] += delta.0;
@@ -492,6 +545,9 @@ impl<'a> Debtri<'a> {
     ///
     /// Rotation does not mutate the model-space of a triangle.
     pub fn rotate<T: Copy + Into<Rad<f32>>>(&mut self, handle: &Handle, deg: T) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.debtris.rotbuf_touch = self.vx.swapconfig.image_count;
         for rot in &mut self.vx.debtris.rotbuffer[handle.0] {
             *rot += deg.into().0;
@@ -502,6 +558,9 @@ impl<'a> Debtri<'a> {
     ///
     /// Scale does not mutate the model-space of a triangle.
     pub fn scale(&mut self, handle: &Handle, scale: f32) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.debtris.scalebuf_touch = self.vx.swapconfig.image_count;
         for sc in self.vx.debtris.scalebuffer[handle.0].iter_mut() {
             *sc *= scale;
@@ -667,7 +726,7 @@ pub(crate) fn create_debug_triangle(
     adapter: &Adapter<back::Backend>,
     format: format::Format,
     image_count: usize,
-) -> DebugTriangleData {
+) -> Result<DebugTriangleData, Error> {
     pub const VERTEX_SOURCE: &[u8] = include_bytes!["../target/spirv/debtri.vert.spirv"];
     pub const FRAGMENT_SOURCE: &[u8] = include_bytes!["../target/spirv/debtri.frag.spirv"];
 
@@ -899,24 +958,25 @@ pub(crate) fn create_debug_triangle(
 
     let posbuf = (0..image_count)
         .map(|_| super::utils::ResizBuf::new(&device, &adapter))
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>, _>>()?;
     let colbuf = (0..image_count)
         .map(|_| super::utils::ResizBuf::new(&device, &adapter))
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>, _>>()?;
     let tranbuf = (0..image_count)
         .map(|_| super::utils::ResizBuf::new(&device, &adapter))
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>, _>>()?;
     let rotbuf = (0..image_count)
         .map(|_| super::utils::ResizBuf::new(&device, &adapter))
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>, _>>()?;
     let scalebuf = (0..image_count)
         .map(|_| super::utils::ResizBuf::new(&device, &adapter))
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>, _>>()?;
 
-    DebugTriangleData {
+    Ok(DebugTriangleData {
         hidden: false,
 
         holes: vec![],
+        generations: vec![],
 
         posbuf_touch: 0,
         colbuf_touch: 0,
@@ -940,7 +1000,7 @@ pub(crate) fn create_debug_triangle(
         pipeline: ManuallyDrop::new(triangle_pipeline),
         pipeline_layout: ManuallyDrop::new(triangle_pipeline_layout),
         render_pass: ManuallyDrop::new(triangle_render_pass),
-    }
+    })
 }
 
 #[cfg(test)]
@@ -1080,6 +1140,23 @@ mod tests {
         utils::assert_swapchain_eq(&mut vx, "fill_remove_hole", img);
     }
 
+    #[test]
+    fn popped_triangle_handle_is_stale() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (100, 100));
+        let mut debtri = vx.debtri();
+
+        let first = debtri.add(DebugTriangle::default());
+        let second = debtri.add(DebugTriangle::default());
+        debtri.pop();
+        assert![debtri.is_alive(&first)];
+        assert![!debtri.is_alive(&second)];
+
+        let third = debtri.add(DebugTriangle::default());
+        assert![debtri.is_alive(&third)];
+        assert![!debtri.is_alive(&second)];
+    }
+
     #[test]
     fn removing_iterates_minus_one() {
         let logger = Logger::root(Discard, o!());
@@ -28,9 +28,10 @@
 //! #[cfg(not(feature = "doctest-headless"))]
 //! std::thread::sleep(std::time::Duration::new(3, 0));
 //! ```
-use super::{blender, utils::*, Color};
+use super::{animation, blender, rentex, utils::*, Color};
 use crate::data::{DrawType, DynamicTexture, VxDraw};
 use ::image as load_image;
+use arrayvec::ArrayVec;
 use cgmath::{Matrix4, Rad};
 use core::ptr::read;
 #[cfg(feature = "dx12")]
@@ -54,27 +55,25 @@ use gfx_hal::{
     queue::CommandQueue,
     Backend,
 };
-use std::{io::Cursor, mem::ManuallyDrop};
+use std::{collections::HashMap, io::Cursor, mem::ManuallyDrop};
 
 // ---
 
 /// A view into a texture (a sprite)
-pub struct Handle(usize, usize);
+///
+/// The handle becomes stale when the sprite or its layer is removed, using a stale handle panics
+/// in debug builds and is ignored in release builds.
+pub struct Handle(pub(crate) usize, pub(crate) usize, pub(crate) u64);
 
 /// Handle to a layer (a single texture)
-pub struct Layer(usize);
+pub struct Layer(usize, u64);
 
 impl Layerable for Layer {
-    fn get_layer(&self, vx: &VxDraw) -> usize {
-        for (idx, ord) in vx.draw_order.iter().enumerate() {
-            match ord {
-                DrawType::DynamicTexture { id } if *id == self.0 => {
-                    return idx;
-                }
-                _ => {}
-            }
-        }
-        panic!["Unable to get layer"]
+    fn get_layer(&self, vx: &VxDraw) -> Option<LayerPosition> {
+        vx.find_in_draw_order(|ord| match ord {
+            DrawType::DynamicTexture { id } => *id == self.0,
+            _ => false,
+        })
     }
 }
 
@@ -114,6 +113,8 @@ pub struct LayerOptions {
     vertex_shader: VertexShader,
     /// The fragment shader for this layer
     fragment_shader: FragmentShader,
+    /// Upload one record per sprite and expand the quad in the vertex shader
+    instanced: bool,
 }
 
 impl LayerOptions {
@@ -165,6 +166,16 @@ impl LayerOptions {
         self.blend = blend_setter(self.blend);
         self
     }
+
+    /// Draw the sprites of this layer using instancing (default is off)
+    ///
+    /// Translation, rotation, and scale are uploaded once per sprite instead of once per vertex,
+    /// which cuts the upload size of layers with many moving sprites. A custom vertex shader must
+    /// take the per-instance inputs of `shaders/dyntex_instanced.vert`.
+    pub fn instanced(mut self, instanced: bool) -> Self {
+        self.instanced = instanced;
+        self
+    }
 }
 
 impl Default for LayerOptions {
@@ -177,6 +188,7 @@ impl Default for LayerOptions {
             blend: blender::Blender::default(),
             vertex_shader: VertexShader::Standard,
             fragment_shader: FragmentShader::Standard,
+            instanced: false,
         }
     }
 }
@@ -288,6 +300,13 @@ impl Sprite {
         self.origin = origin;
         self
     }
+
+    /// Set the UV coordinates to view a region of an atlas
+    pub fn region(mut self, region: Region) -> Self {
+        self.uv_begin = region.uv_begin;
+        self.uv_end = region.uv_end;
+        self
+    }
 }
 
 impl Default for Sprite {
@@ -306,6 +325,192 @@ impl Default for Sprite {
     }
 }
 
+/// Nine-slice sprite builder
+///
+/// A nine-slice sprite is a rectangular panel made of 9 sprites. The texture is cut along its
+/// border insets into corners, edges, and a center. When the panel is resized the corners keep
+/// their size while the edges and the center stretch, see [Dyntex::add_nine_slice].
+#[derive(Clone, Copy)]
+pub struct NineSlice {
+    width: f32,
+    height: f32,
+    insets: [u32; 4],
+    border_scale: f32,
+    opacity: [u8; 4],
+    origin: (f32, f32),
+    rotation: f32,
+    scale: f32,
+    translation: (f32, f32),
+    uv_begin: (f32, f32),
+    uv_end: (f32, f32),
+}
+
+impl NineSlice {
+    /// Same as default
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the width of the panel
+    pub fn width(mut self, width: f32) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Set the height of the panel
+    pub fn height(mut self, height: f32) -> Self {
+        self.height = height;
+        self
+    }
+
+    /// Set the border insets in texture pixels, in the order left, top, right, bottom
+    pub fn insets(mut self, left: u32, top: u32, right: u32, bottom: u32) -> Self {
+        self.insets = [left, top, right, bottom];
+        self
+    }
+
+    /// Set the size of a single texture pixel of the border in the world (default is 0.01)
+    pub fn border_scale(mut self, scale: f32) -> Self {
+        self.border_scale = scale;
+        self
+    }
+
+    /// Set the opacity of the panel
+    pub fn opacity(mut self, opacity: u8) -> Self {
+        self.opacity = [opacity; 4];
+        self
+    }
+
+    /// Set the topleft corner's UV coordinates
+    pub fn uv_begin(mut self, uv: (f32, f32)) -> Self {
+        self.uv_begin = uv;
+        self
+    }
+
+    /// Set the bottom right corner's UV coordinates
+    pub fn uv_end(mut self, uv: (f32, f32)) -> Self {
+        self.uv_end = uv;
+        self
+    }
+
+    /// Set the UV coordinates to view a region of an atlas
+    pub fn region(mut self, region: Region) -> Self {
+        self.uv_begin = region.uv_begin;
+        self.uv_end = region.uv_end;
+        self
+    }
+
+    /// Set the translation
+    pub fn translation(mut self, trn: (f32, f32)) -> Self {
+        self.translation = trn;
+        self
+    }
+
+    /// Set the rotation. Rotation is counter-clockwise
+    pub fn rotation<T: Copy + Into<Rad<f32>>>(mut self, angle: T) -> Self {
+        self.rotation = angle.into().0;
+        self
+    }
+
+    /// Set the scaling factor of the panel
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Set the origin of the panel
+    pub fn origin(mut self, origin: (f32, f32)) -> Self {
+        self.origin = origin;
+        self
+    }
+}
+
+impl Default for NineSlice {
+    fn default() -> Self {
+        NineSlice {
+            width: 2.0,
+            height: 2.0,
+            insets: [0; 4],
+            border_scale: 0.01,
+            opacity: [255; 4],
+            origin: (0.0, 0.0),
+            rotation: 0.0,
+            scale: 1.0,
+            translation: (0.0, 0.0),
+            uv_begin: (0.0, 0.0),
+            uv_end: (1.0, 1.0),
+        }
+    }
+}
+
+/// Handle to the 9 sprites of a nine-slice panel
+///
+/// The sprites are ordered row by row from the top left corner. They can be changed with the
+/// usual sprite functions, for instance to move the panel by translating all of them.
+pub struct NineSliceHandle {
+    sprites: [Handle; 9],
+    /// World size of the borders, left, top, right, bottom
+    borders: [f32; 4],
+    origin: (f32, f32),
+}
+
+impl NineSliceHandle {
+    /// Get the handles of the 9 sprites
+    pub fn sprites(&self) -> &[Handle; 9] {
+        &self.sprites
+    }
+
+    /// Update the sprites of the panel with the moves returned by [Dyntex::compact]
+    ///
+    /// Sprites that were not moved keep their handles.
+    pub fn remap(&mut self, moves: &[(Handle, Handle)]) {
+        for sprite in self.sprites.iter_mut() {
+            let new = moves
+                .iter()
+                .find(|(old, _)| old.0 == sprite.0 && old.1 == sprite.1 && old.2 == sprite.2);
+            if let Some((_, new)) = new {
+                *sprite = Handle(new.0, new.1, new.2);
+            }
+        }
+    }
+}
+
+/// Split a panel of the given size into the model space corners of its 9 quads
+///
+/// Borders that do not fit in the panel are shrunk proportionally.
+fn nine_slice_quads(
+    size: (f32, f32),
+    borders: [f32; 4],
+    origin: (f32, f32),
+) -> [[(f32, f32); 4]; 9] {
+    let fit = |length: f32, before: f32, after: f32| {
+        let total = before + after;
+        let shrink = if total > length.abs() && total > 0.0 {
+            length.abs() / total
+        } else {
+            1.0
+        };
+        let start = -length / 2.0;
+        [
+            start,
+            start + before * shrink,
+            start + length - after * shrink,
+            start + length,
+        ]
+    };
+    let xs = fit(size.0, borders[0], borders[2]);
+    let ys = fit(size.1, borders[1], borders[3]);
+    let mut quads = [[(0.0, 0.0); 4]; 9];
+    for row in 0..3 {
+        for column in 0..3 {
+            let (left, right) = (xs[column] - origin.0, xs[column + 1] - origin.0);
+            let (top, bottom) = (ys[row] - origin.1, ys[row + 1] - origin.1);
+            quads[row * 3 + column] = [(left, top), (left, bottom), (right, bottom), (right, top)];
+        }
+    }
+    quads
+}
+
 /// Specify the type of incoming texture data
 pub enum ImgData<'a> {
     /// Raw PNG bytes, no size is needed as this is included in the bytestream
@@ -322,6 +527,323 @@ pub enum ImgData<'a> {
     },
 }
 
+impl<'a> ImgData<'a> {
+    /// Decode into width, height, and RGBA8 bytes
+    pub(crate) fn decode(&self) -> (usize, usize, Vec<u8>) {
+        match self {
+            ImgData::PNGBytes(ref bytes) => {
+                let image = load_image::load_from_memory_with_format(&bytes[..], load_image::PNG)
+                    .unwrap()
+                    .to_rgba();
+                let (width, height) = (image.width() as usize, image.height() as usize);
+                (width, height, image.into_raw())
+            }
+            ImgData::RawBytes {
+                width,
+                height,
+                bytes,
+            } => (*width, *height, bytes.to_vec()),
+        }
+    }
+}
+
+// ---
+
+/// Identifier of a region in an [Atlas], assigned in the order the images were added
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RegionId(pub usize);
+
+/// A rectangular part of an atlas holding a single packed image
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Region {
+    /// Topleft UV coordinate of the image inside the atlas
+    pub uv_begin: (f32, f32),
+    /// Bottom right UV coordinate of the image inside the atlas
+    pub uv_end: (f32, f32),
+    /// Size of the image in pixels
+    pub size: (u32, u32),
+}
+
+/// Builder that packs many images into a single texture
+///
+/// Sprites created from the regions of an atlas all share one layer, and thus one draw call. See
+/// [Dyntex::add_layer_atlas].
+pub struct AtlasBuilder<'a> {
+    images: Vec<(String, &'a ImgData<'a>)>,
+    padding: u32,
+}
+
+impl<'a> AtlasBuilder<'a> {
+    /// Same as default
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a named image to the atlas
+    ///
+    /// If a name is used twice, the name refers to the last image added.
+    pub fn add(mut self, name: &str, img_data: &'a ImgData<'a>) -> Self {
+        self.images.push((name.to_string(), img_data));
+        self
+    }
+
+    /// Set the amount of empty pixels between images
+    ///
+    /// Padding prevents neighbouring images from bleeding into each other when using linear
+    /// filtering.
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Pack all images into a single RGBA8 image
+    fn build(&self) -> (Atlas, Vec<u8>) {
+        let decoded = self
+            .images
+            .iter()
+            .map(|(_, img_data)| img_data.decode())
+            .collect::<Vec<_>>();
+        let sizes = decoded
+            .iter()
+            .map(|(width, height, _)| (*width as u32, *height as u32))
+            .collect::<Vec<_>>();
+        let ((width, height), positions) = pack_shelves(&sizes, self.padding);
+
+        let mut pixels = vec![0u8; width as usize * height as usize * 4];
+        for ((img_width, img_height, bytes), (x, y)) in decoded.iter().zip(positions.iter()) {
+            if bytes.is_empty() {
+                continue;
+            }
+            let mut idx = 0;
+            for row in 0..*img_height {
+                let dest_base = ((*y as usize + row) * width as usize + *x as usize) * 4;
+                for byte in &mut pixels[dest_base..dest_base + img_width * 4] {
+                    *byte = bytes[idx % bytes.len()];
+                    idx += 1;
+                }
+            }
+        }
+
+        let regions = sizes
+            .iter()
+            .zip(positions.iter())
+            .map(|((w, h), (x, y))| Region {
+                uv_begin: (*x as f32 / width as f32, *y as f32 / height as f32),
+                uv_end: (
+                    (x + w) as f32 / width as f32,
+                    (y + h) as f32 / height as f32,
+                ),
+                size: (*w, *h),
+            })
+            .collect();
+        let names = self
+            .images
+            .iter()
+            .enumerate()
+            .map(|(idx, (name, _))| (name.clone(), RegionId(idx)))
+            .collect();
+
+        (
+            Atlas {
+                size: (width, height),
+                regions,
+                names,
+            },
+            pixels,
+        )
+    }
+}
+
+impl<'a> Default for AtlasBuilder<'a> {
+    fn default() -> Self {
+        Self {
+            images: vec![],
+            padding: 1,
+        }
+    }
+}
+
+/// Named regions of a packed atlas, returned by [Dyntex::add_layer_atlas]
+pub struct Atlas {
+    size: (u32, u32),
+    regions: Vec<Region>,
+    names: HashMap<String, RegionId>,
+}
+
+impl Atlas {
+    /// Get the size of the atlas texture in pixels
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Get the amount of regions in the atlas
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// Check if the atlas has no regions
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// Look up the identifier of a named region
+    pub fn id(&self, name: &str) -> Option<RegionId> {
+        self.names.get(name).cloned()
+    }
+
+    /// Look up a named region
+    pub fn get(&self, name: &str) -> Option<Region> {
+        self.id(name).map(|id| self.region(id))
+    }
+
+    /// Get a region by its identifier
+    pub fn region(&self, id: RegionId) -> Region {
+        self.regions[id.0]
+    }
+
+    /// Create a sprite viewing a named region, panics if the name does not exist
+    pub fn sprite(&self, name: &str) -> Sprite {
+        Sprite::new().region(self.get(name).expect("Region does not exist in atlas"))
+    }
+}
+
+/// Pack rectangles into rows, tallest first, returning the total size and the topleft corner of
+/// each rectangle in input order
+fn pack_shelves(sizes: &[(u32, u32)], padding: u32) -> ((u32, u32), Vec<(u32, u32)>) {
+    let area: u64 = sizes
+        .iter()
+        .map(|(w, h)| u64::from(w + padding) * u64::from(h + padding))
+        .sum();
+    let widest = sizes.iter().map(|(w, _)| *w).max().unwrap_or(1).max(1);
+    let width = ((area as f64).sqrt().ceil() as u32)
+        .next_power_of_two()
+        .max(widest);
+
+    let mut order = (0..sizes.len()).collect::<Vec<_>>();
+    order.sort_by(|a, b| sizes[*b].1.cmp(&sizes[*a].1));
+
+    let mut positions = vec![(0, 0); sizes.len()];
+    let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+    for idx in order {
+        let (w, h) = sizes[idx];
+        if x > 0 && x + w > width {
+            x = 0;
+            y += shelf_height + padding;
+            shelf_height = 0;
+        }
+        positions[idx] = (x, y);
+        x += w + padding;
+        shelf_height = shelf_height.max(h);
+    }
+    ((width, (y + shelf_height).max(1)), positions)
+}
+
+/// Vertex input of an instanced layer, every binding advances once per sprite
+///
+/// Positions and UVs keep their 4 corners per sprite, translation, rotation, and scale are
+/// uploaded once per sprite.
+fn instanced_vertex_input() -> (Vec<pso::VertexBufferDesc>, Vec<pso::AttributeDesc>) {
+    let vertex_buffers = vec![
+        pso::VertexBufferDesc {
+            binding: 0,
+            stride: 32,
+            rate: pso::VertexInputRate::Instance(1),
+        },
+        pso::VertexBufferDesc {
+            binding: 1,
+            stride: 32,
+            rate: pso::VertexInputRate::Instance(1),
+        },
+        pso::VertexBufferDesc {
+            binding: 2,
+            stride: 8,
+            rate: pso::VertexInputRate::Instance(1),
+        },
+        pso::VertexBufferDesc {
+            binding: 3,
+            stride: 4,
+            rate: pso::VertexInputRate::Instance(1),
+        },
+        pso::VertexBufferDesc {
+            binding: 4,
+            stride: 4,
+            rate: pso::VertexInputRate::Instance(1),
+        },
+        pso::VertexBufferDesc {
+            binding: 5,
+            stride: 4,
+            rate: pso::VertexInputRate::Instance(1),
+        },
+    ];
+    let attributes = vec![
+        pso::AttributeDesc {
+            location: 0,
+            binding: 0,
+            element: pso::Element {
+                format: format::Format::Rgba32Sfloat,
+                offset: 0,
+            },
+        },
+        pso::AttributeDesc {
+            location: 1,
+            binding: 0,
+            element: pso::Element {
+                format: format::Format::Rgba32Sfloat,
+                offset: 16,
+            },
+        },
+        pso::AttributeDesc {
+            location: 2,
+            binding: 1,
+            element: pso::Element {
+                format: format::Format::Rgba32Sfloat,
+                offset: 0,
+            },
+        },
+        pso::AttributeDesc {
+            location: 3,
+            binding: 1,
+            element: pso::Element {
+                format: format::Format::Rgba32Sfloat,
+                offset: 16,
+            },
+        },
+        pso::AttributeDesc {
+            location: 4,
+            binding: 2,
+            element: pso::Element {
+                format: format::Format::Rg32Sfloat,
+                offset: 0,
+            },
+        },
+        pso::AttributeDesc {
+            location: 5,
+            binding: 3,
+            element: pso::Element {
+                format: format::Format::R32Sfloat,
+                offset: 0,
+            },
+        },
+        pso::AttributeDesc {
+            location: 6,
+            binding: 4,
+            element: pso::Element {
+                format: format::Format::R32Sfloat,
+                offset: 0,
+            },
+        },
+        pso::AttributeDesc {
+            location: 7,
+            binding: 5,
+            element: pso::Element {
+                format: format::Format::Rgba8Unorm,
+                offset: 0,
+            },
+        },
+    ];
+    (vertex_buffers, attributes)
+}
+
 // ---
 
 /// Accessor object to all dynamic textures
@@ -355,21 +877,24 @@ impl<'a> Dyntex<'a> {
     /// To make sure transparency works correctly you can turn off the depth test for foreground
     /// objects and ensure that the foreground texture is allocated last.
     pub fn add_layer<'x>(&mut self, img_data: &ImgData<'x>, options: &LayerOptions) -> Layer {
-        match img_data {
-            ImgData::PNGBytes(ref bytes) => {
-                let image = load_image::load_from_memory_with_format(&bytes[..], load_image::PNG)
-                    .unwrap()
-                    .to_rgba();
-                let (width, height) = (image.width() as usize, image.height() as usize);
-                let img_bytes = image.into_raw();
-                self.add_layer_internal(width, height, &img_bytes[..], &options)
-            }
-            ImgData::RawBytes {
-                width,
-                height,
-                bytes,
-            } => self.add_layer_internal(*width, *height, bytes, options),
-        }
+        let (width, height, img_bytes) = img_data.decode();
+        self.add_layer_internal(width, height, &img_bytes[..], options)
+    }
+
+    /// Pack many images into a single texture (layer)
+    ///
+    /// Returns the layer and the [Atlas] describing where each named image ended up, use
+    /// [Sprite::region] or [Atlas::sprite] to create sprites showing a single image. Since all
+    /// images share one layer, their sprites are drawn in a single draw call.
+    pub fn add_layer_atlas(
+        &mut self,
+        atlas: &AtlasBuilder,
+        options: &LayerOptions,
+    ) -> (Layer, Atlas) {
+        let (atlas, pixels) = atlas.build();
+        let (width, height) = atlas.size();
+        let layer = self.add_layer_internal(width as usize, height as usize, &pixels[..], options);
+        (layer, atlas)
     }
 
     fn add_layer_internal(
@@ -379,82 +904,38 @@ impl<'a> Dyntex<'a> {
         img: &[u8],
         options: &LayerOptions,
     ) -> Layer {
-        let s = &mut *self.vx;
-        let device = &s.device;
-
-        let pixel_size = 4; //size_of::<image::Rgba<u8>>();
-        let row_size = pixel_size * img_width;
-        let limits = s.adapter.physical_device.limits();
-        let row_alignment_mask = limits.optimal_buffer_copy_pitch_alignment as u32 - 1;
-        let row_pitch = ((row_size as u32 + row_alignment_mask) & !row_alignment_mask) as usize;
-        debug_assert!(row_pitch as usize >= row_size);
-        let required_bytes = row_pitch * img_height;
-
-        let mut image_upload_buffer = unsafe {
-            device.create_buffer(required_bytes as u64, gfx_hal::buffer::Usage::TRANSFER_SRC)
-        }
-        .unwrap();
-        let image_mem_reqs = unsafe { device.get_buffer_requirements(&image_upload_buffer) };
-        let memory_type_id = find_memory_type_id(
-            &s.adapter,
-            image_mem_reqs,
-            Properties::CPU_VISIBLE | Properties::COHERENT,
-        );
-        let image_upload_memory =
-            unsafe { device.allocate_memory(memory_type_id, image_mem_reqs.size) }.unwrap();
-        unsafe { device.bind_buffer_memory(&image_upload_memory, 0, &mut image_upload_buffer) }
-            .unwrap();
-
-        unsafe {
-            let writer = s
-                .device
-                .map_memory(&image_upload_memory, 0..image_mem_reqs.size)
-                .expect("Unable to get mapping writer");
-            let mut idx = 0;
-            for y in 0..img_height {
-                // let row = &(*img)[y * row_size..(y + 1) * row_size];
-                let dest_base = y * row_pitch;
-                for row_index in 0..row_size {
-                    std::slice::from_raw_parts_mut(writer, image_mem_reqs.size as usize)
-                        [dest_base + row_index] = img[idx % img.len()];
-                    idx += 1;
-                }
-            }
-            device.unmap_memory(&image_upload_memory);
-        }
-
-        let mut the_image = unsafe {
-            device
-                .create_image(
-                    image::Kind::D2(img_width as u32, img_height as u32, 1, 1),
-                    1,
-                    format::Format::Rgba8Srgb,
-                    image::Tiling::Optimal,
-                    image::Usage::TRANSFER_DST | image::Usage::SAMPLED,
-                    image::ViewCapabilities::empty(),
-                )
-                .expect("Couldn't create the image!")
-        };
+        let (the_image, image_memory, image_view) =
+            upload_texture(self.vx, img_width, img_height, img);
+        self.add_layer_with_view(
+            Some((the_image, image_memory)),
+            image_view,
+            (img_width as u32, img_height as u32),
+            options,
+        )
+    }
 
-        let image_memory = unsafe {
-            let requirements = device.get_image_requirements(&the_image);
-            let memory_type_id =
-                find_memory_type_id(&s.adapter, requirements, memory::Properties::DEVICE_LOCAL);
-            device
-                .allocate_memory(memory_type_id, requirements.size)
-                .expect("Unable to allocate")
+    /// Add a layer that samples a render texture (see [crate::rentex])
+    ///
+    /// The sprites of this layer show whatever was drawn into the render texture during the same
+    /// frame. The layer must not be attached to the render texture it samples. Removing the render
+    /// texture removes this layer too.
+    pub fn add_layer_from_rentex(
+        &mut self,
+        rentex: &rentex::Layer,
+        options: &LayerOptions,
+    ) -> Layer {
+        let s = &mut *self.vx;
+        let target = match s.rentexs[rentex.0] {
+            Some(ref target) if is_current(&s.log, target.generation, rentex.1) => target,
+            _ => panic!["Render texture has been removed"],
         };
-
+        let size = (target.width, target.height);
         let image_view = unsafe {
-            device
-                .bind_image_memory(&image_memory, 0, &mut the_image)
-                .expect("Unable to bind memory");
-
-            device
+            s.device
                 .create_image_view(
-                    &the_image,
+                    &target.image,
                     image::ViewKind::D2,
-                    format::Format::Rgba8Srgb,
+                    s.format,
                     format::Swizzle::NO,
                     image::SubresourceRange {
                         aspects: format::Aspects::COLOR,
@@ -464,6 +945,22 @@ impl<'a> Dyntex<'a> {
                 )
                 .expect("Couldn't create the image view!")
         };
+        let layer = self.add_layer_with_view(None, image_view, size, options);
+        self.vx.dyntexs[layer.0].rentex = Some(rentex.0);
+        layer
+    }
+
+    fn add_layer_with_view(
+        &mut self,
+        texture: Option<(
+            <back::Backend as Backend>::Image,
+            <back::Backend as Backend>::Memory,
+        )>,
+        image_view: <back::Backend as Backend>::ImageView,
+        texture_size: (u32, u32),
+        options: &LayerOptions,
+    ) -> Layer {
+        let s = &mut *self.vx;
 
         let sampler = unsafe {
             s.device
@@ -481,96 +978,17 @@ impl<'a> Dyntex<'a> {
                 .expect("Couldn't create the sampler!")
         };
 
-        unsafe {
-            let mut cmd_buffer = s
-                .command_pool
-                .allocate_one(gfx_hal::command::Level::Primary);
-            cmd_buffer.begin_primary(CommandBufferFlags::EMPTY);
-            let image_barrier = memory::Barrier::Image {
-                states: (image::Access::empty(), image::Layout::Undefined)
-                    ..(
-                        image::Access::TRANSFER_WRITE,
-                        image::Layout::TransferDstOptimal,
-                    ),
-                target: &the_image,
-                families: None,
-                range: image::SubresourceRange {
-                    aspects: format::Aspects::COLOR,
-                    levels: 0..1,
-                    layers: 0..1,
-                },
-            };
-            cmd_buffer.pipeline_barrier(
-                pso::PipelineStage::TOP_OF_PIPE..pso::PipelineStage::TRANSFER,
-                memory::Dependencies::empty(),
-                &[image_barrier],
-            );
-            cmd_buffer.copy_buffer_to_image(
-                &image_upload_buffer,
-                &the_image,
-                image::Layout::TransferDstOptimal,
-                &[command::BufferImageCopy {
-                    buffer_offset: 0,
-                    buffer_width: (row_pitch / pixel_size) as u32,
-                    buffer_height: img_height as u32,
-                    image_layers: gfx_hal::image::SubresourceLayers {
-                        aspects: format::Aspects::COLOR,
-                        level: 0,
-                        layers: 0..1,
-                    },
-                    image_offset: image::Offset { x: 0, y: 0, z: 0 },
-                    image_extent: image::Extent {
-                        width: img_width as u32,
-                        height: img_height as u32,
-                        depth: 1,
-                    },
-                }],
-            );
-            let image_barrier = memory::Barrier::Image {
-                states: (
-                    image::Access::TRANSFER_WRITE,
-                    image::Layout::TransferDstOptimal,
-                )
-                    ..(
-                        image::Access::SHADER_READ,
-                        image::Layout::ShaderReadOnlyOptimal,
-                    ),
-                target: &the_image,
-                families: None,
-                range: image::SubresourceRange {
-                    aspects: format::Aspects::COLOR,
-                    levels: 0..1,
-                    layers: 0..1,
-                },
-            };
-            cmd_buffer.pipeline_barrier(
-                pso::PipelineStage::TRANSFER..pso::PipelineStage::FRAGMENT_SHADER,
-                memory::Dependencies::empty(),
-                &[image_barrier],
-            );
-            cmd_buffer.finish();
-            let upload_fence = s
-                .device
-                .create_fence(false)
-                .expect("Couldn't create an upload fence!");
-            s.queue_group.queues[0]
-                .submit_without_semaphores(Some(&cmd_buffer), Some(&upload_fence));
-            s.device
-                .wait_for_fence(&upload_fence, u64::max_value())
-                .expect("Couldn't wait for the fence!");
-            s.device.destroy_fence(upload_fence);
-        }
-
-        unsafe {
-            device.destroy_buffer(image_upload_buffer);
-            device.free_memory(image_upload_memory);
-        }
-
         const VERTEX_SOURCE_TEXTURE: &[u8] = include_bytes!["../target/spirv/dyntex.vert.spirv"];
 
+        const VERTEX_SOURCE_INSTANCED: &[u8] =
+            include_bytes!["../target/spirv/dyntex_instanced.vert.spirv"];
+
         const FRAGMENT_SOURCE_TEXTURE: &[u8] = include_bytes!["../target/spirv/dyntex.frag.spirv"];
 
         let vertex_source_texture = match options.vertex_shader {
+            VertexShader::Standard if options.instanced => {
+                pso::read_spirv(Cursor::new(VERTEX_SOURCE_INSTANCED)).unwrap()
+            }
             VertexShader::Standard => pso::read_spirv(Cursor::new(VERTEX_SOURCE_TEXTURE)).unwrap(),
             VertexShader::Spirv(ref data) => pso::read_spirv(Cursor::new(data)).unwrap(),
         };
@@ -610,88 +1028,93 @@ impl<'a> Dyntex<'a> {
         };
         let input_assembler = pso::InputAssemblerDesc::new(Primitive::TriangleList);
 
-        let vertex_buffers: Vec<pso::VertexBufferDesc> = vec![
-            pso::VertexBufferDesc {
-                binding: 0,
-                stride: 8,
-                rate: pso::VertexInputRate::Vertex,
-            },
-            pso::VertexBufferDesc {
-                binding: 1,
-                stride: 8,
-                rate: pso::VertexInputRate::Vertex,
-            },
-            pso::VertexBufferDesc {
-                binding: 2,
-                stride: 8,
-                rate: pso::VertexInputRate::Vertex,
-            },
-            pso::VertexBufferDesc {
-                binding: 3,
-                stride: 4,
-                rate: pso::VertexInputRate::Vertex,
-            },
-            pso::VertexBufferDesc {
-                binding: 4,
-                stride: 4,
-                rate: pso::VertexInputRate::Vertex,
-            },
-            pso::VertexBufferDesc {
-                binding: 5,
-                stride: 1,
-                rate: pso::VertexInputRate::Vertex,
-            },
-        ];
-        let attributes: Vec<pso::AttributeDesc> = vec![
-            pso::AttributeDesc {
-                location: 0,
-                binding: 0,
-                element: pso::Element {
-                    format: format::Format::Rg32Sfloat,
-                    offset: 0,
+        let (vertex_buffers, attributes) = if options.instanced {
+            instanced_vertex_input()
+        } else {
+            let vertex_buffers: Vec<pso::VertexBufferDesc> = vec![
+                pso::VertexBufferDesc {
+                    binding: 0,
+                    stride: 8,
+                    rate: pso::VertexInputRate::Vertex,
                 },
-            },
-            pso::AttributeDesc {
-                location: 1,
-                binding: 1,
-                element: pso::Element {
-                    format: format::Format::Rg32Sfloat,
-                    offset: 0,
+                pso::VertexBufferDesc {
+                    binding: 1,
+                    stride: 8,
+                    rate: pso::VertexInputRate::Vertex,
                 },
-            },
-            pso::AttributeDesc {
-                location: 2,
-                binding: 2,
-                element: pso::Element {
-                    format: format::Format::Rg32Sfloat,
-                    offset: 0,
+                pso::VertexBufferDesc {
+                    binding: 2,
+                    stride: 8,
+                    rate: pso::VertexInputRate::Vertex,
                 },
-            },
-            pso::AttributeDesc {
-                location: 3,
-                binding: 3,
-                element: pso::Element {
-                    format: format::Format::R32Sfloat,
-                    offset: 0,
+                pso::VertexBufferDesc {
+                    binding: 3,
+                    stride: 4,
+                    rate: pso::VertexInputRate::Vertex,
                 },
-            },
-            pso::AttributeDesc {
-                location: 4,
-                binding: 4,
-                element: pso::Element {
-                    format: format::Format::R32Sfloat,
-                    offset: 0,
+                pso::VertexBufferDesc {
+                    binding: 4,
+                    stride: 4,
+                    rate: pso::VertexInputRate::Vertex,
                 },
-            },
-            pso::AttributeDesc {
-                location: 5,
-                binding: 5,
-                element: pso::Element {
-                    format: format::Format::R8Unorm,
-                    offset: 0,
+                pso::VertexBufferDesc {
+                    binding: 5,
+                    stride: 1,
+                    rate: pso::VertexInputRate::Vertex,
                 },
-            },
-        ];
+            ];
+            let attributes: Vec<pso::AttributeDesc> = vec![
+                pso::AttributeDesc {
+                    location: 0,
+                    binding: 0,
+                    element: pso::Element {
+                        format: format::Format::Rg32Sfloat,
+                        offset: 0,
+                    },
+                },
+                pso::AttributeDesc {
+                    location: 1,
+                    binding: 1,
+                    element: pso::Element {
+                        format: format::Format::Rg32Sfloat,
+                        offset: 0,
+                    },
+                },
+                pso::AttributeDesc {
+                    location: 2,
+                    binding: 2,
+                    element: pso::Element {
+                        format: format::Format::Rg32Sfloat,
+                        offset: 0,
+                    },
+                },
+                pso::AttributeDesc {
+                    location: 3,
+                    binding: 3,
+                    element: pso::Element {
+                        format: format::Format::R32Sfloat,
+                        offset: 0,
+                    },
+                },
+                pso::AttributeDesc {
+                    location: 4,
+                    binding: 4,
+                    element: pso::Element {
+                        format: format::Format::R32Sfloat,
+                        offset: 0,
+                    },
+                },
+                pso::AttributeDesc {
+                    location: 5,
+                    binding: 5,
+                    element: pso::Element {
+                        format: format::Format::R8Unorm,
+                        offset: 0,
+                    },
+                },
+            ];
+            (vertex_buffers, attributes)
+        };
 
         let rasterizer = pso::Rasterizer {
             depth_clamping: false,
@@ -863,35 +1286,60 @@ impl<'a> Dyntex<'a> {
             s.device.destroy_shader_module(fs_module);
         }
 
+        let (texture_image_buffer, texture_image_memory) = match texture {
+            Some((image, memory)) => (Some(image), Some(memory)),
+            None => (None, None),
+        };
+
         let image_count = s.swapconfig.image_count;
         let posbuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&s.device, &s.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
         let opacbuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&s.device, &s.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
         let uvbuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&s.device, &s.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
         let tranbuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&s.device, &s.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
         let rotbuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&s.device, &s.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
         let scalebuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&s.device, &s.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
 
         let indices = (0..image_count)
-            .map(|_| super::utils::ResizBufIdx4::new(&s.device, &s.adapter))
+            .map(|_| {
+                super::utils::ResizBufIdx4::new(&s.device, &s.adapter)
+                    .expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
 
         let dyntex = DynamicTexture {
             hidden: false,
 
             fixed_perspective: options.fixed_perspective,
+            instanced: options.instanced,
+            texture_size,
+            rentex: None,
             removed: vec![],
+            generation: s.next_generation(),
+            generations: vec![],
 
             posbuf_touch: 0,
             opacbuf_touch: 0,
@@ -915,8 +1363,8 @@ impl<'a> Dyntex<'a> {
             scalebuf,
             indices,
 
-            texture_image_buffer: ManuallyDrop::new(the_image),
-            texture_image_memory: ManuallyDrop::new(image_memory),
+            texture_image_buffer,
+            texture_image_memory,
 
             descriptor_pool: ManuallyDrop::new(descriptor_pool),
             image_view: ManuallyDrop::new(image_view),
@@ -934,13 +1382,14 @@ impl<'a> Dyntex<'a> {
             _ => false,
         });
 
+        let generation = dyntex.generation;
         if let Some(prev_layer) = prev_layer {
             match prev_layer {
                 DrawType::DynamicTexture { id } => {
                     let old_dyntex = std::mem::replace(&mut s.dyntexs[id], dyntex);
                     destroy_texture(s, old_dyntex);
                     s.draw_order.push(DrawType::DynamicTexture { id });
-                    Layer(id)
+                    Layer(id, generation)
                 }
                 _ => panic!["Got a non-dyntex drawtype, should be impossible!"],
             }
@@ -949,12 +1398,15 @@ impl<'a> Dyntex<'a> {
             s.draw_order.push(DrawType::DynamicTexture {
                 id: s.dyntexs.len() - 1,
             });
-            Layer(s.dyntexs.len() - 1)
+            Layer(s.dyntexs.len() - 1, generation)
         }
     }
 
     /// Set the fixed perspective of a layer. `None` uses the vxdraw perspective.
     pub fn set_perspective(&mut self, layer: &Layer, perspective: Option<Matrix4<f32>>) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.dyntexs[layer.0].fixed_perspective = perspective;
     }
 
@@ -965,11 +1417,17 @@ impl<'a> Dyntex<'a> {
 
     /// Disable drawing of the sprites at this layer
     pub fn hide(&mut self, layer: &Layer) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.dyntexs[layer.0].hidden = true;
     }
 
     /// Enable drawing of the sprites at this layer
     pub fn show(&mut self, layer: &Layer) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.dyntexs[layer.0].hidden = false;
     }
 
@@ -979,27 +1437,37 @@ impl<'a> Dyntex<'a> {
     /// All lingering sprite handles that were spawned using this layer handle will be
     /// invalidated.
     pub fn remove_layer(&mut self, layer: Layer) {
-        let s = &mut *self.vx;
-        let mut index = None;
-        for (idx, x) in s.draw_order.iter().enumerate() {
-            match x {
-                DrawType::DynamicTexture { id } if *id == layer.0 => {
-                    index = Some(idx);
-                    break;
-                }
-                _ => {}
-            }
+        if !self.check_layer(&layer) {
+            return;
         }
-        if let Some(idx) = index {
-            let draw_type = s.draw_order.remove(idx);
+        let s = &mut *self.vx;
+        s.dyntexs[layer.0].generation = 0;
+        let draw_type = s.take_from_draw_order(|x| match x {
+            DrawType::DynamicTexture { id } => *id == layer.0,
+            _ => false,
+        });
+        if let Some(draw_type) = draw_type {
             s.layer_holes.push(draw_type);
         }
+        animation::forget_layer(s, layer.0);
+    }
+
+    /// Remove the layers that sample a render texture, before the render texture is destroyed
+    pub(crate) fn remove_layers_sampling(&mut self, rentex: usize) {
+        for id in 0..self.vx.dyntexs.len() {
+            let dyntex = &self.vx.dyntexs[id];
+            if dyntex.generation != 0 && dyntex.rentex == Some(rentex) {
+                self.remove_layer(Layer(id, dyntex.generation));
+            }
+        }
     }
 
     /// Add a sprite (a rectangular view of a texture) to the system
     ///
     /// The sprite is automatically drawn on each [VxDraw::draw_frame] call, and must be removed by
     /// [Dyntex::remove] to stop it from being drawn.
+    ///
+    /// Adding to a removed layer returns a handle that is already stale.
     pub fn add(&mut self, layer: &Layer, sprite: Sprite) -> Handle {
         // Derive xy from the sprite's initial UV
         let uv_a = sprite.uv_begin;
@@ -1032,14 +1500,16 @@ impl<'a> Dyntex<'a> {
         );
         let bottomright_uv = (uv_b.0, uv_b.1);
 
-        let replace = self.vx.dyntexs.get(layer.0).map(|x| !x.removed.is_empty());
-        if replace.is_none() {
-            panic!("Layer does not exist");
+        if !self.check_layer(layer) {
+            return Handle(layer.0, 0, 0);
         }
+        let replace = !self.vx.dyntexs[layer.0].removed.is_empty();
+        let generation = self.vx.next_generation();
 
-        let handle = if replace.unwrap() {
+        let handle = if replace {
             let hole = self.vx.dyntexs[layer.0].removed.pop().unwrap();
-            let handle = Handle(layer.0, hole);
+            self.vx.dyntexs[layer.0].generations[hole] = generation;
+            let handle = Handle(layer.0, hole, generation);
             self.set_deform(
                 &handle,
                 [
@@ -1109,6 +1579,7 @@ impl<'a> Dyntex<'a> {
                 topright_uv.0,
                 topright_uv.1,
             ]);
+            tex.generations.push(generation);
             tex.posbuffer.len() - 1
         };
 
@@ -1120,7 +1591,7 @@ impl<'a> Dyntex<'a> {
         tex.rotbuf_touch = self.vx.swapconfig.image_count;
         tex.scalebuf_touch = self.vx.swapconfig.image_count;
 
-        Handle(layer.0, handle)
+        Handle(layer.0, handle, generation)
     }
 
     /// Removes a single sprite, making it not be drawn
@@ -1129,11 +1600,189 @@ impl<'a> Dyntex<'a> {
     /// `holes`. Calling [Dyntex::add] with available holes will fill the first available hole
     /// with the new sprite.
     pub fn remove(&mut self, handle: Handle) {
+        if !self.check(&handle) {
+            return;
+        }
         self.vx.dyntexs[handle.0].scalebuf_touch = self.vx.swapconfig.image_count;
         if let Some(dyntex) = self.vx.dyntexs.get_mut(handle.0) {
             dyntex.removed.push(handle.1);
             dyntex.scalebuffer[handle.1].copy_from_slice(&[0.0, 0.0, 0.0, 0.0]);
+            dyntex.generations[handle.1] = 0;
+        }
+        animation::forget(self.vx, &handle);
+    }
+
+    /// Add a nine-slice panel, made of 9 sprites, to the system
+    ///
+    /// The borders of the texture keep their size when the panel is resized with
+    /// [Dyntex::set_size]. Compacting the layer with [Dyntex::compact] may move the sprites of the
+    /// panel, pass the returned moves to [NineSliceHandle::remap] to keep the handle usable.
+    /// Adding to a removed layer returns a handle that is already stale.
+    pub fn add_nine_slice(&mut self, layer: &Layer, nine: NineSlice) -> NineSliceHandle {
+        if !self.check_layer(layer) {
+            let stale = || Handle(layer.0, 0, 0);
+            return NineSliceHandle {
+                sprites: [
+                    stale(),
+                    stale(),
+                    stale(),
+                    stale(),
+                    stale(),
+                    stale(),
+                    stale(),
+                    stale(),
+                    stale(),
+                ],
+                borders: [0.0; 4],
+                origin: nine.origin,
+            };
         }
+        let texture_size = self.vx.dyntexs[layer.0].texture_size;
+        let borders = [
+            nine.insets[0] as f32 * nine.border_scale,
+            nine.insets[1] as f32 * nine.border_scale,
+            nine.insets[2] as f32 * nine.border_scale,
+            nine.insets[3] as f32 * nine.border_scale,
+        ];
+        let us = [
+            nine.uv_begin.0,
+            nine.uv_begin.0 + nine.insets[0] as f32 / texture_size.0 as f32,
+            nine.uv_end.0 - nine.insets[2] as f32 / texture_size.0 as f32,
+            nine.uv_end.0,
+        ];
+        let vs = [
+            nine.uv_begin.1,
+            nine.uv_begin.1 + nine.insets[1] as f32 / texture_size.1 as f32,
+            nine.uv_end.1 - nine.insets[3] as f32 / texture_size.1 as f32,
+            nine.uv_end.1,
+        ];
+        let quads = nine_slice_quads((nine.width, nine.height), borders, nine.origin);
+        let mut add = |index: usize| {
+            let (row, column) = (index / 3, index % 3);
+            let handle = self.add(
+                layer,
+                Sprite::new()
+                    .opacity_raw(nine.opacity)
+                    .translation(nine.translation)
+                    .rotation(Rad(nine.rotation))
+                    .scale(nine.scale)
+                    .uv_begin((us[column], vs[row]))
+                    .uv_end((us[column + 1], vs[row + 1])),
+            );
+            self.set_deform(&handle, quads[index]);
+            handle
+        };
+        NineSliceHandle {
+            sprites: [
+                add(0),
+                add(1),
+                add(2),
+                add(3),
+                add(4),
+                add(5),
+                add(6),
+                add(7),
+                add(8),
+            ],
+            borders,
+            origin: nine.origin,
+        }
+    }
+
+    /// Resize a nine-slice panel
+    ///
+    /// The corners keep their size, the edges stretch along their length, and the center stretches
+    /// in both directions. Borders that do not fit in the new size are shrunk.
+    ///
+    /// Returns false and leaves the panel as it is if any of its sprites is stale, for instance
+    /// after [Dyntex::compact] moved it and the handle was not remapped.
+    pub fn set_size(&mut self, handle: &NineSliceHandle, size: (f32, f32)) -> bool {
+        if !handle.sprites.iter().all(|sprite| self.is_alive(sprite)) {
+            return false;
+        }
+        let quads = nine_slice_quads(size, handle.borders, handle.origin);
+        for (sprite, quad) in handle.sprites.iter().zip(quads.iter()) {
+            self.set_deform(sprite, *quad);
+        }
+        true
+    }
+
+    /// Remove all 9 sprites of a nine-slice panel
+    ///
+    /// Sprites that are already gone, or were moved by [Dyntex::compact], are skipped.
+    pub fn remove_nine_slice(&mut self, handle: NineSliceHandle) {
+        let NineSliceHandle { sprites, .. } = handle;
+        for sprite in ArrayVec::from(sprites) {
+            if self.is_alive(&sprite) {
+                self.remove(sprite);
+            }
+        }
+    }
+
+    /// Repack the sprites of a layer so that removed sprites no longer take up space
+    ///
+    /// [Dyntex::add] reuses the slots of removed sprites, but the buffers never shrink by themselves.
+    /// Compaction moves the last sprites into the free slots and truncates the buffers. Returns
+    /// pairs of `(old, new)` handles for every sprite that was moved, the old handles become stale.
+    /// Handles to sprites that were not moved stay valid.
+    pub fn compact(&mut self, layer: &Layer) -> Vec<(Handle, Handle)> {
+        if !self.check_layer(layer) {
+            return vec![];
+        }
+        let image_count = self.vx.swapconfig.image_count;
+        let dyntex = &mut self.vx.dyntexs[layer.0];
+        let (moves, len) = compaction_moves(dyntex.posbuffer.len(), &dyntex.removed);
+        for (from, to) in moves.iter().cloned() {
+            dyntex.posbuffer[to] = dyntex.posbuffer[from];
+            dyntex.opacbuffer[to] = dyntex.opacbuffer[from];
+            dyntex.uvbuffer[to] = dyntex.uvbuffer[from];
+            dyntex.tranbuffer[to] = dyntex.tranbuffer[from];
+            dyntex.rotbuffer[to] = dyntex.rotbuffer[from];
+            dyntex.scalebuffer[to] = dyntex.scalebuffer[from];
+            dyntex.generations[to] = dyntex.generations[from];
+        }
+        dyntex.posbuffer.truncate(len);
+        dyntex.opacbuffer.truncate(len);
+        dyntex.uvbuffer.truncate(len);
+        dyntex.tranbuffer.truncate(len);
+        dyntex.rotbuffer.truncate(len);
+        dyntex.scalebuffer.truncate(len);
+        dyntex.generations.truncate(len);
+        dyntex.removed.clear();
+        if !moves.is_empty() {
+            dyntex.posbuf_touch = image_count;
+            dyntex.opacbuf_touch = image_count;
+            dyntex.uvbuf_touch = image_count;
+            dyntex.tranbuf_touch = image_count;
+            dyntex.rotbuf_touch = image_count;
+            dyntex.scalebuf_touch = image_count;
+        }
+        animation::remap(self.vx, layer.0, &moves);
+        moves
+            .iter()
+            .map(|(from, to)| {
+                let generation = self.vx.dyntexs[layer.0].generations[*to];
+                (
+                    Handle(layer.0, *from, generation),
+                    Handle(layer.0, *to, generation),
+                )
+            })
+            .collect()
+    }
+
+    /// Check if a sprite handle still refers to a sprite, that is, neither the sprite nor its
+    /// layer has been removed
+    pub fn is_alive(&self, handle: &Handle) -> bool {
+        let dyntex = &self.vx.dyntexs[handle.0];
+        dyntex.generation != 0 && dyntex.generations.get(handle.1) == Some(&handle.2)
+    }
+
+    pub(crate) fn check(&self, handle: &Handle) -> bool {
+        check_sprite(self.vx, handle)
+    }
+
+    fn check_layer(&self, layer: &Layer) -> bool {
+        is_current(&self.vx.log, self.vx.dyntexs[layer.0].generation, layer.1)
     }
 
     // ---
@@ -1144,6 +1793,9 @@ impl<'a> Dyntex<'a> {
     /// What this function does is just setting absolute vertex positions for each vertex in the
     /// sprite.
     pub fn set_deform(&mut self, handle: &Handle, points: [(f32, f32); 4]) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.dyntexs[handle.0].posbuf_touch = self.vx.swapconfig.image_count;
         let vertex = &mut self.vx.dyntexs[handle.0].posbuffer[handle.1];
         for (idx, point) in points.iter().enumerate() {
@@ -1154,6 +1806,9 @@ impl<'a> Dyntex<'a> {
 
     /// Set a solid color of a quad
     pub fn set_solid_color(&mut self, handle: &Handle, rgba: Color) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.dyntexs[handle.0].opacbuf_touch = self.vx.swapconfig.image_count;
         for idx in 0..4 {
             let Color::Rgba(r, g, b, a) = rgba;
@@ -1164,18 +1819,27 @@ impl<'a> Dyntex<'a> {
 
     /// Set an opacity each vertex of a sprite
     pub fn set_opacity(&mut self, handle: &Handle, opacity: u8) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.dyntexs[handle.0].opacbuf_touch = self.vx.swapconfig.image_count;
         self.vx.dyntexs[handle.0].opacbuffer[handle.1].copy_from_slice(&[opacity; 4]);
     }
 
     /// Set an opacity each vertex of a sprite
     pub fn set_opacity_raw(&mut self, handle: &Handle, opacity: [u8; 4]) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.dyntexs[handle.0].opacbuf_touch = self.vx.swapconfig.image_count;
         self.vx.dyntexs[handle.0].opacbuffer[handle.1].copy_from_slice(&opacity);
     }
 
     /// Set the position of a sprite
     pub fn set_translation(&mut self, handle: &Handle, position: (f32, f32)) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.dyntexs[handle.0].tranbuf_touch = self.vx.swapconfig.image_count;
         for idx in 0..4 {
             self.vx.dyntexs[handle.0].tranbuffer[handle.1][idx * 2] = position.0;
@@ -1187,6 +1851,9 @@ impl<'a> Dyntex<'a> {
     ///
     /// Positive rotation goes counter-clockwise. The value of the rotation is in radians.
     pub fn set_rotation<T: Copy + Into<Rad<f32>>>(&mut self, handle: &Handle, angle: T) {
+        if !self.check(handle) {
+            return;
+        }
         let angle = angle.into().0;
         self.vx.dyntexs[handle.0].rotbuf_touch = self.vx.swapconfig.image_count;
         self.vx.dyntexs[handle.0].rotbuffer[handle.1]
@@ -1195,6 +1862,9 @@ impl<'a> Dyntex<'a> {
 
     /// Set the scale of a sprite
     pub fn set_scale(&mut self, handle: &Handle, scale: f32) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.dyntexs[handle.0].scalebuf_touch = self.vx.swapconfig.image_count;
         for sc in &mut self.vx.dyntexs[handle.0].scalebuffer[handle.1] {
             *sc = scale;
@@ -1203,6 +1873,9 @@ impl<'a> Dyntex<'a> {
 
     /// Set the UV values of a single sprite
     pub fn set_uv(&mut self, handle: &Handle, uv_begin: (f32, f32), uv_end: (f32, f32)) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.dyntexs[handle.0].uvbuf_touch = self.vx.swapconfig.image_count;
         self.vx.dyntexs[handle.0].uvbuffer[handle.1].copy_from_slice(&[
             uv_begin.0, uv_begin.1, uv_begin.0, uv_end.1, uv_end.0, uv_end.1, uv_end.0, uv_begin.1,
@@ -1214,6 +1887,9 @@ impl<'a> Dyntex<'a> {
     /// This may be used to repeat a texture multiple times over the same sprite, or to do
     /// something exotic with uv coordinates.
     pub fn set_uv_raw(&mut self, handle: &Handle, uvs: [(f32, f32); 4]) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.dyntexs[handle.0].uvbuf_touch = self.vx.swapconfig.image_count;
         self.vx.dyntexs[handle.0].uvbuffer[handle.1].copy_from_slice(&[
             uvs[0].0, uvs[0].1, uvs[1].0, uvs[1].1, uvs[2].0, uvs[2].1, uvs[3].0, uvs[3].1,
@@ -1226,6 +1902,9 @@ impl<'a> Dyntex<'a> {
     ///
     /// Adds the delta vertices to the sprite. Beware: This changes model space form.
     pub fn deform(&mut self, handle: &Handle, delta: [(f32, f32); 4]) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.dyntexs[handle.0].posbuf_touch = self.vx.swapconfig.image_count;
         let points = &mut self.vx.dyntexs[handle.0].posbuffer[handle.1];
         points[0] += delta[0].0;
@@ -1242,6 +1921,9 @@ impl<'a> Dyntex<'a> {
     ///
     /// Translation does not mutate the model-space of a sprite.
     pub fn translate(&mut self, handle: &Handle, movement: (f32, f32)) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.dyntexs[handle.0].tranbuf_touch = self.vx.swapconfig.image_count;
         for idx in 0..4 {
             self.vx.dyntexs[handle.0].tranbuffer[handle.1][idx * 2] += movement.0;
@@ -1253,6 +1935,9 @@ impl<'a> Dyntex<'a> {
     ///
     /// Rotation does not mutate the model-space of a sprite.
     pub fn rotate<T: Copy + Into<Rad<f32>>>(&mut self, handle: &Handle, angle: T) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.dyntexs[handle.0].rotbuf_touch = self.vx.swapconfig.image_count;
         for rot in &mut self.vx.dyntexs[handle.0].rotbuffer[handle.1] {
             *rot += angle.into().0;
@@ -1263,6 +1948,9 @@ impl<'a> Dyntex<'a> {
     ///
     /// Scale does not mutate the model-space of a sprite.
     pub fn scale(&mut self, handle: &Handle, scale: f32) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.dyntexs[handle.0].scalebuf_touch = self.vx.swapconfig.image_count;
         for sc in &mut self.vx.dyntexs[handle.0].scalebuffer[handle.1] {
             *sc *= scale;
@@ -1275,6 +1963,9 @@ impl<'a> Dyntex<'a> {
     ///
     /// Applies [Dyntex::deform] to each dynamic texture.
     pub fn deform_all(&mut self, layer: &Layer, mut delta: impl FnMut(usize) -> [(f32, f32); 4]) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.dyntexs[layer.0].posbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.dyntexs[layer.0].posbuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1293,6 +1984,9 @@ impl<'a> Dyntex<'a> {
     ///
     /// Applies [Dyntex::translate] to each dynamic texture.
     pub fn translate_all(&mut self, layer: &Layer, mut delta: impl FnMut(usize) -> (f32, f32)) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.dyntexs[layer.0].tranbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.dyntexs[layer.0].tranbuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1311,6 +2005,9 @@ impl<'a> Dyntex<'a> {
         layer: &Layer,
         mut delta: impl FnMut(usize) -> T,
     ) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.dyntexs[layer.0].rotbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.dyntexs[layer.0].rotbuffer.iter_mut().enumerate() {
             let delta = delta(idx).into().0;
@@ -1324,6 +2021,9 @@ impl<'a> Dyntex<'a> {
     ///
     /// Applies [Dyntex::scale] to each dynamic texture.
     pub fn scale_all(&mut self, layer: &Layer, mut delta: impl FnMut(usize) -> f32) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.dyntexs[layer.0].scalebuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.dyntexs[layer.0].scalebuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1343,6 +2043,9 @@ impl<'a> Dyntex<'a> {
         layer: &Layer,
         mut delta: impl FnMut(usize) -> [(f32, f32); 4],
     ) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.dyntexs[layer.0].posbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.dyntexs[layer.0].posbuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1361,6 +2064,9 @@ impl<'a> Dyntex<'a> {
     ///
     /// Applies [Dyntex::set_solid_color] to each dynamic texture.
     pub fn set_solid_color_all(&mut self, layer: &Layer, mut delta: impl FnMut(usize) -> Color) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.dyntexs[layer.0].opacbuf_touch = self.vx.swapconfig.image_count;
         for (idx, dyntex) in self.vx.dyntexs[layer.0].opacbuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1375,6 +2081,9 @@ impl<'a> Dyntex<'a> {
     ///
     /// Applies [Dyntex::set_solid_color] to each dynamic texture.
     pub fn set_color_all(&mut self, layer: &Layer, mut delta: impl FnMut(usize) -> [Color; 4]) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.dyntexs[layer.0].opacbuf_touch = self.vx.swapconfig.image_count;
         for (idx, dyntex) in self.vx.dyntexs[layer.0].opacbuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1393,6 +2102,9 @@ impl<'a> Dyntex<'a> {
         layer: &Layer,
         mut delta: impl FnMut(usize) -> (f32, f32),
     ) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.dyntexs[layer.0].tranbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.dyntexs[layer.0].tranbuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1407,6 +2119,9 @@ impl<'a> Dyntex<'a> {
     ///
     /// Applies [Dyntex::set_uv] to each dynamic texture.
     pub fn set_uv_all(&mut self, layer: &Layer, mut delta: impl FnMut(usize) -> [(f32, f32); 2]) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.dyntexs[layer.0].uvbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.dyntexs[layer.0].uvbuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1427,6 +2142,9 @@ impl<'a> Dyntex<'a> {
         layer: &Layer,
         mut delta: impl FnMut(usize) -> T,
     ) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.dyntexs[layer.0].rotbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.dyntexs[layer.0].rotbuffer.iter_mut().enumerate() {
             let delta = delta(idx).into().0;
@@ -1439,6 +2157,9 @@ impl<'a> Dyntex<'a> {
     /// Applies [Dyntex::set_scale] to each dynamic texture.
     /// Note: This may re-enable removed sprites, see [Dyntex::remove].
     pub fn set_scale_all(&mut self, layer: &Layer, mut delta: impl FnMut(usize) -> f32) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.dyntexs[layer.0].scalebuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.dyntexs[layer.0].scalebuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1465,6 +2186,201 @@ impl<'a> Dyntex<'a> {
 
 // ---
 
+/// Check that a sprite handle is not stale, see [Dyntex::is_alive]
+pub(crate) fn check_sprite(s: &VxDraw, handle: &Handle) -> bool {
+    let dyntex = &s.dyntexs[handle.0];
+    let current = if dyntex.generation == 0 {
+        0
+    } else {
+        dyntex.generations.get(handle.1).cloned().unwrap_or(0)
+    };
+    is_current(&s.log, current, handle.2)
+}
+
+/// Upload an RGBA8 image into a sampled texture
+pub(crate) fn upload_texture(
+    s: &mut VxDraw,
+    img_width: usize,
+    img_height: usize,
+    img: &[u8],
+) -> (
+    <back::Backend as Backend>::Image,
+    <back::Backend as Backend>::Memory,
+    <back::Backend as Backend>::ImageView,
+) {
+    let device = &s.device;
+
+    let pixel_size = 4; //size_of::<image::Rgba<u8>>();
+    let row_size = pixel_size * img_width;
+    let limits = s.adapter.physical_device.limits();
+    let row_alignment_mask = limits.optimal_buffer_copy_pitch_alignment as u32 - 1;
+    let row_pitch = ((row_size as u32 + row_alignment_mask) & !row_alignment_mask) as usize;
+    debug_assert!(row_pitch as usize >= row_size);
+    let required_bytes = row_pitch * img_height;
+
+    let mut image_upload_buffer = unsafe {
+        device.create_buffer(required_bytes as u64, gfx_hal::buffer::Usage::TRANSFER_SRC)
+    }
+    .unwrap();
+    let image_mem_reqs = unsafe { device.get_buffer_requirements(&image_upload_buffer) };
+    let memory_type_id = find_memory_type_id(
+        &s.adapter,
+        image_mem_reqs,
+        Properties::CPU_VISIBLE | Properties::COHERENT,
+    )
+    .expect("Unable to find memory type id");
+    let image_upload_memory =
+        unsafe { device.allocate_memory(memory_type_id, image_mem_reqs.size) }.unwrap();
+    unsafe { device.bind_buffer_memory(&image_upload_memory, 0, &mut image_upload_buffer) }
+        .unwrap();
+
+    unsafe {
+        let writer = s
+            .device
+            .map_memory(&image_upload_memory, 0..image_mem_reqs.size)
+            .expect("Unable to get mapping writer");
+        let mut idx = 0;
+        for y in 0..img_height {
+            // let row = &(*img)[y * row_size..(y + 1) * row_size];
+            let dest_base = y * row_pitch;
+            for row_index in 0..row_size {
+                std::slice::from_raw_parts_mut(writer, image_mem_reqs.size as usize)
+                    [dest_base + row_index] = img[idx % img.len()];
+                idx += 1;
+            }
+        }
+        device.unmap_memory(&image_upload_memory);
+    }
+
+    let mut the_image = unsafe {
+        device
+            .create_image(
+                image::Kind::D2(img_width as u32, img_height as u32, 1, 1),
+                1,
+                format::Format::Rgba8Srgb,
+                image::Tiling::Optimal,
+                image::Usage::TRANSFER_DST | image::Usage::SAMPLED,
+                image::ViewCapabilities::empty(),
+            )
+            .expect("Couldn't create the image!")
+    };
+
+    let image_memory = unsafe {
+        let requirements = device.get_image_requirements(&the_image);
+        let memory_type_id =
+            find_memory_type_id(&s.adapter, requirements, memory::Properties::DEVICE_LOCAL)
+                .expect("Unable to find memory type id");
+        device
+            .allocate_memory(memory_type_id, requirements.size)
+            .expect("Unable to allocate")
+    };
+
+    let image_view = unsafe {
+        device
+            .bind_image_memory(&image_memory, 0, &mut the_image)
+            .expect("Unable to bind memory");
+
+        device
+            .create_image_view(
+                &the_image,
+                image::ViewKind::D2,
+                format::Format::Rgba8Srgb,
+                format::Swizzle::NO,
+                image::SubresourceRange {
+                    aspects: format::Aspects::COLOR,
+                    levels: 0..1,
+                    layers: 0..1,
+                },
+            )
+            .expect("Couldn't create the image view!")
+    };
+
+    unsafe {
+        let mut cmd_buffer = s
+            .command_pool
+            .allocate_one(gfx_hal::command::Level::Primary);
+        cmd_buffer.begin_primary(CommandBufferFlags::EMPTY);
+        let image_barrier = memory::Barrier::Image {
+            states: (image::Access::empty(), image::Layout::Undefined)
+                ..(
+                    image::Access::TRANSFER_WRITE,
+                    image::Layout::TransferDstOptimal,
+                ),
+            target: &the_image,
+            families: None,
+            range: image::SubresourceRange {
+                aspects: format::Aspects::COLOR,
+                levels: 0..1,
+                layers: 0..1,
+            },
+        };
+        cmd_buffer.pipeline_barrier(
+            pso::PipelineStage::TOP_OF_PIPE..pso::PipelineStage::TRANSFER,
+            memory::Dependencies::empty(),
+            &[image_barrier],
+        );
+        cmd_buffer.copy_buffer_to_image(
+            &image_upload_buffer,
+            &the_image,
+            image::Layout::TransferDstOptimal,
+            &[command::BufferImageCopy {
+                buffer_offset: 0,
+                buffer_width: (row_pitch / pixel_size) as u32,
+                buffer_height: img_height as u32,
+                image_layers: gfx_hal::image::SubresourceLayers {
+                    aspects: format::Aspects::COLOR,
+                    level: 0,
+                    layers: 0..1,
+                },
+                image_offset: image::Offset { x: 0, y: 0, z: 0 },
+                image_extent: image::Extent {
+                    width: img_width as u32,
+                    height: img_height as u32,
+                    depth: 1,
+                },
+            }],
+        );
+        let image_barrier = memory::Barrier::Image {
+            states: (
+                image::Access::TRANSFER_WRITE,
+                image::Layout::TransferDstOptimal,
+            )
+                ..(
+                    image::Access::SHADER_READ,
+                    image::Layout::ShaderReadOnlyOptimal,
+                ),
+            target: &the_image,
+            families: None,
+            range: image::SubresourceRange {
+                aspects: format::Aspects::COLOR,
+                levels: 0..1,
+                layers: 0..1,
+            },
+        };
+        cmd_buffer.pipeline_barrier(
+            pso::PipelineStage::TRANSFER..pso::PipelineStage::FRAGMENT_SHADER,
+            memory::Dependencies::empty(),
+            &[image_barrier],
+        );
+        cmd_buffer.finish();
+        let upload_fence = s
+            .device
+            .create_fence(false)
+            .expect("Couldn't create an upload fence!");
+        s.queue_group.queues[0].submit_without_semaphores(Some(&cmd_buffer), Some(&upload_fence));
+        s.device
+            .wait_for_fence(&upload_fence, u64::max_value())
+            .expect("Couldn't wait for the fence!");
+        s.device.destroy_fence(upload_fence);
+    }
+
+    unsafe {
+        device.destroy_buffer(image_upload_buffer);
+        device.free_memory(image_upload_memory);
+    }
+    (the_image, image_memory, image_view)
+}
+
 fn destroy_texture(s: &mut VxDraw, mut dyntex: DynamicTexture) {
     unsafe {
         for mut indices in dyntex.indices.drain(..) {
@@ -1488,10 +2404,12 @@ fn destroy_texture(s: &mut VxDraw, mut dyntex: DynamicTexture) {
         for mut scalebuf in dyntex.scalebuf.drain(..) {
             scalebuf.destroy(&s.device);
         }
-        s.device
-            .destroy_image(ManuallyDrop::into_inner(read(&dyntex.texture_image_buffer)));
-        s.device
-            .free_memory(ManuallyDrop::into_inner(read(&dyntex.texture_image_memory)));
+        if let Some(image) = dyntex.texture_image_buffer.take() {
+            s.device.destroy_image(image);
+        }
+        if let Some(memory) = dyntex.texture_image_memory.take() {
+            s.device.free_memory(memory);
+        }
         s.device
             .destroy_render_pass(ManuallyDrop::into_inner(read(&dyntex.render_pass)));
         s.device
@@ -2110,6 +3028,230 @@ mod tests {
         utils::assert_swapchain_eq(&mut vx, "too_little_data_in_texture_wraps", img);
     }
 
+    #[test]
+    fn atlas_packing_does_not_overlap() {
+        let sizes = [(10, 30), (64, 2), (5, 5), (30, 10), (1, 1), (64, 64)];
+        let ((width, height), positions) = pack_shelves(&sizes, 1);
+        for (idx, ((w, h), (x, y))) in sizes.iter().zip(positions.iter()).enumerate() {
+            assert![x + w <= width && y + h <= height];
+            for ((w2, h2), (x2, y2)) in sizes.iter().zip(positions.iter()).skip(idx + 1) {
+                let disjoint = x + w <= *x2 || x2 + w2 <= *x || y + h <= *y2 || y2 + h2 <= *y;
+                assert![disjoint];
+            }
+        }
+    }
+
+    #[test]
+    fn atlas_sprites_show_their_region() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+
+        let red = ImgData::RawBytes {
+            width: 4,
+            height: 4,
+            bytes: &[255, 0, 0, 255],
+        };
+        let green = ImgData::RawBytes {
+            width: 8,
+            height: 2,
+            bytes: &[0, 255, 0, 255],
+        };
+        let atlas = AtlasBuilder::new()
+            .add("red", &red)
+            .add("green", &green)
+            .add("logo", LOGO);
+        let (layer, atlas) = vx.dyntex().add_layer_atlas(&atlas, &LayerOptions::new());
+        assert_eq![3, atlas.len()];
+        assert_eq![Some(RegionId(1)), atlas.id("green")];
+        assert_eq![(8, 2), atlas.region(RegionId(1)).size];
+        assert![atlas.get("blue").is_none()];
+
+        vx.dyntex().add(
+            &layer,
+            atlas.sprite("red").scale(0.5).translation((-0.5, 0.0)),
+        );
+        vx.dyntex().add(
+            &layer,
+            Sprite::new()
+                .region(atlas.get("green").unwrap())
+                .scale(0.5)
+                .translation((0.5, 0.0)),
+        );
+
+        let img = vx.draw_frame_copy_framebuffer();
+        let pixel = |x: usize, y: usize| {
+            let idx = (y * 1000 + x) * 4;
+            [img[idx], img[idx + 1], img[idx + 2], img[idx + 3]]
+        };
+        assert_eq![[255, 0, 0, 255], pixel(250, 500)];
+        assert_eq![[0, 255, 0, 255], pixel(750, 500)];
+    }
+
+    #[test]
+    fn instanced_layer_draws_like_regular_layer() {
+        let draw = |options: LayerOptions| {
+            let logger = Logger::root(Discard, o!());
+            let mut vx = VxDraw::new_offscreen(logger, (100, 100));
+            let layer = vx.dyntex().add_layer(TESTURE, &options);
+            vx.dyntex().add(
+                &layer,
+                Sprite::new()
+                    .width(0.5)
+                    .translation((0.25, -0.25))
+                    .rotation(Rad(0.3))
+                    .opacity_raw([255, 128, 64, 255]),
+            );
+            let sprite = vx.dyntex().add(&layer, Sprite::new().uv_end((0.5, 0.5)));
+            vx.dyntex().set_scale(&sprite, 0.5);
+            vx.draw_frame_copy_framebuffer()
+        };
+        let regular = draw(LayerOptions::new());
+        let instanced = draw(LayerOptions::new().instanced(true));
+        assert![regular == instanced];
+    }
+
+    #[test]
+    fn compact_moves_last_sprites_into_holes() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (100, 100));
+        let layer = vx.dyntex().add_layer(FIREBALL, &LayerOptions::new());
+
+        let first = vx.dyntex().add(&layer, Sprite::new());
+        let second = vx
+            .dyntex()
+            .add(&layer, Sprite::new().translation((0.5, 0.0)));
+        let third = vx.dyntex().add(&layer, Sprite::new());
+        let last = vx
+            .dyntex()
+            .add(&layer, Sprite::new().translation((0.25, 0.0)));
+        let clip = vx
+            .animation()
+            .add_clip(&crate::animation::ClipOptions::new(10, 6));
+        vx.animation().play(&last, &clip);
+        vx.dyntex().remove(first);
+        vx.dyntex().remove(third);
+
+        let remapped = vx.dyntex().compact(&layer);
+        assert_eq![2, vx.dyntexs[0].posbuffer.len()];
+        assert_eq![1, remapped.len()];
+        let (old, new) = &remapped[0];
+        assert_eq![(3, 0), (old.1, new.1)];
+        assert![!vx.dyntex().is_alive(old)];
+        assert![vx.dyntex().is_alive(new)];
+        assert![vx.dyntex().is_alive(&second)];
+        assert_eq![0.25, vx.dyntexs[0].tranbuffer[0][0]];
+        assert_eq![0.5, vx.dyntexs[0].tranbuffer[1][0]];
+        assert![vx.animation().is_playing(new)];
+
+        let reused = vx.dyntex().add(&layer, Sprite::new());
+        assert_eq![2, reused.1];
+        vx.draw_frame();
+    }
+
+    #[test]
+    fn nine_slice_corners_keep_their_size() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (100, 100));
+        let panel = &ImgData::RawBytes {
+            width: 4,
+            height: 4,
+            bytes: &[255; 64],
+        };
+        let layer = vx.dyntex().add_layer(panel, &LayerOptions::new());
+        let nine = vx.dyntex().add_nine_slice(
+            &layer,
+            NineSlice::new()
+                .width(1.0)
+                .height(1.0)
+                .insets(1, 1, 1, 1)
+                .border_scale(0.1),
+        );
+        assert_eq![9, vx.dyntexs[0].posbuffer.len()];
+        assert_eq![
+            [0.0, 0.0, 0.0, 0.25, 0.25, 0.25, 0.25, 0.0],
+            vx.dyntexs[0].uvbuffer[0]
+        ];
+        assert_eq![
+            [0.25, 0.25, 0.25, 0.75, 0.75, 0.75, 0.75, 0.25],
+            vx.dyntexs[0].uvbuffer[4]
+        ];
+
+        let assert_quad = |vx: &VxDraw, index: usize, expected: [f32; 8]| {
+            let actual = vx.dyntexs[0].posbuffer[index];
+            assert![
+                actual
+                    .iter()
+                    .zip(expected.iter())
+                    .all(|(a, e)| (a - e).abs() < 1e-6),
+                "expected {:?}, got {:?}",
+                expected,
+                actual
+            ];
+        };
+        assert_quad(&vx, 0, [-0.5, -0.5, -0.5, -0.4, -0.4, -0.4, -0.4, -0.5]);
+
+        // The corners stay 0.1 wide, the height is too small for the borders so they shrink
+        assert![vx.dyntex().set_size(&nine, (3.0, 0.1))];
+        assert_quad(&vx, 0, [-1.5, -0.05, -1.5, 0.0, -1.4, 0.0, -1.4, -0.05]);
+        assert_quad(&vx, 5, [1.4, 0.0, 1.4, 0.0, 1.5, 0.0, 1.5, 0.0]);
+        assert_quad(&vx, 7, [-1.4, 0.0, -1.4, 0.05, 1.4, 0.05, 1.4, 0.0]);
+        vx.draw_frame();
+
+        vx.dyntex().remove_nine_slice(nine);
+        assert_eq![9, vx.dyntexs[0].removed.len()];
+    }
+
+    #[test]
+    fn nine_slice_moved_by_compaction_is_not_resized() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (100, 100));
+        let layer = vx.dyntex().add_layer(TESTURE, &LayerOptions::new());
+        let first = vx.dyntex().add(&layer, Sprite::new());
+        let nine = vx.dyntex().add_nine_slice(&layer, NineSlice::new());
+        vx.dyntex().remove(first);
+        assert_eq![1, vx.dyntex().compact(&layer).len()];
+
+        let before = vx.dyntexs[0].posbuffer.clone();
+        assert![!vx.dyntex().set_size(&nine, (3.0, 3.0))];
+        assert_eq![before, vx.dyntexs[0].posbuffer];
+    }
+
+    #[test]
+    fn nine_slice_is_remapped_after_compaction() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (100, 100));
+        let layer = vx.dyntex().add_layer(TESTURE, &LayerOptions::new());
+        let first = vx.dyntex().add(&layer, Sprite::new());
+        let mut nine = vx.dyntex().add_nine_slice(&layer, NineSlice::new());
+        vx.dyntex().remove(first);
+        let moves = vx.dyntex().compact(&layer);
+        assert_eq![1, moves.len()];
+
+        nine.remap(&moves);
+        assert![nine
+            .sprites()
+            .iter()
+            .all(|sprite| vx.dyntex().is_alive(sprite))];
+        assert![vx.dyntex().set_size(&nine, (3.0, 3.0))];
+
+        vx.dyntex().remove_nine_slice(nine);
+        assert_eq![9, vx.dyntexs[0].removed.len()];
+    }
+
+    #[test]
+    fn nine_slice_moved_by_compaction_can_be_removed() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (100, 100));
+        let layer = vx.dyntex().add_layer(TESTURE, &LayerOptions::new());
+        let first = vx.dyntex().add(&layer, Sprite::new());
+        let nine = vx.dyntex().add_nine_slice(&layer, NineSlice::new());
+        vx.dyntex().remove(first);
+        assert_eq![1, vx.dyntex().compact(&layer).len()];
+
+        vx.dyntex().remove_nine_slice(nine);
+        assert_eq![8, vx.dyntexs[0].removed.len()];
+    }
+
     #[bench]
     fn bench_many_sprites(b: &mut Bencher) {
         let logger = Logger::root(Discard, o!());
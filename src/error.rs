@@ -0,0 +1,138 @@
+use gfx_hal::{buffer, device, image, pso, window};
+use std::fmt;
+
+/// Errors that can occur while setting up [crate::VxDraw] or drawing a frame
+#[derive(Debug)]
+pub enum Error {
+    /// The graphics backend is not supported on this platform
+    UnsupportedBackend,
+    /// The window could not be created
+    Window(winit::error::OsError),
+    /// The OpenGL context could not be created together with its window
+    #[cfg(feature = "gl")]
+    Context(crate::back::glutin::CreationError),
+    /// The window was closed before its size could be read
+    WindowClosed,
+    /// The surface could not be created from the window
+    Surface(window::InitError),
+    /// No adapter (GPU) was found
+    NoAdapter,
+    /// No queue family supports graphics (and presenting to the surface)
+    NoQueueFamily,
+    /// The logical device could not be opened
+    Device(device::CreationError),
+    /// The surface supports none of the present modes we can use
+    NoPresentMode,
+    /// A required format or usage is not supported by the device or surface
+    Unsupported(&'static str),
+    /// No memory type satisfies the given requirements and properties
+    NoMemoryType,
+    /// The host or device ran out of memory
+    OutOfMemory(device::OutOfMemory),
+    /// Device memory could not be allocated
+    Allocation(device::AllocationError),
+    /// Memory could not be bound to a buffer or image
+    Bind(device::BindError),
+    /// Memory could not be mapped
+    Map(device::MapError),
+    /// The device was lost or ran out of memory while waiting for a fence
+    Wait(device::OomOrDeviceLost),
+    /// A buffer could not be created
+    Buffer(buffer::CreationError),
+    /// An image could not be created
+    Image(image::CreationError),
+    /// An image view could not be created
+    ImageView(image::ViewError),
+    /// The swapchain could not be created
+    Swapchain(window::CreationError),
+    /// No image could be acquired from the swapchain
+    Acquire(window::AcquireError),
+    /// The frame could not be presented
+    Present(window::PresentError),
+    /// The SPIR-V code of a shader could not be read
+    Spirv(std::io::Error),
+    /// A shader module could not be created
+    Shader(device::ShaderError),
+    /// A graphics pipeline could not be created
+    Pipeline(pso::CreationError),
+    /// A descriptor set could not be allocated from its pool
+    DescriptorSet(pso::AllocationError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnsupportedBackend => write!(f, "Unable to create backend"),
+            Error::Window(err) => write!(f, "Unable to create window: {}", err),
+            #[cfg(feature = "gl")]
+            Error::Context(err) => write!(f, "Unable to create context: {}", err),
+            Error::WindowClosed => write!(f, "The window has been closed"),
+            Error::Surface(err) => write!(f, "Creating surface failed: {:?}", err),
+            Error::NoAdapter => write!(f, "No adapters found"),
+            Error::NoQueueFamily => write!(f, "No suitable queue family found"),
+            Error::Device(err) => write!(f, "Unable to open device: {:?}", err),
+            Error::NoPresentMode => write!(f, "No PresentMode values specified!"),
+            Error::Unsupported(what) => write!(f, "Unsupported: {}", what),
+            Error::NoMemoryType => write!(f, "Unable to find memory type id"),
+            Error::OutOfMemory(err) => write!(f, "{}", err),
+            Error::Allocation(err) => write!(f, "Unable to allocate memory: {:?}", err),
+            Error::Bind(err) => write!(f, "Unable to bind memory: {:?}", err),
+            Error::Map(err) => write!(f, "Unable to map memory: {:?}", err),
+            Error::Wait(err) => write!(f, "Unable to wait for fence: {:?}", err),
+            Error::Buffer(err) => write!(f, "Unable to create buffer: {:?}", err),
+            Error::Image(err) => write!(f, "Unable to create image: {:?}", err),
+            Error::ImageView(err) => write!(f, "Unable to create image view: {:?}", err),
+            Error::Swapchain(err) => write!(f, "Unable to create swapchain: {:?}", err),
+            Error::Acquire(err) => write!(f, "Acquire image error: {:?}", err),
+            Error::Present(err) => write!(f, "Present error: {:?}", err),
+            Error::Spirv(err) => write!(f, "Unable to read SPIR-V: {}", err),
+            Error::Shader(err) => write!(f, "Unable to create shader module: {:?}", err),
+            Error::Pipeline(err) => write!(f, "Unable to create pipeline: {:?}", err),
+            Error::DescriptorSet(err) => write!(f, "Unable to allocate descriptor set: {:?}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+macro_rules! from_error {
+    ($($variant:ident($ty:ty)),* $(,)*) => {
+        $(
+            impl From<$ty> for Error {
+                fn from(err: $ty) -> Self {
+                    Error::$variant(err)
+                }
+            }
+        )*
+    };
+}
+
+from_error![
+    Window(winit::error::OsError),
+    Surface(window::InitError),
+    Device(device::CreationError),
+    OutOfMemory(device::OutOfMemory),
+    Allocation(device::AllocationError),
+    Bind(device::BindError),
+    Map(device::MapError),
+    Wait(device::OomOrDeviceLost),
+    Buffer(buffer::CreationError),
+    Image(image::CreationError),
+    ImageView(image::ViewError),
+    Swapchain(window::CreationError),
+    Acquire(window::AcquireError),
+    Present(window::PresentError),
+    Spirv(std::io::Error),
+    Shader(device::ShaderError),
+    Pipeline(pso::CreationError),
+    DescriptorSet(pso::AllocationError),
+];
+
+#[cfg(feature = "gl")]
+from_error![Context(crate::back::glutin::CreationError)];
+
+impl From<gfx_hal::UnsupportedBackend> for Error {
+    fn from(_: gfx_hal::UnsupportedBackend) -> Self {
+        Error::UnsupportedBackend
+    }
+}
@@ -57,7 +57,8 @@
 extern crate test;
 
 pub use crate::data::VxDraw;
-use crate::data::{DrawType, LayerHoles, StreamingTextureWrite};
+use crate::data::{DrawType, LayerHoles};
+pub use crate::error::Error;
 use arrayvec::ArrayVec;
 pub use cgmath::prelude;
 use cgmath::prelude::*;
@@ -86,17 +87,25 @@ use gfx_hal::{
     Backend, Instance,
 };
 use slog::{crit, debug, error, info, o, trace, warn, Discard, Logger};
+use std::collections::HashMap;
 use std::iter::once;
 use std::mem::ManuallyDrop;
 use winit::{dpi::LogicalSize, event_loop::EventLoop, window::WindowBuilder};
 
+pub mod animation;
 pub mod blender;
+pub mod camera;
 mod data;
 pub mod debtri;
 pub mod dyntex;
+mod error;
+pub mod postproc;
 pub mod quads;
+pub mod rentex;
+pub mod shapes;
 pub mod strtex;
 pub mod text;
+pub mod tilemap;
 pub mod utils;
 
 use utils::*;
@@ -223,9 +232,7 @@ fn set_window_size(window: &glutin::Window, show: ShowWindow) -> Extent2D {
         }
         ShowWindow::Enable => window
             .get_inner_size()
-            .unwrap()
-            .to_physical(dpi_factor)
-            .into(),
+            .map_or((0, 0), |size| size.to_physical(dpi_factor).into()),
         ShowWindow::Custom(width, height) => {
             window.set_inner_size(LogicalSize {
                 width: width as f64 / dpi_factor,
@@ -240,20 +247,109 @@ fn set_window_size(window: &glutin::Window, show: ShowWindow) -> Extent2D {
     }
 }
 
+#[cfg(feature = "gl")]
+static BACKEND: &str = "OpenGL";
+#[cfg(feature = "vulkan")]
+static BACKEND: &str = "Vulkan";
+#[cfg(feature = "metal")]
+static BACKEND: &str = "Metal";
+#[cfg(feature = "dx12")]
+static BACKEND: &str = "Dx12";
+
+/// Device objects created while initializing, before [VxDraw] exists to own them
+#[derive(Default)]
+struct InitObjects {
+    swapchain: Option<<back::Backend as Backend>::Swapchain>,
+    render_pass: Option<<back::Backend as Backend>::RenderPass>,
+    image_views: Vec<<back::Backend as Backend>::ImageView>,
+    depth_images: Vec<<back::Backend as Backend>::Image>,
+    depth_image_memories: Vec<<back::Backend as Backend>::Memory>,
+    depth_image_views: Vec<<back::Backend as Backend>::ImageView>,
+    framebuffers: Vec<<back::Backend as Backend>::Framebuffer>,
+    frames_in_flight_fences: Vec<<back::Backend as Backend>::Fence>,
+    present_wait_semaphores: Vec<<back::Backend as Backend>::Semaphore>,
+    acquire_image_semaphores: Vec<<back::Backend as Backend>::Semaphore>,
+    acquire_image_semaphore_free: Option<<back::Backend as Backend>::Semaphore>,
+    command_pool: Option<<back::Backend as Backend>::CommandPool>,
+}
+
+/// Destroys the objects created so far if initialization returns early
+///
+/// Call [InitGuard::release] once everything has been created to hand the objects over.
+struct InitGuard<'a> {
+    device: &'a <back::Backend as Backend>::Device,
+    objects: InitObjects,
+}
+
+impl<'a> InitGuard<'a> {
+    fn new(device: &'a <back::Backend as Backend>::Device) -> Self {
+        Self {
+            device,
+            objects: InitObjects::default(),
+        }
+    }
+
+    fn release(mut self) -> InitObjects {
+        std::mem::take(&mut self.objects)
+    }
+}
+
+impl Drop for InitGuard<'_> {
+    fn drop(&mut self) {
+        let device = self.device;
+        let objects = &mut self.objects;
+        unsafe {
+            if let Some(command_pool) = objects.command_pool.take() {
+                device.destroy_command_pool(command_pool);
+            }
+            for sema in objects
+                .present_wait_semaphores
+                .drain(..)
+                .chain(objects.acquire_image_semaphores.drain(..))
+                .chain(objects.acquire_image_semaphore_free.take())
+            {
+                device.destroy_semaphore(sema);
+            }
+            for fence in objects.frames_in_flight_fences.drain(..) {
+                device.destroy_fence(fence);
+            }
+            for fb in objects.framebuffers.drain(..) {
+                device.destroy_framebuffer(fb);
+            }
+            for iv in objects
+                .image_views
+                .drain(..)
+                .chain(objects.depth_image_views.drain(..))
+            {
+                device.destroy_image_view(iv);
+            }
+            for di in objects.depth_images.drain(..) {
+                device.destroy_image(di);
+            }
+            for memory in objects.depth_image_memories.drain(..) {
+                device.free_memory(memory);
+            }
+            if let Some(render_pass) = objects.render_pass.take() {
+                device.destroy_render_pass(render_pass);
+            }
+            if let Some(swapchain) = objects.swapchain.take() {
+                device.destroy_swapchain(swapchain);
+            }
+        }
+    }
+}
+
 impl VxDraw {
     /// Spawn a new VxDraw context with a window
     ///
     /// This method sets up all that is necessary for drawing.
+    /// Panics if initialization fails, see [VxDraw::try_new] for a fallible version.
     pub fn new(log: Logger, show: ShowWindow, events: &EventLoop<()>) -> VxDraw {
-        #[cfg(feature = "gl")]
-        static BACKEND: &str = "OpenGL";
-        #[cfg(feature = "vulkan")]
-        static BACKEND: &str = "Vulkan";
-        #[cfg(feature = "metal")]
-        static BACKEND: &str = "Metal";
-        #[cfg(feature = "dx12")]
-        static BACKEND: &str = "Dx12";
+        Self::try_new(log, show, events).expect("Unable to initialize VxDraw")
+    }
 
+    /// Spawn a new VxDraw context with a window, returning an error if initialization fails
+    pub fn try_new(log: Logger, show: ShowWindow, events: &EventLoop<()>) -> Result<VxDraw, Error> {
         info!(log, "Initializing rendering"; "show" => ?show, "backend" => BACKEND);
 
         let window_builder = WindowBuilder::new().with_visible(match show {
@@ -270,8 +366,7 @@ impl VxDraw {
                     None,
                 )
                 .with_vsync(true);
-                back::glutin::WindowedContext::new_windowed(window_builder, builder, &events)
-                    .unwrap()
+                back::glutin::WindowedContext::new_windowed(window_builder, builder, &events)?
             };
 
             set_window_size(window.window(), show);
@@ -280,7 +375,7 @@ impl VxDraw {
                 debug!(log, "Window DPI factor"; "factor" => dpi_factor);
                 let (w, h): (u32, u32) = window
                     .get_inner_size()
-                    .unwrap()
+                    .ok_or(Error::WindowClosed)?
                     .to_physical(dpi_factor)
                     .into();
                 Extent2D {
@@ -299,16 +394,11 @@ impl VxDraw {
         let (window, vk_inst, mut adapters, mut surf, dims) = {
             let mut window = window_builder
                 .with_x11_window_type(vec![winit::platform::unix::XWindowType::Dialog])
-                .build(&events)
-                .unwrap();
+                .build(&events)?;
             let version = 1;
-            let vk_inst =
-                back::Instance::create("renderer", version).expect("Unable to create backend");
-            let surf: <back::Backend as Backend>::Surface = unsafe {
-                vk_inst
-                    .create_surface(&window)
-                    .expect("Creating surface failed")
-            };
+            let vk_inst = back::Instance::create("renderer", version)?;
+            let surf: <back::Backend as Backend>::Surface =
+                unsafe { vk_inst.create_surface(&window)? };
             let adapters = vk_inst.enumerate_adapters();
             let dims = set_window_size(&mut window, show);
             let dpi_factor = window.hidpi_factor();
@@ -322,6 +412,7 @@ impl VxDraw {
 
         if adapters.is_empty() {
             crit!(log, "No adapters found");
+            return Err(Error::NoAdapter);
         }
 
         for (idx, adap) in adapters.iter().enumerate() {
@@ -343,20 +434,17 @@ impl VxDraw {
             .find(|family| {
                 surf.supports_queue_family(family) && family.queue_type().supports_graphics()
             })
-            .unwrap();
+            .ok_or(Error::NoQueueFamily)?;
 
         let mut gpu = unsafe {
             let scheduling_priority = 1.0;
-            adapter
-                .physical_device
-                .open(
-                    &[(family, &[scheduling_priority])],
-                    gfx_hal::Features::empty(),
-                )
-                .unwrap()
+            adapter.physical_device.open(
+                &[(family, &[scheduling_priority])],
+                gfx_hal::Features::empty(),
+            )?
         };
 
-        let queue_group = gpu.queue_groups.pop().unwrap();
+        let queue_group = gpu.queue_groups.pop().ok_or(Error::NoQueueFamily)?;
         let device = gpu.device;
 
         let _phys_dev_limits = adapter.physical_device.limits();
@@ -376,11 +464,16 @@ impl VxDraw {
                 .unwrap_or(formats[0])
         });
 
-        assert!(adapter
+        if !adapter
             .physical_device
             .format_properties(Some(format))
             .optimal_tiling
-            .contains(f::ImageFeature::BLIT_SRC));
+            .contains(f::ImageFeature::BLIT_SRC)
+        {
+            return Err(Error::Unsupported(
+                "Surface format can not be used as blit source",
+            ));
+        }
 
         debug!(log, "Format chosen"; "format" => ?format);
         debug!(log, "Available present modes"; "modes" => ?present_modes);
@@ -397,8 +490,7 @@ impl VxDraw {
             .iter()
             .cloned()
             .find(|pm| present_modes.contains(*pm))
-            .ok_or("No PresentMode values specified!")
-            .unwrap()
+            .ok_or(Error::NoPresentMode)?
         };
         debug!(log, "Using best possible present mode"; "mode" => ?&present_mode);
 
@@ -430,9 +522,10 @@ impl VxDraw {
 
         debug!(log, "Swapchain final configuration"; "swapchain" => ?swap_config);
 
+        let mut guard = InitGuard::new(&device);
         let (swapchain, images) =
-            unsafe { device.create_swapchain(&mut surf, swap_config.clone(), None) }
-                .expect("Unable to create swapchain");
+            unsafe { device.create_swapchain(&mut surf, swap_config.clone(), None) }?;
+        guard.objects.swapchain = Some(swapchain);
 
         debug!(log, "Image information"; "images" => ?images);
 
@@ -476,152 +569,138 @@ impl VxDraw {
 
             debug!(log, "Render pass info"; "color attachment" => ?color_attachment);
 
-            unsafe {
-                device
-                    .create_render_pass(&[color_attachment, depth], &[subpass], &[])
-                    .map_err(|_| "Couldn't create a render pass!")
-                    .unwrap()
-            }
+            let render_pass =
+                unsafe { device.create_render_pass(&[color_attachment, depth], &[subpass], &[])? };
+            &*guard.objects.render_pass.get_or_insert(render_pass)
         };
 
         debug!(log, "Created render pass for framebuffers"; "renderpass" => ?render_pass);
 
-        let mut depth_images: Vec<<back::Backend as Backend>::Image> = vec![];
-        let mut depth_image_views: Vec<<back::Backend as Backend>::ImageView> = vec![];
-        let mut depth_image_memories: Vec<<back::Backend as Backend>::Memory> = vec![];
-        let mut depth_image_requirements: Vec<m::Requirements> = vec![];
-
-        let (image_views, framebuffers) = {
-            let image_views = images
-                .iter()
-                .map(|image| unsafe {
-                    device
-                        .create_image_view(
-                            &image,
-                            i::ViewKind::D2,
-                            format, // MUST be identical to the image's format
-                            Swizzle::NO,
-                            i::SubresourceRange {
-                                aspects: f::Aspects::COLOR,
-                                levels: 0..1,
-                                layers: 0..1,
-                            },
-                        )
-                        .map_err(|_| "Couldn't create the image_view for the image!")
-                })
-                .collect::<Result<Vec<_>, &str>>()
-                .unwrap();
+        for image in &images {
+            let image_view = unsafe {
+                device.create_image_view(
+                    &image,
+                    i::ViewKind::D2,
+                    format, // MUST be identical to the image's format
+                    Swizzle::NO,
+                    i::SubresourceRange {
+                        aspects: f::Aspects::COLOR,
+                        levels: 0..1,
+                        layers: 0..1,
+                    },
+                )
+            }?;
+            guard.objects.image_views.push(image_view);
+        }
 
-            unsafe {
-                for _ in &image_views {
-                    let mut depth_image = device
-                        .create_image(
-                            i::Kind::D2(dims.width, dims.height, 1, 1),
-                            1,
-                            f::Format::D32Sfloat,
-                            i::Tiling::Optimal,
-                            i::Usage::DEPTH_STENCIL_ATTACHMENT,
-                            i::ViewCapabilities::empty(),
-                        )
-                        .expect("Unable to create depth image");
-                    let requirements = device.get_image_requirements(&depth_image);
-                    let memory_type_id =
-                        find_memory_type_id(&adapter, requirements, m::Properties::DEVICE_LOCAL);
-                    let memory = device
-                        .allocate_memory(memory_type_id, requirements.size)
-                        .expect("Couldn't allocate image memory!");
-                    device
-                        .bind_image_memory(&memory, 0, &mut depth_image)
-                        .expect("Couldn't bind the image memory!");
-                    let image_view = device
-                        .create_image_view(
-                            &depth_image,
-                            i::ViewKind::D2,
-                            f::Format::D32Sfloat,
-                            f::Swizzle::NO,
-                            i::SubresourceRange {
-                                aspects: f::Aspects::DEPTH,
-                                levels: 0..1,
-                                layers: 0..1,
-                            },
-                        )
-                        .expect("Couldn't create the image view!");
-                    depth_images.push(depth_image);
-                    depth_image_views.push(image_view);
-                    depth_image_requirements.push(requirements);
-                    depth_image_memories.push(memory);
-                }
+        unsafe {
+            for _ in &images {
+                let depth_image = device.create_image(
+                    i::Kind::D2(dims.width, dims.height, 1, 1),
+                    1,
+                    f::Format::D32Sfloat,
+                    i::Tiling::Optimal,
+                    i::Usage::DEPTH_STENCIL_ATTACHMENT,
+                    i::ViewCapabilities::empty(),
+                )?;
+                let idx = guard.objects.depth_images.len();
+                guard.objects.depth_images.push(depth_image);
+                let depth_image = &mut guard.objects.depth_images[idx];
+                let requirements = device.get_image_requirements(depth_image);
+                let memory_type_id =
+                    find_memory_type_id(&adapter, requirements, m::Properties::DEVICE_LOCAL)?;
+                let memory = device.allocate_memory(memory_type_id, requirements.size)?;
+                guard.objects.depth_image_memories.push(memory);
+                device.bind_image_memory(
+                    &guard.objects.depth_image_memories[idx],
+                    0,
+                    depth_image,
+                )?;
+                let image_view = device.create_image_view(
+                    depth_image,
+                    i::ViewKind::D2,
+                    f::Format::D32Sfloat,
+                    f::Swizzle::NO,
+                    i::SubresourceRange {
+                        aspects: f::Aspects::DEPTH,
+                        levels: 0..1,
+                        layers: 0..1,
+                    },
+                )?;
+                guard.objects.depth_image_views.push(image_view);
             }
-            let framebuffers: Vec<<back::Backend as Backend>::Framebuffer> = {
-                image_views
-                    .iter()
-                    .enumerate()
-                    .map(|(idx, image_view)| unsafe {
-                        device
-                            .create_framebuffer(
-                                &render_pass,
-                                vec![image_view, &depth_image_views[idx]],
-                                i::Extent {
-                                    width: dims.width as u32,
-                                    height: dims.height as u32,
-                                    depth: 1,
-                                },
-                            )
-                            .map_err(|_| "Failed to create a framebuffer!")
-                    })
-                    .collect::<Result<Vec<_>, &str>>()
-                    .unwrap()
-            };
-            (image_views, framebuffers)
-        };
+        }
+
+        for (image_view, depth_image_view) in guard
+            .objects
+            .image_views
+            .iter()
+            .zip(&guard.objects.depth_image_views)
+        {
+            let framebuffer = unsafe {
+                device.create_framebuffer(
+                    render_pass,
+                    vec![image_view, depth_image_view],
+                    i::Extent {
+                        width: dims.width as u32,
+                        height: dims.height as u32,
+                        depth: 1,
+                    },
+                )
+            }?;
+            guard.objects.framebuffers.push(framebuffer);
+        }
 
-        debug!(log, "Created image views"; "image views" => ?image_views);
-        debug!(log, "Framebuffer information"; "framebuffers" => ?framebuffers);
+        debug!(log, "Created image views"; "image views" => ?guard.objects.image_views);
+        debug!(log, "Framebuffer information"; "framebuffers" => ?guard.objects.framebuffers);
 
         let max_frames_in_flight = image_count as usize;
         assert!(max_frames_in_flight > 0);
 
-        let mut frames_in_flight_fences = vec![];
-        let mut present_wait_semaphores = vec![];
         for _ in 0..max_frames_in_flight {
-            frames_in_flight_fences.push(device.create_fence(true).expect("Can't create fence"));
-            present_wait_semaphores
-                .push(device.create_semaphore().expect("Can't create semaphore"));
+            let fence = device.create_fence(true)?;
+            guard.objects.frames_in_flight_fences.push(fence);
+            let sema = device.create_semaphore()?;
+            guard.objects.present_wait_semaphores.push(sema);
         }
 
-        let acquire_image_semaphores = (0..image_count)
-            .map(|_| device.create_semaphore().expect("Can't create semaphore"))
-            .collect::<Vec<_>>();
+        for _ in 0..image_count {
+            let sema = device.create_semaphore()?;
+            guard.objects.acquire_image_semaphores.push(sema);
+        }
+        guard.objects.acquire_image_semaphore_free = Some(device.create_semaphore()?);
 
-        debug!(log, "Allocated fences and semaphores"; "count" => frames_in_flight_fences.len());
+        debug!(log, "Allocated fences and semaphores"; "count" => max_frames_in_flight);
 
-        let mut command_pool = unsafe {
-            device
-                .create_command_pool(
-                    queue_group.family,
-                    pool::CommandPoolCreateFlags::RESET_INDIVIDUAL,
-                )
-                .unwrap()
+        let command_pool = unsafe {
+            device.create_command_pool(
+                queue_group.family,
+                pool::CommandPoolCreateFlags::RESET_INDIVIDUAL,
+            )?
         };
+        let command_pool = guard.objects.command_pool.get_or_insert(command_pool);
 
-        let command_buffers: Vec<_> = framebuffers
-            .iter()
+        let command_buffers: Vec<_> = (0..image_count)
             .map(|_| unsafe { command_pool.allocate_one(command::Level::Primary) })
             .collect();
 
-        let debtris = debtri::create_debug_triangle(&device, &adapter, format, images.len());
+        let debtris = debtri::create_debug_triangle(&device, &adapter, format, images.len())?;
+        let objects = guard.release();
 
         let mut vx = VxDraw {
-            acquire_image_semaphores,
+            acquire_image_semaphores: objects.acquire_image_semaphores,
             acquire_image_semaphore_free: ManuallyDrop::new(
-                device
-                    .create_semaphore()
-                    .expect("Unable to create semaphore"),
+                objects
+                    .acquire_image_semaphore_free
+                    .expect("Semaphore was created"),
             ),
             adapter,
             images,
+            image_memories: vec![],
             command_buffers,
-            command_pool: ManuallyDrop::new(command_pool),
+            command_pool: ManuallyDrop::new(
+                objects.command_pool.expect("Command pool was created"),
+            ),
             current_frame: 0,
             draw_order: vec![],
             layer_holes: LayerHoles::new(image_count as usize),
@@ -629,12 +708,12 @@ impl VxDraw {
             device,
             // device_limits: phys_dev_limits,
             texts: vec![],
-            frames_in_flight_fences,
-            framebuffers,
+            frames_in_flight_fences: objects.frames_in_flight_fences,
+            framebuffers: objects.framebuffers,
             format,
-            image_views,
+            image_views: objects.image_views,
             perspective: Matrix4::identity(),
-            present_wait_semaphores,
+            present_wait_semaphores: objects.present_wait_semaphores,
             queue_group,
             render_area: pso::Rect {
                 x: 0,
@@ -642,21 +721,222 @@ impl VxDraw {
                 w: dims.width as i16,
                 h: dims.height as i16,
             },
-            render_pass: ManuallyDrop::new(render_pass),
+            render_pass: ManuallyDrop::new(objects.render_pass.expect("Render pass was created")),
             resized_since_last_render: false,
-            surf: ManuallyDrop::new(surf),
-            swapchain: ManuallyDrop::new(swapchain),
+            surf: Some(surf),
+            swapchain: objects.swapchain,
             swapconfig: swap_config,
             strtexs: vec![],
             dyntexs: vec![],
+            tilemaps: vec![],
+            shapes: vec![],
+            rentexs: vec![],
+            postprocs: vec![],
+            postproc_targets: None,
+            clips: vec![],
+            animations: HashMap::new(),
+            generation: 0,
             quads: vec![],
-            depth_images,
-            depth_image_views,
-            depth_image_memories,
+            depth_images: objects.depth_images,
+            depth_image_views: objects.depth_image_views,
+            depth_image_memories: objects.depth_image_memories,
             #[cfg(not(feature = "gl"))]
             vk_inst,
             #[cfg(not(feature = "gl"))]
-            window,
+            window: Some(window),
+            log,
+            debtris,
+
+            clear_color: ClearColor {
+                float32: [1.0f32, 0.25, 0.5, 0.0],
+            },
+        };
+        vx.window_resized_recreate_swapchain()?;
+        vx.resized_since_last_render = false;
+        Ok(vx)
+    }
+
+    /// Spawn a new VxDraw context that renders into device-owned images
+    ///
+    /// No window, surface, or swapchain is created, so this works on machines without a display
+    /// server. [VxDraw::draw_frame] and [VxDraw::draw_frame_copy_framebuffer] behave the same as
+    /// with a window, except that nothing is presented.
+    /// Panics if initialization fails, see [VxDraw::try_new_offscreen] for a fallible version.
+    #[cfg(not(feature = "gl"))]
+    pub fn new_offscreen(log: Logger, size: (u32, u32)) -> VxDraw {
+        Self::try_new_offscreen(log, size).expect("Unable to initialize VxDraw")
+    }
+
+    /// Spawn a new offscreen VxDraw context, returning an error if initialization fails
+    #[cfg(not(feature = "gl"))]
+    pub fn try_new_offscreen(log: Logger, size: (u32, u32)) -> Result<VxDraw, Error> {
+        info!(log, "Initializing offscreen rendering"; "size" => ?size, "backend" => BACKEND);
+
+        let version = 1;
+        let vk_inst = back::Instance::create("renderer", version)?;
+        let mut adapters = vk_inst.enumerate_adapters();
+
+        debug!(log, "Adapters found"; "count" => adapters.len());
+
+        if adapters.is_empty() {
+            crit!(log, "No adapters found");
+            return Err(Error::NoAdapter);
+        }
+
+        for (idx, adap) in adapters.iter().enumerate() {
+            let info = adap.info.clone();
+            let limits = adap.physical_device.limits();
+            debug!(log, "Adapter found"; "idx" => idx, "info" => ?info, "device limits" => ?limits);
+        }
+
+        let adapter = adapters.remove(0);
+
+        let family = adapter
+            .queue_families
+            .iter()
+            .find(|family| family.queue_type().supports_graphics())
+            .ok_or(Error::NoQueueFamily)?;
+
+        let mut gpu = unsafe {
+            let scheduling_priority = 1.0;
+            adapter.physical_device.open(
+                &[(family, &[scheduling_priority])],
+                gfx_hal::Features::empty(),
+            )?
+        };
+
+        let queue_group = gpu.queue_groups.pop().ok_or(Error::NoQueueFamily)?;
+        let device = gpu.device;
+
+        let format = f::Format::Rgba8Srgb;
+        let image_count = 2;
+        let mut swap_config = SwapchainConfig::new(size.0, size.1, format, image_count);
+        swap_config.image_usage |= i::Usage::TRANSFER_SRC;
+
+        debug!(log, "Offscreen configuration"; "config" => ?swap_config);
+
+        // Same as the swapchain render pass, but the color images are left ready for copying
+        // instead of presenting
+        let render_pass = {
+            let color_attachment = pass::Attachment {
+                format: Some(format),
+                samples: 1,
+                ops: pass::AttachmentOps {
+                    load: pass::AttachmentLoadOp::Clear,
+                    store: pass::AttachmentStoreOp::Store,
+                },
+                stencil_ops: pass::AttachmentOps::DONT_CARE,
+                layouts: i::Layout::Undefined..i::Layout::TransferSrcOptimal,
+            };
+            let depth = pass::Attachment {
+                format: Some(f::Format::D32Sfloat),
+                samples: 1,
+                ops: pass::AttachmentOps::new(
+                    pass::AttachmentLoadOp::Clear,
+                    pass::AttachmentStoreOp::Store,
+                ),
+                stencil_ops: pass::AttachmentOps::DONT_CARE,
+                layouts: i::Layout::Undefined..i::Layout::DepthStencilAttachmentOptimal,
+            };
+
+            let subpass = pass::SubpassDesc {
+                colors: &[(0, i::Layout::ColorAttachmentOptimal)],
+                depth_stencil: Some(&(1, i::Layout::DepthStencilAttachmentOptimal)),
+                inputs: &[],
+                resolves: &[],
+                preserves: &[],
+            };
+
+            unsafe { device.create_render_pass(&[color_attachment, depth], &[subpass], &[])? }
+        };
+        let mut guard = InitGuard::new(&device);
+        guard.objects.render_pass = Some(render_pass);
+
+        let max_frames_in_flight = image_count as usize;
+
+        for _ in 0..max_frames_in_flight {
+            let fence = device.create_fence(true)?;
+            guard.objects.frames_in_flight_fences.push(fence);
+            let sema = device.create_semaphore()?;
+            guard.objects.present_wait_semaphores.push(sema);
+        }
+
+        for _ in 0..image_count {
+            let sema = device.create_semaphore()?;
+            guard.objects.acquire_image_semaphores.push(sema);
+        }
+        guard.objects.acquire_image_semaphore_free = Some(device.create_semaphore()?);
+
+        let command_pool = unsafe {
+            device.create_command_pool(
+                queue_group.family,
+                pool::CommandPoolCreateFlags::RESET_INDIVIDUAL,
+            )?
+        };
+        let command_pool = guard.objects.command_pool.get_or_insert(command_pool);
+
+        let command_buffers: Vec<_> = (0..image_count)
+            .map(|_| unsafe { command_pool.allocate_one(command::Level::Primary) })
+            .collect();
+
+        let debtris =
+            debtri::create_debug_triangle(&device, &adapter, format, image_count as usize)?;
+        let objects = guard.release();
+
+        let mut vx = VxDraw {
+            acquire_image_semaphores: objects.acquire_image_semaphores,
+            acquire_image_semaphore_free: ManuallyDrop::new(
+                objects
+                    .acquire_image_semaphore_free
+                    .expect("Semaphore was created"),
+            ),
+            adapter,
+            images: vec![],
+            image_memories: vec![],
+            command_buffers,
+            command_pool: ManuallyDrop::new(
+                objects.command_pool.expect("Command pool was created"),
+            ),
+            current_frame: 0,
+            draw_order: vec![],
+            layer_holes: LayerHoles::new(image_count as usize),
+            max_frames_in_flight,
+            device,
+            texts: vec![],
+            frames_in_flight_fences: objects.frames_in_flight_fences,
+            framebuffers: vec![],
+            format,
+            image_views: vec![],
+            perspective: Matrix4::identity(),
+            present_wait_semaphores: objects.present_wait_semaphores,
+            queue_group,
+            render_area: pso::Rect {
+                x: 0,
+                y: 0,
+                w: size.0 as i16,
+                h: size.1 as i16,
+            },
+            render_pass: ManuallyDrop::new(objects.render_pass.expect("Render pass was created")),
+            resized_since_last_render: false,
+            surf: None,
+            swapchain: None,
+            swapconfig: swap_config,
+            strtexs: vec![],
+            dyntexs: vec![],
+            tilemaps: vec![],
+            shapes: vec![],
+            rentexs: vec![],
+            postprocs: vec![],
+            postproc_targets: None,
+            clips: vec![],
+            animations: HashMap::new(),
+            generation: 0,
+            quads: vec![],
+            depth_images: vec![],
+            depth_image_views: vec![],
+            depth_image_memories: vec![],
+            vk_inst,
+            window: None,
             log,
             debtris,
 
@@ -664,9 +944,12 @@ impl VxDraw {
                 float32: [1.0f32, 0.25, 0.5, 0.0],
             },
         };
-        vx.window_resized_recreate_swapchain();
+        vx.recreate_offscreen_images(Extent2D {
+            width: size.0,
+            height: size.1,
+        })?;
         vx.resized_since_last_render = false;
-        vx
+        Ok(vx)
     }
 
     /// Set the perspective to be used when drawing geometry
@@ -674,6 +957,21 @@ impl VxDraw {
         self.perspective = perspective;
     }
 
+    /// Set the perspective from a camera, fitted to the current window size
+    ///
+    /// The perspective does not follow later changes to the camera or window, call this again
+    /// after changing either.
+    pub fn set_camera(&mut self, camera: &camera::Camera2D) {
+        self.perspective = self.camera_matrix(camera);
+    }
+
+    /// Compute the perspective matrix of a camera for the current window size
+    ///
+    /// Use this to give a single layer its own camera through the layer's `set_perspective`.
+    pub fn camera_matrix(&self, camera: &camera::Camera2D) -> Matrix4<f32> {
+        camera.matrix(self.get_window_size_in_pixels_float())
+    }
+
     /// Translate a pixel to the world coordinates according to the current perspective
     ///
     /// To set the current perspective see [VxDraw::set_perspective].
@@ -693,15 +991,32 @@ impl VxDraw {
         }
     }
 
-    pub(crate) fn wait_for_fences(&self) {
+    /// The layout the main render pass leaves the color images in
+    pub(crate) fn color_layout(&self) -> i::Layout {
+        if self.swapchain.is_some() {
+            i::Layout::Present
+        } else {
+            i::Layout::TransferSrcOptimal
+        }
+    }
+
+    pub(crate) fn wait_for_fences(&self) -> Result<(), Error> {
         unsafe {
-            self.device
-                .wait_for_fences(
-                    &self.frames_in_flight_fences[..],
-                    device::WaitFor::All,
-                    u64::max_value(),
-                )
-                .expect("Unable to wait for frame fences");
+            self.device.wait_for_fences(
+                &self.frames_in_flight_fences[..],
+                device::WaitFor::All,
+                u64::max_value(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Wait for all frames in flight, logging instead of failing if the device was lost
+    ///
+    /// Used before destroying resources, which is still done after a failed wait.
+    pub(crate) fn wait_for_fences_or_log(&self) {
+        if let Err(err) = self.wait_for_fences() {
+            error!(self.log, "Unable to wait for frame fences"; "error" => %err);
         }
     }
 
@@ -725,25 +1040,96 @@ impl VxDraw {
         self.swapconfig.image_count as usize
     }
 
+    /// Remove the first layer matching the predicate from the draw order, or from the draw order
+    /// of a render texture it is attached to
+    pub(crate) fn take_from_draw_order(
+        &mut self,
+        matches: impl Fn(&DrawType) -> bool,
+    ) -> Option<DrawType> {
+        let position = self.find_in_draw_order(matches)?;
+        Some(self.draw_order_of(position.rentex).remove(position.index))
+    }
+
+    /// Find the first layer matching the predicate in the draw order, or in the draw order of a
+    /// render texture it is attached to
+    pub(crate) fn find_in_draw_order(
+        &self,
+        matches: impl Fn(&DrawType) -> bool,
+    ) -> Option<LayerPosition> {
+        if let Some(index) = self.draw_order.iter().position(&matches) {
+            return Some(LayerPosition {
+                rentex: None,
+                index,
+            });
+        }
+        for (id, rentex) in self.rentexs.iter().enumerate() {
+            if let Some(rentex) = rentex {
+                if let Some(index) = rentex.draw_order.iter().position(&matches) {
+                    return Some(LayerPosition {
+                        rentex: Some(id),
+                        index,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Get the main draw order, or the draw order of a render texture
+    pub(crate) fn draw_order_of(&mut self, rentex: Option<usize>) -> &mut Vec<DrawType> {
+        match rentex {
+            Some(id) => {
+                &mut self.rentexs[id]
+                    .as_mut()
+                    .expect("Render texture has been removed")
+                    .draw_order
+            }
+            None => &mut self.draw_order,
+        }
+    }
+
     /// Swap two layer orders
+    ///
+    /// Layers attached to render textures can be swapped too, also with layers drawn elsewhere,
+    /// in which case each layer moves to where the other was drawn. Nothing happens if either
+    /// layer has been removed.
     pub fn swap_layers(&mut self, layer1: &impl Layerable, layer2: &impl Layerable) {
-        let idx1 = layer1.get_layer(self);
-        let idx2 = layer2.get_layer(self);
-        self.draw_order.swap(idx1, idx2);
+        let (pos1, pos2) = match (layer1.get_layer(self), layer2.get_layer(self)) {
+            (Some(pos1), Some(pos2)) => (pos1, pos2),
+            _ => return,
+        };
+        let draw_type1 = self.draw_order_of(pos1.rentex)[pos1.index].clone();
+        let draw_type2 =
+            std::mem::replace(&mut self.draw_order_of(pos2.rentex)[pos2.index], draw_type1);
+        self.draw_order_of(pos1.rentex)[pos1.index] = draw_type2;
     }
 
     /// Get the size of the display window in floats
+    ///
+    /// Falls back to the size of the swapchain images if the window has been closed.
     #[cfg(feature = "gl")]
     pub fn get_window_size_in_pixels(&self) -> (u32, u32) {
-        let (w, h): (u32, u32) = self.surf.get_window().get_inner_size().unwrap().into();
-        (w, h)
+        match self
+            .surf
+            .as_ref()
+            .and_then(|surf| surf.get_window().get_inner_size())
+        {
+            Some(size) => size.into(),
+            None => (self.swapconfig.extent.width, self.swapconfig.extent.height),
+        }
     }
 
     /// Get the size of the display window in floats
+    ///
+    /// When rendering offscreen this is the size of the render target.
     #[cfg(feature = "vulkan")]
     pub fn get_window_size_in_pixels(&self) -> (u32, u32) {
-        let (w, h): (u32, u32) = self.window.inner_size().into();
-        (w, h)
+        if let Some(ref window) = self.window {
+            let (w, h): (u32, u32) = window.inner_size().into();
+            (w, h)
+        } else {
+            (self.swapconfig.extent.width, self.swapconfig.extent.height)
+        }
     }
 
     /// Get the size of the display window in floats
@@ -755,21 +1141,33 @@ impl VxDraw {
     /// Set the size of the display window
     #[cfg(feature = "gl")]
     pub fn set_window_size(&mut self, size: (u32, u32)) {
-        let dpi_factor = self.surf.get_window().get_hidpi_factor();
-        self.surf.get_window().set_inner_size(LogicalSize {
-            width: f64::from(size.0) / dpi_factor,
-            height: f64::from(size.1) / dpi_factor,
-        });
+        if let Some(ref surf) = self.surf {
+            let window = surf.get_window();
+            let dpi_factor = window.get_hidpi_factor();
+            window.set_inner_size(LogicalSize {
+                width: f64::from(size.0) / dpi_factor,
+                height: f64::from(size.1) / dpi_factor,
+            });
+        }
     }
 
     /// Set the size of the display window
+    ///
+    /// When rendering offscreen this reallocates the render target images instead.
     #[cfg(feature = "vulkan")]
     pub fn set_window_size(&mut self, size: (u32, u32)) {
-        let dpi_factor = self.window.hidpi_factor();
-        self.window.set_inner_size(LogicalSize {
-            width: f64::from(size.0) * dpi_factor,
-            height: f64::from(size.1) * dpi_factor,
-        });
+        if let Some(ref window) = self.window {
+            let dpi_factor = window.hidpi_factor();
+            window.set_inner_size(LogicalSize {
+                width: f64::from(size.0) * dpi_factor,
+                height: f64::from(size.1) * dpi_factor,
+            });
+        } else if let Err(err) = self.recreate_offscreen_images(Extent2D {
+            width: size.0,
+            height: size.1,
+        }) {
+            error!(self.log, "Unable to resize offscreen images"; "error" => %err);
+        }
     }
 
     /// Get a handle to all debug triangles, allows editing, removal, or creation of debtris
@@ -801,13 +1199,53 @@ impl VxDraw {
         text::Texts::new(self)
     }
 
+    /// Get a handle to all shapes, allows drawing polygons, lines, circles, and rounded
+    /// rectangles. See [shapes::Shapes] for more details.
+    pub fn shapes(&mut self) -> shapes::Shapes {
+        shapes::Shapes::new(self)
+    }
+
+    /// Get a handle to all tile maps, allows drawing grids of tiles from a tileset. See
+    /// [tilemap::Tilemaps] for more details.
+    pub fn tilemap(&mut self) -> tilemap::Tilemaps {
+        tilemap::Tilemaps::new(self)
+    }
+
+    /// Get a handle to all render textures, allows drawing layers into textures instead of the
+    /// screen. See [rentex::Rentex] for more details.
+    pub fn rentex(&mut self) -> rentex::Rentex {
+        rentex::Rentex::new(self)
+    }
+
+    /// Get a handle to the post-processing chain, allows adding full-screen passes that run on
+    /// the composed frame. See [postproc::Postproc] for more details.
+    pub fn postproc(&mut self) -> postproc::Postproc {
+        postproc::Postproc::new(self)
+    }
+
+    /// Hand out a new generation for a layer or object, never 0
+    pub(crate) fn next_generation(&mut self) -> u64 {
+        self.generation += 1;
+        self.generation
+    }
+
+    /// Get a handle to sprite-sheet animations, allows playing clips on dyntex sprites. See
+    /// [animation::Animations] for more details.
+    pub fn animation(&mut self) -> animation::Animations {
+        animation::Animations::new(self)
+    }
+
     /// Draw a frame but also copy the resulting image out
     pub fn draw_frame_copy_framebuffer(&mut self) -> Vec<u8> {
+        self.try_draw_frame_copy_framebuffer()
+            .expect("Unable to draw frame")
+    }
+
+    /// Draw a frame and copy the resulting image out, returning an error on failure
+    pub fn try_draw_frame_copy_framebuffer(&mut self) -> Result<Vec<u8>, Error> {
         let mut vec = vec![];
-        self.draw_frame_internal(true, |s, idx| {
-            copy_image_to_rgb(s, idx, &mut vec);
-        });
-        vec
+        self.draw_frame_internal(true, |s, idx| copy_image_to_rgb(s, idx, &mut vec))?;
+        Ok(vec)
     }
 
     /// Draw a single frame and present it to the screen
@@ -815,7 +1253,15 @@ impl VxDraw {
     /// The view matrix is used to translate all elements on the screen with the exception of debug
     /// triangles and layers that have their own view.
     pub fn draw_frame(&mut self) {
-        self.draw_frame_internal(false, |_, _| {});
+        self.try_draw_frame().expect("Unable to draw frame");
+    }
+
+    /// Draw a single frame and present it to the screen, returning an error on failure
+    ///
+    /// Errors such as a lost device or running out of memory are returned instead of panicking,
+    /// so the caller can report them or fall back.
+    pub fn try_draw_frame(&mut self) -> Result<(), Error> {
+        self.draw_frame_internal(false, |_, _| Ok(()))
     }
 
     /// Check if the window has been resized since the last rendering
@@ -824,20 +1270,32 @@ impl VxDraw {
     }
 
     /// Recreate the swapchain, must be called after a window resize
-    fn window_resized_recreate_swapchain(&mut self) {
+    fn window_resized_recreate_swapchain(&mut self) -> Result<(), Error> {
+        if self.swapchain.is_none() {
+            let extent = self.swapconfig.extent;
+            return self.recreate_offscreen_images(extent);
+        }
         self.resized_since_last_render = true;
-        self.device.wait_idle().unwrap();
+        self.device.wait_idle()?;
 
-        let caps = self.surf.capabilities(&self.adapter.physical_device);
+        let surf = self
+            .surf
+            .as_ref()
+            .ok_or(Error::Unsupported("No surface to present to"))?;
+        let caps = surf.capabilities(&self.adapter.physical_device);
         let present_modes = caps.present_modes;
-        let formats = self.surf.supported_formats(&self.adapter.physical_device);
+        let formats = surf.supported_formats(&self.adapter.physical_device);
         debug!(
             self.log, "Surface capabilities";
             "capabilities" => ?caps,
             "formats" => ?formats
         );
 
-        assert!(formats.iter().any(|f| f.contains(&self.swapconfig.format)));
+        if !formats.iter().any(|f| f.contains(&self.swapconfig.format)) {
+            return Err(Error::Unsupported(
+                "Surface no longer supports the swapchain format",
+            ));
+        }
 
         let pixels = self.get_window_size_in_pixels();
         info!(self.log, "New window size"; "size" => ?pixels);
@@ -873,8 +1331,7 @@ impl VxDraw {
             .iter()
             .cloned()
             .find(|pm| present_modes.contains(*pm))
-            .ok_or("No PresentMode values specified!")
-            .unwrap()
+            .ok_or(Error::NoPresentMode)?
         };
         debug!(self.log, "Using best possible present mode"; "mode" => ?present_mode);
 
@@ -902,17 +1359,17 @@ impl VxDraw {
         }
 
         info!(self.log, "Recreating swapchain"; "config" => ?&swap_config);
+        let surf = self
+            .surf
+            .as_mut()
+            .ok_or(Error::Unsupported("No surface to present to"))?;
         let (swapchain, images) = unsafe {
-            self.device.create_swapchain(
-                &mut self.surf,
-                swap_config,
-                Some(ManuallyDrop::into_inner(core::ptr::read(&self.swapchain))),
-            )
-        }
-        .expect("Unable to create swapchain");
+            self.device
+                .create_swapchain(surf, swap_config, self.swapchain.take())
+        }?;
 
         self.swapconfig.image_count = images.len() as u32;
-        self.swapchain = ManuallyDrop::new(swapchain);
+        self.swapchain = Some(swapchain);
 
         debug!(self.log, "Image information"; "images" => ?images);
 
@@ -925,68 +1382,57 @@ impl VxDraw {
             let image_views = images
                 .iter()
                 .map(|image| unsafe {
-                    self.device
-                        .create_image_view(
-                            &image,
-                            i::ViewKind::D2,
-                            self.swapconfig.format, // MUST be identical to the image's format
-                            Swizzle::NO,
-                            i::SubresourceRange {
-                                aspects: f::Aspects::COLOR,
-                                levels: 0..1,
-                                layers: 0..1,
-                            },
-                        )
-                        .map_err(|_| "Couldn't create the image_view for the image!")
+                    self.device.create_image_view(
+                        &image,
+                        i::ViewKind::D2,
+                        self.swapconfig.format, // MUST be identical to the image's format
+                        Swizzle::NO,
+                        i::SubresourceRange {
+                            aspects: f::Aspects::COLOR,
+                            levels: 0..1,
+                            layers: 0..1,
+                        },
+                    )
                 })
-                .collect::<Result<Vec<_>, &str>>()
-                .unwrap();
+                .collect::<Result<Vec<_>, _>>()?;
 
             unsafe {
                 for _ in &image_views {
-                    let mut depth_image = self
-                        .device
-                        .create_image(
-                            i::Kind::D2(
-                                self.swapconfig.extent.width,
-                                self.swapconfig.extent.height,
-                                1,
-                                1,
-                            ),
+                    let mut depth_image = self.device.create_image(
+                        i::Kind::D2(
+                            self.swapconfig.extent.width,
+                            self.swapconfig.extent.height,
                             1,
-                            f::Format::D32Sfloat,
-                            i::Tiling::Optimal,
-                            i::Usage::DEPTH_STENCIL_ATTACHMENT,
-                            i::ViewCapabilities::empty(),
-                        )
-                        .expect("Unable to create depth image");
+                            1,
+                        ),
+                        1,
+                        f::Format::D32Sfloat,
+                        i::Tiling::Optimal,
+                        i::Usage::DEPTH_STENCIL_ATTACHMENT,
+                        i::ViewCapabilities::empty(),
+                    )?;
                     let requirements = self.device.get_image_requirements(&depth_image);
                     let memory_type_id = find_memory_type_id(
                         &self.adapter,
                         requirements,
                         m::Properties::DEVICE_LOCAL,
-                    );
+                    )?;
                     let memory = self
                         .device
-                        .allocate_memory(memory_type_id, requirements.size)
-                        .expect("Couldn't allocate image memory!");
+                        .allocate_memory(memory_type_id, requirements.size)?;
                     self.device
-                        .bind_image_memory(&memory, 0, &mut depth_image)
-                        .expect("Couldn't bind the image memory!");
-                    let image_view = self
-                        .device
-                        .create_image_view(
-                            &depth_image,
-                            i::ViewKind::D2,
-                            f::Format::D32Sfloat,
-                            f::Swizzle::NO,
-                            i::SubresourceRange {
-                                aspects: f::Aspects::DEPTH,
-                                levels: 0..1,
-                                layers: 0..1,
-                            },
-                        )
-                        .expect("Couldn't create the image view!");
+                        .bind_image_memory(&memory, 0, &mut depth_image)?;
+                    let image_view = self.device.create_image_view(
+                        &depth_image,
+                        i::ViewKind::D2,
+                        f::Format::D32Sfloat,
+                        f::Swizzle::NO,
+                        i::SubresourceRange {
+                            aspects: f::Aspects::DEPTH,
+                            levels: 0..1,
+                            layers: 0..1,
+                        },
+                    )?;
                     depth_images.push(depth_image);
                     depth_image_views.push(image_view);
                     depth_image_requirements.push(requirements);
@@ -998,20 +1444,17 @@ impl VxDraw {
                     .iter()
                     .enumerate()
                     .map(|(idx, image_view)| unsafe {
-                        self.device
-                            .create_framebuffer(
-                                &self.render_pass,
-                                vec![image_view, &depth_image_views[idx]],
-                                i::Extent {
-                                    width: self.swapconfig.extent.width,
-                                    height: self.swapconfig.extent.height,
-                                    depth: 1,
-                                },
-                            )
-                            .map_err(|_| "Failed to create a framebuffer!")
+                        self.device.create_framebuffer(
+                            &self.render_pass,
+                            vec![image_view, &depth_image_views[idx]],
+                            i::Extent {
+                                width: self.swapconfig.extent.width,
+                                height: self.swapconfig.extent.height,
+                                depth: 1,
+                            },
+                        )
                     })
-                    .collect::<Result<Vec<_>, &str>>()
-                    .unwrap()
+                    .collect::<Result<Vec<_>, _>>()?
             };
             (image_views, framebuffers)
         };
@@ -1049,9 +1492,142 @@ impl VxDraw {
         unsafe {
             self.device.destroy_semaphore(std::mem::replace(
                 &mut self.acquire_image_semaphore_free,
-                self.device.create_semaphore().unwrap(),
+                self.device.create_semaphore()?,
             ));
         }
+        postproc::recreate_targets(self)
+    }
+
+    /// Reallocate the device-owned color and depth images used when rendering offscreen
+    fn recreate_offscreen_images(&mut self, extent: Extent2D) -> Result<(), Error> {
+        self.resized_since_last_render = true;
+        self.device.wait_idle()?;
+
+        info!(self.log, "Recreating offscreen images"; "extent" => ?extent);
+
+        let mut images: Vec<<back::Backend as Backend>::Image> = vec![];
+        let mut image_views: Vec<<back::Backend as Backend>::ImageView> = vec![];
+        let mut image_memories: Vec<<back::Backend as Backend>::Memory> = vec![];
+        let mut depth_images: Vec<<back::Backend as Backend>::Image> = vec![];
+        let mut depth_image_views: Vec<<back::Backend as Backend>::ImageView> = vec![];
+        let mut depth_image_memories: Vec<<back::Backend as Backend>::Memory> = vec![];
+
+        unsafe {
+            for _ in 0..self.swapconfig.image_count {
+                let mut image = self.device.create_image(
+                    i::Kind::D2(extent.width, extent.height, 1, 1),
+                    1,
+                    self.format,
+                    i::Tiling::Optimal,
+                    self.swapconfig.image_usage,
+                    i::ViewCapabilities::empty(),
+                )?;
+                let requirements = self.device.get_image_requirements(&image);
+                let memory_type_id =
+                    find_memory_type_id(&self.adapter, requirements, m::Properties::DEVICE_LOCAL)?;
+                let memory = self
+                    .device
+                    .allocate_memory(memory_type_id, requirements.size)?;
+                self.device.bind_image_memory(&memory, 0, &mut image)?;
+                let image_view = self.device.create_image_view(
+                    &image,
+                    i::ViewKind::D2,
+                    self.format,
+                    Swizzle::NO,
+                    i::SubresourceRange {
+                        aspects: f::Aspects::COLOR,
+                        levels: 0..1,
+                        layers: 0..1,
+                    },
+                )?;
+                images.push(image);
+                image_views.push(image_view);
+                image_memories.push(memory);
+
+                let mut depth_image = self.device.create_image(
+                    i::Kind::D2(extent.width, extent.height, 1, 1),
+                    1,
+                    f::Format::D32Sfloat,
+                    i::Tiling::Optimal,
+                    i::Usage::DEPTH_STENCIL_ATTACHMENT,
+                    i::ViewCapabilities::empty(),
+                )?;
+                let requirements = self.device.get_image_requirements(&depth_image);
+                let memory_type_id =
+                    find_memory_type_id(&self.adapter, requirements, m::Properties::DEVICE_LOCAL)?;
+                let memory = self
+                    .device
+                    .allocate_memory(memory_type_id, requirements.size)?;
+                self.device
+                    .bind_image_memory(&memory, 0, &mut depth_image)?;
+                let image_view = self.device.create_image_view(
+                    &depth_image,
+                    i::ViewKind::D2,
+                    f::Format::D32Sfloat,
+                    f::Swizzle::NO,
+                    i::SubresourceRange {
+                        aspects: f::Aspects::DEPTH,
+                        levels: 0..1,
+                        layers: 0..1,
+                    },
+                )?;
+                depth_images.push(depth_image);
+                depth_image_views.push(image_view);
+                depth_image_memories.push(memory);
+            }
+        }
+
+        let framebuffers: Vec<<back::Backend as Backend>::Framebuffer> = image_views
+            .iter()
+            .enumerate()
+            .map(|(idx, image_view)| unsafe {
+                self.device.create_framebuffer(
+                    &self.render_pass,
+                    vec![image_view, &depth_image_views[idx]],
+                    i::Extent {
+                        width: extent.width,
+                        height: extent.height,
+                        depth: 1,
+                    },
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        unsafe {
+            for fb in self.framebuffers.drain(..) {
+                self.device.destroy_framebuffer(fb);
+            }
+            for iv in self.image_views.drain(..) {
+                self.device.destroy_image_view(iv);
+            }
+            for image in self.images.drain(..) {
+                self.device.destroy_image(image);
+            }
+            for memory in self.image_memories.drain(..) {
+                self.device.free_memory(memory);
+            }
+            for di in self.depth_images.drain(..) {
+                self.device.destroy_image(di);
+            }
+            for div in self.depth_image_views.drain(..) {
+                self.device.destroy_image_view(div);
+            }
+            for div in self.depth_image_memories.drain(..) {
+                self.device.free_memory(div);
+            }
+        }
+
+        self.images = images;
+        self.image_memories = image_memories;
+        self.framebuffers = framebuffers;
+        self.image_views = image_views;
+        self.depth_images = depth_images;
+        self.depth_image_views = depth_image_views;
+        self.depth_image_memories = depth_image_memories;
+        self.swapconfig.extent = extent;
+        self.render_area.w = extent.width as i16;
+        self.render_area.h = extent.height as i16;
+        postproc::recreate_targets(self)
     }
 
     /// Internal drawing routine
@@ -1059,671 +1635,93 @@ impl VxDraw {
     fn draw_frame_internal(
         &mut self,
         do_postproc: bool,
-        mut postproc: impl FnMut(&mut VxDraw, w::SwapImageIndex),
-    ) {
+        mut postproc: impl FnMut(&mut VxDraw, w::SwapImageIndex) -> Result<(), Error>,
+    ) -> Result<(), Error> {
         self.resized_since_last_render = false;
 
         let view = self.perspective;
         unsafe {
-            let swap_image: (_, Option<w::Suboptimal>) = match self.swapchain.acquire_image(
-                u64::max_value(),
-                Some(&*self.acquire_image_semaphore_free),
-                None,
-            ) {
-                Ok((index, None)) => (index, None),
-                Ok((_index, Some(_suboptimal))) => {
-                    info!(self.log, "Swapchain in suboptimal state, recreating" ; "type" => "acquire_image");
-                    self.window_resized_recreate_swapchain();
-                    return self.draw_frame_internal(do_postproc, postproc);
-                }
-                Err(w::AcquireError::OutOfDate) => {
-                    info!(self.log, "Swapchain out of date, recreating"; "type" => "acquire_image");
-                    self.window_resized_recreate_swapchain();
-                    return self.draw_frame_internal(do_postproc, postproc);
-                }
-                Err(err) => {
-                    error!(self.log, "Acquire image error"; "error" => ?&err, "type" => "acquire_image");
-                    unimplemented![]
+            let swap_image: (_, Option<w::Suboptimal>) = if let Some(ref mut swapchain) =
+                self.swapchain
+            {
+                match swapchain.acquire_image(
+                    u64::max_value(),
+                    Some(&*self.acquire_image_semaphore_free),
+                    None,
+                ) {
+                    Ok((index, None)) => (index, None),
+                    Ok((_index, Some(_suboptimal))) => {
+                        info!(self.log, "Swapchain in suboptimal state, recreating" ; "type" => "acquire_image");
+                        self.window_resized_recreate_swapchain()?;
+                        return self.draw_frame_internal(do_postproc, postproc);
+                    }
+                    Err(w::AcquireError::OutOfDate) => {
+                        info!(self.log, "Swapchain out of date, recreating"; "type" => "acquire_image");
+                        self.window_resized_recreate_swapchain()?;
+                        return self.draw_frame_internal(do_postproc, postproc);
+                    }
+                    Err(err) => {
+                        error!(self.log, "Acquire image error"; "error" => ?&err, "type" => "acquire_image");
+                        return Err(err.into());
+                    }
                 }
+            } else {
+                // Offscreen images are owned by us, one per frame in flight
+                (self.current_frame as w::SwapImageIndex, None)
             };
 
-            core::mem::swap(
-                &mut *self.acquire_image_semaphore_free,
-                &mut self.acquire_image_semaphores[swap_image.0 as usize],
-            );
-
-            self.device
-                .wait_for_fence(
-                    &self.frames_in_flight_fences[self.current_frame],
-                    u64::max_value(),
-                )
-                .unwrap();
-
-            self.device
-                .reset_fence(&self.frames_in_flight_fences[self.current_frame])
-                .unwrap();
-
-            trace!(self.log, "Drawing frame"; "swapchain image" => swap_image.0, "flight" => self.current_frame, "textures" => self.dyntexs.len(), "debug triangles" => self.debtris.posbuffer.len());
-
-            {
-                let buffer = &mut self.command_buffers[self.current_frame as usize];
-
-                let clear_values = [
-                    ClearValue {
-                        color: self.clear_color,
-                    },
-                    ClearValue {
-                        depth_stencil: ClearDepthStencil {
-                            depth: 1f32,
-                            stencil: 0,
-                        },
-                    },
-                ];
-                buffer.begin_primary(CommandBufferFlags::ONE_TIME_SUBMIT);
-
-                let rect = pso::Rect {
-                    x: 0,
-                    y: 0,
-                    w: self.swapconfig.extent.width as i16,
-                    h: self.swapconfig.extent.height as i16,
-                };
-                buffer.set_viewports(
-                    0,
-                    std::iter::once(pso::Viewport {
-                        rect,
-                        depth: (0.0..1.0),
-                    }),
+            if self.swapchain.is_some() {
+                core::mem::swap(
+                    &mut *self.acquire_image_semaphore_free,
+                    &mut self.acquire_image_semaphores[swap_image.0 as usize],
                 );
-                buffer.set_scissors(0, std::iter::once(&rect));
-
-                // Fix for the validation layer complaining about the image not being in PRESENT
-                // mode
-                let image_barrier = m::Barrier::Image {
-                    states: (i::Access::empty(), i::Layout::Undefined)
-                        ..(i::Access::empty(), i::Layout::Present),
-                    target: &self.images[swap_image.0 as usize],
-                    families: None,
-                    range: i::SubresourceRange {
-                        aspects: f::Aspects::COLOR,
-                        levels: 0..1,
-                        layers: 0..1,
-                    },
-                };
-                buffer.pipeline_barrier(
-                    pso::PipelineStage::BOTTOM_OF_PIPE..pso::PipelineStage::TRANSFER,
-                    m::Dependencies::empty(),
-                    &[image_barrier],
-                );
-
-                {
-                    buffer.begin_render_pass(
-                        &self.render_pass,
-                        &self.framebuffers[swap_image.0 as usize],
-                        self.render_area,
-                        clear_values.iter(),
-                        command::SubpassContents::Inline,
-                    );
-                    for draw_cmd in self.draw_order.iter() {
-                        match draw_cmd {
-                            DrawType::Text { id } => {
-                                let text = &mut self.texts[*id];
-                                if !text.hidden {
-                                    buffer.bind_graphics_pipeline(&text.pipeline);
-                                    if text.posbuf_touch != 0 {
-                                        text.posbuf[self.current_frame]
-                                            .copy_from_slice_and_maybe_resize(
-                                                &self.device,
-                                                &self.adapter,
-                                                &text.posbuffer[..],
-                                            );
-                                        text.posbuf_touch -= 1;
-                                    }
-                                    if text.opacbuf_touch != 0 {
-                                        text.opacbuf[self.current_frame]
-                                            .copy_from_slice_and_maybe_resize(
-                                                &self.device,
-                                                &self.adapter,
-                                                &text.opacbuffer[..],
-                                            );
-                                        text.opacbuf_touch -= 1;
-                                    }
-                                    if text.uvbuf_touch != 0 {
-                                        text.uvbuf[self.current_frame]
-                                            .copy_from_slice_and_maybe_resize(
-                                                &self.device,
-                                                &self.adapter,
-                                                &text.uvbuffer[..],
-                                            );
-                                        text.uvbuf_touch -= 1;
-                                    }
-                                    if text.tranbuf_touch != 0 {
-                                        text.tranbuf[self.current_frame]
-                                            .copy_from_slice_and_maybe_resize(
-                                                &self.device,
-                                                &self.adapter,
-                                                &text.tranbuffer[..],
-                                            );
-                                        text.tranbuf_touch -= 1;
-                                    }
-                                    if text.rotbuf_touch != 0 {
-                                        text.rotbuf[self.current_frame]
-                                            .copy_from_slice_and_maybe_resize(
-                                                &self.device,
-                                                &self.adapter,
-                                                &text.rotbuffer[..],
-                                            );
-                                        text.rotbuf_touch -= 1;
-                                    }
-                                    if text.scalebuf_touch != 0 {
-                                        text.scalebuf[self.current_frame]
-                                            .copy_from_slice_and_maybe_resize(
-                                                &self.device,
-                                                &self.adapter,
-                                                &text.scalebuffer[..],
-                                            );
-                                        text.scalebuf_touch -= 1;
-                                    }
-                                    let count = text.posbuffer.len();
-                                    text.indices[self.current_frame].ensure_capacity(
-                                        &self.device,
-                                        &self.adapter,
-                                        count,
-                                    );
-                                    let buffers: ArrayVec<[_; 6]> = [
-                                        (text.posbuf[self.current_frame].buffer(), 0),
-                                        (text.uvbuf[self.current_frame].buffer(), 0),
-                                        (text.tranbuf[self.current_frame].buffer(), 0),
-                                        (text.rotbuf[self.current_frame].buffer(), 0),
-                                        (text.scalebuf[self.current_frame].buffer(), 0),
-                                        (text.opacbuf[self.current_frame].buffer(), 0),
-                                    ]
-                                    .into();
-                                    if let Some(persp) = text.fixed_perspective {
-                                        buffer.push_graphics_constants(
-                                            &text.pipeline_layout,
-                                            pso::ShaderStageFlags::VERTEX,
-                                            0,
-                                            &*(persp.as_ptr() as *const [u32; 16]),
-                                        );
-                                    } else {
-                                        buffer.push_graphics_constants(
-                                            &text.pipeline_layout,
-                                            pso::ShaderStageFlags::VERTEX,
-                                            0,
-                                            &*(view.as_ptr() as *const [u32; 16]),
-                                        );
-                                    }
-                                    buffer.bind_graphics_descriptor_sets(
-                                        &text.pipeline_layout,
-                                        0,
-                                        Some(&*text.descriptor_set),
-                                        &[],
-                                    );
-                                    buffer.bind_vertex_buffers(0, buffers);
-                                    buffer.bind_index_buffer(b::IndexBufferView {
-                                        buffer: text.indices[self.current_frame].buffer(),
-                                        offset: 0,
-                                        index_type: gfx_hal::IndexType::U32,
-                                    });
-                                    buffer.draw_indexed(
-                                        0..text.posbuffer.len() as u32 * 6,
-                                        0,
-                                        0..1,
-                                    );
-                                }
-                            }
-                            DrawType::StreamingTexture { id } => {
-                                let strtex = &mut self.strtexs[*id];
-                                let foot = self.device.get_image_subresource_footprint(
-                                    &strtex.image_buffer[self.current_frame],
-                                    i::Subresource {
-                                        aspects: f::Aspects::COLOR,
-                                        level: 0,
-                                        layer: 0,
-                                    },
-                                );
-
-                                let target = self
-                                    .device
-                                    .map_memory(
-                                        &strtex.image_memory[self.current_frame],
-                                        0..strtex.image_requirements[self.current_frame].size,
-                                    )
-                                    .expect("unable to acquire mapping writer");
-
-                                for items in &strtex.circular_writes {
-                                    for item in items {
-                                        match item {
-                                            StreamingTextureWrite::Single((x, y), color) => {
-                                                if !(*x < strtex.width && *y < strtex.height) {
-                                                    continue;
-                                                }
-                                                let access = foot.row_pitch * u64::from(*y)
-                                                    + u64::from(*x * 4);
-                                                std::slice::from_raw_parts_mut(
-                                                    target,
-                                                    (access + 4) as usize,
-                                                )
-                                                    [access as usize..(access + 4) as usize]
-                                                    .copy_from_slice(&[
-                                                        color.0, color.1, color.2, color.3,
-                                                    ]);
-                                            }
-                                            StreamingTextureWrite::Block((x, y), (w, h), color) => {
-                                                for idx in *y..*y + h {
-                                                    let pitch = foot.row_pitch as usize;
-                                                    for x in *x..*x + w {
-                                                        let idx = (idx as usize * pitch
-                                                            + x as usize * 4)
-                                                            as usize;
-                                                        std::slice::from_raw_parts_mut(
-                                                            target,
-                                                            idx + 4,
-                                                        )
-                                                            [idx..idx + 4]
-                                                            .copy_from_slice(&[
-                                                                color.0, color.1, color.2, color.3,
-                                                            ]);
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                                self.device
-                                    .unmap_memory(&strtex.image_memory[self.current_frame]);
-                                if !strtex.hidden {
-                                    buffer.bind_graphics_pipeline(&strtex.pipeline);
-                                    if strtex.posbuf_touch != 0 {
-                                        strtex.posbuf[self.current_frame]
-                                            .copy_from_slice_and_maybe_resize(
-                                                &self.device,
-                                                &self.adapter,
-                                                &strtex.posbuffer[..],
-                                            );
-                                        strtex.posbuf_touch -= 1;
-                                    }
-                                    if strtex.opacbuf_touch != 0 {
-                                        strtex.opacbuf[self.current_frame]
-                                            .copy_from_slice_and_maybe_resize(
-                                                &self.device,
-                                                &self.adapter,
-                                                &strtex.opacbuffer[..],
-                                            );
-                                        strtex.opacbuf_touch -= 1;
-                                    }
-                                    if strtex.uvbuf_touch != 0 {
-                                        strtex.uvbuf[self.current_frame]
-                                            .copy_from_slice_and_maybe_resize(
-                                                &self.device,
-                                                &self.adapter,
-                                                &strtex.uvbuffer[..],
-                                            );
-                                        strtex.uvbuf_touch -= 1;
-                                    }
-                                    if strtex.tranbuf_touch != 0 {
-                                        strtex.tranbuf[self.current_frame]
-                                            .copy_from_slice_and_maybe_resize(
-                                                &self.device,
-                                                &self.adapter,
-                                                &strtex.tranbuffer[..],
-                                            );
-                                        strtex.tranbuf_touch -= 1;
-                                    }
-                                    if strtex.rotbuf_touch != 0 {
-                                        strtex.rotbuf[self.current_frame]
-                                            .copy_from_slice_and_maybe_resize(
-                                                &self.device,
-                                                &self.adapter,
-                                                &strtex.rotbuffer[..],
-                                            );
-                                        strtex.rotbuf_touch -= 1;
-                                    }
-                                    if strtex.scalebuf_touch != 0 {
-                                        strtex.scalebuf[self.current_frame]
-                                            .copy_from_slice_and_maybe_resize(
-                                                &self.device,
-                                                &self.adapter,
-                                                &strtex.scalebuffer[..],
-                                            );
-                                        strtex.scalebuf_touch -= 1;
-                                    }
-                                    let count = strtex.posbuffer.len();
-                                    strtex.indices[self.current_frame].ensure_capacity(
-                                        &self.device,
-                                        &self.adapter,
-                                        count,
-                                    );
-                                    let buffers: ArrayVec<[_; 6]> = [
-                                        (strtex.posbuf[self.current_frame].buffer(), 0),
-                                        (strtex.uvbuf[self.current_frame].buffer(), 0),
-                                        (strtex.tranbuf[self.current_frame].buffer(), 0),
-                                        (strtex.rotbuf[self.current_frame].buffer(), 0),
-                                        (strtex.scalebuf[self.current_frame].buffer(), 0),
-                                        (strtex.opacbuf[self.current_frame].buffer(), 0),
-                                    ]
-                                    .into();
-                                    if let Some(persp) = strtex.fixed_perspective {
-                                        buffer.push_graphics_constants(
-                                            &strtex.pipeline_layout,
-                                            pso::ShaderStageFlags::VERTEX,
-                                            0,
-                                            &*(persp.as_ptr() as *const [u32; 16]),
-                                        );
-                                    } else {
-                                        buffer.push_graphics_constants(
-                                            &strtex.pipeline_layout,
-                                            pso::ShaderStageFlags::VERTEX,
-                                            0,
-                                            &*(view.as_ptr() as *const [u32; 16]),
-                                        );
-                                    }
-                                    buffer.bind_graphics_descriptor_sets(
-                                        &strtex.pipeline_layout,
-                                        0,
-                                        Some(&strtex.descriptor_sets[self.current_frame]),
-                                        &[],
-                                    );
-                                    buffer.bind_vertex_buffers(0, buffers);
-                                    buffer.bind_index_buffer(b::IndexBufferView {
-                                        buffer: strtex.indices[self.current_frame].buffer(),
-                                        offset: 0,
-                                        index_type: gfx_hal::IndexType::U32,
-                                    });
-                                    buffer.draw_indexed(
-                                        0..strtex.posbuffer.len() as u32 * 6,
-                                        0,
-                                        0..1,
-                                    );
-                                }
-                            }
-                            DrawType::DynamicTexture { id } => {
-                                let dyntex = &mut self.dyntexs[*id];
-                                if !dyntex.hidden {
-                                    buffer.bind_graphics_pipeline(&dyntex.pipeline);
-                                    if dyntex.posbuf_touch != 0 {
-                                        dyntex.posbuf[self.current_frame]
-                                            .copy_from_slice_and_maybe_resize(
-                                                &self.device,
-                                                &self.adapter,
-                                                &dyntex.posbuffer[..],
-                                            );
-                                        dyntex.posbuf_touch -= 1;
-                                    }
-                                    if dyntex.opacbuf_touch != 0 {
-                                        dyntex.opacbuf[self.current_frame]
-                                            .copy_from_slice_and_maybe_resize(
-                                                &self.device,
-                                                &self.adapter,
-                                                &dyntex.opacbuffer[..],
-                                            );
-                                        dyntex.opacbuf_touch -= 1;
-                                    }
-                                    if dyntex.uvbuf_touch != 0 {
-                                        dyntex.uvbuf[self.current_frame]
-                                            .copy_from_slice_and_maybe_resize(
-                                                &self.device,
-                                                &self.adapter,
-                                                &dyntex.uvbuffer[..],
-                                            );
-                                        dyntex.uvbuf_touch -= 1;
-                                    }
-                                    if dyntex.tranbuf_touch != 0 {
-                                        dyntex.tranbuf[self.current_frame]
-                                            .copy_from_slice_and_maybe_resize(
-                                                &self.device,
-                                                &self.adapter,
-                                                &dyntex.tranbuffer[..],
-                                            );
-                                        dyntex.tranbuf_touch -= 1;
-                                    }
-                                    if dyntex.rotbuf_touch != 0 {
-                                        dyntex.rotbuf[self.current_frame]
-                                            .copy_from_slice_and_maybe_resize(
-                                                &self.device,
-                                                &self.adapter,
-                                                &dyntex.rotbuffer[..],
-                                            );
-                                        dyntex.rotbuf_touch -= 1;
-                                    }
-                                    if dyntex.scalebuf_touch != 0 {
-                                        dyntex.scalebuf[self.current_frame]
-                                            .copy_from_slice_and_maybe_resize(
-                                                &self.device,
-                                                &self.adapter,
-                                                &dyntex.scalebuffer[..],
-                                            );
-                                        dyntex.scalebuf_touch -= 1;
-                                    }
-                                    let count = dyntex.posbuffer.len();
-                                    dyntex.indices[self.current_frame].ensure_capacity(
-                                        &self.device,
-                                        &self.adapter,
-                                        count,
-                                    );
-                                    let buffers: ArrayVec<[_; 6]> = [
-                                        (dyntex.posbuf[self.current_frame].buffer(), 0),
-                                        (dyntex.uvbuf[self.current_frame].buffer(), 0),
-                                        (dyntex.tranbuf[self.current_frame].buffer(), 0),
-                                        (dyntex.rotbuf[self.current_frame].buffer(), 0),
-                                        (dyntex.scalebuf[self.current_frame].buffer(), 0),
-                                        (dyntex.opacbuf[self.current_frame].buffer(), 0),
-                                    ]
-                                    .into();
-                                    if let Some(persp) = dyntex.fixed_perspective {
-                                        buffer.push_graphics_constants(
-                                            &dyntex.pipeline_layout,
-                                            pso::ShaderStageFlags::VERTEX,
-                                            0,
-                                            &*(persp.as_ptr() as *const [u32; 16]),
-                                        );
-                                    } else {
-                                        buffer.push_graphics_constants(
-                                            &dyntex.pipeline_layout,
-                                            pso::ShaderStageFlags::VERTEX,
-                                            0,
-                                            &*(view.as_ptr() as *const [u32; 16]),
-                                        );
-                                    }
-                                    buffer.bind_graphics_descriptor_sets(
-                                        &dyntex.pipeline_layout,
-                                        0,
-                                        Some(&*dyntex.descriptor_set),
-                                        &[],
-                                    );
-                                    buffer.bind_vertex_buffers(0, buffers);
-                                    buffer.bind_index_buffer(b::IndexBufferView {
-                                        buffer: dyntex.indices[self.current_frame].buffer(),
-                                        offset: 0,
-                                        index_type: gfx_hal::IndexType::U32,
-                                    });
-                                    buffer.draw_indexed(
-                                        0..dyntex.posbuffer.len() as u32 * 6,
-                                        0,
-                                        0..1,
-                                    );
-                                }
-                            }
-                            DrawType::Quad { id } => {
-                                if let Some(quad) = self.quads.get_mut(*id) {
-                                    if !quad.hidden {
-                                        buffer.bind_graphics_pipeline(&quad.pipeline);
-                                        {
-                                            let view =
-                                                if let Some(ref view) = quad.fixed_perspective {
-                                                    view
-                                                } else {
-                                                    &view
-                                                };
-                                            buffer.push_graphics_constants(
-                                                &quad.pipeline_layout,
-                                                pso::ShaderStageFlags::VERTEX,
-                                                0,
-                                                &*(view.as_ptr() as *const [u32; 16]),
-                                            );
-                                        }
-                                        if quad.posbuf_touch != 0 {
-                                            quad.posbuf[self.current_frame]
-                                                .copy_from_slice_and_maybe_resize(
-                                                    &self.device,
-                                                    &self.adapter,
-                                                    &quad.posbuffer[..],
-                                                );
-                                            quad.posbuf_touch -= 1;
-                                        }
-                                        if quad.colbuf_touch != 0 {
-                                            quad.colbuf[self.current_frame]
-                                                .copy_from_slice_and_maybe_resize(
-                                                    &self.device,
-                                                    &self.adapter,
-                                                    &quad.colbuffer[..],
-                                                );
-                                            quad.colbuf_touch -= 1;
-                                        }
-                                        if quad.tranbuf_touch != 0 {
-                                            quad.tranbuf[self.current_frame]
-                                                .copy_from_slice_and_maybe_resize(
-                                                    &self.device,
-                                                    &self.adapter,
-                                                    &quad.tranbuffer[..],
-                                                );
-                                            quad.tranbuf_touch -= 1;
-                                        }
-                                        if quad.rotbuf_touch != 0 {
-                                            quad.rotbuf[self.current_frame]
-                                                .copy_from_slice_and_maybe_resize(
-                                                    &self.device,
-                                                    &self.adapter,
-                                                    &quad.rotbuffer[..],
-                                                );
-                                            quad.rotbuf_touch -= 1;
-                                        }
-                                        if quad.scalebuf_touch != 0 {
-                                            quad.scalebuf[self.current_frame]
-                                                .copy_from_slice_and_maybe_resize(
-                                                    &self.device,
-                                                    &self.adapter,
-                                                    &quad.scalebuffer[..],
-                                                );
-                                            quad.scalebuf_touch -= 1;
-                                        }
-                                        let count = quad.posbuffer.len();
-                                        quad.indices[self.current_frame].ensure_capacity(
-                                            &self.device,
-                                            &self.adapter,
-                                            count,
-                                        );
-                                        let buffers: ArrayVec<[_; 5]> = [
-                                            (quad.posbuf[self.current_frame].buffer(), 0),
-                                            (quad.colbuf[self.current_frame].buffer(), 0),
-                                            (quad.tranbuf[self.current_frame].buffer(), 0),
-                                            (quad.rotbuf[self.current_frame].buffer(), 0),
-                                            (quad.scalebuf[self.current_frame].buffer(), 0),
-                                        ]
-                                        .into();
-                                        buffer.bind_vertex_buffers(0, buffers);
-                                        buffer.bind_index_buffer(b::IndexBufferView {
-                                            buffer: &quad.indices[self.current_frame].buffer(),
-                                            offset: 0,
-                                            index_type: gfx_hal::IndexType::U32,
-                                        });
-                                        buffer.draw_indexed(
-                                            0..quad.posbuffer.len() as u32 * 6,
-                                            0,
-                                            0..1,
-                                        );
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    if !self.debtris.hidden {
-                        buffer.bind_graphics_pipeline(&self.debtris.pipeline);
-                        let ratio = self.swapconfig.extent.width as f32
-                            / self.swapconfig.extent.height as f32;
-                        buffer.push_graphics_constants(
-                            &self.debtris.pipeline_layout,
-                            pso::ShaderStageFlags::VERTEX,
-                            0,
-                            &(std::mem::transmute::<f32, [u32; 1]>(ratio)),
-                        );
-                        if self.debtris.posbuf_touch != 0 {
-                            self.debtris.posbuf[self.current_frame]
-                                .copy_from_slice_and_maybe_resize(
-                                    &self.device,
-                                    &self.adapter,
-                                    &self.debtris.posbuffer[..],
-                                );
-                            self.debtris.posbuf_touch -= 1;
-                        }
-                        if self.debtris.colbuf_touch != 0 {
-                            self.debtris.colbuf[self.current_frame]
-                                .copy_from_slice_and_maybe_resize(
-                                    &self.device,
-                                    &self.adapter,
-                                    &self.debtris.colbuffer[..],
-                                );
-                            self.debtris.colbuf_touch -= 1;
-                        }
-                        if self.debtris.tranbuf_touch != 0 {
-                            self.debtris.tranbuf[self.current_frame]
-                                .copy_from_slice_and_maybe_resize(
-                                    &self.device,
-                                    &self.adapter,
-                                    &self.debtris.tranbuffer[..],
-                                );
-                            self.debtris.tranbuf_touch -= 1;
-                        }
-                        if self.debtris.rotbuf_touch != 0 {
-                            self.debtris.rotbuf[self.current_frame]
-                                .copy_from_slice_and_maybe_resize(
-                                    &self.device,
-                                    &self.adapter,
-                                    &self.debtris.rotbuffer[..],
-                                );
-                            self.debtris.rotbuf_touch -= 1;
-                        }
-                        if self.debtris.scalebuf_touch != 0 {
-                            self.debtris.scalebuf[self.current_frame]
-                                .copy_from_slice_and_maybe_resize(
-                                    &self.device,
-                                    &self.adapter,
-                                    &self.debtris.scalebuffer[..],
-                                );
-                            self.debtris.scalebuf_touch -= 1;
-                        }
-                        let count = self.debtris.posbuffer.len();
-                        let buffers: ArrayVec<[_; 5]> = [
-                            (self.debtris.posbuf[self.current_frame].buffer(), 0),
-                            (self.debtris.colbuf[self.current_frame].buffer(), 0),
-                            (self.debtris.tranbuf[self.current_frame].buffer(), 0),
-                            (self.debtris.rotbuf[self.current_frame].buffer(), 0),
-                            (self.debtris.scalebuf[self.current_frame].buffer(), 0),
-                        ]
-                        .into();
-                        buffer.bind_vertex_buffers(0, buffers);
+            }
 
-                        buffer.draw(0..(count * 3) as u32, 0..1);
+            self.device.wait_for_fence(
+                &self.frames_in_flight_fences[self.current_frame],
+                u64::max_value(),
+            )?;
+            for strtex in self.strtexs.iter_mut() {
+                for read in strtex.reads.iter_mut() {
+                    if read.recorded == Some(self.current_frame) {
+                        read.done = true;
                     }
                 }
+            }
 
-                buffer.end_render_pass();
+            trace!(self.log, "Drawing frame"; "swapchain image" => swap_image.0, "flight" => self.current_frame, "textures" => self.dyntexs.len(), "debug triangles" => self.debtris.posbuffer.len());
 
-                buffer.finish();
-            }
+            let color_layout = self.color_layout();
+            // Move the command buffers out so the layers can be drawn through `&mut self`
+            let mut command_buffers = std::mem::take(&mut self.command_buffers);
+            let recorded = self.record_frame(
+                &mut command_buffers[self.current_frame],
+                swap_image.0,
+                color_layout,
+                &view,
+            );
+            self.command_buffers = command_buffers;
+            recorded?;
+
+            // Reset the fence only once recording succeeded, so an early error does not leave the
+            // next frame waiting on a fence that is never signalled
+            self.device
+                .reset_fence(&self.frames_in_flight_fences[self.current_frame])?;
 
             let command_buffers = &self.command_buffers[self.current_frame];
-            let wait_semaphores: ArrayVec<[_; 1]> = [(
-                &self.acquire_image_semaphores[swap_image.0 as usize],
-                pso::PipelineStage::BOTTOM_OF_PIPE,
-            )]
-            .into();
+            let mut wait_semaphores: ArrayVec<[_; 1]> = ArrayVec::new();
+            if self.swapchain.is_some() {
+                wait_semaphores.push((
+                    &self.acquire_image_semaphores[swap_image.0 as usize],
+                    pso::PipelineStage::BOTTOM_OF_PIPE,
+                ));
+            }
             {
                 let present_wait_semaphore = &self.present_wait_semaphores[self.current_frame];
                 let submission = Submission {
                     command_buffers: once(command_buffers),
                     wait_semaphores,
-                    signal_semaphores: if do_postproc {
+                    signal_semaphores: if do_postproc || self.swapchain.is_none() {
                         None
                     } else {
                         Some(present_wait_semaphore)
@@ -1735,32 +1733,38 @@ impl VxDraw {
                 );
             }
             if do_postproc {
-                postproc(self, swap_image.0);
+                postproc(self, swap_image.0)?;
             }
-            let present_wait_semaphore = &self.present_wait_semaphores[self.current_frame];
-            let present_wait_semaphores: ArrayVec<[_; 1]> = [present_wait_semaphore].into();
-            match self.swapchain.present(
-                &mut self.queue_group.queues[0],
-                swap_image.0,
-                present_wait_semaphores,
-            ) {
+            let present = match self.swapchain {
+                Some(ref swapchain) => {
+                    let present_wait_semaphore = &self.present_wait_semaphores[self.current_frame];
+                    let present_wait_semaphores: ArrayVec<[_; 1]> = [present_wait_semaphore].into();
+                    swapchain.present(
+                        &mut self.queue_group.queues[0],
+                        swap_image.0,
+                        present_wait_semaphores,
+                    )
+                }
+                None => Ok(None),
+            };
+            match present {
                 Ok(None) => {}
                 Ok(Some(_suboptimal)) => {
                     info!(
                         self.log,
                         "Swapchain in suboptimal state, recreating"; "type" => "present"
                     );
-                    self.window_resized_recreate_swapchain();
+                    self.window_resized_recreate_swapchain()?;
                     return self.draw_frame_internal(do_postproc, postproc);
                 }
                 Err(w::PresentError::OutOfDate) => {
                     info!(self.log, "Swapchain out of date, recreating"; "type" => "present");
-                    self.window_resized_recreate_swapchain();
+                    self.window_resized_recreate_swapchain()?;
                     return self.draw_frame_internal(do_postproc, postproc);
                 }
                 Err(err) => {
-                    error!(self.log, "Acquire image error"; "error" => ?&err, "type" => "present");
-                    unimplemented![]
+                    error!(self.log, "Present error"; "error" => ?&err, "type" => "present");
+                    return Err(err.into());
                 }
             }
         }
@@ -1769,6 +1773,854 @@ impl VxDraw {
             strtex.circular_writes[self.current_frame].clear();
         }
         self.layer_holes.advance_state();
+        Ok(())
+    }
+
+    /// Record all draw commands of a frame into the command buffer
+    unsafe fn record_frame(
+        &mut self,
+        buffer: &mut <back::Backend as Backend>::CommandBuffer,
+        image_index: w::SwapImageIndex,
+        color_layout: i::Layout,
+        view: &Matrix4<f32>,
+    ) -> Result<(), Error> {
+        buffer.begin_primary(CommandBufferFlags::ONE_TIME_SUBMIT);
+
+        for rentex_idx in 0..self.rentexs.len() {
+            let (rentex_view, count) = match self.rentexs[rentex_idx] {
+                Some(ref rentex) => {
+                    let rect = pso::Rect {
+                        x: 0,
+                        y: 0,
+                        w: rentex.width as i16,
+                        h: rentex.height as i16,
+                    };
+                    buffer.set_viewports(
+                        0,
+                        std::iter::once(pso::Viewport {
+                            rect,
+                            depth: (0.0..1.0),
+                        }),
+                    );
+                    buffer.set_scissors(0, std::iter::once(&rect));
+                    let clear_values = [
+                        ClearValue {
+                            color: rentex.clear_color,
+                        },
+                        ClearValue {
+                            depth_stencil: ClearDepthStencil {
+                                depth: 1f32,
+                                stencil: 0,
+                            },
+                        },
+                    ];
+                    buffer.begin_render_pass(
+                        &rentex.render_pass,
+                        &rentex.framebuffer,
+                        rect,
+                        clear_values.iter(),
+                        command::SubpassContents::Inline,
+                    );
+                    (
+                        rentex.fixed_perspective.unwrap_or_else(Matrix4::identity),
+                        rentex.draw_order.len(),
+                    )
+                }
+                None => continue,
+            };
+            for idx in 0..count {
+                let draw_cmd = match self.rentexs[rentex_idx] {
+                    Some(ref rentex) => rentex.draw_order[idx].clone(),
+                    None => unreachable![],
+                };
+                self.draw_layer(buffer, &draw_cmd, &rentex_view)?;
+            }
+            buffer.end_render_pass();
+        }
+
+        let clear_values = [
+            ClearValue {
+                color: self.clear_color,
+            },
+            ClearValue {
+                depth_stencil: ClearDepthStencil {
+                    depth: 1f32,
+                    stencil: 0,
+                },
+            },
+        ];
+        let rect = pso::Rect {
+            x: 0,
+            y: 0,
+            w: self.swapconfig.extent.width as i16,
+            h: self.swapconfig.extent.height as i16,
+        };
+        buffer.set_viewports(
+            0,
+            std::iter::once(pso::Viewport {
+                rect,
+                depth: (0.0..1.0),
+            }),
+        );
+        buffer.set_scissors(0, std::iter::once(&rect));
+
+        // Fix for the validation layer complaining about the image not being in PRESENT
+        // mode
+        let image_barrier = m::Barrier::Image {
+            states: (i::Access::empty(), i::Layout::Undefined)..(i::Access::empty(), color_layout),
+            target: &self.images[image_index as usize],
+            families: None,
+            range: i::SubresourceRange {
+                aspects: f::Aspects::COLOR,
+                levels: 0..1,
+                layers: 0..1,
+            },
+        };
+        buffer.pipeline_barrier(
+            pso::PipelineStage::BOTTOM_OF_PIPE..pso::PipelineStage::TRANSFER,
+            m::Dependencies::empty(),
+            &[image_barrier],
+        );
+
+        let postprocs = self
+            .postprocs
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, pass)| match pass {
+                Some(pass) if !pass.hidden => Some(idx),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        {
+            // With post-processing the scene is drawn into an intermediate image instead
+            match self.postproc_targets {
+                Some(ref targets) if !postprocs.is_empty() => buffer.begin_render_pass(
+                    &targets.render_pass,
+                    &targets.framebuffers[0],
+                    self.render_area,
+                    clear_values.iter(),
+                    command::SubpassContents::Inline,
+                ),
+                _ => buffer.begin_render_pass(
+                    &self.render_pass,
+                    &self.framebuffers[image_index as usize],
+                    self.render_area,
+                    clear_values.iter(),
+                    command::SubpassContents::Inline,
+                ),
+            }
+            for idx in 0..self.draw_order.len() {
+                let draw_cmd = self.draw_order[idx].clone();
+                self.draw_layer(buffer, &draw_cmd, view)?;
+            }
+            if !self.debtris.hidden {
+                buffer.bind_graphics_pipeline(&self.debtris.pipeline);
+                let ratio =
+                    self.swapconfig.extent.width as f32 / self.swapconfig.extent.height as f32;
+                buffer.push_graphics_constants(
+                    &self.debtris.pipeline_layout,
+                    pso::ShaderStageFlags::VERTEX,
+                    0,
+                    &(std::mem::transmute::<f32, [u32; 1]>(ratio)),
+                );
+                if self.debtris.posbuf_touch != 0 {
+                    self.debtris.posbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                        &self.device,
+                        &self.adapter,
+                        &self.debtris.posbuffer[..],
+                    )?;
+                    self.debtris.posbuf_touch -= 1;
+                }
+                if self.debtris.colbuf_touch != 0 {
+                    self.debtris.colbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                        &self.device,
+                        &self.adapter,
+                        &self.debtris.colbuffer[..],
+                    )?;
+                    self.debtris.colbuf_touch -= 1;
+                }
+                if self.debtris.tranbuf_touch != 0 {
+                    self.debtris.tranbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                        &self.device,
+                        &self.adapter,
+                        &self.debtris.tranbuffer[..],
+                    )?;
+                    self.debtris.tranbuf_touch -= 1;
+                }
+                if self.debtris.rotbuf_touch != 0 {
+                    self.debtris.rotbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                        &self.device,
+                        &self.adapter,
+                        &self.debtris.rotbuffer[..],
+                    )?;
+                    self.debtris.rotbuf_touch -= 1;
+                }
+                if self.debtris.scalebuf_touch != 0 {
+                    self.debtris.scalebuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                        &self.device,
+                        &self.adapter,
+                        &self.debtris.scalebuffer[..],
+                    )?;
+                    self.debtris.scalebuf_touch -= 1;
+                }
+                let count = self.debtris.posbuffer.len();
+                let buffers: ArrayVec<[_; 5]> = [
+                    (self.debtris.posbuf[self.current_frame].buffer(), 0),
+                    (self.debtris.colbuf[self.current_frame].buffer(), 0),
+                    (self.debtris.tranbuf[self.current_frame].buffer(), 0),
+                    (self.debtris.rotbuf[self.current_frame].buffer(), 0),
+                    (self.debtris.scalebuf[self.current_frame].buffer(), 0),
+                ]
+                .into();
+                buffer.bind_vertex_buffers(0, buffers);
+
+                buffer.draw(0..(count * 3) as u32, 0..1);
+            }
+        }
+
+        buffer.end_render_pass();
+
+        if let Some(ref targets) = self.postproc_targets {
+            for (nth, idx) in postprocs.iter().enumerate() {
+                let pass = match self.postprocs[*idx] {
+                    Some(ref pass) => pass,
+                    None => unreachable![],
+                };
+                let input = nth % 2;
+                if nth + 1 == postprocs.len() {
+                    buffer.begin_render_pass(
+                        &self.render_pass,
+                        &self.framebuffers[image_index as usize],
+                        self.render_area,
+                        clear_values.iter(),
+                        command::SubpassContents::Inline,
+                    );
+                } else {
+                    buffer.begin_render_pass(
+                        &targets.render_pass,
+                        &targets.framebuffers[1 - input],
+                        self.render_area,
+                        clear_values.iter(),
+                        command::SubpassContents::Inline,
+                    );
+                }
+                buffer.bind_graphics_pipeline(&pass.pipeline);
+                buffer.bind_graphics_descriptor_sets(
+                    &pass.pipeline_layout,
+                    0,
+                    Some(&targets.descriptor_sets[input]),
+                    &[],
+                );
+                if !pass.push_constants.is_empty() {
+                    buffer.push_graphics_constants(
+                        &pass.pipeline_layout,
+                        pso::ShaderStageFlags::FRAGMENT,
+                        0,
+                        std::slice::from_raw_parts(
+                            pass.push_constants.as_ptr() as *const u32,
+                            pass.push_constants.len(),
+                        ),
+                    );
+                }
+                buffer.draw(0..3, 0..1);
+                buffer.end_render_pass();
+            }
+        }
+
+        // Readbacks are copies, so they are recorded outside of the render passes
+        for strtex in self.strtexs.iter_mut() {
+            strtex::record_reads(buffer, strtex, self.current_frame);
+        }
+
+        buffer.finish();
+        Ok(())
+    }
+
+    /// Upload the changed buffers of a single layer and record its draw commands
+    #[allow(clippy::cognitive_complexity)]
+    unsafe fn draw_layer(
+        &mut self,
+        buffer: &mut <back::Backend as Backend>::CommandBuffer,
+        draw_cmd: &DrawType,
+        view: &Matrix4<f32>,
+    ) -> Result<(), Error> {
+        match draw_cmd {
+            DrawType::Text { id } => {
+                let text = &mut self.texts[*id];
+                if !text.hidden {
+                    buffer.bind_graphics_pipeline(&text.pipeline);
+                    if text.posbuf_touch != 0 {
+                        text.posbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &text.posbuffer[..],
+                        )?;
+                        text.posbuf_touch -= 1;
+                    }
+                    if text.opacbuf_touch != 0 {
+                        text.opacbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &text.opacbuffer[..],
+                        )?;
+                        text.opacbuf_touch -= 1;
+                    }
+                    if text.uvbuf_touch != 0 {
+                        text.uvbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &text.uvbuffer[..],
+                        )?;
+                        text.uvbuf_touch -= 1;
+                    }
+                    if text.tranbuf_touch != 0 {
+                        text.tranbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &text.tranbuffer[..],
+                        )?;
+                        text.tranbuf_touch -= 1;
+                    }
+                    if text.rotbuf_touch != 0 {
+                        text.rotbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &text.rotbuffer[..],
+                        )?;
+                        text.rotbuf_touch -= 1;
+                    }
+                    if text.scalebuf_touch != 0 {
+                        text.scalebuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &text.scalebuffer[..],
+                        )?;
+                        text.scalebuf_touch -= 1;
+                    }
+                    if text.colbuf_touch != 0 {
+                        text.colbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &text.colbuffer[..],
+                        )?;
+                        text.colbuf_touch -= 1;
+                    }
+                    let count = text.posbuffer.len();
+                    text.indices[self.current_frame].ensure_capacity(
+                        &self.device,
+                        &self.adapter,
+                        count,
+                    )?;
+                    let buffers: ArrayVec<[_; 7]> = [
+                        (text.posbuf[self.current_frame].buffer(), 0),
+                        (text.uvbuf[self.current_frame].buffer(), 0),
+                        (text.tranbuf[self.current_frame].buffer(), 0),
+                        (text.rotbuf[self.current_frame].buffer(), 0),
+                        (text.scalebuf[self.current_frame].buffer(), 0),
+                        (text.opacbuf[self.current_frame].buffer(), 0),
+                        (text.colbuf[self.current_frame].buffer(), 0),
+                    ]
+                    .into();
+                    if let Some(persp) = text.fixed_perspective {
+                        buffer.push_graphics_constants(
+                            &text.pipeline_layout,
+                            pso::ShaderStageFlags::VERTEX,
+                            0,
+                            &*(persp.as_ptr() as *const [u32; 16]),
+                        );
+                    } else {
+                        buffer.push_graphics_constants(
+                            &text.pipeline_layout,
+                            pso::ShaderStageFlags::VERTEX,
+                            0,
+                            &*(view.as_ptr() as *const [u32; 16]),
+                        );
+                    }
+                    if let Some(ref distance_field) = text.distance_field {
+                        buffer.push_graphics_constants(
+                            &text.pipeline_layout,
+                            pso::ShaderStageFlags::FRAGMENT,
+                            64,
+                            &*(distance_field.effects.as_ptr() as *const [u32; 16]),
+                        );
+                    }
+                    buffer.bind_graphics_descriptor_sets(
+                        &text.pipeline_layout,
+                        0,
+                        Some(&*text.descriptor_set),
+                        &[],
+                    );
+                    buffer.bind_vertex_buffers(0, buffers);
+                    buffer.bind_index_buffer(b::IndexBufferView {
+                        buffer: text.indices[self.current_frame].buffer(),
+                        offset: 0,
+                        index_type: gfx_hal::IndexType::U32,
+                    });
+                    buffer.draw_indexed(0..text.posbuffer.len() as u32 * 6, 0, 0..1);
+                }
+            }
+            DrawType::StreamingTexture { id } => {
+                let strtex = &mut self.strtexs[*id];
+                strtex::apply_writes(&self.device, strtex, self.current_frame)?;
+                if !strtex.hidden {
+                    buffer.bind_graphics_pipeline(&strtex.pipeline);
+                    if strtex.posbuf_touch != 0 {
+                        strtex.posbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &strtex.posbuffer[..],
+                        )?;
+                        strtex.posbuf_touch -= 1;
+                    }
+                    if strtex.opacbuf_touch != 0 {
+                        strtex.opacbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &strtex.opacbuffer[..],
+                        )?;
+                        strtex.opacbuf_touch -= 1;
+                    }
+                    if strtex.uvbuf_touch != 0 {
+                        strtex.uvbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &strtex.uvbuffer[..],
+                        )?;
+                        strtex.uvbuf_touch -= 1;
+                    }
+                    if strtex.tranbuf_touch != 0 {
+                        strtex.tranbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &strtex.tranbuffer[..],
+                        )?;
+                        strtex.tranbuf_touch -= 1;
+                    }
+                    if strtex.rotbuf_touch != 0 {
+                        strtex.rotbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &strtex.rotbuffer[..],
+                        )?;
+                        strtex.rotbuf_touch -= 1;
+                    }
+                    if strtex.scalebuf_touch != 0 {
+                        strtex.scalebuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &strtex.scalebuffer[..],
+                        )?;
+                        strtex.scalebuf_touch -= 1;
+                    }
+                    let count = strtex.posbuffer.len();
+                    strtex.indices[self.current_frame].ensure_capacity(
+                        &self.device,
+                        &self.adapter,
+                        count,
+                    )?;
+                    let buffers: ArrayVec<[_; 6]> = [
+                        (strtex.posbuf[self.current_frame].buffer(), 0),
+                        (strtex.uvbuf[self.current_frame].buffer(), 0),
+                        (strtex.tranbuf[self.current_frame].buffer(), 0),
+                        (strtex.rotbuf[self.current_frame].buffer(), 0),
+                        (strtex.scalebuf[self.current_frame].buffer(), 0),
+                        (strtex.opacbuf[self.current_frame].buffer(), 0),
+                    ]
+                    .into();
+                    if let Some(persp) = strtex.fixed_perspective {
+                        buffer.push_graphics_constants(
+                            &strtex.pipeline_layout,
+                            pso::ShaderStageFlags::VERTEX,
+                            0,
+                            &*(persp.as_ptr() as *const [u32; 16]),
+                        );
+                    } else {
+                        buffer.push_graphics_constants(
+                            &strtex.pipeline_layout,
+                            pso::ShaderStageFlags::VERTEX,
+                            0,
+                            &*(view.as_ptr() as *const [u32; 16]),
+                        );
+                    }
+                    buffer.bind_graphics_descriptor_sets(
+                        &strtex.pipeline_layout,
+                        0,
+                        Some(&strtex.descriptor_sets[self.current_frame]),
+                        &[],
+                    );
+                    buffer.bind_vertex_buffers(0, buffers);
+                    buffer.bind_index_buffer(b::IndexBufferView {
+                        buffer: strtex.indices[self.current_frame].buffer(),
+                        offset: 0,
+                        index_type: gfx_hal::IndexType::U32,
+                    });
+                    buffer.draw_indexed(0..strtex.posbuffer.len() as u32 * 6, 0, 0..1);
+                }
+            }
+            DrawType::DynamicTexture { id } => {
+                let dyntex = &mut self.dyntexs[*id];
+                if !dyntex.hidden {
+                    buffer.bind_graphics_pipeline(&dyntex.pipeline);
+                    if dyntex.posbuf_touch != 0 {
+                        dyntex.posbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &dyntex.posbuffer[..],
+                        )?;
+                        dyntex.posbuf_touch -= 1;
+                    }
+                    if dyntex.opacbuf_touch != 0 {
+                        dyntex.opacbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &dyntex.opacbuffer[..],
+                        )?;
+                        dyntex.opacbuf_touch -= 1;
+                    }
+                    if dyntex.uvbuf_touch != 0 {
+                        dyntex.uvbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &dyntex.uvbuffer[..],
+                        )?;
+                        dyntex.uvbuf_touch -= 1;
+                    }
+                    if dyntex.tranbuf_touch != 0 {
+                        if dyntex.instanced {
+                            dyntex.tranbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                                &self.device,
+                                &self.adapter,
+                                &per_instance(&dyntex.tranbuffer, 2),
+                            )?;
+                        } else {
+                            dyntex.tranbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                                &self.device,
+                                &self.adapter,
+                                &dyntex.tranbuffer[..],
+                            )?;
+                        }
+                        dyntex.tranbuf_touch -= 1;
+                    }
+                    if dyntex.rotbuf_touch != 0 {
+                        if dyntex.instanced {
+                            dyntex.rotbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                                &self.device,
+                                &self.adapter,
+                                &per_instance(&dyntex.rotbuffer, 1),
+                            )?;
+                        } else {
+                            dyntex.rotbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                                &self.device,
+                                &self.adapter,
+                                &dyntex.rotbuffer[..],
+                            )?;
+                        }
+                        dyntex.rotbuf_touch -= 1;
+                    }
+                    if dyntex.scalebuf_touch != 0 {
+                        if dyntex.instanced {
+                            dyntex.scalebuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                                &self.device,
+                                &self.adapter,
+                                &per_instance(&dyntex.scalebuffer, 1),
+                            )?;
+                        } else {
+                            dyntex.scalebuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                                &self.device,
+                                &self.adapter,
+                                &dyntex.scalebuffer[..],
+                            )?;
+                        }
+                        dyntex.scalebuf_touch -= 1;
+                    }
+                    let count = if dyntex.instanced {
+                        1
+                    } else {
+                        dyntex.posbuffer.len()
+                    };
+                    dyntex.indices[self.current_frame].ensure_capacity(
+                        &self.device,
+                        &self.adapter,
+                        count,
+                    )?;
+                    let buffers: ArrayVec<[_; 6]> = [
+                        (dyntex.posbuf[self.current_frame].buffer(), 0),
+                        (dyntex.uvbuf[self.current_frame].buffer(), 0),
+                        (dyntex.tranbuf[self.current_frame].buffer(), 0),
+                        (dyntex.rotbuf[self.current_frame].buffer(), 0),
+                        (dyntex.scalebuf[self.current_frame].buffer(), 0),
+                        (dyntex.opacbuf[self.current_frame].buffer(), 0),
+                    ]
+                    .into();
+                    if let Some(persp) = dyntex.fixed_perspective {
+                        buffer.push_graphics_constants(
+                            &dyntex.pipeline_layout,
+                            pso::ShaderStageFlags::VERTEX,
+                            0,
+                            &*(persp.as_ptr() as *const [u32; 16]),
+                        );
+                    } else {
+                        buffer.push_graphics_constants(
+                            &dyntex.pipeline_layout,
+                            pso::ShaderStageFlags::VERTEX,
+                            0,
+                            &*(view.as_ptr() as *const [u32; 16]),
+                        );
+                    }
+                    buffer.bind_graphics_descriptor_sets(
+                        &dyntex.pipeline_layout,
+                        0,
+                        Some(&*dyntex.descriptor_set),
+                        &[],
+                    );
+                    buffer.bind_vertex_buffers(0, buffers);
+                    buffer.bind_index_buffer(b::IndexBufferView {
+                        buffer: dyntex.indices[self.current_frame].buffer(),
+                        offset: 0,
+                        index_type: gfx_hal::IndexType::U32,
+                    });
+                    if dyntex.instanced {
+                        buffer.draw_indexed(0..6, 0, 0..dyntex.posbuffer.len() as u32);
+                    } else {
+                        buffer.draw_indexed(0..dyntex.posbuffer.len() as u32 * 6, 0, 0..1);
+                    }
+                }
+            }
+            DrawType::Quad { id } => {
+                if let Some(quad) = self.quads.get_mut(*id) {
+                    if !quad.hidden {
+                        buffer.bind_graphics_pipeline(&quad.pipeline);
+                        {
+                            let view = if let Some(ref view) = quad.fixed_perspective {
+                                view
+                            } else {
+                                &view
+                            };
+                            buffer.push_graphics_constants(
+                                &quad.pipeline_layout,
+                                pso::ShaderStageFlags::VERTEX,
+                                0,
+                                &*(view.as_ptr() as *const [u32; 16]),
+                            );
+                        }
+                        if quad.posbuf_touch != 0 {
+                            quad.posbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                                &self.device,
+                                &self.adapter,
+                                &quad.posbuffer[..],
+                            )?;
+                            quad.posbuf_touch -= 1;
+                        }
+                        if quad.colbuf_touch != 0 {
+                            quad.colbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                                &self.device,
+                                &self.adapter,
+                                &quad.colbuffer[..],
+                            )?;
+                            quad.colbuf_touch -= 1;
+                        }
+                        if quad.tranbuf_touch != 0 {
+                            if quad.instanced {
+                                quad.tranbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                                    &self.device,
+                                    &self.adapter,
+                                    &per_instance(&quad.tranbuffer, 2),
+                                )?;
+                            } else {
+                                quad.tranbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                                    &self.device,
+                                    &self.adapter,
+                                    &quad.tranbuffer[..],
+                                )?;
+                            }
+                            quad.tranbuf_touch -= 1;
+                        }
+                        if quad.rotbuf_touch != 0 {
+                            if quad.instanced {
+                                quad.rotbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                                    &self.device,
+                                    &self.adapter,
+                                    &per_instance(&quad.rotbuffer, 1),
+                                )?;
+                            } else {
+                                quad.rotbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                                    &self.device,
+                                    &self.adapter,
+                                    &quad.rotbuffer[..],
+                                )?;
+                            }
+                            quad.rotbuf_touch -= 1;
+                        }
+                        if quad.scalebuf_touch != 0 {
+                            if quad.instanced {
+                                quad.scalebuf[self.current_frame]
+                                    .copy_from_slice_and_maybe_resize(
+                                        &self.device,
+                                        &self.adapter,
+                                        &per_instance(&quad.scalebuffer, 1),
+                                    )?;
+                            } else {
+                                quad.scalebuf[self.current_frame]
+                                    .copy_from_slice_and_maybe_resize(
+                                        &self.device,
+                                        &self.adapter,
+                                        &quad.scalebuffer[..],
+                                    )?;
+                            }
+                            quad.scalebuf_touch -= 1;
+                        }
+                        let count = if quad.instanced {
+                            1
+                        } else {
+                            quad.posbuffer.len()
+                        };
+                        quad.indices[self.current_frame].ensure_capacity(
+                            &self.device,
+                            &self.adapter,
+                            count,
+                        )?;
+                        let buffers: ArrayVec<[_; 5]> = [
+                            (quad.posbuf[self.current_frame].buffer(), 0),
+                            (quad.colbuf[self.current_frame].buffer(), 0),
+                            (quad.tranbuf[self.current_frame].buffer(), 0),
+                            (quad.rotbuf[self.current_frame].buffer(), 0),
+                            (quad.scalebuf[self.current_frame].buffer(), 0),
+                        ]
+                        .into();
+                        buffer.bind_vertex_buffers(0, buffers);
+                        buffer.bind_index_buffer(b::IndexBufferView {
+                            buffer: &quad.indices[self.current_frame].buffer(),
+                            offset: 0,
+                            index_type: gfx_hal::IndexType::U32,
+                        });
+                        if quad.instanced {
+                            buffer.draw_indexed(0..6, 0, 0..quad.posbuffer.len() as u32);
+                        } else {
+                            buffer.draw_indexed(0..quad.posbuffer.len() as u32 * 6, 0, 0..1);
+                        }
+                    }
+                }
+            }
+            DrawType::Shapes { id } => {
+                let shapes = &mut self.shapes[*id];
+                if !shapes.hidden {
+                    buffer.bind_graphics_pipeline(&shapes.pipeline);
+                    {
+                        let view = if let Some(ref view) = shapes.fixed_perspective {
+                            view
+                        } else {
+                            &view
+                        };
+                        buffer.push_graphics_constants(
+                            &shapes.pipeline_layout,
+                            pso::ShaderStageFlags::VERTEX,
+                            0,
+                            &*(view.as_ptr() as *const [u32; 16]),
+                        );
+                    }
+                    if shapes.posbuf_touch != 0 {
+                        shapes.posbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &shapes.posbuffer[..],
+                        )?;
+                        shapes.posbuf_touch -= 1;
+                    }
+                    if shapes.colbuf_touch != 0 {
+                        shapes.colbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &shapes.colbuffer[..],
+                        )?;
+                        shapes.colbuf_touch -= 1;
+                    }
+                    if shapes.tranbuf_touch != 0 {
+                        shapes.tranbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &shapes.tranbuffer[..],
+                        )?;
+                        shapes.tranbuf_touch -= 1;
+                    }
+                    if shapes.rotbuf_touch != 0 {
+                        shapes.rotbuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &shapes.rotbuffer[..],
+                        )?;
+                        shapes.rotbuf_touch -= 1;
+                    }
+                    if shapes.scalebuf_touch != 0 {
+                        shapes.scalebuf[self.current_frame].copy_from_slice_and_maybe_resize(
+                            &self.device,
+                            &self.adapter,
+                            &shapes.scalebuffer[..],
+                        )?;
+                        shapes.scalebuf_touch -= 1;
+                    }
+                    let buffers: ArrayVec<[_; 5]> = [
+                        (shapes.posbuf[self.current_frame].buffer(), 0),
+                        (shapes.colbuf[self.current_frame].buffer(), 0),
+                        (shapes.tranbuf[self.current_frame].buffer(), 0),
+                        (shapes.rotbuf[self.current_frame].buffer(), 0),
+                        (shapes.scalebuf[self.current_frame].buffer(), 0),
+                    ]
+                    .into();
+                    buffer.bind_vertex_buffers(0, buffers);
+                    buffer.draw(0..shapes.posbuffer.len() as u32, 0..1);
+                }
+            }
+            DrawType::Tilemap { id } => {
+                let tilemap = &mut self.tilemaps[*id];
+                if !tilemap.hidden {
+                    buffer.bind_graphics_pipeline(&tilemap.pipeline);
+                    let dirty = tilemap::take_dirty_chunks(&mut tilemap.chunk_touch);
+                    tilemap.posbuf[self.current_frame].copy_ranges(
+                        &self.device,
+                        &tilemap.posbuffer[..],
+                        &dirty,
+                    )?;
+                    tilemap.uvbuf[self.current_frame].copy_ranges(
+                        &self.device,
+                        &tilemap.uvbuffer[..],
+                        &dirty,
+                    )?;
+                    let view = if let Some(ref view) = tilemap.fixed_perspective {
+                        view
+                    } else {
+                        &view
+                    };
+                    buffer.push_graphics_constants(
+                        &tilemap.pipeline_layout,
+                        pso::ShaderStageFlags::VERTEX,
+                        0,
+                        &*(view.as_ptr() as *const [u32; 16]),
+                    );
+                    buffer.bind_graphics_descriptor_sets(
+                        &tilemap.pipeline_layout,
+                        0,
+                        Some(&*tilemap.descriptor_set),
+                        &[],
+                    );
+                    let buffers: ArrayVec<[_; 2]> = [
+                        (tilemap.posbuf[self.current_frame].buffer(), 0),
+                        (tilemap.uvbuf[self.current_frame].buffer(), 0),
+                    ]
+                    .into();
+                    buffer.bind_vertex_buffers(0, buffers);
+                    buffer.bind_index_buffer(b::IndexBufferView {
+                        buffer: tilemap.indices[self.current_frame].buffer(),
+                        offset: 0,
+                        index_type: gfx_hal::IndexType::U32,
+                    });
+                    for quads in tilemap::visible_quads(tilemap, view) {
+                        buffer.draw_indexed(quads.start as u32 * 6..quads.end as u32 * 6, 0, 0..1);
+                    }
+                }
+            }
+        }
+        Ok(())
     }
 
     /// Generate the perspective projection so that the window's size does not stretch its
@@ -1870,6 +2722,70 @@ mod tests {
         assert_swapchain_eq(&mut vx, "setup_and_teardown_draw_resize", img);
     }
 
+    #[test]
+    fn offscreen_setup_and_teardown() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        assert_eq![(1000, 1000), vx.get_window_size_in_pixels()];
+        vx.draw_frame();
+        vx.draw_frame();
+        vx.draw_frame();
+    }
+
+    #[test]
+    fn offscreen_custom_clear_color() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        vx.set_clear_color(Color::Rgba(255, 0, 255, 128));
+
+        let img = vx.draw_frame_copy_framebuffer();
+
+        assert_swapchain_eq(&mut vx, "setup_and_teardown_custom_clear_color", img);
+    }
+
+    #[test]
+    fn offscreen_windmills() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        add_windmills(&mut vx, false);
+
+        let img = vx.draw_frame_copy_framebuffer();
+
+        assert_swapchain_eq(&mut vx, "windmills", img);
+    }
+
+    #[test]
+    fn offscreen_resize() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        vx.draw_frame();
+
+        vx.set_window_size((300, 200));
+        assert![vx.resized_since_last_render()];
+        assert_eq![(300, 200), vx.get_window_size_in_pixels()];
+
+        let img = vx.draw_frame_copy_framebuffer();
+        assert_eq![300 * 200 * 4, img.len()];
+    }
+
+    #[test]
+    fn try_draw_frame_offscreen() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::try_new_offscreen(logger, (1000, 1000)).unwrap();
+        vx.try_draw_frame().unwrap();
+
+        let img = vx.try_draw_frame_copy_framebuffer().unwrap();
+        assert_eq![1000 * 1000 * 4, img.len()];
+    }
+
+    #[test]
+    fn error_display() {
+        assert_eq![
+            "Unable to find memory type id",
+            format!["{}", Error::NoMemoryType]
+        ];
+    }
+
     #[test]
     fn setup_and_teardown_with_gpu_upload() {
         let logger = Logger::root(Discard, o!());
@@ -0,0 +1,733 @@
+//! Methods and types to control post-processing passes
+//!
+//! A post-processing pass is a fragment shader that runs over the whole composed frame after all
+//! layers have been drawn. Passes run in the order in which they were added, each one sampling the
+//! output of the previous pass, and the last pass writes the image that is presented. This is
+//! where effects such as blur, bloom, CRT emulation, or color grading go.
+//!
+//! A custom fragment shader receives the frame through `set = 0, binding = 0` (`texture2D`) and
+//! `binding = 1` (`sampler`), the UV coordinate at `location = 0`, and the pass' push constants:
+//! ```glsl
+//! #version 450
+//! layout(location = 0) in vec2 uv;
+//! layout(location = 0) out vec4 color;
+//! layout(set = 0, binding = 0) uniform texture2D frame;
+//! layout(set = 0, binding = 1) uniform sampler frame_sampler;
+//! layout(push_constant) uniform PushConstant { float strength; } push;
+//! void main() {
+//!     color = texture(sampler2D(frame, frame_sampler), uv) * push.strength;
+//! }
+//! ```
+//!
+//! # Example - Tinting the frame red #
+//! ```
+//! use vxdraw::{postproc::PassOptions, void_logger, ShowWindow, VxDraw};
+//! use winit::platform::unix::EventLoopExtUnix;
+//! use winit::event_loop::EventLoop;
+//!
+//! // Create an event loop
+//! let event_loop = EventLoop::new_any_thread();
+//!
+//! #[cfg(feature = "doctest-headless")]
+//! let mut vx = VxDraw::new(void_logger(), ShowWindow::Headless1k, &event_loop);
+//! #[cfg(not(feature = "doctest-headless"))]
+//! let mut vx = VxDraw::new(void_logger(), ShowWindow::Enable, &event_loop);
+//!
+//! // The standard shader multiplies the frame with the RGBA in its push constants
+//! let pass = vx.postproc().add_pass(&PassOptions::new()).unwrap();
+//! vx.postproc().set_push_constants(&pass, &[1.0, 0.0, 0.0, 1.0]);
+//!
+//! vx.draw_frame();
+//! #[cfg(not(feature = "doctest-headless"))]
+//! std::thread::sleep(std::time::Duration::new(3, 0));
+//! ```
+use crate::data::{PostProcPass, PostProcTargets, VxDraw};
+use crate::error::Error;
+use crate::utils::*;
+#[cfg(feature = "dx12")]
+use gfx_backend_dx12 as back;
+#[cfg(feature = "gl")]
+use gfx_backend_gl as back;
+#[cfg(feature = "metal")]
+use gfx_backend_metal as back;
+#[cfg(feature = "vulkan")]
+use gfx_backend_vulkan as back;
+use gfx_hal::{
+    device::Device,
+    format, image, memory, pass,
+    pso::{self, DescriptorPool, Primitive},
+    Backend,
+};
+use slog::error;
+use std::{io::Cursor, mem::ManuallyDrop};
+
+// ---
+
+/// Handle to a post-processing pass
+pub struct Pass(usize, u64);
+
+/// Enum describing which fragment shader to use
+#[derive(Clone)]
+pub enum FragmentShader {
+    /// Use the given SPIRV code
+    Spirv(Vec<u8>),
+    /// Use the shader provided by `vxdraw`, which multiplies the frame with the RGBA given in the
+    /// first four push constants
+    Standard,
+}
+
+/// Options for creating a post-processing pass
+#[derive(Clone)]
+pub struct PassOptions {
+    /// The fragment shader for this pass
+    fragment_shader: FragmentShader,
+    /// Initial push constants, this also decides how many push constants the pass has
+    push_constants: Vec<f32>,
+}
+
+impl PassOptions {
+    /// Same as default
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the fragment shader
+    pub fn fragment_shader(mut self, shader: FragmentShader) -> Self {
+        self.fragment_shader = shader;
+        self
+    }
+
+    /// Set the initial push constants
+    ///
+    /// The amount of push constants is fixed when the pass is created, at most 32 are allowed.
+    pub fn push_constants(mut self, values: &[f32]) -> Self {
+        self.push_constants = values.to_vec();
+        self
+    }
+}
+
+impl Default for PassOptions {
+    fn default() -> Self {
+        Self {
+            fragment_shader: FragmentShader::Standard,
+            push_constants: vec![1.0; 4],
+        }
+    }
+}
+
+// ---
+
+/// Accessor object to all post-processing passes
+///
+/// See [crate::postproc] for examples.
+pub struct Postproc<'a> {
+    vx: &'a mut VxDraw,
+}
+
+impl<'a> Postproc<'a> {
+    /// Prepare to edit post-processing passes
+    ///
+    /// You're not supposed to use this function directly (although you can).
+    /// The recommended way of spawning a postproc is via [VxDraw::postproc()].
+    pub(crate) fn new(s: &'a mut VxDraw) -> Self {
+        Self { vx: s }
+    }
+
+    /// Add a pass to the end of the post-processing chain
+    ///
+    /// Fails if the SPIR-V code of a custom fragment shader is invalid, if more than 32 push
+    /// constants are given, or if the standard shader gets fewer than the 4 it reads.
+    pub fn add_pass(&mut self, options: &PassOptions) -> Result<Pass, Error> {
+        if options.push_constants.len() > 32 {
+            return Err(Error::Unsupported(
+                "At most 32 push constants are supported",
+            ));
+        }
+        if let FragmentShader::Standard = options.fragment_shader {
+            if options.push_constants.len() < 4 {
+                return Err(Error::Unsupported(
+                    "The standard shader needs at least 4 push constants",
+                ));
+            }
+        }
+        let s = &mut *self.vx;
+        if s.postproc_targets.is_none() {
+            s.wait_for_fences()?;
+            s.postproc_targets = Some(create_targets(s)?);
+        }
+        let targets = s.postproc_targets.as_ref().unwrap();
+
+        const VERTEX_SOURCE: &[u8] = include_bytes!["../target/spirv/postproc.vert.spirv"];
+        const FRAGMENT_SOURCE: &[u8] = include_bytes!["../target/spirv/postproc.frag.spirv"];
+
+        let vertex_source = pso::read_spirv(Cursor::new(VERTEX_SOURCE))?;
+        let fragment_source = match options.fragment_shader {
+            FragmentShader::Standard => pso::read_spirv(Cursor::new(FRAGMENT_SOURCE))?,
+            FragmentShader::Spirv(ref data) => pso::read_spirv(Cursor::new(data))?,
+        };
+
+        let vs_module = unsafe { s.device.create_shader_module(&vertex_source) }?;
+        let fs_module = match unsafe { s.device.create_shader_module(&fragment_source) } {
+            Ok(fs_module) => fs_module,
+            Err(err) => {
+                unsafe { s.device.destroy_shader_module(vs_module) };
+                return Err(err.into());
+            }
+        };
+
+        const ENTRY_NAME: &str = "main";
+        let vs_module: <back::Backend as Backend>::ShaderModule = vs_module;
+        let (vs_entry, fs_entry) = (
+            pso::EntryPoint {
+                entry: ENTRY_NAME,
+                module: &vs_module,
+                specialization: pso::Specialization::default(),
+            },
+            pso::EntryPoint {
+                entry: ENTRY_NAME,
+                module: &fs_module,
+                specialization: pso::Specialization::default(),
+            },
+        );
+        let shader_entries = pso::GraphicsShaderSet {
+            vertex: vs_entry,
+            hull: None,
+            domain: None,
+            geometry: None,
+            fragment: Some(fs_entry),
+        };
+
+        let rasterizer = pso::Rasterizer {
+            depth_clamping: false,
+            polygon_mode: pso::PolygonMode::Fill,
+            cull_face: pso::Face::NONE,
+            front_face: pso::FrontFace::CounterClockwise,
+            depth_bias: None,
+            conservative: false,
+        };
+
+        let depth_stencil = pso::DepthStencilDesc {
+            depth: None,
+            depth_bounds: false,
+            stencil: None,
+        };
+
+        let blender = pso::BlendDesc {
+            logic_op: None,
+            targets: vec![pso::ColorBlendDesc {
+                mask: pso::ColorMask::ALL,
+                blend: None,
+            }],
+        };
+
+        let baked_states = pso::BakedStates {
+            viewport: None,
+            scissor: None,
+            blend_color: None,
+            depth_bounds: None,
+        };
+
+        let mut push_constants = Vec::<(pso::ShaderStageFlags, core::ops::Range<u32>)>::new();
+        if !options.push_constants.is_empty() {
+            push_constants.push((
+                pso::ShaderStageFlags::FRAGMENT,
+                0..options.push_constants.len() as u32 * 4,
+            ));
+        }
+        let pipeline_layout = unsafe {
+            s.device
+                .create_pipeline_layout(&targets.descriptor_set_layouts, push_constants)
+        };
+        let pipeline_layout = match pipeline_layout {
+            Ok(pipeline_layout) => pipeline_layout,
+            Err(err) => {
+                unsafe {
+                    s.device.destroy_shader_module(vs_module);
+                    s.device.destroy_shader_module(fs_module);
+                }
+                return Err(err.into());
+            }
+        };
+
+        let pipeline_desc = pso::GraphicsPipelineDesc {
+            shaders: shader_entries,
+            rasterizer,
+            vertex_buffers: vec![],
+            attributes: vec![],
+            input_assembler: pso::InputAssemblerDesc::new(Primitive::TriangleList),
+            blender,
+            depth_stencil,
+            multisampling: None,
+            baked_states,
+            layout: &pipeline_layout,
+            subpass: pass::Subpass {
+                index: 0,
+                main_pass: &*targets.render_pass,
+            },
+            flags: pso::PipelineCreationFlags::empty(),
+            parent: pso::BasePipeline::None,
+        };
+
+        let pipeline = unsafe { s.device.create_graphics_pipeline(&pipeline_desc, None) };
+
+        unsafe {
+            s.device.destroy_shader_module(vs_module);
+            s.device.destroy_shader_module(fs_module);
+        }
+        let pipeline = match pipeline {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                unsafe { s.device.destroy_pipeline_layout(pipeline_layout) };
+                return Err(err.into());
+            }
+        };
+
+        let generation = s.next_generation();
+        let pass = PostProcPass {
+            generation,
+            hidden: false,
+            push_constants: options.push_constants.clone(),
+            pipeline: ManuallyDrop::new(pipeline),
+            pipeline_layout: ManuallyDrop::new(pipeline_layout),
+        };
+
+        s.postprocs.push(Some(pass));
+        Ok(Pass(s.postprocs.len() - 1, generation))
+    }
+
+    /// Set the push constants of a pass
+    ///
+    /// The amount of values must be the same as when the pass was created, otherwise the call is
+    /// logged and ignored.
+    pub fn set_push_constants(&mut self, pass: &Pass, values: &[f32]) {
+        if !self.check(pass) {
+            return;
+        }
+        let expected = self.get_mut(pass).push_constants.len();
+        if expected != values.len() {
+            error!(self.vx.log, "The amount of push constants can not change"; "expected" => expected, "given" => values.len());
+            return;
+        }
+        self.get_mut(pass).push_constants.copy_from_slice(values);
+    }
+
+    /// Query the amount of passes there are
+    pub fn pass_count(&self) -> usize {
+        self.vx.postprocs.iter().filter(|x| x.is_some()).count()
+    }
+
+    /// Skip this pass when drawing
+    pub fn hide(&mut self, pass: &Pass) {
+        if !self.check(pass) {
+            return;
+        }
+        self.get_mut(pass).hidden = true;
+    }
+
+    /// Run this pass when drawing
+    pub fn show(&mut self, pass: &Pass) {
+        if !self.check(pass) {
+            return;
+        }
+        self.get_mut(pass).hidden = false;
+    }
+
+    /// Remove a pass from the chain
+    pub fn remove_pass(&mut self, pass: Pass) {
+        if !self.check(&pass) {
+            return;
+        }
+        let s = &mut *self.vx;
+        s.wait_for_fences_or_log();
+        if let Some(pass) = s.postprocs[pass.0].take() {
+            pass.destroy(&s.device);
+        }
+    }
+
+    fn check(&self, pass: &Pass) -> bool {
+        let current = self.vx.postprocs[pass.0]
+            .as_ref()
+            .map_or(0, |pass| pass.generation);
+        is_current(&self.vx.log, current, pass.1)
+    }
+
+    fn get_mut(&mut self, pass: &Pass) -> &mut PostProcPass {
+        self.vx.postprocs[pass.0]
+            .as_mut()
+            .expect("Pass has been removed")
+    }
+}
+
+// ---
+
+/// Images, views, and framebuffers that depend on the size of the frame
+struct TargetImages {
+    images: Vec<<back::Backend as Backend>::Image>,
+    image_memories: Vec<<back::Backend as Backend>::Memory>,
+    image_views: Vec<<back::Backend as Backend>::ImageView>,
+    framebuffers: Vec<<back::Backend as Backend>::Framebuffer>,
+    depth_image: <back::Backend as Backend>::Image,
+    depth_image_memory: <back::Backend as Backend>::Memory,
+    depth_image_view: <back::Backend as Backend>::ImageView,
+}
+
+fn create_target_images(
+    s: &VxDraw,
+    render_pass: &<back::Backend as Backend>::RenderPass,
+) -> Result<TargetImages, Error> {
+    let extent = s.swapconfig.extent;
+    let mut images = vec![];
+    let mut image_memories = vec![];
+    let mut image_views = vec![];
+    unsafe {
+        for _ in 0..2 {
+            let mut image = s.device.create_image(
+                image::Kind::D2(extent.width, extent.height, 1, 1),
+                1,
+                s.format,
+                image::Tiling::Optimal,
+                image::Usage::COLOR_ATTACHMENT | image::Usage::SAMPLED,
+                image::ViewCapabilities::empty(),
+            )?;
+            let requirements = s.device.get_image_requirements(&image);
+            let memory_type_id =
+                find_memory_type_id(&s.adapter, requirements, memory::Properties::DEVICE_LOCAL)?;
+            let memory = s
+                .device
+                .allocate_memory(memory_type_id, requirements.size)?;
+            s.device.bind_image_memory(&memory, 0, &mut image)?;
+            let image_view = s.device.create_image_view(
+                &image,
+                image::ViewKind::D2,
+                s.format,
+                format::Swizzle::NO,
+                image::SubresourceRange {
+                    aspects: format::Aspects::COLOR,
+                    levels: 0..1,
+                    layers: 0..1,
+                },
+            )?;
+            images.push(image);
+            image_memories.push(memory);
+            image_views.push(image_view);
+        }
+
+        let mut depth_image = s.device.create_image(
+            image::Kind::D2(extent.width, extent.height, 1, 1),
+            1,
+            format::Format::D32Sfloat,
+            image::Tiling::Optimal,
+            image::Usage::DEPTH_STENCIL_ATTACHMENT,
+            image::ViewCapabilities::empty(),
+        )?;
+        let requirements = s.device.get_image_requirements(&depth_image);
+        let memory_type_id =
+            find_memory_type_id(&s.adapter, requirements, memory::Properties::DEVICE_LOCAL)?;
+        let depth_image_memory = s
+            .device
+            .allocate_memory(memory_type_id, requirements.size)?;
+        s.device
+            .bind_image_memory(&depth_image_memory, 0, &mut depth_image)?;
+        let depth_image_view = s.device.create_image_view(
+            &depth_image,
+            image::ViewKind::D2,
+            format::Format::D32Sfloat,
+            format::Swizzle::NO,
+            image::SubresourceRange {
+                aspects: format::Aspects::DEPTH,
+                levels: 0..1,
+                layers: 0..1,
+            },
+        )?;
+
+        let framebuffers = image_views
+            .iter()
+            .map(|image_view| {
+                s.device.create_framebuffer(
+                    render_pass,
+                    vec![image_view, &depth_image_view],
+                    image::Extent {
+                        width: extent.width,
+                        height: extent.height,
+                        depth: 1,
+                    },
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(TargetImages {
+            images,
+            image_memories,
+            image_views,
+            framebuffers,
+            depth_image,
+            depth_image_memory,
+            depth_image_view,
+        })
+    }
+}
+
+/// Point descriptor set `n` at intermediate image `n`
+fn write_descriptor_sets(device: &back::Device, targets: &PostProcTargets) {
+    for (set, image_view) in targets.descriptor_sets.iter().zip(&targets.image_views) {
+        unsafe {
+            device.write_descriptor_sets(vec![
+                pso::DescriptorSetWrite {
+                    set,
+                    binding: 0,
+                    array_offset: 0,
+                    descriptors: Some(pso::Descriptor::Image(
+                        image_view,
+                        image::Layout::ShaderReadOnlyOptimal,
+                    )),
+                },
+                pso::DescriptorSetWrite {
+                    set,
+                    binding: 1,
+                    array_offset: 0,
+                    descriptors: Some(pso::Descriptor::Sampler(&*targets.sampler)),
+                },
+            ]);
+        }
+    }
+}
+
+fn create_targets(s: &VxDraw) -> Result<PostProcTargets, Error> {
+    let render_pass = {
+        let attachment = pass::Attachment {
+            format: Some(s.format),
+            samples: 1,
+            ops: pass::AttachmentOps::new(
+                pass::AttachmentLoadOp::Clear,
+                pass::AttachmentStoreOp::Store,
+            ),
+            stencil_ops: pass::AttachmentOps::DONT_CARE,
+            layouts: image::Layout::Undefined..image::Layout::ShaderReadOnlyOptimal,
+        };
+        let depth = pass::Attachment {
+            format: Some(format::Format::D32Sfloat),
+            samples: 1,
+            ops: pass::AttachmentOps::new(
+                pass::AttachmentLoadOp::Clear,
+                pass::AttachmentStoreOp::DontCare,
+            ),
+            stencil_ops: pass::AttachmentOps::DONT_CARE,
+            layouts: image::Layout::Undefined..image::Layout::DepthStencilAttachmentOptimal,
+        };
+
+        let subpass = pass::SubpassDesc {
+            colors: &[(0, image::Layout::ColorAttachmentOptimal)],
+            depth_stencil: Some(&(1, image::Layout::DepthStencilAttachmentOptimal)),
+            inputs: &[],
+            resolves: &[],
+            preserves: &[],
+        };
+
+        // An image is sampled by one pass and then drawn into by the next
+        let dependencies = [
+            pass::SubpassDependency {
+                passes: pass::SubpassRef::External..pass::SubpassRef::Pass(0),
+                stages: pso::PipelineStage::FRAGMENT_SHADER
+                    ..pso::PipelineStage::COLOR_ATTACHMENT_OUTPUT,
+                accesses: image::Access::SHADER_READ..image::Access::COLOR_ATTACHMENT_WRITE,
+                flags: memory::Dependencies::empty(),
+            },
+            pass::SubpassDependency {
+                passes: pass::SubpassRef::Pass(0)..pass::SubpassRef::External,
+                stages: pso::PipelineStage::COLOR_ATTACHMENT_OUTPUT
+                    ..pso::PipelineStage::FRAGMENT_SHADER,
+                accesses: image::Access::COLOR_ATTACHMENT_WRITE..image::Access::SHADER_READ,
+                flags: memory::Dependencies::empty(),
+            },
+        ];
+
+        unsafe {
+            s.device
+                .create_render_pass(&[attachment, depth], &[subpass], &dependencies)?
+        }
+    };
+
+    let sampler = unsafe {
+        s.device.create_sampler(&image::SamplerDesc::new(
+            image::Filter::Linear,
+            image::WrapMode::Clamp,
+        ))?
+    };
+
+    let bindings = vec![
+        pso::DescriptorSetLayoutBinding {
+            binding: 0,
+            ty: pso::DescriptorType::SampledImage,
+            count: 1,
+            stage_flags: pso::ShaderStageFlags::FRAGMENT,
+            immutable_samplers: false,
+        },
+        pso::DescriptorSetLayoutBinding {
+            binding: 1,
+            ty: pso::DescriptorType::Sampler,
+            count: 1,
+            stage_flags: pso::ShaderStageFlags::FRAGMENT,
+            immutable_samplers: false,
+        },
+    ];
+    let immutable_samplers = Vec::<<back::Backend as Backend>::Sampler>::new();
+    let descriptor_set_layouts = vec![unsafe {
+        s.device
+            .create_descriptor_set_layout(bindings, immutable_samplers)?
+    }];
+
+    let mut descriptor_pool = unsafe {
+        s.device.create_descriptor_pool(
+            2, // sets
+            &[
+                pso::DescriptorRangeDesc {
+                    ty: pso::DescriptorType::SampledImage,
+                    count: 2,
+                },
+                pso::DescriptorRangeDesc {
+                    ty: pso::DescriptorType::Sampler,
+                    count: 2,
+                },
+            ],
+            pso::DescriptorPoolCreateFlags::empty(),
+        )?
+    };
+
+    let descriptor_sets = (0..2)
+        .map(|_| unsafe {
+            descriptor_pool
+                .allocate_set(&descriptor_set_layouts[0])
+                .expect("Couldn't make a Descriptor Set!")
+        })
+        .collect::<Vec<_>>();
+
+    let images = create_target_images(s, &render_pass)?;
+
+    let targets = PostProcTargets {
+        images: images.images,
+        image_memories: images.image_memories,
+        image_views: images.image_views,
+        framebuffers: images.framebuffers,
+
+        depth_image: ManuallyDrop::new(images.depth_image),
+        depth_image_memory: ManuallyDrop::new(images.depth_image_memory),
+        depth_image_view: ManuallyDrop::new(images.depth_image_view),
+
+        sampler: ManuallyDrop::new(sampler),
+        descriptor_pool: ManuallyDrop::new(descriptor_pool),
+        descriptor_sets,
+        descriptor_set_layouts,
+        render_pass: ManuallyDrop::new(render_pass),
+    };
+    write_descriptor_sets(&s.device, &targets);
+    Ok(targets)
+}
+
+/// Recreate the intermediate images after the frame has changed size
+///
+/// The device must be idle when calling this.
+pub(crate) fn recreate_targets(s: &mut VxDraw) -> Result<(), Error> {
+    let mut targets = match s.postproc_targets.take() {
+        Some(targets) => targets,
+        None => return Ok(()),
+    };
+    let images = match create_target_images(s, &targets.render_pass) {
+        Ok(images) => images,
+        Err(err) => {
+            s.postproc_targets = Some(targets);
+            return Err(err);
+        }
+    };
+    targets.destroy_images(&s.device);
+    targets.images = images.images;
+    targets.image_memories = images.image_memories;
+    targets.image_views = images.image_views;
+    targets.framebuffers = images.framebuffers;
+    targets.depth_image = ManuallyDrop::new(images.depth_image);
+    targets.depth_image_memory = ManuallyDrop::new(images.depth_image_memory);
+    targets.depth_image_view = ManuallyDrop::new(images.depth_image_view);
+    write_descriptor_sets(&s.device, &targets);
+    s.postproc_targets = Some(targets);
+    Ok(())
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+    use slog::{o, Discard, Logger};
+
+    #[test]
+    fn tint_pass() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        vx.set_clear_color(Color::Rgba(255, 255, 255, 255));
+
+        let pass = vx.postproc().add_pass(&PassOptions::new()).unwrap();
+        vx.postproc()
+            .set_push_constants(&pass, &[1.0, 0.0, 0.0, 1.0]);
+
+        let img = vx.draw_frame_copy_framebuffer();
+        assert_eq![[255, 0, 0, 255], pixel(&vx, &img, (500, 500))];
+
+        vx.postproc().set_push_constants(&pass, &[0.0, 1.0, 0.0]);
+        let img = vx.draw_frame_copy_framebuffer();
+        assert_eq![[255, 0, 0, 255], pixel(&vx, &img, (500, 500))];
+    }
+
+    #[test]
+    fn passes_run_in_sequence() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        vx.set_clear_color(Color::Rgba(255, 255, 255, 255));
+
+        vx.postproc()
+            .add_pass(&PassOptions::new().push_constants(&[1.0, 1.0, 0.0, 1.0]))
+            .unwrap();
+        let second = vx
+            .postproc()
+            .add_pass(&PassOptions::new().push_constants(&[1.0, 0.0, 1.0, 1.0]))
+            .unwrap();
+        assert_eq![2, vx.postproc().pass_count()];
+
+        let img = vx.draw_frame_copy_framebuffer();
+        assert_eq![[255, 0, 0, 255], pixel(&vx, &img, (10, 10))];
+
+        vx.postproc().hide(&second);
+        let img = vx.draw_frame_copy_framebuffer();
+        assert_eq![[255, 255, 0, 255], pixel(&vx, &img, (10, 10))];
+
+        vx.postproc().remove_pass(second);
+        assert_eq![1, vx.postproc().pass_count()];
+        let img = vx.draw_frame_copy_framebuffer();
+        assert_eq![[255, 255, 0, 255], pixel(&vx, &img, (10, 10))];
+    }
+
+    #[test]
+    fn invalid_spirv_is_an_error() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let options = PassOptions::new().fragment_shader(FragmentShader::Spirv(vec![1, 2, 3]));
+        assert![vx.postproc().add_pass(&options).is_err()];
+        let options = PassOptions::new().push_constants(&[0.0; 33]);
+        assert![vx.postproc().add_pass(&options).is_err()];
+        let options = PassOptions::new().push_constants(&[1.0; 3]);
+        assert![vx.postproc().add_pass(&options).is_err()];
+        assert_eq![0, vx.postproc().pass_count()];
+        vx.draw_frame();
+    }
+
+    #[test]
+    fn resize_with_pass() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        vx.postproc().add_pass(&PassOptions::new()).unwrap();
+        vx.draw_frame();
+
+        vx.set_window_size((300, 200));
+        let img = vx.draw_frame_copy_framebuffer();
+        assert_eq![300 * 200 * 4, img.len()];
+    }
+}
@@ -144,24 +144,22 @@ use std::{io::Cursor, mem::ManuallyDrop};
 // ---
 
 /// Handle referring to a single quad
+///
+/// The handle becomes stale when the quad or its layer is removed, using a stale handle panics in
+/// debug builds and is ignored in release builds.
 #[derive(Debug)]
-pub struct Handle(usize, usize);
+pub struct Handle(usize, usize, u64);
 
 /// Handle referring to a quad layer
 #[derive(Debug)]
-pub struct Layer(usize);
+pub struct Layer(usize, u64);
 
 impl Layerable for Layer {
-    fn get_layer(&self, vx: &VxDraw) -> usize {
-        for (idx, ord) in vx.draw_order.iter().enumerate() {
-            match ord {
-                DrawType::Quad { id } if *id == self.0 => {
-                    return idx;
-                }
-                _ => {}
-            }
-        }
-        panic!["Unable to get layer"]
+    fn get_layer(&self, vx: &VxDraw) -> Option<LayerPosition> {
+        vx.find_in_draw_order(|ord| match ord {
+            DrawType::Quad { id } => *id == self.0,
+            _ => false,
+        })
     }
 }
 
@@ -192,6 +190,7 @@ pub struct LayerOptions {
     fixed_perspective: Option<Matrix4<f32>>,
     vertex_shader: VertexShader,
     fragment_shader: FragmentShader,
+    instanced: bool,
 }
 
 impl Default for LayerOptions {
@@ -203,6 +202,7 @@ impl Default for LayerOptions {
             fixed_perspective: None,
             vertex_shader: VertexShader::Standard,
             fragment_shader: FragmentShader::Standard,
+            instanced: false,
         }
     }
 }
@@ -248,6 +248,15 @@ impl LayerOptions {
         self.blend = blend_setter(self.blend);
         self
     }
+
+    /// Draw the quads of this layer using instancing (default is off)
+    ///
+    /// Translation, rotation, and scale are uploaded once per quad instead of once per vertex.
+    /// A custom vertex shader must take the per-instance inputs of `shaders/quads_instanced.vert`.
+    pub fn instanced(mut self, instanced: bool) -> Self {
+        self.instanced = instanced;
+        self
+    }
 }
 
 // ---
@@ -333,6 +342,115 @@ impl Default for Quad {
     }
 }
 
+/// Vertex input of an instanced layer, every binding advances once per quad
+///
+/// Positions and colors keep their 4 corners per quad, translation, rotation, and scale are
+/// uploaded once per quad.
+fn instanced_vertex_input() -> (Vec<pso::VertexBufferDesc>, Vec<pso::AttributeDesc>) {
+    let vertex_buffers = vec![
+        pso::VertexBufferDesc {
+            binding: 0,
+            stride: 32,
+            rate: pso::VertexInputRate::Instance(1),
+        },
+        pso::VertexBufferDesc {
+            binding: 1,
+            stride: 16,
+            rate: pso::VertexInputRate::Instance(1),
+        },
+        pso::VertexBufferDesc {
+            binding: 2,
+            stride: 8,
+            rate: pso::VertexInputRate::Instance(1),
+        },
+        pso::VertexBufferDesc {
+            binding: 3,
+            stride: 4,
+            rate: pso::VertexInputRate::Instance(1),
+        },
+        pso::VertexBufferDesc {
+            binding: 4,
+            stride: 4,
+            rate: pso::VertexInputRate::Instance(1),
+        },
+    ];
+    let attributes = vec![
+        pso::AttributeDesc {
+            location: 0,
+            binding: 0,
+            element: pso::Element {
+                format: format::Format::Rgba32Sfloat,
+                offset: 0,
+            },
+        },
+        pso::AttributeDesc {
+            location: 1,
+            binding: 0,
+            element: pso::Element {
+                format: format::Format::Rgba32Sfloat,
+                offset: 16,
+            },
+        },
+        pso::AttributeDesc {
+            location: 2,
+            binding: 1,
+            element: pso::Element {
+                format: format::Format::Rgba8Unorm,
+                offset: 0,
+            },
+        },
+        pso::AttributeDesc {
+            location: 3,
+            binding: 1,
+            element: pso::Element {
+                format: format::Format::Rgba8Unorm,
+                offset: 4,
+            },
+        },
+        pso::AttributeDesc {
+            location: 4,
+            binding: 1,
+            element: pso::Element {
+                format: format::Format::Rgba8Unorm,
+                offset: 8,
+            },
+        },
+        pso::AttributeDesc {
+            location: 5,
+            binding: 1,
+            element: pso::Element {
+                format: format::Format::Rgba8Unorm,
+                offset: 12,
+            },
+        },
+        pso::AttributeDesc {
+            location: 6,
+            binding: 2,
+            element: pso::Element {
+                format: format::Format::Rg32Sfloat,
+                offset: 0,
+            },
+        },
+        pso::AttributeDesc {
+            location: 7,
+            binding: 3,
+            element: pso::Element {
+                format: format::Format::R32Sfloat,
+                offset: 0,
+            },
+        },
+        pso::AttributeDesc {
+            location: 8,
+            binding: 4,
+            element: pso::Element {
+                format: format::Format::R32Sfloat,
+                offset: 0,
+            },
+        },
+    ];
+    (vertex_buffers, attributes)
+}
+
 // ---
 
 /// Accessor object to all quads
@@ -373,6 +491,9 @@ impl<'a> Quads<'a> {
     /// This function can swap quads from two different layers, but also quads in the same
     /// layer.
     pub fn swap_draw_order(&mut self, left: &mut Handle, right: &mut Handle) {
+        if !self.check(left) || !self.check(right) {
+            return;
+        }
         let q1d = self.vx.quads[left.0].posbuffer[left.1];
         let q2d = self.vx.quads[right.0].posbuffer[right.1];
         self.vx.quads[left.0].posbuffer[left.1] = q2d;
@@ -410,8 +531,7 @@ impl<'a> Quads<'a> {
         self.vx.quads[right.0].rotbuf_touch = self.vx.swapconfig.image_count;
         self.vx.quads[right.0].scalebuf_touch = self.vx.swapconfig.image_count;
 
-        std::mem::swap(&mut left.0, &mut right.0);
-        std::mem::swap(&mut left.1, &mut right.1);
+        std::mem::swap(left, right);
     }
 
     /// Create a new layer for quads
@@ -423,9 +543,15 @@ impl<'a> Quads<'a> {
         let s = &mut *self.vx;
         pub const VERTEX_SOURCE: &[u8] = include_bytes!["../target/spirv/quads.vert.spirv"];
 
+        pub const VERTEX_SOURCE_INSTANCED: &[u8] =
+            include_bytes!["../target/spirv/quads_instanced.vert.spirv"];
+
         pub const FRAGMENT_SOURCE: &[u8] = include_bytes!["../target/spirv/quads.frag.spirv"];
 
         let vertex_source = match options.vertex_shader {
+            VertexShader::Standard if options.instanced => {
+                pso::read_spirv(Cursor::new(VERTEX_SOURCE_INSTANCED)).unwrap()
+            }
             VertexShader::Standard => pso::read_spirv(Cursor::new(VERTEX_SOURCE)).unwrap(),
             VertexShader::Spirv(ref data) => pso::read_spirv(Cursor::new(data)).unwrap(),
         };
@@ -461,75 +587,80 @@ impl<'a> Quads<'a> {
         };
         let input_assembler = pso::InputAssemblerDesc::new(Primitive::TriangleList);
 
-        let vertex_buffers: Vec<pso::VertexBufferDesc> = vec![
-            pso::VertexBufferDesc {
-                binding: 0,
-                stride: 2 * 4,
-                rate: pso::VertexInputRate::Vertex,
-            },
-            pso::VertexBufferDesc {
-                binding: 1,
-                stride: 4,
-                rate: pso::VertexInputRate::Vertex,
-            },
-            pso::VertexBufferDesc {
-                binding: 2,
-                stride: 8,
-                rate: pso::VertexInputRate::Vertex,
-            },
-            pso::VertexBufferDesc {
-                binding: 3,
-                stride: 4,
-                rate: pso::VertexInputRate::Vertex,
-            },
-            pso::VertexBufferDesc {
-                binding: 4,
-                stride: 4,
-                rate: pso::VertexInputRate::Vertex,
-            },
-        ];
-        let attributes: Vec<pso::AttributeDesc> = vec![
-            pso::AttributeDesc {
-                location: 0,
-                binding: 0,
-                element: pso::Element {
-                    format: format::Format::Rg32Sfloat,
-                    offset: 0,
+        let (vertex_buffers, attributes) = if options.instanced {
+            instanced_vertex_input()
+        } else {
+            let vertex_buffers: Vec<pso::VertexBufferDesc> = vec![
+                pso::VertexBufferDesc {
+                    binding: 0,
+                    stride: 2 * 4,
+                    rate: pso::VertexInputRate::Vertex,
                 },
-            },
-            pso::AttributeDesc {
-                location: 1,
-                binding: 1,
-                element: pso::Element {
-                    format: format::Format::Rgba8Unorm,
-                    offset: 0,
+                pso::VertexBufferDesc {
+                    binding: 1,
+                    stride: 4,
+                    rate: pso::VertexInputRate::Vertex,
                 },
-            },
-            pso::AttributeDesc {
-                location: 2,
-                binding: 2,
-                element: pso::Element {
-                    format: format::Format::Rg32Sfloat,
-                    offset: 0,
+                pso::VertexBufferDesc {
+                    binding: 2,
+                    stride: 8,
+                    rate: pso::VertexInputRate::Vertex,
                 },
-            },
-            pso::AttributeDesc {
-                location: 3,
-                binding: 3,
-                element: pso::Element {
-                    format: format::Format::R32Sfloat,
-                    offset: 0,
+                pso::VertexBufferDesc {
+                    binding: 3,
+                    stride: 4,
+                    rate: pso::VertexInputRate::Vertex,
                 },
-            },
-            pso::AttributeDesc {
-                location: 4,
-                binding: 4,
-                element: pso::Element {
-                    format: format::Format::R32Sfloat,
-                    offset: 0,
+                pso::VertexBufferDesc {
+                    binding: 4,
+                    stride: 4,
+                    rate: pso::VertexInputRate::Vertex,
                 },
-            },
-        ];
+            ];
+            let attributes: Vec<pso::AttributeDesc> = vec![
+                pso::AttributeDesc {
+                    location: 0,
+                    binding: 0,
+                    element: pso::Element {
+                        format: format::Format::Rg32Sfloat,
+                        offset: 0,
+                    },
+                },
+                pso::AttributeDesc {
+                    location: 1,
+                    binding: 1,
+                    element: pso::Element {
+                        format: format::Format::Rgba8Unorm,
+                        offset: 0,
+                    },
+                },
+                pso::AttributeDesc {
+                    location: 2,
+                    binding: 2,
+                    element: pso::Element {
+                        format: format::Format::Rg32Sfloat,
+                        offset: 0,
+                    },
+                },
+                pso::AttributeDesc {
+                    location: 3,
+                    binding: 3,
+                    element: pso::Element {
+                        format: format::Format::R32Sfloat,
+                        offset: 0,
+                    },
+                },
+                pso::AttributeDesc {
+                    location: 4,
+                    binding: 4,
+                    element: pso::Element {
+                        format: format::Format::R32Sfloat,
+                        offset: 0,
+                    },
+                },
+            ];
+            (vertex_buffers, attributes)
+        };
 
         let rasterizer = pso::Rasterizer {
             depth_clamping: false,
@@ -646,30 +777,46 @@ impl<'a> Quads<'a> {
 
         let image_count = s.swapconfig.image_count;
         let posbuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&s.device, &s.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
         let colbuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&s.device, &s.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
         let tranbuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&s.device, &s.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
         let rotbuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&s.device, &s.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
         let scalebuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&s.device, &s.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
 
         let indices = (0..image_count)
-            .map(|_| super::utils::ResizBufIdx4::new(&s.device, &s.adapter))
+            .map(|_| {
+                super::utils::ResizBufIdx4::new(&s.device, &s.adapter)
+                    .expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
 
         let quads = QuadsData {
             hidden: options.hide,
 
             fixed_perspective: options.fixed_perspective,
+            instanced: options.instanced,
             holes: vec![],
+            generation: s.next_generation(),
+            generations: vec![],
 
             posbuf_touch: 0,
             colbuf_touch: 0,
@@ -702,13 +849,14 @@ impl<'a> Quads<'a> {
             _ => false,
         });
 
+        let generation = quads.generation;
         if let Some(prev_layer) = prev_layer {
             match prev_layer {
                 DrawType::Quad { id } => {
                     let old_quad = std::mem::replace(&mut s.quads[id], quads);
                     destroy_layer(s, old_quad);
                     s.draw_order.push(DrawType::Quad { id });
-                    Layer(id)
+                    Layer(id, generation)
                 }
                 _ => panic!["Got a non-quads drawtype, should be impossible!"],
             }
@@ -717,12 +865,15 @@ impl<'a> Quads<'a> {
             s.draw_order.push(DrawType::Quad {
                 id: s.quads.len() - 1,
             });
-            Layer(s.quads.len() - 1)
+            Layer(s.quads.len() - 1, generation)
         }
     }
 
     /// Set the fixed perspective of a layer. `None` uses the vxdraw perspective.
     pub fn set_perspective(&mut self, layer: &Layer, perspective: Option<Matrix4<f32>>) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.quads[layer.0].fixed_perspective = perspective;
     }
 
@@ -733,17 +884,25 @@ impl<'a> Quads<'a> {
 
     /// Disable drawing of the quads at this layer
     pub fn hide(&mut self, layer: &Layer) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.quads[layer.0].hidden = true;
     }
 
     /// Enable drawing of the quads at this layer
     pub fn show(&mut self, layer: &Layer) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.quads[layer.0].hidden = false;
     }
 
     /// Add a new quad to the given layer
     ///
     /// The new quad will be based on the data in [Quad], and inserted into the given [Layer].
+    ///
+    /// Adding to a removed layer returns a handle that is already stale.
     pub fn add(&mut self, layer: &Layer, quad: Quad) -> Handle {
         let width = quad.width;
         let height = quad.height;
@@ -755,13 +914,15 @@ impl<'a> Quads<'a> {
         let topright = (width / 2f32 - quad.origin.0, -height / 2f32 - quad.origin.1);
         let bottomleft = (-width / 2f32 - quad.origin.0, height / 2f32 - quad.origin.1);
         let bottomright = (width / 2f32 - quad.origin.0, height / 2f32 - quad.origin.1);
-        let replace = self.vx.quads.get(layer.0).map(|x| !x.holes.is_empty());
-        if replace.is_none() {
-            panic!("Layer does not exist");
+        if !self.check_layer(layer) {
+            return Handle(layer.0, 0, 0);
         }
-        let handle = if replace.unwrap() {
+        let replace = !self.vx.quads[layer.0].holes.is_empty();
+        let generation = self.vx.next_generation();
+        let handle = if replace {
             let hole = self.vx.quads.get_mut(layer.0).unwrap().holes.pop().unwrap();
-            let handle = Handle(layer.0, hole);
+            self.vx.quads[layer.0].generations[hole] = generation;
+            let handle = Handle(layer.0, hole, generation);
             self.set_deform(
                 &handle,
                 [
@@ -850,8 +1011,9 @@ impl<'a> Quads<'a> {
             quads
                 .scalebuffer
                 .push([quad.scale, quad.scale, quad.scale, quad.scale]);
+            quads.generations.push(generation);
 
-            Handle(layer.0, quads.posbuffer.len() - 1)
+            Handle(layer.0, quads.posbuffer.len() - 1, generation)
         };
 
         let quads = self.vx.quads.get_mut(layer.0).unwrap();
@@ -869,19 +1031,16 @@ impl<'a> Quads<'a> {
     /// Removes the quad layer from memory and destroys all quads associated with it.
     /// All lingering quad handles that were spawned using this layer will be invalidated.
     pub fn remove_layer(&mut self, layer: Layer) {
-        let s = &mut *self.vx;
-        let mut index = None;
-        for (idx, x) in s.draw_order.iter().enumerate() {
-            match x {
-                DrawType::Quad { id } if *id == layer.0 => {
-                    index = Some(idx);
-                    break;
-                }
-                _ => {}
-            }
+        if !self.check_layer(&layer) {
+            return;
         }
-        if let Some(idx) = index {
-            let draw_type = s.draw_order.remove(idx);
+        let s = &mut *self.vx;
+        s.quads[layer.0].generation = 0;
+        let draw_type = s.take_from_draw_order(|x| match x {
+            DrawType::Quad { id } => *id == layer.0,
+            _ => false,
+        });
+        if let Some(draw_type) = draw_type {
             s.layer_holes.push(draw_type);
         }
     }
@@ -892,8 +1051,80 @@ impl<'a> Quads<'a> {
     /// `holes`. Calling [Quads::add] with available holes will fill the first available hole
     /// with the new quad.
     pub fn remove(&mut self, handle: Handle) {
+        if !self.check(&handle) {
+            return;
+        }
         self.vx.quads[handle.0].holes.push(handle.1);
         self.set_scale(&handle, 0.0);
+        self.vx.quads[handle.0].generations[handle.1] = 0;
+    }
+
+    /// Repack the quads of a layer so that removed quads no longer take up space
+    ///
+    /// [Quads::add] reuses the slots of removed quads, but the buffers never shrink by themselves.
+    /// Compaction moves the last quads into the free slots and truncates the buffers. Returns
+    /// pairs of `(old, new)` handles for every quad that was moved, the old handles become stale.
+    /// Handles to quads that were not moved stay valid.
+    pub fn compact(&mut self, layer: &Layer) -> Vec<(Handle, Handle)> {
+        if !self.check_layer(layer) {
+            return vec![];
+        }
+        let image_count = self.vx.swapconfig.image_count;
+        let quads = &mut self.vx.quads[layer.0];
+        let (moves, len) = compaction_moves(quads.posbuffer.len(), &quads.holes);
+        for (from, to) in moves.iter().cloned() {
+            quads.posbuffer[to] = quads.posbuffer[from];
+            quads.colbuffer[to] = quads.colbuffer[from];
+            quads.tranbuffer[to] = quads.tranbuffer[from];
+            quads.rotbuffer[to] = quads.rotbuffer[from];
+            quads.scalebuffer[to] = quads.scalebuffer[from];
+            quads.generations[to] = quads.generations[from];
+        }
+        quads.posbuffer.truncate(len);
+        quads.colbuffer.truncate(len);
+        quads.tranbuffer.truncate(len);
+        quads.rotbuffer.truncate(len);
+        quads.scalebuffer.truncate(len);
+        quads.generations.truncate(len);
+        quads.holes.clear();
+        if !moves.is_empty() {
+            quads.posbuf_touch = image_count;
+            quads.colbuf_touch = image_count;
+            quads.tranbuf_touch = image_count;
+            quads.rotbuf_touch = image_count;
+            quads.scalebuf_touch = image_count;
+        }
+        moves
+            .iter()
+            .map(|(from, to)| {
+                let generation = quads.generations[*to];
+                (
+                    Handle(layer.0, *from, generation),
+                    Handle(layer.0, *to, generation),
+                )
+            })
+            .collect()
+    }
+
+    /// Check if a quad handle still refers to a quad, that is, neither the quad nor its layer has
+    /// been removed
+    pub fn is_alive(&self, handle: &Handle) -> bool {
+        let quads = &self.vx.quads[handle.0];
+        quads.generation != 0 && quads.generations.get(handle.1) == Some(&handle.2)
+    }
+
+    fn check(&self, handle: &Handle) -> bool {
+        let quads = &self.vx.quads[handle.0];
+        let current = if quads.generation == 0 {
+            0
+        } else {
+            quads.generations.get(handle.1).cloned().unwrap_or(0)
+        };
+        is_current(&self.vx.log, current, handle.2)
+    }
+
+    fn check_layer(&self, layer: &Layer) -> bool {
+        is_current(&self.vx.log, self.vx.quads[layer.0].generation, layer.1)
     }
 
     // ---
@@ -904,6 +1135,9 @@ impl<'a> Quads<'a> {
     /// What this function does is just setting absolute vertex positions for each vertex in the
     /// quad.
     pub fn set_deform(&mut self, handle: &Handle, points: [(f32, f32); 4]) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.quads[handle.0].posbuf_touch = self.vx.swapconfig.image_count;
         let vertex = &mut self.vx.quads[handle.0].posbuffer[handle.1];
         for (idx, point) in points.iter().enumerate() {
@@ -914,6 +1148,9 @@ impl<'a> Quads<'a> {
 
     /// Set a solid color of a quad
     pub fn set_solid_color(&mut self, handle: &Handle, rgba: Color) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.quads[handle.0].colbuf_touch = self.vx.swapconfig.image_count;
         for idx in 0..4 {
             let Color::Rgba(r, g, b, a) = rgba;
@@ -924,6 +1161,9 @@ impl<'a> Quads<'a> {
 
     /// Set a solid color each vertex of a quad
     pub fn set_color(&mut self, handle: &Handle, rgba: [Color; 4]) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.quads[handle.0].colbuf_touch = self.vx.swapconfig.image_count;
         for (idx, dt) in rgba.iter().enumerate() {
             let Color::Rgba(r, g, b, a) = dt;
@@ -938,6 +1178,9 @@ impl<'a> Quads<'a> {
     /// `translate_all` consistent. This function can purely be thought of as setting the position
     /// of the quad with respect to the model-space's origin.
     pub fn set_translation(&mut self, handle: &Handle, position: (f32, f32)) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.quads[handle.0].tranbuf_touch = self.vx.swapconfig.image_count;
         for idx in 0..4 {
             self.vx.quads[handle.0].tranbuffer[handle.1][idx * 2] = position.0;
@@ -949,6 +1192,9 @@ impl<'a> Quads<'a> {
     ///
     /// The rotation is about the model space origin.
     pub fn set_rotation<T: Copy + Into<Rad<f32>>>(&mut self, handle: &Handle, angle: T) {
+        if !self.check(handle) {
+            return;
+        }
         let angle = angle.into().0;
         self.vx.quads[handle.0].rotbuf_touch = self.vx.swapconfig.image_count;
         self.vx.quads[handle.0].rotbuffer[handle.1].copy_from_slice(&[angle, angle, angle, angle]);
@@ -956,6 +1202,9 @@ impl<'a> Quads<'a> {
 
     /// Set the scale of a quad
     pub fn set_scale(&mut self, handle: &Handle, scale: f32) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.quads[handle.0].scalebuf_touch = self.vx.swapconfig.image_count;
         for sc in &mut self.vx.quads[handle.0].scalebuffer[handle.1] {
             *sc = scale;
@@ -968,6 +1217,9 @@ impl<'a> Quads<'a> {
     ///
     /// Adds the delta vertices to the quad. Beware: This changes model space form.
     pub fn deform(&mut self, handle: &Handle, delta: [(f32, f32); 4]) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.quads[handle.0].posbuf_touch = self.vx.swapconfig.image_count;
         let points = &mut self.vx.quads[handle.0].posbuffer[handle.1];
         points[0] += delta[0].0;
@@ -984,6 +1236,9 @@ impl<'a> Quads<'a> {
     ///
     /// Translation does not mutate the model-space of a quad.
     pub fn translate(&mut self, handle: &Handle, movement: (f32, f32)) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.quads[handle.0].tranbuf_touch = self.vx.swapconfig.image_count;
         for idx in 0..4 {
             self.vx.quads[handle.0].tranbuffer[handle.1][idx * 2] += movement.0;
@@ -995,6 +1250,9 @@ impl<'a> Quads<'a> {
     ///
     /// Rotation does not mutate the model-space of a quad.
     pub fn rotate<T: Copy + Into<Rad<f32>>>(&mut self, handle: &Handle, deg: T) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.quads[handle.0].rotbuf_touch = self.vx.swapconfig.image_count;
         for rot in &mut self.vx.quads[handle.0].rotbuffer[handle.1] {
             *rot += deg.into().0;
@@ -1005,6 +1263,9 @@ impl<'a> Quads<'a> {
     ///
     /// Scale does not mutate the model-space of a quad.
     pub fn scale(&mut self, handle: &Handle, scale: f32) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.quads[handle.0].scalebuf_touch = self.vx.swapconfig.image_count;
         for sc in &mut self.vx.quads[handle.0].scalebuffer[handle.1] {
             *sc *= scale;
@@ -1017,6 +1278,9 @@ impl<'a> Quads<'a> {
     ///
     /// Applies [Quads::deform] to each quad.
     pub fn deform_all(&mut self, layer: &Layer, mut delta: impl FnMut(usize) -> [(f32, f32); 4]) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.quads[layer.0].posbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.quads[layer.0].posbuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1035,6 +1299,9 @@ impl<'a> Quads<'a> {
     ///
     /// Applies [Quads::translate] to each quad.
     pub fn translate_all(&mut self, layer: &Layer, mut delta: impl FnMut(usize) -> (f32, f32)) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.quads[layer.0].tranbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.quads[layer.0].tranbuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1053,6 +1320,9 @@ impl<'a> Quads<'a> {
         layer: &Layer,
         mut delta: impl FnMut(usize) -> T,
     ) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.quads[layer.0].rotbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.quads[layer.0].rotbuffer.iter_mut().enumerate() {
             let delta = delta(idx).into().0;
@@ -1066,6 +1336,9 @@ impl<'a> Quads<'a> {
     ///
     /// Applies [Quads::scale] to each quad.
     pub fn scale_all(&mut self, layer: &Layer, mut delta: impl FnMut(usize) -> f32) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.quads[layer.0].scalebuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.quads[layer.0].scalebuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1085,6 +1358,9 @@ impl<'a> Quads<'a> {
         layer: &Layer,
         mut delta: impl FnMut(usize) -> [(f32, f32); 4],
     ) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.quads[layer.0].posbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.quads[layer.0].posbuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1103,6 +1379,9 @@ impl<'a> Quads<'a> {
     ///
     /// Applies [Quads::set_solid_color] to each quad.
     pub fn set_solid_color_all(&mut self, layer: &Layer, mut delta: impl FnMut(usize) -> Color) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.quads[layer.0].colbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.quads[layer.0].colbuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1117,6 +1396,9 @@ impl<'a> Quads<'a> {
     ///
     /// Applies [Quads::set_color] to each quad.
     pub fn set_color_all(&mut self, layer: &Layer, mut delta: impl FnMut(usize) -> [Color; 4]) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.quads[layer.0].colbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.quads[layer.0].colbuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1135,6 +1417,9 @@ impl<'a> Quads<'a> {
         layer: &Layer,
         mut delta: impl FnMut(usize) -> (f32, f32),
     ) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.quads[layer.0].tranbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.quads[layer.0].tranbuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1153,6 +1438,9 @@ impl<'a> Quads<'a> {
         layer: &Layer,
         mut delta: impl FnMut(usize) -> T,
     ) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.quads[layer.0].rotbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.quads[layer.0].rotbuffer.iter_mut().enumerate() {
             let delta = delta(idx).into().0;
@@ -1165,6 +1453,9 @@ impl<'a> Quads<'a> {
     /// Applies [Quads::set_scale] to each quad.
     /// Note: This may re-enable removed quads, see [Quads::remove].
     pub fn set_scale_all(&mut self, layer: &Layer, mut delta: impl FnMut(usize) -> f32) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.quads[layer.0].scalebuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.quads[layer.0].scalebuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1636,4 +1927,108 @@ mod tests {
             assert!(0 < vx.quads().layer_count());
         }
     }
+
+    #[test]
+    fn reused_slot_does_not_revive_handle() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (100, 100));
+        let layer = vx.quads().add_layer(&LayerOptions::new());
+
+        let old = vx.quads().add(&layer, Quad::new());
+        let copy = Handle(old.0, old.1, old.2);
+        vx.quads().remove(old);
+        assert![!vx.quads().is_alive(&copy)];
+
+        let new = vx.quads().add(&layer, Quad::new());
+        assert_eq![copy.1, new.1];
+        assert![vx.quads().is_alive(&new)];
+        assert![!vx.quads().is_alive(&copy)];
+
+        vx.quads().remove_layer(layer);
+        assert![!vx.quads().is_alive(&new)];
+    }
+
+    #[test]
+    fn compact_shrinks_layer_and_remaps_handles() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (100, 100));
+        let layer = vx.quads().add_layer(&LayerOptions::new());
+
+        let quads = (0..5)
+            .map(|_| vx.quads().add(&layer, Quad::new()))
+            .collect::<Vec<_>>();
+        let mut quads = quads.into_iter();
+        vx.quads().remove(quads.next().unwrap());
+        let kept = quads.next().unwrap();
+        vx.quads().remove(quads.next().unwrap());
+        vx.quads().remove(quads.nth(1).unwrap());
+
+        let remapped = vx.quads().compact(&layer);
+        assert_eq![2, vx.quads[0].posbuffer.len()];
+        assert_eq![vec![(3, 0)], {
+            remapped
+                .iter()
+                .map(|(old, new)| (old.1, new.1))
+                .collect::<Vec<_>>()
+        }];
+        assert![vx.quads().is_alive(&kept)];
+        assert![vx.quads().is_alive(&remapped[0].1)];
+        assert![!vx.quads().is_alive(&remapped[0].0)];
+
+        assert![vx.quads().compact(&layer).is_empty()];
+        vx.draw_frame();
+    }
+
+    #[test]
+    fn instanced_layer_draws_like_regular_layer() {
+        let draw = |options: LayerOptions| {
+            let logger = Logger::root(Discard, o!());
+            let mut vx = VxDraw::new_offscreen(logger, (100, 100));
+            let layer = vx.quads().add_layer(&options);
+            vx.quads().add(
+                &layer,
+                Quad::new()
+                    .width(0.5)
+                    .colors([
+                        (255, 0, 0, 255),
+                        (0, 255, 0, 255),
+                        (0, 0, 255, 255),
+                        (255, 255, 255, 255),
+                    ])
+                    .translation((0.25, -0.25))
+                    .rotation(Rad(0.3)),
+            );
+            let quad = vx.quads().add(&layer, Quad::new().height(0.25));
+            vx.quads().set_scale(&quad, 0.5);
+            vx.draw_frame_copy_framebuffer()
+        };
+        let regular = draw(LayerOptions::new());
+        let instanced = draw(LayerOptions::new().instanced(true));
+        assert![regular == instanced];
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Stale handle")]
+    fn stale_handle_panics_in_debug() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (100, 100));
+        let layer = vx.quads().add_layer(&LayerOptions::new());
+        let quad = vx.quads().add(&layer, Quad::new());
+        let copy = Handle(quad.0, quad.1, quad.2);
+        vx.quads().remove(quad);
+        vx.quads().set_translation(&copy, (1.0, 0.0));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Stale handle")]
+    fn add_to_removed_layer_panics_in_debug() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (100, 100));
+        let layer = vx.quads().add_layer(&LayerOptions::new());
+        let copy = Layer(layer.0, layer.1);
+        vx.quads().remove_layer(layer);
+        vx.quads().add(&copy, Quad::new());
+    }
 }
@@ -0,0 +1,607 @@
+//! Methods and types to control render textures
+//!
+//! A render texture is an image that other layers can be drawn into instead of the screen. The
+//! layers attached to a render texture are drawn into it at the start of every frame, before the
+//! main render pass. The result can then be sampled by a dynamic texture layer created with
+//! [crate::dyntex::Dyntex::add_layer_from_rentex], which is useful for minimaps, mirrors, or
+//! cached UI panels.
+//!
+//! # Example - Drawing a quad through a render texture #
+//! ```
+//! use vxdraw::{dyntex, quads, rentex, void_logger, Color, ShowWindow, VxDraw};
+//! use winit::platform::unix::EventLoopExtUnix;
+//! use winit::event_loop::EventLoop;
+//!
+//! // Create an event loop
+//! let event_loop = EventLoop::new_any_thread();
+//!
+//! #[cfg(feature = "doctest-headless")]
+//! let mut vx = VxDraw::new(void_logger(), ShowWindow::Headless1k, &event_loop);
+//! #[cfg(not(feature = "doctest-headless"))]
+//! let mut vx = VxDraw::new(void_logger(), ShowWindow::Enable, &event_loop);
+//!
+//! // Create a render texture and draw a layer of quads into it
+//! let target = vx
+//!     .rentex()
+//!     .add_layer(&rentex::LayerOptions::new().width(256).height(256))
+//!     .unwrap();
+//! let quads = vx.quads().add_layer(&quads::LayerOptions::new());
+//! let quad = vx.quads().add(&quads, quads::Quad::new().scale(0.5));
+//! vx.quads().set_solid_color(&quad, Color::Rgba(255, 0, 0, 255));
+//! vx.rentex().attach(&target, &quads);
+//!
+//! // Show the render texture on screen as a sprite
+//! let screen = vx.dyntex().add_layer_from_rentex(&target, &dyntex::LayerOptions::new());
+//! vx.dyntex().add(&screen, dyntex::Sprite::new().scale(0.5));
+//!
+//! vx.draw_frame();
+//! #[cfg(not(feature = "doctest-headless"))]
+//! std::thread::sleep(std::time::Duration::new(3, 0));
+//! ```
+use super::{utils::*, Color};
+use crate::data::{RenderTexture, VxDraw};
+use crate::error::Error;
+use cgmath::Matrix4;
+#[cfg(feature = "dx12")]
+use gfx_backend_dx12 as back;
+#[cfg(feature = "gl")]
+use gfx_backend_gl as back;
+#[cfg(feature = "metal")]
+use gfx_backend_metal as back;
+#[cfg(feature = "vulkan")]
+use gfx_backend_vulkan as back;
+use gfx_hal::{command::ClearColor, device::Device, format, image, memory, pass, pso, Backend};
+use std::mem::ManuallyDrop;
+
+// ---
+
+/// Handle to a render texture
+pub struct Layer(pub(crate) usize, pub(crate) u64);
+
+/// Options for creating a render texture
+#[derive(Clone)]
+pub struct LayerOptions {
+    /// Width of the texture in pixels
+    width: u32,
+    /// Height of the texture in pixels
+    height: u32,
+    /// Color the texture is cleared to before the attached layers are drawn
+    clear_color: (u8, u8, u8, u8),
+    /// Perspective used for the attached layers, layers with a fixed perspective keep theirs
+    fixed_perspective: Option<Matrix4<f32>>,
+}
+
+impl LayerOptions {
+    /// Same as default
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the width of the texture in pixels
+    pub fn width(mut self, width: u32) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Set the height of the texture in pixels
+    pub fn height(mut self, height: u32) -> Self {
+        self.height = height;
+        self
+    }
+
+    /// Set the color the texture is cleared to every frame
+    pub fn clear_color(mut self, color: Color) -> Self {
+        self.clear_color = color.into();
+        self
+    }
+
+    /// Set the perspective used when drawing the attached layers
+    ///
+    /// The default is the identity matrix, so -1..1 spans the whole texture on both axes.
+    pub fn fixed_perspective(mut self, mat: Matrix4<f32>) -> Self {
+        self.fixed_perspective = Some(mat);
+        self
+    }
+}
+
+impl Default for LayerOptions {
+    fn default() -> Self {
+        Self {
+            width: 256,
+            height: 256,
+            clear_color: (0, 0, 0, 0),
+            fixed_perspective: None,
+        }
+    }
+}
+
+fn to_clear_color((r, g, b, a): (u8, u8, u8, u8)) -> ClearColor {
+    ClearColor {
+        float32: [
+            f32::from(r) / 255.0,
+            f32::from(g) / 255.0,
+            f32::from(b) / 255.0,
+            f32::from(a) / 255.0,
+        ],
+    }
+}
+
+/// An image with its memory and view, used as one of the attachments of a render texture
+type Attachment = (
+    <back::Backend as Backend>::Image,
+    <back::Backend as Backend>::Memory,
+    <back::Backend as Backend>::ImageView,
+);
+
+/// Create an attachment, destroying whatever was already created if a step fails
+fn create_attachment(
+    s: &VxDraw,
+    (width, height): (u32, u32),
+    format: format::Format,
+    usage: image::Usage,
+    aspects: format::Aspects,
+) -> Result<Attachment, Error> {
+    unsafe {
+        let mut image = s.device.create_image(
+            image::Kind::D2(width, height, 1, 1),
+            1,
+            format,
+            image::Tiling::Optimal,
+            usage,
+            image::ViewCapabilities::empty(),
+        )?;
+        let requirements = s.device.get_image_requirements(&image);
+        let memory =
+            match find_memory_type_id(&s.adapter, requirements, memory::Properties::DEVICE_LOCAL)
+                .and_then(|id| {
+                    s.device
+                        .allocate_memory(id, requirements.size)
+                        .map_err(Error::from)
+                }) {
+                Ok(memory) => memory,
+                Err(err) => {
+                    s.device.destroy_image(image);
+                    return Err(err);
+                }
+            };
+        if let Err(err) = s.device.bind_image_memory(&memory, 0, &mut image) {
+            s.device.destroy_image(image);
+            s.device.free_memory(memory);
+            return Err(err.into());
+        }
+        match s.device.create_image_view(
+            &image,
+            image::ViewKind::D2,
+            format,
+            format::Swizzle::NO,
+            image::SubresourceRange {
+                aspects,
+                levels: 0..1,
+                layers: 0..1,
+            },
+        ) {
+            Ok(view) => Ok((image, memory, view)),
+            Err(err) => {
+                s.device.destroy_image(image);
+                s.device.free_memory(memory);
+                Err(err.into())
+            }
+        }
+    }
+}
+
+fn destroy_attachment(s: &VxDraw, (image, memory, view): Attachment) {
+    unsafe {
+        s.device.destroy_image_view(view);
+        s.device.destroy_image(image);
+        s.device.free_memory(memory);
+    }
+}
+
+// ---
+
+/// Accessor object to all render textures
+///
+/// A render texture is an image that layers are drawn into instead of the screen.
+/// See [crate::rentex] for examples.
+pub struct Rentex<'a> {
+    vx: &'a mut VxDraw,
+}
+
+impl<'a> Rentex<'a> {
+    /// Prepare to edit render textures
+    ///
+    /// You're not supposed to use this function directly (although you can).
+    /// The recommended way of spawning a rentex is via [VxDraw::rentex()].
+    pub(crate) fn new(s: &'a mut VxDraw) -> Self {
+        Self { vx: s }
+    }
+
+    /// Add a render texture to the system
+    ///
+    /// Render textures are drawn in the order in which they were created, so a render texture may
+    /// sample another render texture that was created before it.
+    ///
+    /// Fails if the device is unable to create the images of the render texture, in which case
+    /// nothing is added.
+    pub fn add_layer(&mut self, options: &LayerOptions) -> Result<Layer, Error> {
+        let s = &mut *self.vx;
+        let (width, height) = (options.width, options.height);
+
+        let render_pass = {
+            let attachment = pass::Attachment {
+                format: Some(s.format),
+                samples: 1,
+                ops: pass::AttachmentOps::new(
+                    pass::AttachmentLoadOp::Clear,
+                    pass::AttachmentStoreOp::Store,
+                ),
+                stencil_ops: pass::AttachmentOps::DONT_CARE,
+                layouts: image::Layout::Undefined..image::Layout::ShaderReadOnlyOptimal,
+            };
+            let depth = pass::Attachment {
+                format: Some(format::Format::D32Sfloat),
+                samples: 1,
+                ops: pass::AttachmentOps::new(
+                    pass::AttachmentLoadOp::Clear,
+                    pass::AttachmentStoreOp::DontCare,
+                ),
+                stencil_ops: pass::AttachmentOps::DONT_CARE,
+                layouts: image::Layout::Undefined..image::Layout::DepthStencilAttachmentOptimal,
+            };
+
+            let subpass = pass::SubpassDesc {
+                colors: &[(0, image::Layout::ColorAttachmentOptimal)],
+                depth_stencil: Some(&(1, image::Layout::DepthStencilAttachmentOptimal)),
+                inputs: &[],
+                resolves: &[],
+                preserves: &[],
+            };
+
+            // The previous frame may still be sampling the image, and the sprites of this frame
+            // must see everything that was drawn into it
+            let dependencies = [
+                pass::SubpassDependency {
+                    passes: pass::SubpassRef::External..pass::SubpassRef::Pass(0),
+                    stages: pso::PipelineStage::FRAGMENT_SHADER
+                        ..pso::PipelineStage::COLOR_ATTACHMENT_OUTPUT,
+                    accesses: image::Access::SHADER_READ..image::Access::COLOR_ATTACHMENT_WRITE,
+                    flags: memory::Dependencies::empty(),
+                },
+                pass::SubpassDependency {
+                    passes: pass::SubpassRef::Pass(0)..pass::SubpassRef::External,
+                    stages: pso::PipelineStage::COLOR_ATTACHMENT_OUTPUT
+                        ..pso::PipelineStage::FRAGMENT_SHADER,
+                    accesses: image::Access::COLOR_ATTACHMENT_WRITE..image::Access::SHADER_READ,
+                    flags: memory::Dependencies::empty(),
+                },
+            ];
+
+            unsafe {
+                s.device
+                    .create_render_pass(&[attachment, depth], &[subpass], &dependencies)?
+            }
+        };
+
+        let (image, image_memory, image_view) = match create_attachment(
+            s,
+            (width, height),
+            s.format,
+            image::Usage::COLOR_ATTACHMENT | image::Usage::SAMPLED,
+            format::Aspects::COLOR,
+        ) {
+            Ok(attachment) => attachment,
+            Err(err) => {
+                unsafe { s.device.destroy_render_pass(render_pass) };
+                return Err(err);
+            }
+        };
+
+        let (depth_image, depth_image_memory, depth_image_view) = match create_attachment(
+            s,
+            (width, height),
+            format::Format::D32Sfloat,
+            image::Usage::DEPTH_STENCIL_ATTACHMENT,
+            format::Aspects::DEPTH,
+        ) {
+            Ok(attachment) => attachment,
+            Err(err) => {
+                destroy_attachment(s, (image, image_memory, image_view));
+                unsafe { s.device.destroy_render_pass(render_pass) };
+                return Err(err);
+            }
+        };
+
+        let framebuffer = match unsafe {
+            s.device.create_framebuffer(
+                &render_pass,
+                vec![&image_view, &depth_image_view],
+                image::Extent {
+                    width,
+                    height,
+                    depth: 1,
+                },
+            )
+        } {
+            Ok(framebuffer) => framebuffer,
+            Err(err) => {
+                destroy_attachment(s, (depth_image, depth_image_memory, depth_image_view));
+                destroy_attachment(s, (image, image_memory, image_view));
+                unsafe { s.device.destroy_render_pass(render_pass) };
+                return Err(err.into());
+            }
+        };
+
+        let generation = s.next_generation();
+        let rentex = RenderTexture {
+            generation,
+            width,
+            height,
+
+            fixed_perspective: options.fixed_perspective,
+            clear_color: to_clear_color(options.clear_color),
+
+            draw_order: vec![],
+
+            image: ManuallyDrop::new(image),
+            image_memory: ManuallyDrop::new(image_memory),
+            image_view: ManuallyDrop::new(image_view),
+
+            depth_image: ManuallyDrop::new(depth_image),
+            depth_image_memory: ManuallyDrop::new(depth_image_memory),
+            depth_image_view: ManuallyDrop::new(depth_image_view),
+
+            framebuffer: ManuallyDrop::new(framebuffer),
+            render_pass: ManuallyDrop::new(render_pass),
+        };
+
+        if let Some(id) = s.rentexs.iter().position(Option::is_none) {
+            s.rentexs[id] = Some(rentex);
+            Ok(Layer(id, generation))
+        } else {
+            s.rentexs.push(Some(rentex));
+            Ok(Layer(s.rentexs.len() - 1, generation))
+        }
+    }
+
+    /// Query the amount of render textures there are
+    pub fn layer_count(&self) -> usize {
+        self.vx.rentexs.iter().filter(|x| x.is_some()).count()
+    }
+
+    /// Get the size of a render texture in pixels
+    ///
+    /// A removed render texture has no size.
+    pub fn get_size(&self, layer: &Layer) -> (u32, u32) {
+        if !self.check_layer(layer) {
+            return (0, 0);
+        }
+        let rentex = self.get(layer);
+        (rentex.width, rentex.height)
+    }
+
+    /// Draw a layer into the render texture instead of the screen
+    ///
+    /// The layer is moved out of the main draw order, or out of the render texture it was
+    /// attached to, and drawn after the layers that were attached before it. Removed layers are
+    /// ignored.
+    pub fn attach(&mut self, layer: &Layer, attachee: &impl Layerable) {
+        if !self.check_layer(layer) {
+            return;
+        }
+        let s = &mut *self.vx;
+        let position = match attachee.get_layer(s) {
+            Some(position) => position,
+            None => return,
+        };
+        let draw_type = s.draw_order_of(position.rentex).remove(position.index);
+        s.rentexs[layer.0]
+            .as_mut()
+            .expect("Render texture has been removed")
+            .draw_order
+            .push(draw_type);
+    }
+
+    /// Move all layers drawn into the render texture back to the main draw order
+    ///
+    /// The layers are put on top of the main draw order, keeping their relative order.
+    pub fn detach_all(&mut self, layer: &Layer) {
+        if !self.check_layer(layer) {
+            return;
+        }
+        let mut draw_order = std::mem::take(&mut self.get_mut(layer).draw_order);
+        self.vx.draw_order.append(&mut draw_order);
+    }
+
+    /// Set the color the render texture is cleared to every frame
+    pub fn set_clear_color(&mut self, layer: &Layer, color: Color) {
+        if !self.check_layer(layer) {
+            return;
+        }
+        self.get_mut(layer).clear_color = to_clear_color(color.into());
+    }
+
+    /// Set the perspective used for the attached layers. `None` uses the identity matrix.
+    pub fn set_perspective(&mut self, layer: &Layer, perspective: Option<Matrix4<f32>>) {
+        if !self.check_layer(layer) {
+            return;
+        }
+        self.get_mut(layer).fixed_perspective = perspective;
+    }
+
+    /// Remove a render texture
+    ///
+    /// The attached layers are moved back to the main draw order. Dynamic texture layers that
+    /// sample this render texture are removed too, as their texture is destroyed.
+    pub fn remove_layer(&mut self, layer: Layer) {
+        if !self.check_layer(&layer) {
+            return;
+        }
+        self.detach_all(&layer);
+        self.vx.dyntex().remove_layers_sampling(layer.0);
+        let s = &mut *self.vx;
+        s.wait_for_fences_or_log();
+        if let Some(rentex) = s.rentexs[layer.0].take() {
+            rentex.destroy(&s.device);
+        }
+    }
+
+    fn check_layer(&self, layer: &Layer) -> bool {
+        let current = self.vx.rentexs[layer.0]
+            .as_ref()
+            .map_or(0, |rentex| rentex.generation);
+        is_current(&self.vx.log, current, layer.1)
+    }
+
+    fn get(&self, layer: &Layer) -> &RenderTexture {
+        self.vx.rentexs[layer.0]
+            .as_ref()
+            .expect("Render texture has been removed")
+    }
+
+    fn get_mut(&mut self, layer: &Layer) -> &mut RenderTexture {
+        self.vx.rentexs[layer.0]
+            .as_mut()
+            .expect("Render texture has been removed")
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dyntex, quads, shapes};
+    use slog::{o, Discard, Logger};
+
+    #[test]
+    fn attach_moves_layer_out_of_draw_order() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let target = vx.rentex().add_layer(&LayerOptions::new()).unwrap();
+        let quads = vx.quads().add_layer(&quads::LayerOptions::new());
+        assert_eq![1, vx.draw_order.len()];
+
+        vx.rentex().attach(&target, &quads);
+        assert_eq![0, vx.draw_order.len()];
+        vx.draw_frame();
+
+        vx.rentex().detach_all(&target);
+        assert_eq![1, vx.draw_order.len()];
+        vx.draw_frame();
+    }
+
+    #[test]
+    fn attached_layers_can_be_swapped_and_moved() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let first = vx.rentex().add_layer(&LayerOptions::new()).unwrap();
+        let second = vx.rentex().add_layer(&LayerOptions::new()).unwrap();
+        let quads = vx.quads().add_layer(&quads::LayerOptions::new());
+        let shapes = vx.shapes().add_layer(&shapes::LayerOptions::new());
+
+        vx.rentex().attach(&first, &quads);
+        vx.swap_layers(&quads, &shapes);
+        assert_eq![
+            Some(LayerPosition {
+                rentex: None,
+                index: 0
+            }),
+            quads.get_layer(&vx)
+        ];
+        assert_eq![
+            Some(LayerPosition {
+                rentex: Some(first.0),
+                index: 0
+            }),
+            shapes.get_layer(&vx)
+        ];
+
+        vx.rentex().attach(&second, &shapes);
+        assert_eq![0, vx.rentexs[first.0].as_ref().unwrap().draw_order.len()];
+        assert_eq![Some(second.0), shapes.get_layer(&vx).unwrap().rentex];
+        vx.draw_frame();
+    }
+
+    #[test]
+    fn sprite_samples_rentex() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        vx.set_clear_color(Color::Rgba(0, 0, 255, 255));
+
+        let target = vx
+            .rentex()
+            .add_layer(&LayerOptions::new().width(64).height(64))
+            .unwrap();
+        let quads = vx.quads().add_layer(&quads::LayerOptions::new());
+        let quad = vx.quads().add(&quads, quads::Quad::new());
+        vx.quads()
+            .set_solid_color(&quad, Color::Rgba(255, 0, 0, 255));
+        vx.rentex().attach(&target, &quads);
+
+        let screen = vx
+            .dyntex()
+            .add_layer_from_rentex(&target, &dyntex::LayerOptions::new());
+        vx.dyntex().add(&screen, dyntex::Sprite::new().scale(0.5));
+
+        let img = vx.draw_frame_copy_framebuffer();
+        assert_eq![[255, 0, 0, 255], pixel(&vx, &img, (500, 500))];
+        assert_eq![[0, 0, 255, 255], pixel(&vx, &img, (10, 10))];
+    }
+
+    #[test]
+    fn remove_rentex_layer() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let target = vx.rentex().add_layer(&LayerOptions::new()).unwrap();
+        let quads = vx.quads().add_layer(&quads::LayerOptions::new());
+        vx.rentex().attach(&target, &quads);
+        vx.draw_frame();
+
+        vx.rentex().remove_layer(target);
+        assert_eq![0, vx.rentex().layer_count()];
+        assert_eq![1, vx.draw_order.len()];
+        vx.draw_frame();
+
+        let target = vx
+            .rentex()
+            .add_layer(&LayerOptions::new().width(10).height(20))
+            .unwrap();
+        assert_eq![(10, 20), vx.rentex().get_size(&target)];
+    }
+
+    #[test]
+    fn removing_rentex_removes_the_layers_sampling_it() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let target = vx.rentex().add_layer(&LayerOptions::new()).unwrap();
+        let screen = vx
+            .dyntex()
+            .add_layer_from_rentex(&target, &dyntex::LayerOptions::new());
+        let sprite = vx.dyntex().add(&screen, dyntex::Sprite::new());
+        vx.draw_frame();
+
+        let removed = Layer(target.0, target.1);
+        vx.rentex().remove_layer(target);
+        assert![!vx.dyntex().is_alive(&sprite)];
+        vx.draw_frame();
+
+        let target = vx.rentex().add_layer(&LayerOptions::new()).unwrap();
+        assert_eq![removed.0, target.0];
+        assert_ne![removed.1, target.1];
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "Stale handle")]
+    fn removed_rentex_handle_panics_in_debug() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let target = vx.rentex().add_layer(&LayerOptions::new()).unwrap();
+        let removed = Layer(target.0, target.1);
+        vx.rentex().remove_layer(target);
+        vx.rentex().add_layer(&LayerOptions::new()).unwrap();
+        vx.rentex()
+            .set_clear_color(&removed, Color::Rgba(0, 0, 0, 255));
+    }
+}
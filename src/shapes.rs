@@ -0,0 +1,1240 @@
+//! Methods and types to control shapes
+//!
+//! A shape is a list of colored triangles, built from a polygon, a thick polyline, an ellipse, or
+//! a rounded rectangle. Every vertex has its own color, and every shape has a translation,
+//! rotation, and scale just like a quad. Shapes of different sizes can be mixed freely in the same
+//! layer, they are drawn in the order they were added.
+//!
+//! See [shapes::Shapes] for all operations supported on shapes.
+//!
+//! # Example #
+//! ```
+//! use vxdraw::{shapes::{Cap, Join, LayerOptions, Shape}, void_logger, Color, Deg, VxDraw};
+//! let mut vx = VxDraw::new_offscreen(void_logger(), (200, 100));
+//!
+//! let layer = vx.shapes().add_layer(&LayerOptions::new());
+//!
+//! // A red star
+//! let star = (0..10)
+//!     .map(|idx| {
+//!         let angle = idx as f32 * std::f32::consts::PI / 5.0;
+//!         let radius = if idx % 2 == 0 { 0.5 } else { 0.2 };
+//!         (radius * angle.cos(), radius * angle.sin())
+//!     })
+//!     .collect::<Vec<_>>();
+//! vx.shapes().add(&layer, Shape::polygon(&star).color(Color::Rgba(255, 0, 0, 255)));
+//!
+//! // A zig-zag line with rounded joins and ends
+//! let line = Shape::polyline(
+//!     &[(-0.8, 0.5), (-0.4, 0.8), (0.0, 0.5), (0.4, 0.8)],
+//!     0.05,
+//!     Join::Round,
+//!     Cap::Round,
+//! );
+//! vx.shapes().add(&layer, line);
+//!
+//! // A circle fading from white at the top to black at the bottom
+//! let circle = Shape::circle(0.2, 32)
+//!     .vertex_colors(|(_, y)| {
+//!         let shade = ((0.2 - y) / 0.4 * 255.0) as u8;
+//!         Color::Rgba(shade, shade, shade, 255)
+//!     })
+//!     .translation((0.6, -0.5));
+//! let handle = vx.shapes().add(&layer, circle);
+//! vx.shapes().set_rotation(&handle, Deg(45.0));
+//!
+//! vx.draw_frame();
+//! ```
+use super::{blender, utils::*, Color};
+use crate::data::{DrawType, ShapesData, VxDraw};
+use cgmath::{Matrix4, Rad};
+#[cfg(feature = "dx12")]
+use gfx_backend_dx12 as back;
+#[cfg(feature = "gl")]
+use gfx_backend_gl as back;
+#[cfg(feature = "metal")]
+use gfx_backend_metal as back;
+#[cfg(feature = "vulkan")]
+use gfx_backend_vulkan as back;
+use gfx_hal::{device::Device, format, image, pass, pso, pso::Primitive, Backend};
+use std::{f32::consts::PI, io::Cursor, mem::ManuallyDrop};
+
+// ---
+
+/// Handle referring to a single shape
+///
+/// The handle becomes stale when the shape or its layer is removed, using a stale handle panics in
+/// debug builds and is ignored in release builds.
+#[derive(Debug)]
+pub struct Handle(usize, usize, u64);
+
+/// Handle referring to a shape layer
+#[derive(Debug)]
+pub struct Layer(usize, u64);
+
+impl Layerable for Layer {
+    fn get_layer(&self, vx: &VxDraw) -> Option<LayerPosition> {
+        vx.find_in_draw_order(|ord| match ord {
+            DrawType::Shapes { id } => *id == self.0,
+            _ => false,
+        })
+    }
+}
+
+/// Options for creating a layer of shapes
+#[derive(Debug, Default)]
+pub struct LayerOptions {
+    hide: bool,
+    blend: blender::Blender,
+    fixed_perspective: Option<Matrix4<f32>>,
+}
+
+impl LayerOptions {
+    /// Same as default
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hide this layer
+    pub fn hide(mut self) -> Self {
+        self.hide = true;
+        self
+    }
+
+    /// Show this layer (default)
+    pub fn show(mut self) -> Self {
+        self.hide = false;
+        self
+    }
+
+    /// Set a fixed perspective for this layer
+    pub fn fixed_perspective(mut self, mat: Matrix4<f32>) -> Self {
+        self.fixed_perspective = Some(mat);
+        self
+    }
+
+    /// Set the blender of this layer (see [blender])
+    pub fn blend(mut self, blend_setter: impl Fn(blender::Blender) -> blender::Blender) -> Self {
+        self.blend = blend_setter(self.blend);
+        self
+    }
+}
+
+// ---
+
+/// How two segments of a polyline are connected
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Join {
+    /// Extend the outer edges until they meet, sharp corners fall back to [Join::Bevel]
+    Miter,
+    /// Cut the corner off with a straight edge
+    Bevel,
+    /// Round the corner with an arc
+    Round,
+}
+
+/// How the ends of a polyline are drawn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cap {
+    /// End exactly at the end points
+    Butt,
+    /// Extend the ends by half the thickness
+    Square,
+    /// Extend the ends with half a circle
+    Round,
+}
+
+/// Shape information used for creating shapes
+///
+/// The constructors turn the geometry into triangles right away, the remaining functions set the
+/// colors and placement of the shape.
+#[derive(Clone, Debug)]
+pub struct Shape {
+    /// Three vertices per triangle
+    vertices: Vec<(f32, f32)>,
+    colors: Vec<[u8; 4]>,
+    translation: (f32, f32),
+    rotation: f32,
+    scale: f32,
+    /// Moves the origin of the shape to some point. This affects rotation and translation of the
+    /// shape.
+    origin: (f32, f32),
+}
+
+impl Shape {
+    /// Create a shape from triangles, three vertices per triangle
+    pub fn triangles(vertices: &[(f32, f32)]) -> Self {
+        assert![
+            vertices.len() % 3 == 0,
+            "Triangles must consist of three vertices each"
+        ];
+        Self::from_vertices(vertices.to_vec())
+    }
+
+    /// Create a shape from a triangulated mesh, every triangle indexes three points
+    pub fn mesh(points: &[(f32, f32)], triangles: &[[usize; 3]]) -> Self {
+        Self::from_vertices(
+            triangles
+                .iter()
+                .flat_map(|triangle| triangle.iter().map(|idx| points[*idx]))
+                .collect(),
+        )
+    }
+
+    /// Create a filled polygon from its outline
+    ///
+    /// The outline may be concave and in either winding order, but must not intersect itself.
+    pub fn polygon(outline: &[(f32, f32)]) -> Self {
+        Self::from_vertices(triangulate(outline))
+    }
+
+    /// Create a line of the given thickness through the points
+    pub fn polyline(points: &[(f32, f32)], thickness: f32, join: Join, cap: Cap) -> Self {
+        Self::from_vertices(stroke(points, thickness / 2.0, join, cap))
+    }
+
+    /// Create a circle made of `segments` triangles
+    pub fn circle(radius: f32, segments: usize) -> Self {
+        Self::ellipse((radius, radius), segments)
+    }
+
+    /// Create an ellipse made of `segments` triangles
+    pub fn ellipse(radii: (f32, f32), segments: usize) -> Self {
+        let outline = (0..segments.max(3))
+            .map(|idx| {
+                let angle = idx as f32 * 2.0 * PI / segments.max(3) as f32;
+                (radii.0 * angle.cos(), radii.1 * angle.sin())
+            })
+            .collect::<Vec<_>>();
+        Self::from_vertices(fan((0.0, 0.0), &outline))
+    }
+
+    /// Create a rectangle centered on the origin with rounded corners
+    ///
+    /// Each corner is an arc of `segments` triangles, the radius is limited to half the shorter
+    /// side.
+    pub fn rounded_rectangle(size: (f32, f32), radius: f32, segments: usize) -> Self {
+        let (half_width, half_height) = (size.0 / 2.0, size.1 / 2.0);
+        let radius = radius.max(0.0).min(half_width).min(half_height);
+        let segments = segments.max(1);
+        let corners = [
+            (half_width - radius, half_height - radius, 0.0),
+            (-half_width + radius, half_height - radius, PI / 2.0),
+            (-half_width + radius, -half_height + radius, PI),
+            (half_width - radius, -half_height + radius, PI * 3.0 / 2.0),
+        ];
+        let mut outline = vec![];
+        for (x, y, start) in corners.iter() {
+            for step in 0..=segments {
+                let angle = start + step as f32 * PI / 2.0 / segments as f32;
+                outline.push((x + radius * angle.cos(), y + radius * angle.sin()));
+            }
+        }
+        Self::from_vertices(fan((0.0, 0.0), &outline))
+    }
+
+    fn from_vertices(vertices: Vec<(f32, f32)>) -> Self {
+        Self {
+            colors: vec![[0, 0, 0, 255]; vertices.len()],
+            vertices,
+            translation: (0.0, 0.0),
+            rotation: 0.0,
+            scale: 1.0,
+            origin: (0.0, 0.0),
+        }
+    }
+
+    /// Set a solid color of the shape
+    pub fn color(mut self, color: Color) -> Self {
+        let Color::Rgba(r, g, b, a) = color;
+        for col in self.colors.iter_mut() {
+            *col = [r, g, b, a];
+        }
+        self
+    }
+
+    /// Set the color of every vertex from its model space position
+    pub fn vertex_colors(mut self, mut color: impl FnMut((f32, f32)) -> Color) -> Self {
+        for (col, vertex) in self.colors.iter_mut().zip(self.vertices.iter()) {
+            let Color::Rgba(r, g, b, a) = color(*vertex);
+            *col = [r, g, b, a];
+        }
+        self
+    }
+
+    /// Set the translation
+    pub fn translation(mut self, trn: (f32, f32)) -> Self {
+        self.translation = trn;
+        self
+    }
+
+    /// Set the rotation. Rotation is counter-clockwise
+    pub fn rotation<T: Copy + Into<Rad<f32>>>(mut self, angle: T) -> Self {
+        self.rotation = angle.into().0;
+        self
+    }
+
+    /// Set the scaling factor of this shape
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Set the origin of this shape
+    pub fn origin(mut self, origin: (f32, f32)) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Get the number of vertices of this shape, three per triangle
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    /// Get the vertices with origin, scale, rotation, and translation applied, and their colors
+    ///
+    /// Matches the transformation done by the vertex shader of a shapes layer.
+    pub(crate) fn placed_vertices(&self) -> impl Iterator<Item = ((f32, f32), [u8; 4])> + '_ {
+        let (sin, cos) = self.rotation.sin_cos();
+        self.vertices
+            .iter()
+            .zip(self.colors.iter())
+            .map(move |(&(x, y), &color)| {
+                let (x, y) = mul(sub((x, y), self.origin), self.scale);
+                let rotated = (cos * x + sin * y, -sin * x + cos * y);
+                (add(rotated, self.translation), color)
+            })
+    }
+}
+
+// ---
+
+fn cross(a: (f32, f32), b: (f32, f32), c: (f32, f32)) -> f32 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+fn add(a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn sub(a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn mul(a: (f32, f32), factor: f32) -> (f32, f32) {
+    (a.0 * factor, a.1 * factor)
+}
+
+fn normalize(a: (f32, f32)) -> (f32, f32) {
+    let length = (a.0 * a.0 + a.1 * a.1).sqrt();
+    if length == 0.0 {
+        (0.0, 0.0)
+    } else {
+        (a.0 / length, a.1 / length)
+    }
+}
+
+/// Triangles spanning from a center to every edge of a closed outline
+fn fan(center: (f32, f32), outline: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    let mut vertices = Vec::with_capacity(outline.len() * 3);
+    for (idx, point) in outline.iter().enumerate() {
+        vertices.push(center);
+        vertices.push(*point);
+        vertices.push(outline[(idx + 1) % outline.len()]);
+    }
+    vertices
+}
+
+/// Triangulate a simple polygon by clipping ears
+///
+/// Self-intersecting outlines have no ears left at some point, the rest of such an outline is
+/// filled as a fan.
+pub(crate) fn triangulate(outline: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    if outline.len() < 3 {
+        return vec![];
+    }
+    let area: f32 = (0..outline.len())
+        .map(|idx| cross((0.0, 0.0), outline[idx], outline[(idx + 1) % outline.len()]))
+        .sum();
+    let mut remaining = (0..outline.len()).collect::<Vec<_>>();
+    if area < 0.0 {
+        remaining.reverse();
+    }
+    let mut vertices = vec![];
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|idx| {
+            let (a, b, c) = (
+                remaining[(idx + count - 1) % count],
+                remaining[*idx],
+                remaining[(idx + 1) % count],
+            );
+            let (pa, pb, pc) = (outline[a], outline[b], outline[c]);
+            cross(pa, pb, pc) > 0.0
+                && !remaining.iter().any(|other| {
+                    let point = outline[*other];
+                    ![a, b, c].contains(other)
+                        && cross(pa, pb, point) >= 0.0
+                        && cross(pb, pc, point) >= 0.0
+                        && cross(pc, pa, point) >= 0.0
+                })
+        });
+        match ear {
+            Some(idx) => {
+                vertices.push(outline[remaining[(idx + count - 1) % count]]);
+                vertices.push(outline[remaining[idx]]);
+                vertices.push(outline[remaining[(idx + 1) % count]]);
+                remaining.remove(idx);
+            }
+            None => break,
+        }
+    }
+    for idx in 1..remaining.len() - 1 {
+        vertices.push(outline[remaining[0]]);
+        vertices.push(outline[remaining[idx]]);
+        vertices.push(outline[remaining[idx + 1]]);
+    }
+    vertices
+}
+
+/// Arc of triangles around a center, from one offset to another going the shorter way
+fn arc(center: (f32, f32), from: (f32, f32), to: (f32, f32), half: f32) -> Vec<(f32, f32)> {
+    let start = from.1.atan2(from.0);
+    let mut sweep = to.1.atan2(to.0) - start;
+    if sweep > PI {
+        sweep -= 2.0 * PI;
+    } else if sweep < -PI {
+        sweep += 2.0 * PI;
+    }
+    let steps = ((sweep.abs() / (PI / 8.0)).ceil() as usize).max(1);
+    let outline = (0..=steps)
+        .map(|step| {
+            let angle = start + sweep * step as f32 / steps as f32;
+            add(center, (half * angle.cos(), half * angle.sin()))
+        })
+        .collect::<Vec<_>>();
+    let mut vertices = vec![];
+    for pair in outline.windows(2) {
+        vertices.push(center);
+        vertices.push(pair[0]);
+        vertices.push(pair[1]);
+    }
+    vertices
+}
+
+/// Triangles of a line with the given half thickness
+pub(crate) fn stroke(points: &[(f32, f32)], half: f32, join: Join, cap: Cap) -> Vec<(f32, f32)> {
+    let mut points = points.to_vec();
+    points.dedup();
+    if points.len() < 2 {
+        return vec![];
+    }
+    let last = points.len() - 1;
+    let first_direction = normalize(sub(points[1], points[0]));
+    let last_direction = normalize(sub(points[last], points[last - 1]));
+    if cap == Cap::Square {
+        points[0] = sub(points[0], mul(first_direction, half));
+        points[last] = add(points[last], mul(last_direction, half));
+    }
+
+    let normal = |direction: (f32, f32)| (-direction.1 * half, direction.0 * half);
+    let mut vertices = vec![];
+    for pair in points.windows(2) {
+        let offset = normal(normalize(sub(pair[1], pair[0])));
+        let (a, b) = (add(pair[0], offset), sub(pair[0], offset));
+        let (c, d) = (add(pair[1], offset), sub(pair[1], offset));
+        vertices.extend_from_slice(&[a, b, d, d, c, a]);
+    }
+
+    for idx in 1..last {
+        let (before, point, after) = (points[idx - 1], points[idx], points[idx + 1]);
+        let turn = cross(before, point, after);
+        if turn == 0.0 {
+            continue;
+        }
+        // The outer side of the corner is on the right when turning left
+        let side = if turn > 0.0 { -1.0 } else { 1.0 };
+        let from = mul(normal(normalize(sub(point, before))), side);
+        let to = mul(normal(normalize(sub(after, point))), side);
+        match join {
+            Join::Miter => {
+                let bisector = normalize(add(from, to));
+                let cosine = (bisector.0 * from.0 + bisector.1 * from.1) / half;
+                if cosine > 0.25 {
+                    let tip = add(point, mul(bisector, half / cosine));
+                    vertices.extend_from_slice(&[point, add(point, from), tip]);
+                    vertices.extend_from_slice(&[point, tip, add(point, to)]);
+                } else {
+                    vertices.extend_from_slice(&[point, add(point, from), add(point, to)]);
+                }
+            }
+            Join::Bevel => {
+                vertices.extend_from_slice(&[point, add(point, from), add(point, to)]);
+            }
+            Join::Round => {
+                vertices.extend(arc(point, from, to, half));
+            }
+        }
+    }
+
+    if cap == Cap::Round {
+        let start = normal(first_direction);
+        let outward = mul(first_direction, -half);
+        vertices.extend(arc(points[0], start, outward, half));
+        vertices.extend(arc(points[0], outward, mul(start, -1.0), half));
+        let end = normal(last_direction);
+        let outward = mul(last_direction, half);
+        vertices.extend(arc(points[last], end, outward, half));
+        vertices.extend(arc(points[last], outward, mul(end, -1.0), half));
+    }
+    vertices
+}
+
+// ---
+
+/// Accessor object to all shapes
+///
+/// A shape is a list of colored triangles. See [crate::shapes] for examples.
+pub struct Shapes<'a> {
+    vx: &'a mut VxDraw,
+}
+
+impl<'a> Shapes<'a> {
+    /// Spawn the accessor object from [VxDraw].
+    ///
+    /// This is a very cheap operation.
+    pub(crate) fn new(vx: &'a mut VxDraw) -> Self {
+        Self { vx }
+    }
+
+    /// Create a new layer for shapes
+    ///
+    /// This new layer will be ordered on top of all previous layers, meaning that its shapes will
+    /// be drawn on top of all other drawn items.
+    pub fn add_layer(&mut self, options: &LayerOptions) -> Layer {
+        let s = &mut *self.vx;
+        pub const VERTEX_SOURCE: &[u8] = include_bytes!["../target/spirv/quads.vert.spirv"];
+
+        pub const FRAGMENT_SOURCE: &[u8] = include_bytes!["../target/spirv/quads.frag.spirv"];
+
+        let vertex_source = pso::read_spirv(Cursor::new(VERTEX_SOURCE)).unwrap();
+        let fragment_source = pso::read_spirv(Cursor::new(FRAGMENT_SOURCE)).unwrap();
+
+        let vs_module = { unsafe { s.device.create_shader_module(&vertex_source) }.unwrap() };
+        let fs_module = { unsafe { s.device.create_shader_module(&fragment_source) }.unwrap() };
+
+        // Describe the shaders
+        const ENTRY_NAME: &str = "main";
+        let vs_module: <back::Backend as Backend>::ShaderModule = vs_module;
+        let (vs_entry, fs_entry) = (
+            pso::EntryPoint {
+                entry: ENTRY_NAME,
+                module: &vs_module,
+                specialization: pso::Specialization::default(),
+            },
+            pso::EntryPoint {
+                entry: ENTRY_NAME,
+                module: &fs_module,
+                specialization: pso::Specialization::default(),
+            },
+        );
+        let shader_entries = pso::GraphicsShaderSet {
+            vertex: vs_entry,
+            hull: None,
+            domain: None,
+            geometry: None,
+            fragment: Some(fs_entry),
+        };
+        let input_assembler = pso::InputAssemblerDesc::new(Primitive::TriangleList);
+
+        let vertex_buffers: Vec<pso::VertexBufferDesc> = vec![
+            pso::VertexBufferDesc {
+                binding: 0,
+                stride: 2 * 4,
+                rate: pso::VertexInputRate::Vertex,
+            },
+            pso::VertexBufferDesc {
+                binding: 1,
+                stride: 4,
+                rate: pso::VertexInputRate::Vertex,
+            },
+            pso::VertexBufferDesc {
+                binding: 2,
+                stride: 8,
+                rate: pso::VertexInputRate::Vertex,
+            },
+            pso::VertexBufferDesc {
+                binding: 3,
+                stride: 4,
+                rate: pso::VertexInputRate::Vertex,
+            },
+            pso::VertexBufferDesc {
+                binding: 4,
+                stride: 4,
+                rate: pso::VertexInputRate::Vertex,
+            },
+        ];
+        let attributes: Vec<pso::AttributeDesc> = vec![
+            pso::AttributeDesc {
+                location: 0,
+                binding: 0,
+                element: pso::Element {
+                    format: format::Format::Rg32Sfloat,
+                    offset: 0,
+                },
+            },
+            pso::AttributeDesc {
+                location: 1,
+                binding: 1,
+                element: pso::Element {
+                    format: format::Format::Rgba8Unorm,
+                    offset: 0,
+                },
+            },
+            pso::AttributeDesc {
+                location: 2,
+                binding: 2,
+                element: pso::Element {
+                    format: format::Format::Rg32Sfloat,
+                    offset: 0,
+                },
+            },
+            pso::AttributeDesc {
+                location: 3,
+                binding: 3,
+                element: pso::Element {
+                    format: format::Format::R32Sfloat,
+                    offset: 0,
+                },
+            },
+            pso::AttributeDesc {
+                location: 4,
+                binding: 4,
+                element: pso::Element {
+                    format: format::Format::R32Sfloat,
+                    offset: 0,
+                },
+            },
+        ];
+
+        let rasterizer = pso::Rasterizer {
+            depth_clamping: false,
+            polygon_mode: pso::PolygonMode::Fill,
+            cull_face: pso::Face::NONE,
+            front_face: pso::FrontFace::Clockwise,
+            depth_bias: None,
+            conservative: false,
+        };
+
+        let depth_stencil = pso::DepthStencilDesc {
+            depth: None,
+            depth_bounds: false,
+            stencil: None,
+        };
+        let blender = options.blend.clone().into_gfx_blender();
+        let render_pass = {
+            let attachment = pass::Attachment {
+                format: Some(s.format),
+                samples: 1,
+                ops: pass::AttachmentOps::new(
+                    pass::AttachmentLoadOp::Clear,
+                    pass::AttachmentStoreOp::Store,
+                ),
+                stencil_ops: pass::AttachmentOps::DONT_CARE,
+                layouts: image::Layout::Undefined..image::Layout::Present,
+            };
+
+            let depth = pass::Attachment {
+                format: Some(format::Format::D32Sfloat),
+                samples: 1,
+                ops: pass::AttachmentOps::new(
+                    pass::AttachmentLoadOp::Clear,
+                    pass::AttachmentStoreOp::Store,
+                ),
+                stencil_ops: pass::AttachmentOps::DONT_CARE,
+                layouts: image::Layout::Undefined..image::Layout::DepthStencilAttachmentOptimal,
+            };
+
+            let subpass = pass::SubpassDesc {
+                colors: &[(0, image::Layout::ColorAttachmentOptimal)],
+                depth_stencil: Some(&(1, image::Layout::DepthStencilAttachmentOptimal)),
+                inputs: &[],
+                resolves: &[],
+                preserves: &[],
+            };
+
+            unsafe {
+                s.device
+                    .create_render_pass(&[attachment, depth], &[subpass], &[])
+            }
+            .expect("Can't create render pass")
+        };
+        let baked_states = pso::BakedStates {
+            viewport: None,
+            scissor: None,
+            blend_color: None,
+            depth_bounds: None,
+        };
+        let bindings = Vec::<pso::DescriptorSetLayoutBinding>::new();
+        let immutable_samplers = Vec::<<back::Backend as Backend>::Sampler>::new();
+        let descriptor_set_layouts: Vec<<back::Backend as Backend>::DescriptorSetLayout> =
+            vec![unsafe {
+                s.device
+                    .create_descriptor_set_layout(bindings, immutable_samplers)
+                    .expect("Couldn't make a DescriptorSetLayout")
+            }];
+        let mut push_constants = Vec::<(pso::ShaderStageFlags, std::ops::Range<u32>)>::new();
+        push_constants.push((pso::ShaderStageFlags::VERTEX, 0..64));
+
+        let pipeline_layout = unsafe {
+            s.device
+                .create_pipeline_layout(&descriptor_set_layouts, push_constants)
+                .expect("Couldn't create a pipeline layout")
+        };
+
+        let pipeline_desc = pso::GraphicsPipelineDesc {
+            shaders: shader_entries,
+            rasterizer,
+            vertex_buffers,
+            attributes,
+            input_assembler,
+            blender,
+            depth_stencil,
+            multisampling: None,
+            baked_states,
+            layout: &pipeline_layout,
+            subpass: pass::Subpass {
+                index: 0,
+                main_pass: &render_pass,
+            },
+            flags: pso::PipelineCreationFlags::empty(),
+            parent: pso::BasePipeline::None,
+        };
+
+        let pipeline = unsafe {
+            s.device
+                .create_graphics_pipeline(&pipeline_desc, None)
+                .expect("Couldn't create a graphics pipeline!")
+        };
+
+        unsafe {
+            s.device.destroy_shader_module(vs_module);
+            s.device.destroy_shader_module(fs_module);
+        }
+
+        let image_count = s.swapconfig.image_count;
+        let posbuf = (0..image_count)
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
+            .collect::<Vec<_>>();
+        let colbuf = (0..image_count)
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
+            .collect::<Vec<_>>();
+        let tranbuf = (0..image_count)
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
+            .collect::<Vec<_>>();
+        let rotbuf = (0..image_count)
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
+            .collect::<Vec<_>>();
+        let scalebuf = (0..image_count)
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
+            .collect::<Vec<_>>();
+
+        let shapes = ShapesData {
+            hidden: options.hide,
+
+            fixed_perspective: options.fixed_perspective,
+            removed: vec![],
+            generation: s.next_generation(),
+            generations: vec![],
+            ranges: vec![],
+
+            posbuf_touch: 0,
+            colbuf_touch: 0,
+            tranbuf_touch: 0,
+            rotbuf_touch: 0,
+            scalebuf_touch: 0,
+
+            posbuffer: vec![],
+            colbuffer: vec![],
+            tranbuffer: vec![],
+            rotbuffer: vec![],
+            scalebuffer: vec![],
+
+            posbuf,
+            colbuf,
+            tranbuf,
+            rotbuf,
+            scalebuf,
+
+            descriptor_set: descriptor_set_layouts,
+            pipeline: ManuallyDrop::new(pipeline),
+            pipeline_layout: ManuallyDrop::new(pipeline_layout),
+            render_pass: ManuallyDrop::new(render_pass),
+        };
+
+        let prev_layer = s.layer_holes.find_available(|x| match x {
+            DrawType::Shapes { .. } => true,
+            _ => false,
+        });
+
+        let generation = shapes.generation;
+        if let Some(prev_layer) = prev_layer {
+            match prev_layer {
+                DrawType::Shapes { id } => {
+                    let old_shapes = std::mem::replace(&mut s.shapes[id], shapes);
+                    old_shapes.destroy(&s.device);
+                    s.draw_order.push(DrawType::Shapes { id });
+                    Layer(id, generation)
+                }
+                _ => panic!["Got a non-shapes drawtype, should be impossible!"],
+            }
+        } else {
+            s.shapes.push(shapes);
+            s.draw_order.push(DrawType::Shapes {
+                id: s.shapes.len() - 1,
+            });
+            Layer(s.shapes.len() - 1, generation)
+        }
+    }
+
+    /// Set the fixed perspective of a layer. `None` uses the vxdraw perspective.
+    pub fn set_perspective(&mut self, layer: &Layer, perspective: Option<Matrix4<f32>>) {
+        if !self.check_layer(layer) {
+            return;
+        }
+        self.vx.shapes[layer.0].fixed_perspective = perspective;
+    }
+
+    /// Query the amount of layers of this type there are
+    pub fn layer_count(&self) -> usize {
+        self.vx.shapes.len()
+    }
+
+    /// Disable drawing of the shapes at this layer
+    pub fn hide(&mut self, layer: &Layer) {
+        if !self.check_layer(layer) {
+            return;
+        }
+        self.vx.shapes[layer.0].hidden = true;
+    }
+
+    /// Enable drawing of the shapes at this layer
+    pub fn show(&mut self, layer: &Layer) {
+        if !self.check_layer(layer) {
+            return;
+        }
+        self.vx.shapes[layer.0].hidden = false;
+    }
+
+    /// Remove a layer of shapes
+    ///
+    /// Removes the shape layer from memory and destroys all shapes associated with it.
+    /// All lingering shape handles that were spawned using this layer will be invalidated.
+    pub fn remove_layer(&mut self, layer: Layer) {
+        if !self.check_layer(&layer) {
+            return;
+        }
+        let s = &mut *self.vx;
+        s.shapes[layer.0].generation = 0;
+        let draw_type = s.take_from_draw_order(|x| match x {
+            DrawType::Shapes { id } => *id == layer.0,
+            _ => false,
+        });
+        if let Some(draw_type) = draw_type {
+            s.layer_holes.push(draw_type);
+        }
+    }
+
+    /// Add a new shape to the given layer
+    ///
+    /// The shape is drawn on top of all shapes added before it to the same layer.
+    ///
+    /// Adding to a removed layer returns a handle that is already stale.
+    pub fn add(&mut self, layer: &Layer, shape: Shape) -> Handle {
+        if !self.check_layer(layer) {
+            return Handle(layer.0, 0, 0);
+        }
+        let generation = self.vx.next_generation();
+        let shapes = &mut self.vx.shapes[layer.0];
+        let slot = match shapes.removed.pop() {
+            Some(slot) => {
+                shapes.generations[slot] = generation;
+                slot
+            }
+            None => {
+                shapes.generations.push(generation);
+                shapes.ranges.push(0..0);
+                shapes.ranges.len() - 1
+            }
+        };
+        let end = shapes.posbuffer.len();
+        shapes.ranges[slot] = end..end;
+        let handle = Handle(layer.0, slot, generation);
+        self.replace_vertices(&handle, &shape);
+        handle
+    }
+
+    /// Replace the geometry and colors of a shape
+    ///
+    /// The translation, rotation, and scale of the new shape are used as well. The shape keeps
+    /// its place in the draw order.
+    pub fn set_shape(&mut self, handle: &Handle, shape: Shape) {
+        if !self.check(handle) {
+            return;
+        }
+        self.replace_vertices(handle, &shape);
+    }
+
+    /// Remove a shape
+    ///
+    /// The vertices of the shape are removed from the layer and its slot is reused by the next
+    /// call to [Shapes::add].
+    pub fn remove(&mut self, handle: Handle) {
+        if !self.check(&handle) {
+            return;
+        }
+        self.replace_vertices(&handle, &Shape::triangles(&[]));
+        let shapes = &mut self.vx.shapes[handle.0];
+        shapes.generations[handle.1] = 0;
+        shapes.removed.push(handle.1);
+    }
+
+    /// Check if a shape handle still refers to a shape, that is, neither the shape nor its layer
+    /// has been removed
+    pub fn is_alive(&self, handle: &Handle) -> bool {
+        let shapes = &self.vx.shapes[handle.0];
+        shapes.generation != 0 && shapes.generations.get(handle.1) == Some(&handle.2)
+    }
+
+    /// Get the number of vertices of a shape, three per triangle
+    pub fn vertex_count(&self, handle: &Handle) -> usize {
+        if !self.check(handle) {
+            return 0;
+        }
+        self.vx.shapes[handle.0].ranges[handle.1].len()
+    }
+
+    fn check(&self, handle: &Handle) -> bool {
+        let shapes = &self.vx.shapes[handle.0];
+        let current = if shapes.generation == 0 {
+            0
+        } else {
+            shapes.generations.get(handle.1).cloned().unwrap_or(0)
+        };
+        is_current(&self.vx.log, current, handle.2)
+    }
+
+    fn check_layer(&self, layer: &Layer) -> bool {
+        is_current(&self.vx.log, self.vx.shapes[layer.0].generation, layer.1)
+    }
+
+    /// Splice the vertices of a shape into the buffers, moving the shapes after it
+    fn replace_vertices(&mut self, handle: &Handle, shape: &Shape) {
+        let image_count = self.vx.swapconfig.image_count;
+        let shapes = &mut self.vx.shapes[handle.0];
+        let range = shapes.ranges[handle.1].clone();
+        let count = shape.vertices.len();
+        shapes.posbuffer.splice(
+            range.clone(),
+            shape
+                .vertices
+                .iter()
+                .map(|(x, y)| [x - shape.origin.0, y - shape.origin.1]),
+        );
+        shapes
+            .colbuffer
+            .splice(range.clone(), shape.colors.iter().cloned());
+        shapes.tranbuffer.splice(
+            range.clone(),
+            std::iter::repeat_n([shape.translation.0, shape.translation.1], count),
+        );
+        shapes
+            .rotbuffer
+            .splice(range.clone(), std::iter::repeat_n(shape.rotation, count));
+        shapes
+            .scalebuffer
+            .splice(range.clone(), std::iter::repeat_n(shape.scale, count));
+        // Empty shapes after this one move as well. Empty shapes sharing the start of an empty
+        // range may stay, but a non-empty shape there lies after the inserted vertices
+        for other in shapes.ranges.iter_mut() {
+            if other.start >= range.end && (other.start > range.start || other.end > other.start) {
+                *other = other.start + count - range.len()..other.end + count - range.len();
+            }
+        }
+        shapes.ranges[handle.1] = range.start..range.start + count;
+
+        shapes.posbuf_touch = image_count;
+        shapes.colbuf_touch = image_count;
+        shapes.tranbuf_touch = image_count;
+        shapes.rotbuf_touch = image_count;
+        shapes.scalebuf_touch = image_count;
+    }
+
+    // ---
+
+    /// Set a solid color of a shape
+    pub fn set_solid_color(&mut self, handle: &Handle, rgba: Color) {
+        let Color::Rgba(r, g, b, a) = rgba;
+        self.set_vertex_colors(handle, |_| Color::Rgba(r, g, b, a));
+    }
+
+    /// Set the color of every vertex of a shape from its model space position
+    pub fn set_vertex_colors(
+        &mut self,
+        handle: &Handle,
+        mut color: impl FnMut((f32, f32)) -> Color,
+    ) {
+        if !self.check(handle) {
+            return;
+        }
+        self.vx.shapes[handle.0].colbuf_touch = self.vx.swapconfig.image_count;
+        let shapes = &mut self.vx.shapes[handle.0];
+        let range = shapes.ranges[handle.1].clone();
+        for (col, pos) in shapes.colbuffer[range.clone()]
+            .iter_mut()
+            .zip(shapes.posbuffer[range].iter())
+        {
+            let Color::Rgba(r, g, b, a) = color((pos[0], pos[1]));
+            *col = [r, g, b, a];
+        }
+    }
+
+    /// Set the position (translation) of a shape
+    pub fn set_translation(&mut self, handle: &Handle, position: (f32, f32)) {
+        if !self.check(handle) {
+            return;
+        }
+        self.vx.shapes[handle.0].tranbuf_touch = self.vx.swapconfig.image_count;
+        let shapes = &mut self.vx.shapes[handle.0];
+        for tran in &mut shapes.tranbuffer[shapes.ranges[handle.1].clone()] {
+            *tran = [position.0, position.1];
+        }
+    }
+
+    /// Set the rotation of a shape
+    ///
+    /// The rotation is about the model space origin.
+    pub fn set_rotation<T: Copy + Into<Rad<f32>>>(&mut self, handle: &Handle, angle: T) {
+        if !self.check(handle) {
+            return;
+        }
+        let angle = angle.into().0;
+        self.vx.shapes[handle.0].rotbuf_touch = self.vx.swapconfig.image_count;
+        let shapes = &mut self.vx.shapes[handle.0];
+        for rot in &mut shapes.rotbuffer[shapes.ranges[handle.1].clone()] {
+            *rot = angle;
+        }
+    }
+
+    /// Set the scale of a shape
+    pub fn set_scale(&mut self, handle: &Handle, scale: f32) {
+        if !self.check(handle) {
+            return;
+        }
+        self.vx.shapes[handle.0].scalebuf_touch = self.vx.swapconfig.image_count;
+        let shapes = &mut self.vx.shapes[handle.0];
+        for sc in &mut shapes.scalebuffer[shapes.ranges[handle.1].clone()] {
+            *sc = scale;
+        }
+    }
+
+    /// Translate a shape by a vector
+    ///
+    /// Translation does not mutate the model-space of a shape.
+    pub fn translate(&mut self, handle: &Handle, movement: (f32, f32)) {
+        if !self.check(handle) {
+            return;
+        }
+        self.vx.shapes[handle.0].tranbuf_touch = self.vx.swapconfig.image_count;
+        let shapes = &mut self.vx.shapes[handle.0];
+        for tran in &mut shapes.tranbuffer[shapes.ranges[handle.1].clone()] {
+            tran[0] += movement.0;
+            tran[1] += movement.1;
+        }
+    }
+
+    /// Rotate a shape
+    ///
+    /// Rotation does not mutate the model-space of a shape.
+    pub fn rotate<T: Copy + Into<Rad<f32>>>(&mut self, handle: &Handle, angle: T) {
+        if !self.check(handle) {
+            return;
+        }
+        let angle = angle.into().0;
+        self.vx.shapes[handle.0].rotbuf_touch = self.vx.swapconfig.image_count;
+        let shapes = &mut self.vx.shapes[handle.0];
+        for rot in &mut shapes.rotbuffer[shapes.ranges[handle.1].clone()] {
+            *rot += angle;
+        }
+    }
+
+    /// Scale a shape
+    ///
+    /// Scale does not mutate the model-space of a shape.
+    pub fn scale(&mut self, handle: &Handle, scale: f32) {
+        if !self.check(handle) {
+            return;
+        }
+        self.vx.shapes[handle.0].scalebuf_touch = self.vx.swapconfig.image_count;
+        let shapes = &mut self.vx.shapes[handle.0];
+        for sc in &mut shapes.scalebuffer[shapes.ranges[handle.1].clone()] {
+            *sc *= scale;
+        }
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use slog::{Discard, Logger};
+
+    fn area(triangles: &[(f32, f32)]) -> f32 {
+        triangles
+            .chunks(3)
+            .map(|tri| cross(tri[0], tri[1], tri[2]).abs() / 2.0)
+            .sum()
+    }
+
+    #[test]
+    fn concave_polygons_are_triangulated() {
+        // An L shape, wound both ways
+        let outline = [
+            (0.0, 0.0),
+            (2.0, 0.0),
+            (2.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 2.0),
+            (0.0, 2.0),
+        ];
+        let triangles = triangulate(&outline);
+        assert_eq![4 * 3, triangles.len()];
+        assert![(3.0 - area(&triangles)).abs() < 1e-6];
+
+        let reversed = outline.iter().rev().cloned().collect::<Vec<_>>();
+        assert![(3.0 - area(&triangulate(&reversed))).abs() < 1e-6];
+        assert![triangulate(&outline[..2]).is_empty()];
+    }
+
+    #[test]
+    fn polylines_cover_their_segments() {
+        let straight = stroke(&[(0.0, 0.0), (1.0, 0.0)], 0.1, Join::Miter, Cap::Butt);
+        assert_eq![6, straight.len()];
+        assert![(0.2 - area(&straight)).abs() < 1e-6];
+
+        let square = stroke(&[(0.0, 0.0), (1.0, 0.0)], 0.1, Join::Miter, Cap::Square);
+        assert![(0.24 - area(&square)).abs() < 1e-6];
+
+        let round = stroke(&[(0.0, 0.0), (1.0, 0.0)], 0.1, Join::Miter, Cap::Round);
+        assert![(0.2 + PI * 0.01 - area(&round)).abs() < 1e-3];
+
+        // A right angle with a miter fills the outer corner square
+        let corner = stroke(
+            &[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)],
+            0.1,
+            Join::Miter,
+            Cap::Butt,
+        );
+        assert_eq![2 * 6 + 6, corner.len()];
+        assert![(0.4 + 0.01 - area(&corner)).abs() < 1e-6];
+        let bevel = stroke(
+            &[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)],
+            0.1,
+            Join::Bevel,
+            Cap::Butt,
+        );
+        assert![(0.4 + 0.005 - area(&bevel)).abs() < 1e-6];
+    }
+
+    #[test]
+    fn shapes_keep_their_vertices_when_others_are_removed() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (100, 100));
+        let layer = vx.shapes().add_layer(&LayerOptions::new());
+
+        let circle = vx.shapes().add(
+            &layer,
+            Shape::circle(0.5, 16).color(Color::Rgba(255, 0, 0, 255)),
+        );
+        let rectangle = vx
+            .shapes()
+            .add(&layer, Shape::rounded_rectangle((1.0, 0.5), 0.1, 4));
+        assert_eq![16 * 3, vx.shapes().vertex_count(&circle)];
+        assert_eq![20 * 3, vx.shapes().vertex_count(&rectangle)];
+
+        vx.shapes().set_translation(&rectangle, (0.5, 0.0));
+        vx.shapes().remove(circle);
+        assert_eq![20 * 3, vx.shapes[0].posbuffer.len()];
+        assert_eq![[0.5, 0.0], vx.shapes[0].tranbuffer[0]];
+
+        let triangle = vx.shapes().add(
+            &layer,
+            Shape::triangles(&[(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]).origin((0.5, 0.0)),
+        );
+        assert_eq![0, triangle.1];
+        assert_eq![[-0.5, 0.0], vx.shapes[0].posbuffer[20 * 3]];
+
+        vx.shapes()
+            .set_shape(&rectangle, Shape::ellipse((0.5, 0.2), 8));
+        assert_eq![8 * 3 + 3, vx.shapes[0].posbuffer.len()];
+        assert_eq![[-0.5, 0.0], vx.shapes[0].posbuffer[8 * 3]];
+        vx.draw_frame();
+    }
+
+    #[test]
+    fn empty_shapes_move_when_shapes_before_them_change() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (100, 100));
+        let layer = vx.shapes().add_layer(&LayerOptions::new());
+
+        let first = vx.shapes().add(
+            &layer,
+            Shape::triangles(&[
+                (0.0, 0.0),
+                (1.0, 0.0),
+                (0.0, 1.0),
+                (0.0, 0.0),
+                (-1.0, 0.0),
+                (0.0, -1.0),
+            ]),
+        );
+        let empty = vx.shapes().add(&layer, Shape::triangles(&[]));
+        assert_eq![6..6, vx.shapes[0].ranges[empty.1]];
+
+        vx.shapes().remove(first);
+        assert_eq![0..0, vx.shapes[0].ranges[empty.1]];
+
+        vx.shapes().set_shape(
+            &empty,
+            Shape::triangles(&[(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]),
+        );
+        assert_eq![3, vx.shapes().vertex_count(&empty)];
+        assert_eq![3, vx.shapes[0].posbuffer.len()];
+
+        vx.shapes().set_shape(&empty, Shape::triangles(&[]));
+        let last = vx.shapes().add(&layer, Shape::triangles(&[]));
+        vx.shapes().set_shape(
+            &last,
+            Shape::triangles(&[(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]),
+        );
+        vx.shapes().set_shape(
+            &empty,
+            Shape::triangles(&[(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]),
+        );
+        assert_eq![0..3, vx.shapes[0].ranges[empty.1]];
+        assert_eq![3..6, vx.shapes[0].ranges[last.1]];
+        vx.draw_frame();
+    }
+}
@@ -53,8 +53,17 @@
 //!     std::thread::sleep(std::time::Duration::from_millis(16));
 //! }
 //! ```
-use super::{blender, utils::*, Color};
-use crate::data::{DrawType, StreamingTexture, StreamingTextureWrite, VxDraw};
+use super::{
+    blender,
+    dyntex::{self, ImgData},
+    shapes::{Cap, Join, Shape},
+    utils::*,
+    Color,
+};
+use crate::data::{
+    DrawType, StreamingTexture, StreamingTextureRead, StreamingTextureWrite, VxDraw,
+};
+use crate::error::Error;
 use arrayvec::ArrayVec;
 use cgmath::Matrix4;
 use cgmath::Rad;
@@ -69,6 +78,7 @@ use gfx_backend_metal as back;
 use gfx_backend_vulkan as back;
 use gfx_hal::{
     adapter::PhysicalDevice,
+    buffer,
     command::{self, CommandBuffer, CommandBufferFlags},
     device::Device,
     format, image, memory, pass,
@@ -77,6 +87,7 @@ use gfx_hal::{
     queue::CommandQueue,
     Backend,
 };
+use slog::error;
 use smallvec::SmallVec;
 use std::iter::once;
 use std::{io::Cursor, mem::ManuallyDrop};
@@ -84,22 +95,20 @@ use std::{io::Cursor, mem::ManuallyDrop};
 // ---
 
 /// A view into a sprite
-pub struct Handle(usize, usize);
+///
+/// The handle becomes stale when the sprite or its layer is removed, using a stale handle panics
+/// in debug builds and is ignored in release builds.
+pub struct Handle(usize, usize, u64);
 
 /// Handle to a texture (layer)
-pub struct Layer(usize);
+pub struct Layer(usize, u64);
 
 impl Layerable for Layer {
-    fn get_layer(&self, vx: &VxDraw) -> usize {
-        for (idx, ord) in vx.draw_order.iter().enumerate() {
-            match ord {
-                DrawType::StreamingTexture { id } if *id == self.0 => {
-                    return idx;
-                }
-                _ => {}
-            }
-        }
-        panic!["Unable to get layer"]
+    fn get_layer(&self, vx: &VxDraw) -> Option<LayerPosition> {
+        vx.find_in_draw_order(|ord| match ord {
+            DrawType::StreamingTexture { id } => *id == self.0,
+            _ => false,
+        })
     }
 }
 
@@ -142,6 +151,29 @@ pub enum WrapMode {
     // Border, // Not supported, need borders
 }
 
+/// Which part of the existing pixels stays in place when a layer is resized, see [Strtex::resize]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Anchor {
+    /// Keep the top-left corner
+    TopLeft,
+    /// Keep the middle of the top edge
+    Top,
+    /// Keep the top-right corner
+    TopRight,
+    /// Keep the middle of the left edge
+    Left,
+    /// Keep the center
+    Center,
+    /// Keep the middle of the right edge
+    Right,
+    /// Keep the bottom-left corner
+    BottomLeft,
+    /// Keep the middle of the bottom edge
+    Bottom,
+    /// Keep the bottom-right corner
+    BottomRight,
+}
+
 /// Specify how to initialize the layer
 #[derive(Clone)]
 pub enum InitialPixels {
@@ -149,6 +181,158 @@ pub enum InitialPixels {
     Zero,
     /// Use the perlin noise generator to fill the pixels
     Perlin([f32; 3]),
+    /// Fill the pixels with configurable noise, see [Strtex::fill_with_noise]
+    Noise(NoiseOptions),
+}
+
+/// The noise function used by [NoiseOptions]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NoiseType {
+    /// Gradient noise on a square lattice
+    Perlin,
+    /// Gradient noise on a simplex lattice, with fewer directional artifacts than perlin noise
+    Simplex,
+    /// Distance to the nearest of randomly scattered points, giving a cellular look
+    Worley,
+    /// Smoothly interpolated random values on a square lattice
+    Value,
+}
+
+/// How a noise value in `[0, 1]` is turned into a pixel color
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NoiseMapping {
+    /// Store the value as an opaque gray level
+    Grayscale,
+    /// White where the value is at least the threshold, black elsewhere
+    Threshold(f32),
+    /// Interpolate between two RGBA colors
+    Gradient((u8, u8, u8, u8), (u8, u8, u8, u8)),
+}
+
+/// Options for filling a streaming texture with noise, see [Strtex::fill_with_noise]
+///
+/// Noise is sampled in pixel coordinates of the whole texture, so filling adjacent regions with
+/// the same options gives seamless results.
+#[derive(Clone, Debug)]
+pub struct NoiseOptions {
+    noise: NoiseType,
+    seed: [f32; 3],
+    octaves: u32,
+    lacunarity: f32,
+    gain: f32,
+    frequency: f32,
+    mapping: NoiseMapping,
+    region: Option<((u32, u32), (u32, u32))>,
+}
+
+impl NoiseOptions {
+    /// Create the default options: 4 octaves of perlin noise in grayscale over the whole texture
+    pub fn new() -> Self {
+        Self {
+            noise: NoiseType::Perlin,
+            seed: [0.0, 0.0, 0.0],
+            octaves: 4,
+            lacunarity: 2.0,
+            gain: 0.5,
+            frequency: 1.0 / 32.0,
+            mapping: NoiseMapping::Grayscale,
+            region: None,
+        }
+    }
+
+    /// Set the noise function
+    pub fn noise(mut self, noise: NoiseType) -> Self {
+        self.noise = noise;
+        self
+    }
+
+    /// Set the seed, different seeds give unrelated noise
+    pub fn seed(mut self, seed: [f32; 3]) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Set the number of layers of noise that are summed, between 1 and 16
+    pub fn octaves(mut self, octaves: u32) -> Self {
+        self.octaves = octaves.clamp(1, 16);
+        self
+    }
+
+    /// Set the frequency multiplier from one octave to the next
+    pub fn lacunarity(mut self, lacunarity: f32) -> Self {
+        self.lacunarity = lacunarity;
+        self
+    }
+
+    /// Set the amplitude multiplier from one octave to the next
+    pub fn gain(mut self, gain: f32) -> Self {
+        self.gain = gain;
+        self
+    }
+
+    /// Set the frequency of the first octave in cycles per pixel
+    pub fn frequency(mut self, frequency: f32) -> Self {
+        self.frequency = frequency;
+        self
+    }
+
+    /// Set how noise values are mapped to colors
+    pub fn mapping(mut self, mapping: NoiseMapping) -> Self {
+        self.mapping = mapping;
+        self
+    }
+
+    /// Only fill the given region, starting at the top-left pixel with the given width and height
+    pub fn region(mut self, start: (u32, u32), size: (u32, u32)) -> Self {
+        self.region = Some((start, size));
+        self
+    }
+
+    /// Lay out the options to match the push constants of `noise.frag`
+    fn push_constants(&self, start: (u32, u32), size: (u32, u32)) -> [u32; 24] {
+        let (mapping, threshold, low, high) = match self.mapping {
+            NoiseMapping::Grayscale => (0, 0.0, [0.0; 4], [0.0; 4]),
+            NoiseMapping::Threshold(threshold) => (1, threshold, [0.0; 4], [0.0; 4]),
+            NoiseMapping::Gradient(low, high) => (
+                2,
+                0.0,
+                srgb_to_linear([low.0, low.1, low.2, low.3]),
+                srgb_to_linear([high.0, high.1, high.2, high.3]),
+            ),
+        };
+        let noise = match self.noise {
+            NoiseType::Perlin => 0,
+            NoiseType::Simplex => 1,
+            NoiseType::Worley => 2,
+            NoiseType::Value => 3,
+        };
+        #[rustfmt::skip]
+        let floats = [
+            self.seed[0], self.seed[1], self.seed[2], self.frequency,
+            start.0 as f32, start.1 as f32, size.0 as f32, size.1 as f32,
+            self.lacunarity, self.gain, threshold, 0.0,
+        ];
+        let mut constants = [0u32; 24];
+        for (constant, value) in constants.iter_mut().zip(floats.iter()) {
+            *constant = value.to_bits();
+        }
+        constants[12] = self.octaves;
+        constants[13] = noise;
+        constants[14] = mapping;
+        for (constant, value) in constants[16..]
+            .iter_mut()
+            .zip(low.iter().chain(high.iter()))
+        {
+            *constant = value.to_bits();
+        }
+        constants
+    }
+}
+
+impl Default for NoiseOptions {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// Options for creating a layer of a single streaming texture with sprites
@@ -356,6 +540,125 @@ impl Default for Sprite {
 
 // ---
 
+/// A pending read of pixels from a streaming texture, see [Strtex::request_read]
+#[derive(Debug)]
+pub struct ReadTicket(usize, u64);
+
+/// State of a read requested with [Strtex::request_read]
+#[derive(Debug, PartialEq)]
+pub enum ReadStatus {
+    /// The copy has not finished on the GPU yet, poll again after a later frame
+    Pending,
+    /// The pixels of the region, row by row
+    Ready(Vec<(u8, u8, u8, u8)>),
+    /// The ticket has already been redeemed, or its layer was removed
+    Unknown,
+}
+
+// ---
+
+/// Drawing operations to paint into a streaming texture, see [Strtex::paint]
+///
+/// Coordinates are in pixels with `(0, 0)` being the top-left corner of the texture. Operations
+/// are applied in the order in which they were added.
+pub struct Canvas {
+    ops: Vec<CanvasOp>,
+    blend: blender::Blender,
+}
+
+enum CanvasOp {
+    /// Three vertices per triangle, each being a position, a UV, and a linear color
+    Triangles(Vec<[f32; 8]>),
+    Stamp {
+        position: (f32, f32),
+        width: usize,
+        height: usize,
+        pixels: Vec<u8>,
+    },
+    FloodFill((u32, u32), (u8, u8, u8, u8)),
+}
+
+impl Canvas {
+    /// Create an empty canvas that uses alpha blending
+    pub fn new() -> Self {
+        Self {
+            ops: vec![],
+            blend: blender::Blender::default(),
+        }
+    }
+
+    /// Set the blender used for shapes and stamps (see [blender])
+    pub fn blend(mut self, blend_setter: impl Fn(blender::Blender) -> blender::Blender) -> Self {
+        self.blend = blend_setter(self.blend);
+        self
+    }
+
+    /// Draw a line of the given thickness
+    pub fn line(self, from: (f32, f32), to: (f32, f32), thickness: f32, color: Color) -> Self {
+        self.shape(&Shape::polyline(&[from, to], thickness, Join::Miter, Cap::Butt).color(color))
+    }
+
+    /// Draw a filled circle
+    pub fn circle(self, center: (f32, f32), radius: f32, color: Color) -> Self {
+        let segments = (radius.max(0.0) as usize).clamp(8, 128);
+        self.shape(
+            &Shape::circle(radius, segments)
+                .translation(center)
+                .color(color),
+        )
+    }
+
+    /// Draw a filled polygon, the outline may be concave but must not intersect itself
+    pub fn polygon(self, outline: &[(f32, f32)], color: Color) -> Self {
+        self.shape(&Shape::polygon(outline).color(color))
+    }
+
+    /// Draw an arbitrary shape (see [Shape]) using its colors and transformation
+    pub fn shape(mut self, shape: &Shape) -> Self {
+        let vertices = shape
+            .placed_vertices()
+            .map(|((x, y), color)| {
+                let color = srgb_to_linear(color);
+                [x, y, 0.0, 0.0, color[0], color[1], color[2], color[3]]
+            })
+            .collect::<Vec<_>>();
+        match self.ops.last_mut() {
+            Some(CanvasOp::Triangles(ref mut triangles)) => triangles.extend(vertices),
+            _ => self.ops.push(CanvasOp::Triangles(vertices)),
+        }
+        self
+    }
+
+    /// Blend an image into the texture with its top-left corner at the given position
+    pub fn stamp(mut self, position: (f32, f32), img: &ImgData) -> Self {
+        let (width, height, pixels) = img.decode();
+        self.ops.push(CanvasOp::Stamp {
+            position,
+            width,
+            height,
+            pixels,
+        });
+        self
+    }
+
+    /// Replace the connected area having the same color as the start pixel with a new color
+    ///
+    /// Flood fills are sequential by nature, so they are done on the texture memory instead of
+    /// on the GPU, and they do not blend.
+    pub fn flood_fill(mut self, start: (u32, u32), color: Color) -> Self {
+        self.ops.push(CanvasOp::FloodFill(start, color.into()));
+        self
+    }
+}
+
+impl Default for Canvas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ---
+
 /// Accessor object to all streaming textures
 ///
 /// A streaming texture is a texture which can be edited at run-time. Sprites are made from this
@@ -390,77 +693,8 @@ impl<'a> Strtex<'a> {
     pub fn add_layer(&mut self, options: &LayerOptions) -> Layer {
         let s = &mut *self.vx;
 
-        let device = &s.device;
-
-        let (the_images, image_memories, image_views, image_requirements) = {
-            let mut the_images = vec![];
-            let mut image_memories = vec![];
-            let mut image_views = vec![];
-            let mut image_requirements = vec![];
-            s.adapter
-                .physical_device
-                .image_format_properties(
-                    format::Format::Rgba8Srgb,
-                    2,
-                    image::Tiling::Linear,
-                    image::Usage::SAMPLED | image::Usage::TRANSFER_DST,
-                    image::ViewCapabilities::empty(),
-                )
-                .expect("Device does not support linear sampled textures");
-            for _ in 0..s.swapconfig.image_count {
-                let mut the_image = unsafe {
-                    device
-                        .create_image(
-                            image::Kind::D2(options.width as u32, options.height as u32, 1, 1),
-                            1,
-                            format::Format::Rgba8Srgb,
-                            image::Tiling::Linear,
-                            image::Usage::SAMPLED | image::Usage::TRANSFER_DST,
-                            image::ViewCapabilities::empty(),
-                        )
-                        .expect("Couldn't create the image!")
-                };
-
-                let requirements = unsafe { device.get_image_requirements(&the_image) };
-                let image_memory = unsafe {
-                    let memory_type_id = find_memory_type_id(
-                        &s.adapter,
-                        requirements,
-                        memory::Properties::COHERENT
-                            | memory::Properties::CPU_VISIBLE
-                            | memory::Properties::DEVICE_LOCAL,
-                    );
-                    device
-                        .allocate_memory(memory_type_id, requirements.size)
-                        .expect("Unable to allocate")
-                };
-
-                let image_view = unsafe {
-                    device
-                        .bind_image_memory(&image_memory, 0, &mut the_image)
-                        .expect("Unable to bind memory");
-
-                    device
-                        .create_image_view(
-                            &the_image,
-                            image::ViewKind::D2,
-                            format::Format::Rgba8Srgb,
-                            format::Swizzle::NO,
-                            image::SubresourceRange {
-                                aspects: format::Aspects::COLOR,
-                                levels: 0..1,
-                                layers: 0..1,
-                            },
-                        )
-                        .expect("Couldn't create the image view!")
-                };
-                the_images.push(the_image);
-                image_memories.push(image_memory);
-                image_views.push(image_view);
-                image_requirements.push(requirements);
-            }
-            (the_images, image_memories, image_views, image_requirements)
-        };
+        let (the_images, image_memories, image_views, image_requirements) =
+            make_frame_images(s, options.width as u32, options.height as u32);
 
         let sampler = unsafe {
             s.device
@@ -784,69 +1018,52 @@ impl<'a> Strtex<'a> {
             s.device.destroy_shader_module(fs_module);
         }
 
-        unsafe {
-            let barrier_fence = s.device.create_fence(false).expect("unable to make fence");
-            // TODO Use a proper command buffer here
-            s.device.wait_idle().unwrap();
-            let buffer = &mut s.command_buffers[s.current_frame];
-            buffer.begin_primary(CommandBufferFlags::EMPTY);
-            for the_image in &the_images {
-                let image_barrier = memory::Barrier::Image {
-                    states: (image::Access::empty(), image::Layout::Undefined)
-                        ..(
-                            // image::Access::HOST_READ | image::Access::HOST_WRITE,
-                            image::Access::empty(),
-                            image::Layout::General,
-                        ),
-                    target: the_image,
-                    families: None,
-                    range: image::SubresourceRange {
-                        aspects: format::Aspects::COLOR,
-                        levels: 0..1,
-                        layers: 0..1,
-                    },
-                };
-                buffer.pipeline_barrier(
-                    pso::PipelineStage::TOP_OF_PIPE..pso::PipelineStage::HOST,
-                    memory::Dependencies::empty(),
-                    &[image_barrier],
-                );
-            }
-            buffer.finish();
-            s.queue_group.queues[0].submit_without_semaphores(Some(&*buffer), Some(&barrier_fence));
-            s.device
-                .wait_for_fence(&barrier_fence, u64::max_value())
-                .unwrap();
-            s.device.destroy_fence(barrier_fence);
-        }
+        transition_to_general(s, &the_images);
 
         let image_count = s.swapconfig.image_count;
         let posbuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&s.device, &s.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
         let opacbuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&s.device, &s.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
         let uvbuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&s.device, &s.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
         let tranbuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&s.device, &s.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
         let rotbuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&s.device, &s.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
         let scalebuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&s.device, &s.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&s.device, &s.adapter).expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
 
         let indices = (0..image_count)
-            .map(|_| super::utils::ResizBufIdx4::new(&s.device, &s.adapter))
+            .map(|_| {
+                super::utils::ResizBufIdx4::new(&s.device, &s.adapter)
+                    .expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
 
         let strtex = StreamingTexture {
             hidden: false,
             removed: vec![],
+            generation: s.next_generation(),
+            generations: vec![],
 
             fixed_perspective: options.fixed_perspective,
 
@@ -881,6 +1098,7 @@ impl<'a> Strtex<'a> {
             image_view: image_views,
 
             circular_writes: (0..s.swapconfig.image_count).map(|_| vec![]).collect::<_>(),
+            reads: vec![],
 
             descriptor_pool: ManuallyDrop::new(descriptor_pool),
             sampler: ManuallyDrop::new(sampler),
@@ -898,13 +1116,14 @@ impl<'a> Strtex<'a> {
             _ => false,
         });
 
+        let generation = strtex.generation;
         let layer = if let Some(prev_layer) = prev_layer {
             match prev_layer {
                 DrawType::StreamingTexture { id } => {
                     let old_strtex = std::mem::replace(&mut s.strtexs[id], strtex);
                     self.destroy_texture(old_strtex);
                     self.vx.draw_order.push(DrawType::StreamingTexture { id });
-                    Layer(id)
+                    Layer(id, generation)
                 }
                 _ => panic!["Got a non-strtex drawtype, should be impossible!"],
             }
@@ -913,18 +1132,22 @@ impl<'a> Strtex<'a> {
             s.draw_order.push(DrawType::StreamingTexture {
                 id: s.strtexs.len() - 1,
             });
-            Layer(s.strtexs.len() - 1)
+            Layer(s.strtexs.len() - 1, generation)
         };
 
         match options.initial_pixels {
             InitialPixels::Zero => self.vx.strtex().write_all(&layer, (0, 0, 0, 0)),
             InitialPixels::Perlin(seeds) => self.vx.strtex().fill_with_perlin_noise(&layer, seeds),
+            InitialPixels::Noise(ref noise) => self.vx.strtex().fill_with_noise(&layer, noise),
         }
         layer
     }
 
     /// Set the fixed perspective of a layer. `None` uses the vxdraw perspective.
     pub fn set_perspective(&mut self, layer: &Layer, perspective: Option<Matrix4<f32>>) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.strtexs[layer.0].fixed_perspective = perspective;
     }
 
@@ -935,11 +1158,17 @@ impl<'a> Strtex<'a> {
 
     /// Disable drawing of the sprites at this layer
     pub fn hide(&mut self, layer: &Layer) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.strtexs[layer.0].hidden = true;
     }
 
     /// Enable drawing of the sprites at this layer
     pub fn show(&mut self, layer: &Layer) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.strtexs[layer.0].hidden = false;
     }
 
@@ -948,21 +1177,125 @@ impl<'a> Strtex<'a> {
     /// This also stops drawing all associated sprites, so the sprite handles that use this layer
     /// that still exist will be invalidated.
     pub fn remove_layer(&mut self, texture: Layer) {
+        if !self.check_layer(&texture) {
+            return;
+        }
+        let s = &mut *self.vx;
+        // Recorded copies are still in flight, the rest are not referenced by any frame
+        if s.strtexs[texture.0]
+            .reads
+            .iter()
+            .any(|read| read.recorded.is_some() && !read.done)
+        {
+            s.wait_for_fences_or_log();
+        }
+        for read in s.strtexs[texture.0].reads.drain(..) {
+            read.destroy(&s.device);
+        }
+        s.strtexs[texture.0].generation = 0;
+        let draw_type = s.take_from_draw_order(|x| match x {
+            DrawType::StreamingTexture { id } => *id == texture.0,
+            _ => false,
+        });
+        if let Some(draw_type) = draw_type {
+            s.layer_holes.push(draw_type);
+        }
+    }
+
+    /// Resize the texture of a layer
+    ///
+    /// The existing pixels are kept at the given anchor, cropped if the texture shrinks, and new
+    /// pixels are zeroed. Sprites stay attached, but their UV coordinates are relative so they
+    /// will now show the corresponding part of the resized texture. On error the layer keeps its
+    /// old texture.
+    pub fn resize(&mut self, layer: &Layer, wh: (u32, u32), anchor: Anchor) -> Result<(), Error> {
+        if !self.check_layer(layer) {
+            return Ok(());
+        }
+        if wh.0 == 0 || wh.1 == 0 {
+            return Ok(());
+        }
         let s = &mut *self.vx;
-        let mut index = None;
-        for (idx, x) in s.draw_order.iter().enumerate() {
-            match x {
-                DrawType::StreamingTexture { id } if *id == texture.0 => {
-                    index = Some(idx);
-                    break;
+        let old_wh = (s.strtexs[layer.0].width, s.strtexs[layer.0].height);
+        if old_wh == wh {
+            return Ok(());
+        }
+        s.wait_for_fences()?;
+        unsafe {
+            flush_writes(s, layer.0)?;
+        }
+        let (the_images, image_memories, image_views, image_requirements) =
+            make_frame_images(s, wh.0, wh.1);
+        transition_to_general(s, &the_images);
+
+        let filled = unsafe {
+            fill_resized_images(
+                s,
+                layer.0,
+                &the_images,
+                &image_memories,
+                &image_requirements,
+                resize_overlap(anchor, old_wh, wh),
+            )
+        };
+        if let Err(err) = filled {
+            unsafe {
+                for image_view in image_views {
+                    s.device.destroy_image_view(image_view);
+                }
+                for the_image in the_images {
+                    s.device.destroy_image(the_image);
+                }
+                for image_memory in image_memories {
+                    s.device.free_memory(image_memory);
                 }
-                _ => {}
             }
+            return Err(err);
         }
-        if let Some(idx) = index {
-            let draw_type = s.draw_order.remove(idx);
-            s.layer_holes.push(draw_type);
+
+        unsafe {
+            let strtex = &mut s.strtexs[layer.0];
+            for (set, image_view) in strtex.descriptor_sets.iter().zip(&image_views) {
+                s.device
+                    .write_descriptor_sets(vec![pso::DescriptorSetWrite {
+                        set,
+                        binding: 0,
+                        array_offset: 0,
+                        descriptors: Some(pso::Descriptor::Image(
+                            image_view,
+                            image::Layout::General,
+                        )),
+                    }]);
+            }
+
+            let old_images = std::mem::replace(&mut strtex.image_buffer, the_images);
+            let old_memories = std::mem::replace(&mut strtex.image_memory, image_memories);
+            let old_views = std::mem::replace(&mut strtex.image_view, image_views);
+            strtex.image_requirements = image_requirements;
+            strtex.width = wh.0;
+            strtex.height = wh.1;
+
+            // Reads that are not recorded yet would copy from outside of the new texture
+            let (fits, outside) = strtex.reads.drain(..).partition::<Vec<_>, _>(|read| {
+                read.recorded.is_some()
+                    || (read.start.0 + read.size.0 <= wh.0 && read.start.1 + read.size.1 <= wh.1)
+            });
+            strtex.reads = fits;
+            for read in outside {
+                read.destroy(&s.device);
+            }
+
+            for image_view in old_views {
+                s.device.destroy_image_view(image_view);
+            }
+            for image_buffer in old_images {
+                s.device.destroy_image(image_buffer);
+            }
+            for image_memory in old_memories {
+                s.device.free_memory(image_memory);
+            }
         }
+        Ok(())
     }
 
     fn destroy_texture(&mut self, mut strtex: StreamingTexture) {
@@ -1015,10 +1348,15 @@ impl<'a> Strtex<'a> {
             for image_view in strtex.image_view.drain(..) {
                 s.device.destroy_image_view(image_view);
             }
+            for read in strtex.reads.drain(..) {
+                read.destroy(&s.device);
+            }
         }
     }
 
     /// Add a sprite (a rectangular view of a texture) to the system
+    ///
+    /// Adding to a removed layer returns a handle that is already stale.
     pub fn add(&mut self, layer: &Layer, sprite: Sprite) -> Handle {
         // Derive xy from the sprite's initial UV
         let uv_a = sprite.uv_begin;
@@ -1051,14 +1389,15 @@ impl<'a> Strtex<'a> {
         );
         let bottomright_uv = (uv_b.0, uv_b.1);
 
-        let replace = self.vx.strtexs.get(layer.0).map(|x| !x.removed.is_empty());
-        if replace.is_none() {
-            panic!("Layer does not exist");
+        if !self.check_layer(layer) {
+            return Handle(layer.0, 0, 0);
         }
-
-        let handle = if replace.unwrap() {
+        let replace = !self.vx.strtexs[layer.0].removed.is_empty();
+        let generation = self.vx.next_generation();
+        let handle = if replace {
             let hole = self.vx.strtexs[layer.0].removed.pop().unwrap();
-            let handle = Handle(layer.0, hole);
+            self.vx.strtexs[layer.0].generations[hole] = generation;
+            let handle = Handle(layer.0, hole, generation);
             self.set_deform(
                 &handle,
                 [
@@ -1128,6 +1467,7 @@ impl<'a> Strtex<'a> {
                 topright_uv.0,
                 topright_uv.1,
             ]);
+            tex.generations.push(generation);
             tex.posbuffer.len() - 1
         };
 
@@ -1139,7 +1479,7 @@ impl<'a> Strtex<'a> {
         tex.rotbuf_touch = self.vx.swapconfig.image_count;
         tex.scalebuf_touch = self.vx.swapconfig.image_count;
 
-        Handle(layer.0, handle)
+        Handle(layer.0, handle, generation)
     }
 
     /// Removes a single sprite, making it not be drawn
@@ -1148,18 +1488,26 @@ impl<'a> Strtex<'a> {
     /// `holes`. Calling [Strtex::add] with available holes will fill the first available hole
     /// with the new sprite.
     pub fn remove(&mut self, handle: Handle) {
+        if !self.check(&handle) {
+            return;
+        }
         self.vx.strtexs[handle.0].scalebuf_touch = self.vx.swapconfig.image_count;
         if let Some(strtex) = self.vx.strtexs.get_mut(handle.0) {
             strtex.removed.push(handle.1);
             strtex.scalebuffer[handle.1].copy_from_slice(&[0.0, 0.0, 0.0, 0.0]);
+            strtex.generations[handle.1] = 0;
         }
     }
 
     /// Removes all sprites, clearing the layer
     pub fn remove_all(&mut self, layer: &Layer) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.strtexs[layer.0].scalebuf_touch = self.vx.swapconfig.image_count;
         if let Some(strtex) = self.vx.strtexs.get_mut(layer.0) {
             strtex.removed.clear();
+            strtex.generations.clear();
             strtex.opacbuffer.clear();
             strtex.posbuffer.clear();
             strtex.rotbuffer.clear();
@@ -1169,6 +1517,77 @@ impl<'a> Strtex<'a> {
         }
     }
 
+    /// Repack the sprites of a layer so that removed sprites no longer take up space
+    ///
+    /// [Strtex::add] reuses the slots of removed sprites, but the buffers never shrink by themselves.
+    /// Compaction moves the last sprites into the free slots and truncates the buffers. Returns
+    /// pairs of `(old, new)` handles for every sprite that was moved, the old handles become stale.
+    /// Handles to sprites that were not moved stay valid.
+    pub fn compact(&mut self, layer: &Layer) -> Vec<(Handle, Handle)> {
+        if !self.check_layer(layer) {
+            return vec![];
+        }
+        let image_count = self.vx.swapconfig.image_count;
+        let strtex = &mut self.vx.strtexs[layer.0];
+        let (moves, len) = compaction_moves(strtex.posbuffer.len(), &strtex.removed);
+        for (from, to) in moves.iter().cloned() {
+            strtex.posbuffer[to] = strtex.posbuffer[from];
+            strtex.opacbuffer[to] = strtex.opacbuffer[from];
+            strtex.uvbuffer[to] = strtex.uvbuffer[from];
+            strtex.tranbuffer[to] = strtex.tranbuffer[from];
+            strtex.rotbuffer[to] = strtex.rotbuffer[from];
+            strtex.scalebuffer[to] = strtex.scalebuffer[from];
+            strtex.generations[to] = strtex.generations[from];
+        }
+        strtex.posbuffer.truncate(len);
+        strtex.opacbuffer.truncate(len);
+        strtex.uvbuffer.truncate(len);
+        strtex.tranbuffer.truncate(len);
+        strtex.rotbuffer.truncate(len);
+        strtex.scalebuffer.truncate(len);
+        strtex.generations.truncate(len);
+        strtex.removed.clear();
+        if !moves.is_empty() {
+            strtex.posbuf_touch = image_count;
+            strtex.opacbuf_touch = image_count;
+            strtex.uvbuf_touch = image_count;
+            strtex.tranbuf_touch = image_count;
+            strtex.rotbuf_touch = image_count;
+            strtex.scalebuf_touch = image_count;
+        }
+        moves
+            .iter()
+            .map(|(from, to)| {
+                let generation = strtex.generations[*to];
+                (
+                    Handle(layer.0, *from, generation),
+                    Handle(layer.0, *to, generation),
+                )
+            })
+            .collect()
+    }
+
+    /// Check if a sprite handle still refers to a sprite, that is, neither the sprite nor its
+    /// layer has been removed
+    pub fn is_alive(&self, handle: &Handle) -> bool {
+        let strtex = &self.vx.strtexs[handle.0];
+        strtex.generation != 0 && strtex.generations.get(handle.1) == Some(&handle.2)
+    }
+
+    fn check(&self, handle: &Handle) -> bool {
+        let strtex = &self.vx.strtexs[handle.0];
+        let current = if strtex.generation == 0 {
+            0
+        } else {
+            strtex.generations.get(handle.1).cloned().unwrap_or(0)
+        };
+        is_current(&self.vx.log, current, handle.2)
+    }
+
+    fn check_layer(&self, layer: &Layer) -> bool {
+        is_current(&self.vx.log, self.vx.strtexs[layer.0].generation, layer.1)
+    }
+
     /// Get the current number of sprites
     pub fn sprite_count(&mut self, layer: &Layer) -> usize {
         self.vx.strtexs[layer.0].posbuffer.len() - self.vx.strtexs[layer.0].removed.len()
@@ -1178,6 +1597,9 @@ impl<'a> Strtex<'a> {
 
     /// Set the color of a specific pixel
     pub fn set_pixel(&mut self, id: &Layer, w: u32, h: u32, color: Color) {
+        if !self.check_layer(id) {
+            return;
+        }
         let s = &mut *self.vx;
         if let Some(strtex) = s.strtexs.get_mut(id.0) {
             if !(w < strtex.width && h < strtex.height) {
@@ -1190,6 +1612,9 @@ impl<'a> Strtex<'a> {
 
     /// Set multiple pixels in the texture
     pub fn set_pixels(&mut self, id: &Layer, modifier: impl Iterator<Item = (u32, u32, Color)>) {
+        if !self.check_layer(id) {
+            return;
+        }
         let s = &mut *self.vx;
         if let Some(strtex) = s.strtexs.get_mut(id.0) {
             for item in modifier {
@@ -1210,6 +1635,9 @@ impl<'a> Strtex<'a> {
         wh: (u32, u32),
         color: Color,
     ) {
+        if !self.check_layer(id) {
+            return;
+        }
         let s = &mut *self.vx;
         if let Some(strtex) = s.strtexs.get_mut(id.0) {
             if start.0 + wh.0 > strtex.width || start.1 + wh.1 > strtex.height {
@@ -1223,39 +1651,253 @@ impl<'a> Strtex<'a> {
         }
     }
 
-    // ---
-
-    /// Change the vertices of the model-space
+    /// Set a block of pixels from a slice of RGBA bytes
     ///
-    /// The name `set_deform` is used to keep consistent [Strtex::deform].
-    /// What this function does is just setting absolute vertex positions for each vertex in the
-    /// sprite.
-    pub fn set_deform(&mut self, handle: &Handle, points: [(f32, f32); 4]) {
-        self.vx.strtexs[handle.0].posbuf_touch = self.vx.swapconfig.image_count;
-        let vertex = &mut self.vx.strtexs[handle.0].posbuffer[handle.1];
-        for (idx, point) in points.iter().enumerate() {
-            vertex[idx * 2] = point.0;
-            vertex[idx * 2 + 1] = point.1;
+    /// The slice is read row by row and must contain exactly `wh.0 * wh.1 * 4` bytes, otherwise an
+    /// error is returned. The pixels are uploaded immediately with a buffer-to-image copy instead
+    /// of being queued per pixel. Blocks that do not fit inside the texture are ignored.
+    pub fn set_pixels_from_slice(
+        &mut self,
+        id: &Layer,
+        start: (u32, u32),
+        wh: (u32, u32),
+        pixels: &[u8],
+    ) -> Result<(), Error> {
+        if !self.check_layer(id) {
+            return Ok(());
         }
-    }
+        let s = &mut *self.vx;
+        {
+            let strtex = &s.strtexs[id.0];
+            let fits = |start: u32, size: u32, limit: u32| {
+                start.checked_add(size).map_or(false, |end| end <= limit)
+            };
+            if !fits(start.0, wh.0, strtex.width) || !fits(start.1, wh.1, strtex.height) {
+                return Ok(());
+            }
+        }
+        if pixels.len() != wh.0 as usize * wh.1 as usize * 4 {
+            return Err(Error::Unsupported(
+                "The amount of pixels does not match the size of the block",
+            ));
+        }
+        if wh.0 == 0 || wh.1 == 0 {
+            return Ok(());
+        }
+        s.wait_for_fences()?;
+        unsafe {
+            flush_writes(s, id.0)?;
+            let (staging, memory, requirements) =
+                make_transfer_buffer_of_size(s, pixels.len() as u64)?;
+            match s.device.map_memory(&memory, 0..requirements.size) {
+                Ok(target) => {
+                    ptr::copy_nonoverlapping(pixels.as_ptr(), target, pixels.len());
+                    s.device.unmap_memory(&memory);
+                }
+                Err(err) => {
+                    s.device.destroy_buffer(staging);
+                    s.device.free_memory(memory);
+                    return Err(err.into());
+                }
+            }
 
-    /// Set a solid color each vertex of a sprite
-    pub fn set_opacity(&mut self, handle: &Handle, opacity: u8) {
-        self.vx.strtexs[handle.0].opacbuf_touch = self.vx.swapconfig.image_count;
-        self.vx.strtexs[handle.0].opacbuffer[handle.1].copy_from_slice(&[opacity; 4]);
-    }
+            let mut cmd_buffer = s
+                .command_pool
+                .allocate_one(gfx_hal::command::Level::Primary);
+            cmd_buffer.begin_primary(CommandBufferFlags::ONE_TIME_SUBMIT);
+            copy_buffer_to_frames(&mut cmd_buffer, &staging, &s.strtexs[id.0], start, wh);
+            cmd_buffer.finish();
+            let copied = submit_and_wait(s, cmd_buffer);
 
-    /// Set a solid color each vertex of a sprite
-    pub fn set_opacity_raw(&mut self, handle: &Handle, opacity: [u8; 4]) {
-        self.vx.strtexs[handle.0].opacbuf_touch = self.vx.swapconfig.image_count;
-        self.vx.strtexs[handle.0].opacbuffer[handle.1].copy_from_slice(&opacity);
+            s.device.destroy_buffer(staging);
+            s.device.free_memory(memory);
+            copied
+        }
     }
 
-    /// Set the position of a sprite
-    pub fn set_translation(&mut self, handle: &Handle, position: (f32, f32)) {
-        self.vx.strtexs[handle.0].tranbuf_touch = self.vx.swapconfig.image_count;
-        for idx in 0..4 {
-            self.vx.strtexs[handle.0].tranbuffer[handle.1][idx * 2] = position.0;
+    /// Copy a block of pixels from one streaming texture to another
+    ///
+    /// Source and destination may be the same layer, overlapping regions are copied as if the
+    /// source was read in its entirety first. Regions that do not fit inside either texture are
+    /// ignored.
+    pub fn copy_region(
+        &mut self,
+        src: &Layer,
+        src_start: (u32, u32),
+        wh: (u32, u32),
+        dst: &Layer,
+        dst_start: (u32, u32),
+    ) -> Result<(), Error> {
+        if !self.check_layer(src) || !self.check_layer(dst) {
+            return Ok(());
+        }
+        let s = &mut *self.vx;
+        {
+            let source = &s.strtexs[src.0];
+            let target = &s.strtexs[dst.0];
+            let fits = |start: u32, size: u32, limit: u32| {
+                start.checked_add(size).map_or(false, |end| end <= limit)
+            };
+            if !fits(src_start.0, wh.0, source.width)
+                || !fits(src_start.1, wh.1, source.height)
+                || !fits(dst_start.0, wh.0, target.width)
+                || !fits(dst_start.1, wh.1, target.height)
+            {
+                return Ok(());
+            }
+        }
+        if wh.0 == 0 || wh.1 == 0 {
+            return Ok(());
+        }
+        s.wait_for_fences()?;
+        unsafe {
+            flush_writes(s, src.0)?;
+            flush_writes(s, dst.0)?;
+            let (staging, memory, _) =
+                make_transfer_buffer_of_size(s, u64::from(wh.0) * u64::from(wh.1) * 4)?;
+
+            let mut cmd_buffer = s
+                .command_pool
+                .allocate_one(gfx_hal::command::Level::Primary);
+            cmd_buffer.begin_primary(CommandBufferFlags::ONE_TIME_SUBMIT);
+            // After flushing every frame holds the same pixels, so any image can be the source
+            cmd_buffer.copy_image_to_buffer(
+                &s.strtexs[src.0].image_buffer[0],
+                image::Layout::General,
+                &staging,
+                once(region_copy(src_start, wh)),
+            );
+            cmd_buffer.pipeline_barrier(
+                pso::PipelineStage::TRANSFER..pso::PipelineStage::TRANSFER,
+                memory::Dependencies::empty(),
+                &[memory::Barrier::Buffer {
+                    states: buffer::Access::TRANSFER_WRITE..buffer::Access::TRANSFER_READ,
+                    target: &staging,
+                    families: None,
+                    range: None..None,
+                }],
+            );
+            copy_buffer_to_frames(&mut cmd_buffer, &staging, &s.strtexs[dst.0], dst_start, wh);
+            cmd_buffer.finish();
+            let copied = submit_and_wait(s, cmd_buffer);
+
+            s.device.destroy_buffer(staging);
+            s.device.free_memory(memory);
+            copied
+        }
+    }
+
+    /// Paint the operations of a canvas into a layer
+    ///
+    /// Consecutive shapes and stamps are rasterized together in one GPU pass, see [Canvas] for
+    /// the available operations. Fails if the GPU objects needed for painting can not be created,
+    /// operations before the failing one have already been painted.
+    pub fn paint(&mut self, layer: &Layer, canvas: &Canvas) -> Result<(), Error> {
+        if !self.check_layer(layer) {
+            return Ok(());
+        }
+        let s = &mut *self.vx;
+        s.wait_for_fences()?;
+        unsafe {
+            flush_writes(s, layer.0)?;
+        }
+        let mut ops = &canvas.ops[..];
+        while !ops.is_empty() {
+            let rasterized = ops
+                .iter()
+                .take_while(|op| match op {
+                    CanvasOp::FloodFill(..) => false,
+                    _ => true,
+                })
+                .count();
+            if rasterized > 0 {
+                unsafe {
+                    rasterize(s, layer.0, &ops[..rasterized], &canvas.blend)?;
+                }
+                ops = &ops[rasterized..];
+                continue;
+            }
+            if let CanvasOp::FloodFill(start, color) = ops[0] {
+                let strtex = &s.strtexs[layer.0];
+                for frame in 0..strtex.image_buffer.len() {
+                    unsafe {
+                        let subres = s.device.get_image_subresource_footprint(
+                            &strtex.image_buffer[frame],
+                            gfx_hal::image::Subresource {
+                                aspects: gfx_hal::format::Aspects::COLOR,
+                                level: 0,
+                                layer: 0,
+                            },
+                        );
+                        let target = s.device.map_memory(
+                            &strtex.image_memory[frame],
+                            0..strtex.image_requirements[frame].size,
+                        )?;
+                        let slice = std::slice::from_raw_parts_mut(
+                            target as *mut (u8, u8, u8, u8),
+                            strtex.image_requirements[frame].size as usize / 4,
+                        );
+                        flood_fill(
+                            slice,
+                            (strtex.width, strtex.height),
+                            (subres.row_pitch / 4) as usize,
+                            start,
+                            color,
+                        );
+                        s.device.unmap_memory(&strtex.image_memory[frame]);
+                    }
+                }
+            }
+            ops = &ops[1..];
+        }
+        Ok(())
+    }
+
+    // ---
+
+    /// Change the vertices of the model-space
+    ///
+    /// The name `set_deform` is used to keep consistent [Strtex::deform].
+    /// What this function does is just setting absolute vertex positions for each vertex in the
+    /// sprite.
+    pub fn set_deform(&mut self, handle: &Handle, points: [(f32, f32); 4]) {
+        if !self.check(handle) {
+            return;
+        }
+        self.vx.strtexs[handle.0].posbuf_touch = self.vx.swapconfig.image_count;
+        let vertex = &mut self.vx.strtexs[handle.0].posbuffer[handle.1];
+        for (idx, point) in points.iter().enumerate() {
+            vertex[idx * 2] = point.0;
+            vertex[idx * 2 + 1] = point.1;
+        }
+    }
+
+    /// Set a solid color each vertex of a sprite
+    pub fn set_opacity(&mut self, handle: &Handle, opacity: u8) {
+        if !self.check(handle) {
+            return;
+        }
+        self.vx.strtexs[handle.0].opacbuf_touch = self.vx.swapconfig.image_count;
+        self.vx.strtexs[handle.0].opacbuffer[handle.1].copy_from_slice(&[opacity; 4]);
+    }
+
+    /// Set a solid color each vertex of a sprite
+    pub fn set_opacity_raw(&mut self, handle: &Handle, opacity: [u8; 4]) {
+        if !self.check(handle) {
+            return;
+        }
+        self.vx.strtexs[handle.0].opacbuf_touch = self.vx.swapconfig.image_count;
+        self.vx.strtexs[handle.0].opacbuffer[handle.1].copy_from_slice(&opacity);
+    }
+
+    /// Set the position of a sprite
+    pub fn set_translation(&mut self, handle: &Handle, position: (f32, f32)) {
+        if !self.check(handle) {
+            return;
+        }
+        self.vx.strtexs[handle.0].tranbuf_touch = self.vx.swapconfig.image_count;
+        for idx in 0..4 {
+            self.vx.strtexs[handle.0].tranbuffer[handle.1][idx * 2] = position.0;
             self.vx.strtexs[handle.0].tranbuffer[handle.1][idx * 2 + 1] = position.1;
         }
     }
@@ -1264,6 +1906,9 @@ impl<'a> Strtex<'a> {
     ///
     /// Positive rotation goes counter-clockwise. The value of the rotation is in radians.
     pub fn set_rotation<T: Copy + Into<Rad<f32>>>(&mut self, handle: &Handle, rotation: T) {
+        if !self.check(handle) {
+            return;
+        }
         let angle = rotation.into().0;
         self.vx.strtexs[handle.0].rotbuf_touch = self.vx.swapconfig.image_count;
         self.vx.strtexs[handle.0].rotbuffer[handle.1]
@@ -1272,6 +1917,9 @@ impl<'a> Strtex<'a> {
 
     /// Set the scale of a sprite
     pub fn set_scale(&mut self, handle: &Handle, scale: f32) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.strtexs[handle.0].scalebuf_touch = self.vx.swapconfig.image_count;
         for sc in &mut self.vx.strtexs[handle.0].scalebuffer[handle.1] {
             *sc = scale;
@@ -1280,6 +1928,9 @@ impl<'a> Strtex<'a> {
 
     /// Set the UV values of a single sprite
     pub fn set_uv(&mut self, handle: &Handle, uv_begin: (f32, f32), uv_end: (f32, f32)) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.strtexs[handle.0].uvbuf_touch = self.vx.swapconfig.image_count;
         self.vx.strtexs[handle.0].uvbuffer[handle.1].copy_from_slice(&[
             uv_begin.0, uv_begin.1, uv_begin.0, uv_end.1, uv_end.0, uv_end.1, uv_end.0, uv_begin.1,
@@ -1291,6 +1942,9 @@ impl<'a> Strtex<'a> {
     /// This may be used to repeat a texture multiple times over the same sprite, or to do
     /// something exotic with uv coordinates.
     pub fn set_uv_raw(&mut self, handle: &Handle, uvs: [(f32, f32); 4]) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.strtexs[handle.0].uvbuf_touch = self.vx.swapconfig.image_count;
         self.vx.strtexs[handle.0].uvbuffer[handle.1].copy_from_slice(&[
             uvs[0].0, uvs[0].1, uvs[1].0, uvs[1].1, uvs[2].0, uvs[2].1, uvs[3].0, uvs[3].1,
@@ -1303,6 +1957,9 @@ impl<'a> Strtex<'a> {
     ///
     /// Adds the delta vertices to the sprite. Beware: This changes model space form.
     pub fn deform(&mut self, handle: &Handle, delta: [(f32, f32); 4]) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.strtexs[handle.0].posbuf_touch = self.vx.swapconfig.image_count;
         let points = &mut self.vx.strtexs[handle.0].posbuffer[handle.1];
         points[0] += delta[0].0;
@@ -1319,6 +1976,9 @@ impl<'a> Strtex<'a> {
     ///
     /// Translation does not mutate the model-space of a sprite.
     pub fn translate(&mut self, handle: &Handle, movement: (f32, f32)) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.strtexs[handle.0].tranbuf_touch = self.vx.swapconfig.image_count;
         for idx in 0..4 {
             self.vx.strtexs[handle.0].tranbuffer[handle.1][idx * 2] += movement.0;
@@ -1330,6 +1990,9 @@ impl<'a> Strtex<'a> {
     ///
     /// Rotation does not mutate the model-space of a sprite.
     pub fn rotate<T: Copy + Into<Rad<f32>>>(&mut self, handle: &Handle, angle: T) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.strtexs[handle.0].rotbuf_touch = self.vx.swapconfig.image_count;
         for rot in &mut self.vx.strtexs[handle.0].rotbuffer[handle.1] {
             *rot += angle.into().0;
@@ -1340,6 +2003,9 @@ impl<'a> Strtex<'a> {
     ///
     /// Scale does not mutate the model-space of a sprite.
     pub fn scale(&mut self, handle: &Handle, scale: f32) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.strtexs[handle.0].scalebuf_touch = self.vx.swapconfig.image_count;
         for sc in &mut self.vx.strtexs[handle.0].scalebuffer[handle.1] {
             *sc *= scale;
@@ -1352,6 +2018,9 @@ impl<'a> Strtex<'a> {
     ///
     /// Applies [Strtex::deform] to each dynamic texture.
     pub fn deform_all(&mut self, layer: &Layer, mut delta: impl FnMut(usize) -> [(f32, f32); 4]) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.strtexs[layer.0].posbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.strtexs[layer.0].posbuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1370,6 +2039,9 @@ impl<'a> Strtex<'a> {
     ///
     /// Applies [Strtex::translate] to each dynamic texture.
     pub fn translate_all(&mut self, layer: &Layer, mut delta: impl FnMut(usize) -> (f32, f32)) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.strtexs[layer.0].tranbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.strtexs[layer.0].tranbuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1382,6 +2054,9 @@ impl<'a> Strtex<'a> {
 
     /// Set opacity on all sprites
     pub fn set_opacity_all(&mut self, layer: &Layer, mut delta: impl FnMut(usize) -> u8) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.strtexs[layer.0].opacbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.strtexs[layer.0].opacbuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1397,6 +2072,9 @@ impl<'a> Strtex<'a> {
         layer: &Layer,
         mut delta: impl FnMut(usize) -> T,
     ) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.strtexs[layer.0].rotbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.strtexs[layer.0].rotbuffer.iter_mut().enumerate() {
             let delta = delta(idx).into().0;
@@ -1410,6 +2088,9 @@ impl<'a> Strtex<'a> {
     ///
     /// Applies [Strtex::scale] to each dynamic texture.
     pub fn scale_all(&mut self, layer: &Layer, mut delta: impl FnMut(usize) -> f32) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.strtexs[layer.0].scalebuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.strtexs[layer.0].scalebuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1429,6 +2110,9 @@ impl<'a> Strtex<'a> {
         layer: &Layer,
         mut delta: impl FnMut(usize) -> [(f32, f32); 4],
     ) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.strtexs[layer.0].posbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.strtexs[layer.0].posbuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1451,6 +2135,9 @@ impl<'a> Strtex<'a> {
         layer: &Layer,
         mut delta: impl FnMut(usize) -> (f32, f32),
     ) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.strtexs[layer.0].tranbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.strtexs[layer.0].tranbuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1465,6 +2152,9 @@ impl<'a> Strtex<'a> {
     ///
     /// Applies [Strtex::set_uv] to each dynamic texture.
     pub fn set_uv_all(&mut self, layer: &Layer, mut delta: impl FnMut(usize) -> [(f32, f32); 2]) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.strtexs[layer.0].uvbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.strtexs[layer.0].uvbuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1485,6 +2175,9 @@ impl<'a> Strtex<'a> {
         layer: &Layer,
         mut delta: impl FnMut(usize) -> T,
     ) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.strtexs[layer.0].rotbuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.strtexs[layer.0].rotbuffer.iter_mut().enumerate() {
             let delta = delta(idx).into().0;
@@ -1497,6 +2190,9 @@ impl<'a> Strtex<'a> {
     /// Applies [Strtex::set_scale] to each dynamic texture.
     /// Note: This may re-enable removed sprites, see [Strtex::remove].
     pub fn set_scale_all(&mut self, layer: &Layer, mut delta: impl FnMut(usize) -> f32) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.strtexs[layer.0].scalebuf_touch = self.vx.swapconfig.image_count;
         for (idx, quad) in self.vx.strtexs[layer.0].scalebuffer.iter_mut().enumerate() {
             let delta = delta(idx);
@@ -1507,6 +2203,9 @@ impl<'a> Strtex<'a> {
 
     /// Read pixels from arbitrary coordinates
     pub fn read(&mut self, id: &Layer, mut map: impl FnMut(&[(u8, u8, u8, u8)], usize)) {
+        if !self.check_layer(id) {
+            return;
+        }
         let s = &mut *self.vx;
         let frame_number = s
             .current_frame
@@ -1546,6 +2245,9 @@ impl<'a> Strtex<'a> {
 
     /// Write pixels to arbitrary coordinates
     pub fn write(&mut self, id: &Layer, mut map: impl FnMut(&mut [(u8, u8, u8, u8)], usize)) {
+        if !self.check_layer(id) {
+            return;
+        }
         let s = &mut *self.vx;
         if let Some(ref strtex) = s.strtexs.get(id.0) {
             for frame in 0..s.swapconfig.image_count {
@@ -1583,6 +2285,9 @@ impl<'a> Strtex<'a> {
 
     /// Write a color to all pixels
     pub fn write_all(&mut self, id: &Layer, color: (u8, u8, u8, u8)) {
+        if !self.check_layer(id) {
+            return;
+        }
         let width = self.vx.strtexs[id.0].width;
         let height = self.vx.strtexs[id.0].height;
         self.write(id, |col, pitch| {
@@ -1594,19 +2299,156 @@ impl<'a> Strtex<'a> {
         });
     }
 
+    /// Request a copy of a region of the texture without stalling
+    ///
+    /// The copy is recorded into the next drawn frame and includes every write done before that
+    /// frame. Use [Strtex::poll_read] on later frames to get the pixels. Returns `None` if the
+    /// region is empty or does not fit inside the texture, or if no buffer could be allocated for
+    /// the copy.
+    pub fn request_read(
+        &mut self,
+        id: &Layer,
+        start: (u32, u32),
+        size: (u32, u32),
+    ) -> Option<ReadTicket> {
+        if !self.check_layer(id) {
+            return None;
+        }
+        {
+            let strtex = &self.vx.strtexs[id.0];
+            let fits = |start: u32, size: u32, limit: u32| {
+                start.checked_add(size).map_or(false, |end| end <= limit)
+            };
+            if !fits(start.0, size.0, strtex.width) || !fits(start.1, size.1, strtex.height) {
+                return None;
+            }
+        }
+        if size.0 == 0 || size.1 == 0 {
+            return None;
+        }
+        let s = &mut *self.vx;
+        let bytes = u64::from(size.0)
+            .checked_mul(u64::from(size.1))?
+            .checked_mul(4)?;
+        let (buffer, memory, _) = match make_transfer_buffer_of_size(s, bytes) {
+            Ok(transfer) => transfer,
+            Err(err) => {
+                error!(s.log, "Unable to create readback buffer"; "error" => %err);
+                return None;
+            }
+        };
+        let ticket = s.next_generation();
+        s.strtexs[id.0].reads.push(StreamingTextureRead {
+            ticket,
+            start,
+            size,
+            buffer,
+            memory,
+            recorded: None,
+            done: false,
+        });
+        Some(ReadTicket(id.0, ticket))
+    }
+
+    /// Check whether a requested read has finished
+    ///
+    /// Never blocks. The pixels are returned once, after which the ticket is [ReadStatus::Unknown].
+    /// If the pixels can not be mapped the read is dropped and the error is returned.
+    pub fn poll_read(&mut self, ticket: &ReadTicket) -> Result<ReadStatus, Error> {
+        let s = &mut *self.vx;
+        let strtex = match s.strtexs.get_mut(ticket.0) {
+            Some(strtex) => strtex,
+            None => return Ok(ReadStatus::Unknown),
+        };
+        let idx = match strtex.reads.iter().position(|x| x.ticket == ticket.1) {
+            Some(idx) => idx,
+            None => return Ok(ReadStatus::Unknown),
+        };
+        let read = &strtex.reads[idx];
+        let finished = read.done
+            || match read.recorded {
+                Some(frame) => unsafe {
+                    s.device
+                        .get_fence_status(&s.frames_in_flight_fences[frame])
+                        .unwrap_or(false)
+                },
+                None => false,
+            };
+        if !finished {
+            return Ok(ReadStatus::Pending);
+        }
+        let read = strtex.reads.swap_remove(idx);
+        let count = read.size.0 as usize * read.size.1 as usize;
+        let pixels = unsafe {
+            match s.device.map_memory(&read.memory, 0..count as u64 * 4) {
+                Ok(source) => {
+                    let pixels =
+                        std::slice::from_raw_parts(source as *const (u8, u8, u8, u8), count)
+                            .to_vec();
+                    s.device.unmap_memory(&read.memory);
+                    Ok(pixels)
+                }
+                Err(err) => Err(err.into()),
+            }
+        };
+        read.destroy(&s.device);
+        pixels.map(ReadStatus::Ready)
+    }
+
     /// Fills the streaming texture with perlin noise generated from an input seed
     pub fn fill_with_perlin_noise(&mut self, blitid: &Layer, seed: [f32; 3]) {
+        if !self.check_layer(blitid) {
+            return;
+        }
+        static FRAGMENT_SOURCE: &[u8] = include_bytes!("../target/spirv/proc1.frag.spirv");
+        let w = self.vx.strtexs[blitid.0].width;
+        let h = self.vx.strtexs[blitid.0].height;
+        let constants = unsafe {
+            std::mem::transmute::<[f32; 4], [u32; 4]>([w as f32, seed[0], seed[1], seed[2]])
+        };
+        self.fill_with_procedure(blitid.0, FRAGMENT_SOURCE, &constants, (0, 0), (w, h));
+    }
+
+    /// Fills the streaming texture, or a region of it, with configurable noise
+    pub fn fill_with_noise(&mut self, layer: &Layer, options: &NoiseOptions) {
+        if !self.check_layer(layer) {
+            return;
+        }
+        static FRAGMENT_SOURCE: &[u8] = include_bytes!("../target/spirv/noise.frag.spirv");
+        let strtex = &self.vx.strtexs[layer.0];
+        let (start, size) = options
+            .region
+            .unwrap_or(((0, 0), (strtex.width, strtex.height)));
+        let fits = |start: u32, size: u32, limit: u32| {
+            start.checked_add(size).map_or(false, |end| end <= limit)
+        };
+        if !fits(start.0, size.0, strtex.width) || !fits(start.1, size.1, strtex.height) {
+            return;
+        }
+        if size.0 == 0 || size.1 == 0 {
+            return;
+        }
+        let constants = options.push_constants(start, size);
+        self.fill_with_procedure(layer.0, FRAGMENT_SOURCE, &constants, start, size);
+    }
+
+    /// Render a full-screen fragment shader into a region of the streaming texture
+    fn fill_with_procedure(
+        &mut self,
+        id: usize,
+        fragment: &[u8],
+        constants: &[u32],
+        start: (u32, u32),
+        (w, h): (u32, u32),
+    ) {
         let s = &mut *self.vx;
-        for circ in &mut s.strtexs[blitid.0].circular_writes {
-            circ.clear();
+        unsafe {
+            flush_writes(s, id).expect("Unable to flush pending writes");
         }
         static VERTEX_SOURCE: &[u8] = include_bytes!("../target/spirv/proc1.vert.spirv");
-        static FRAGMENT_SOURCE: &[u8] = include_bytes!("../target/spirv/proc1.frag.spirv");
-        let w = s.strtexs[blitid.0].width;
-        let h = s.strtexs[blitid.0].height;
 
         let vertex_source = pso::read_spirv(Cursor::new(VERTEX_SOURCE)).unwrap();
-        let fragment_source = pso::read_spirv(Cursor::new(FRAGMENT_SOURCE)).unwrap();
+        let fragment_source = pso::read_spirv(Cursor::new(fragment)).unwrap();
 
         let vs_module = { unsafe { s.device.create_shader_module(&vertex_source) }.unwrap() };
         let fs_module = { unsafe { s.device.create_shader_module(&fragment_source) }.unwrap() };
@@ -1737,7 +2579,10 @@ impl<'a> Strtex<'a> {
                 .expect("Couldn't make a DescriptorSetLayout")
         }];
         let mut push_constants = Vec::<(pso::ShaderStageFlags, core::ops::Range<u32>)>::new();
-        push_constants.push((pso::ShaderStageFlags::FRAGMENT, 0..16));
+        push_constants.push((
+            pso::ShaderStageFlags::FRAGMENT,
+            0..(constants.len() * 4) as u32,
+        ));
 
         let mapgen_pipeline_layout = unsafe {
             s.device
@@ -1795,7 +2640,8 @@ impl<'a> Strtex<'a> {
                 .expect("Unable to create image");
             let requirements = s.device.get_image_requirements(&image);
             let memory_type_id =
-                find_memory_type_id(&s.adapter, requirements, memory::Properties::DEVICE_LOCAL);
+                find_memory_type_id(&s.adapter, requirements, memory::Properties::DEVICE_LOCAL)
+                    .expect("Unable to find memory type id");
             let memory = s
                 .device
                 .allocate_memory(memory_type_id, requirements.size)
@@ -1844,7 +2690,8 @@ impl<'a> Strtex<'a> {
                     -1.0, 1.0,
                     -1.0, -1.0,
                 ],
-            );
+            )
+            .expect("Unable to create vertex buffer");
 
             let mut cmd_buffer = s
                 .command_pool
@@ -1884,9 +2731,7 @@ impl<'a> Strtex<'a> {
                     &mapgen_pipeline_layout,
                     pso::ShaderStageFlags::FRAGMENT,
                     0,
-                    &(std::mem::transmute::<[f32; 4], [u32; 4]>([
-                        w as f32, seed[0], seed[1], seed[2],
-                    ])),
+                    constants,
                 );
                 let buffers: ArrayVec<[_; 1]> = [(&pt_buffer, 0)].into();
                 cmd_buffer.bind_vertex_buffers(0, buffers);
@@ -1908,7 +2753,7 @@ impl<'a> Strtex<'a> {
                 .expect("Unable to wait for fence");
 
             cmd_buffer.begin_primary(CommandBufferFlags::EMPTY);
-            for image_buffer in &s.strtexs[blitid.0].image_buffer {
+            for image_buffer in &s.strtexs[id].image_buffer {
                 cmd_buffer.copy_image(
                     &image,
                     image::Layout::General,
@@ -1926,7 +2771,11 @@ impl<'a> Strtex<'a> {
                             level: 0,
                             layers: 0..1,
                         },
-                        dst_offset: image::Offset { x: 0, y: 0, z: 0 },
+                        dst_offset: image::Offset {
+                            x: start.0 as i32,
+                            y: start.1 as i32,
+                            z: 0,
+                        },
                         extent: image::Extent {
                             width: w,
                             height: h,
@@ -1961,6 +2810,943 @@ impl<'a> Strtex<'a> {
     }
 }
 
+/// Per-frame images, their memory, views, and memory requirements
+type FrameImages = (
+    Vec<<back::Backend as Backend>::Image>,
+    Vec<<back::Backend as Backend>::Memory>,
+    Vec<<back::Backend as Backend>::ImageView>,
+    Vec<memory::Requirements>,
+);
+
+fn make_frame_images(s: &VxDraw, width: u32, height: u32) -> FrameImages {
+    let device = &s.device;
+    let mut the_images = vec![];
+    let mut image_memories = vec![];
+    let mut image_views = vec![];
+    let mut image_requirements = vec![];
+    s.adapter
+        .physical_device
+        .image_format_properties(
+            format::Format::Rgba8Srgb,
+            2,
+            image::Tiling::Linear,
+            image::Usage::SAMPLED | image::Usage::TRANSFER_SRC | image::Usage::TRANSFER_DST,
+            image::ViewCapabilities::empty(),
+        )
+        .expect("Device does not support linear sampled textures");
+    for _ in 0..s.swapconfig.image_count {
+        let mut the_image = unsafe {
+            device
+                .create_image(
+                    image::Kind::D2(width, height, 1, 1),
+                    1,
+                    format::Format::Rgba8Srgb,
+                    image::Tiling::Linear,
+                    image::Usage::SAMPLED | image::Usage::TRANSFER_SRC | image::Usage::TRANSFER_DST,
+                    image::ViewCapabilities::empty(),
+                )
+                .expect("Couldn't create the image!")
+        };
+
+        let requirements = unsafe { device.get_image_requirements(&the_image) };
+        let image_memory = unsafe {
+            let memory_type_id = find_memory_type_id(
+                &s.adapter,
+                requirements,
+                memory::Properties::COHERENT
+                    | memory::Properties::CPU_VISIBLE
+                    | memory::Properties::DEVICE_LOCAL,
+            )
+            .expect("Unable to find memory type id");
+            device
+                .allocate_memory(memory_type_id, requirements.size)
+                .expect("Unable to allocate")
+        };
+
+        let image_view = unsafe {
+            device
+                .bind_image_memory(&image_memory, 0, &mut the_image)
+                .expect("Unable to bind memory");
+
+            device
+                .create_image_view(
+                    &the_image,
+                    image::ViewKind::D2,
+                    format::Format::Rgba8Srgb,
+                    format::Swizzle::NO,
+                    image::SubresourceRange {
+                        aspects: format::Aspects::COLOR,
+                        levels: 0..1,
+                        layers: 0..1,
+                    },
+                )
+                .expect("Couldn't create the image view!")
+        };
+        the_images.push(the_image);
+        image_memories.push(image_memory);
+        image_views.push(image_view);
+        image_requirements.push(requirements);
+    }
+    (the_images, image_memories, image_views, image_requirements)
+}
+
+/// Convert an sRGB color to linear values, the alpha channel is already linear
+fn srgb_to_linear(color: [u8; 4]) -> [f32; 4] {
+    let channel = |value: u8| {
+        let value = f32::from(value) / 255.0;
+        if value <= 0.04045 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    [
+        channel(color[0]),
+        channel(color[1]),
+        channel(color[2]),
+        f32::from(color[3]) / 255.0,
+    ]
+}
+
+/// Fill the 4-connected area of pixels equal to the start pixel with a color
+fn flood_fill(
+    pixels: &mut [(u8, u8, u8, u8)],
+    size: (u32, u32),
+    pitch: usize,
+    start: (u32, u32),
+    color: (u8, u8, u8, u8),
+) {
+    if start.0 >= size.0 || start.1 >= size.1 {
+        return;
+    }
+    let index = |(x, y): (u32, u32)| x as usize + y as usize * pitch;
+    let replaced = pixels[index(start)];
+    if replaced == color {
+        return;
+    }
+    let mut stack = vec![start];
+    while let Some((x, y)) = stack.pop() {
+        if pixels[index((x, y))] != replaced {
+            continue;
+        }
+        pixels[index((x, y))] = color;
+        if x > 0 {
+            stack.push((x - 1, y));
+        }
+        if x + 1 < size.0 {
+            stack.push((x + 1, y));
+        }
+        if y > 0 {
+            stack.push((x, y - 1));
+        }
+        if y + 1 < size.1 {
+            stack.push((x, y + 1));
+        }
+    }
+}
+
+/// GPU objects created by [rasterize], destroyed together once it is done or has failed
+#[derive(Default)]
+struct RasterObjects {
+    textures: Vec<(
+        <back::Backend as Backend>::Image,
+        <back::Backend as Backend>::Memory,
+        <back::Backend as Backend>::ImageView,
+    )>,
+    shader_modules: Vec<<back::Backend as Backend>::ShaderModule>,
+    render_pass: Option<<back::Backend as Backend>::RenderPass>,
+    descriptor_set_layout: Option<<back::Backend as Backend>::DescriptorSetLayout>,
+    descriptor_pool: Option<<back::Backend as Backend>::DescriptorPool>,
+    sampler: Option<<back::Backend as Backend>::Sampler>,
+    pipeline_layout: Option<<back::Backend as Backend>::PipelineLayout>,
+    pipeline: Option<<back::Backend as Backend>::GraphicsPipeline>,
+    target: Option<<back::Backend as Backend>::Image>,
+    target_memory: Option<<back::Backend as Backend>::Memory>,
+    target_view: Option<<back::Backend as Backend>::ImageView>,
+    framebuffer: Option<<back::Backend as Backend>::Framebuffer>,
+    vertex_buffer: Option<<back::Backend as Backend>::Buffer>,
+    vertex_memory: Option<<back::Backend as Backend>::Memory>,
+}
+
+impl RasterObjects {
+    unsafe fn destroy(self, device: &back::Device) {
+        if let Some(buffer) = self.vertex_buffer {
+            device.destroy_buffer(buffer);
+        }
+        if let Some(memory) = self.vertex_memory {
+            device.free_memory(memory);
+        }
+        if let Some(framebuffer) = self.framebuffer {
+            device.destroy_framebuffer(framebuffer);
+        }
+        if let Some(view) = self.target_view {
+            device.destroy_image_view(view);
+        }
+        if let Some(image) = self.target {
+            device.destroy_image(image);
+        }
+        if let Some(memory) = self.target_memory {
+            device.free_memory(memory);
+        }
+        if let Some(pipeline) = self.pipeline {
+            device.destroy_graphics_pipeline(pipeline);
+        }
+        if let Some(layout) = self.pipeline_layout {
+            device.destroy_pipeline_layout(layout);
+        }
+        if let Some(pool) = self.descriptor_pool {
+            device.destroy_descriptor_pool(pool);
+        }
+        if let Some(layout) = self.descriptor_set_layout {
+            device.destroy_descriptor_set_layout(layout);
+        }
+        if let Some(sampler) = self.sampler {
+            device.destroy_sampler(sampler);
+        }
+        if let Some(render_pass) = self.render_pass {
+            device.destroy_render_pass(render_pass);
+        }
+        for module in self.shader_modules {
+            device.destroy_shader_module(module);
+        }
+        for (texture, memory, image_view) in self.textures {
+            device.destroy_image_view(image_view);
+            device.destroy_image(texture);
+            device.free_memory(memory);
+        }
+    }
+}
+
+/// Draw shapes and stamps into a streaming texture in a single render pass
+///
+/// The texture is copied into an intermediate color attachment, drawn upon, and copied back
+/// into the image of every frame.
+unsafe fn rasterize(
+    s: &mut VxDraw,
+    id: usize,
+    ops: &[CanvasOp],
+    blend: &blender::Blender,
+) -> Result<(), Error> {
+    let mut objects = RasterObjects::default();
+    let drawn = draw_canvas_ops(s, id, ops, blend, &mut objects);
+    objects.destroy(&s.device);
+    drawn
+}
+
+/// Record and submit the draws of [rasterize], keeping every created object in `objects`
+unsafe fn draw_canvas_ops(
+    s: &mut VxDraw,
+    id: usize,
+    ops: &[CanvasOp],
+    blend: &blender::Blender,
+    objects: &mut RasterObjects,
+) -> Result<(), Error> {
+    let (w, h) = (s.strtexs[id].width, s.strtexs[id].height);
+
+    let mut vertices: Vec<f32> = vec![];
+    let mut draws = vec![];
+    let mut stamps = vec![];
+    for op in ops {
+        let begin = (vertices.len() / 8) as u32;
+        match op {
+            CanvasOp::Triangles(triangles) => {
+                for vertex in triangles {
+                    vertices.extend(vertex.iter());
+                }
+                draws.push((begin..(vertices.len() / 8) as u32, 0));
+            }
+            CanvasOp::Stamp {
+                position: (x, y),
+                width,
+                height,
+                pixels,
+            } => {
+                let (x2, y2) = (x + *width as f32, y + *height as f32);
+                #[rustfmt::skip]
+                vertices.extend(&[
+                    *x, *y, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0,
+                    x2, *y, 1.0, 0.0, 1.0, 1.0, 1.0, 1.0,
+                    x2, y2, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0,
+                    x2, y2, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0,
+                    *x, y2, 0.0, 1.0, 1.0, 1.0, 1.0, 1.0,
+                    *x, *y, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0,
+                ]);
+                stamps.push((*width, *height, pixels));
+                draws.push((begin..begin + 6, stamps.len()));
+            }
+            CanvasOp::FloodFill(..) => {}
+        }
+    }
+    draws.retain(|(range, _)| range.start != range.end);
+    if draws.is_empty() {
+        return Ok(());
+    }
+
+    // The first texture is a single white pixel so shapes can share the stamp pipeline
+    objects
+        .textures
+        .push(dyntex::upload_texture(s, 1, 1, &[255, 255, 255, 255]));
+    for (width, height, pixels) in stamps {
+        objects
+            .textures
+            .push(dyntex::upload_texture(s, width, height, &pixels[..]));
+    }
+    let textures = &objects.textures;
+
+    static VERTEX_SOURCE: &[u8] = include_bytes!("../target/spirv/canvas.vert.spirv");
+    static FRAGMENT_SOURCE: &[u8] = include_bytes!("../target/spirv/canvas.frag.spirv");
+    let vertex_source = pso::read_spirv(Cursor::new(VERTEX_SOURCE))?;
+    let fragment_source = pso::read_spirv(Cursor::new(FRAGMENT_SOURCE))?;
+    objects
+        .shader_modules
+        .push(s.device.create_shader_module(&vertex_source)?);
+    objects
+        .shader_modules
+        .push(s.device.create_shader_module(&fragment_source)?);
+    const ENTRY_NAME: &str = "main";
+    let shader_entries = pso::GraphicsShaderSet {
+        vertex: pso::EntryPoint {
+            entry: ENTRY_NAME,
+            module: &objects.shader_modules[0],
+            specialization: pso::Specialization::default(),
+        },
+        hull: None,
+        domain: None,
+        geometry: None,
+        fragment: Some(pso::EntryPoint {
+            entry: ENTRY_NAME,
+            module: &objects.shader_modules[1],
+            specialization: pso::Specialization::default(),
+        }),
+    };
+
+    let vertex_buffers = vec![pso::VertexBufferDesc {
+        binding: 0,
+        stride: 32,
+        rate: pso::VertexInputRate::Vertex,
+    }];
+    let attributes = vec![
+        pso::AttributeDesc {
+            location: 0,
+            binding: 0,
+            element: pso::Element {
+                format: format::Format::Rg32Sfloat,
+                offset: 0,
+            },
+        },
+        pso::AttributeDesc {
+            location: 1,
+            binding: 0,
+            element: pso::Element {
+                format: format::Format::Rg32Sfloat,
+                offset: 8,
+            },
+        },
+        pso::AttributeDesc {
+            location: 2,
+            binding: 0,
+            element: pso::Element {
+                format: format::Format::Rgba32Sfloat,
+                offset: 16,
+            },
+        },
+    ];
+
+    let rasterizer = pso::Rasterizer {
+        depth_clamping: false,
+        polygon_mode: pso::PolygonMode::Fill,
+        cull_face: pso::Face::NONE,
+        front_face: pso::FrontFace::CounterClockwise,
+        depth_bias: None,
+        conservative: false,
+    };
+
+    let depth_stencil = pso::DepthStencilDesc {
+        depth: None,
+        depth_bounds: false,
+        stencil: None,
+    };
+
+    let extent = image::Extent {
+        width: w,
+        height: h,
+        depth: 1,
+    }
+    .rect();
+
+    let render_pass = {
+        let attachment = pass::Attachment {
+            format: Some(format::Format::Rgba8Srgb),
+            samples: 1,
+            ops: pass::AttachmentOps::new(
+                pass::AttachmentLoadOp::Load,
+                pass::AttachmentStoreOp::Store,
+            ),
+            stencil_ops: pass::AttachmentOps::DONT_CARE,
+            layouts: image::Layout::General..image::Layout::General,
+        };
+        let subpass = pass::SubpassDesc {
+            colors: &[(0, image::Layout::General)],
+            depth_stencil: None,
+            inputs: &[],
+            resolves: &[],
+            preserves: &[],
+        };
+        let render_pass = s
+            .device
+            .create_render_pass(&[attachment], &[subpass], &[])?;
+        &*objects.render_pass.get_or_insert(render_pass)
+    };
+
+    let baked_states = pso::BakedStates {
+        viewport: Some(pso::Viewport {
+            rect: extent,
+            depth: (0.0..1.0),
+        }),
+        scissor: Some(extent),
+        blend_color: None,
+        depth_bounds: None,
+    };
+
+    let bindings = vec![
+        pso::DescriptorSetLayoutBinding {
+            binding: 0,
+            ty: pso::DescriptorType::SampledImage,
+            count: 1,
+            stage_flags: pso::ShaderStageFlags::FRAGMENT,
+            immutable_samplers: false,
+        },
+        pso::DescriptorSetLayoutBinding {
+            binding: 1,
+            ty: pso::DescriptorType::Sampler,
+            count: 1,
+            stage_flags: pso::ShaderStageFlags::FRAGMENT,
+            immutable_samplers: false,
+        },
+    ];
+    let immutable_samplers = Vec::<<back::Backend as Backend>::Sampler>::new();
+    let descriptor_set_layout = s
+        .device
+        .create_descriptor_set_layout(bindings, immutable_samplers)?;
+    let descriptor_set_layout = &*objects
+        .descriptor_set_layout
+        .get_or_insert(descriptor_set_layout);
+    let descriptor_pool = s.device.create_descriptor_pool(
+        textures.len(),
+        &[
+            pso::DescriptorRangeDesc {
+                ty: pso::DescriptorType::SampledImage,
+                count: textures.len(),
+            },
+            pso::DescriptorRangeDesc {
+                ty: pso::DescriptorType::Sampler,
+                count: textures.len(),
+            },
+        ],
+        pso::DescriptorPoolCreateFlags::empty(),
+    )?;
+    let descriptor_pool = objects.descriptor_pool.get_or_insert(descriptor_pool);
+    let sampler = s.device.create_sampler(&image::SamplerDesc::new(
+        image::Filter::Nearest,
+        image::WrapMode::Clamp,
+    ))?;
+    let sampler = &*objects.sampler.get_or_insert(sampler);
+    let descriptor_sets = textures
+        .iter()
+        .map(|(_, _, image_view)| {
+            let set = descriptor_pool.allocate_set(descriptor_set_layout)?;
+            s.device.write_descriptor_sets(vec![
+                pso::DescriptorSetWrite {
+                    set: &set,
+                    binding: 0,
+                    array_offset: 0,
+                    descriptors: Some(pso::Descriptor::Image(
+                        image_view,
+                        image::Layout::ShaderReadOnlyOptimal,
+                    )),
+                },
+                pso::DescriptorSetWrite {
+                    set: &set,
+                    binding: 1,
+                    array_offset: 0,
+                    descriptors: Some(pso::Descriptor::Sampler(sampler)),
+                },
+            ]);
+            Ok(set)
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let pipeline_layout = s.device.create_pipeline_layout(
+        once(descriptor_set_layout),
+        once((pso::ShaderStageFlags::VERTEX, 0..8)),
+    )?;
+    let pipeline_layout = &*objects.pipeline_layout.get_or_insert(pipeline_layout);
+
+    let pipeline_desc = pso::GraphicsPipelineDesc {
+        shaders: shader_entries,
+        rasterizer,
+        vertex_buffers,
+        attributes,
+        input_assembler: pso::InputAssemblerDesc::new(Primitive::TriangleList),
+        blender: blend.clone().into_gfx_blender(),
+        depth_stencil,
+        multisampling: None,
+        baked_states,
+        layout: pipeline_layout,
+        subpass: pass::Subpass {
+            index: 0,
+            main_pass: render_pass,
+        },
+        flags: pso::PipelineCreationFlags::empty(),
+        parent: pso::BasePipeline::None,
+    };
+    let pipeline = s.device.create_graphics_pipeline(&pipeline_desc, None)?;
+    let pipeline = &*objects.pipeline.get_or_insert(pipeline);
+
+    let target = s.device.create_image(
+        image::Kind::D2(w, h, 1, 1),
+        1,
+        format::Format::Rgba8Srgb,
+        image::Tiling::Optimal,
+        image::Usage::COLOR_ATTACHMENT | image::Usage::TRANSFER_SRC | image::Usage::TRANSFER_DST,
+        image::ViewCapabilities::empty(),
+    )?;
+    let target = objects.target.get_or_insert(target);
+    let requirements = s.device.get_image_requirements(target);
+    let memory_type_id =
+        find_memory_type_id(&s.adapter, requirements, memory::Properties::DEVICE_LOCAL)?;
+    let target_memory = s
+        .device
+        .allocate_memory(memory_type_id, requirements.size)?;
+    let target_memory = &*objects.target_memory.get_or_insert(target_memory);
+    s.device.bind_image_memory(target_memory, 0, target)?;
+    let target = &*target;
+    let target_view = s.device.create_image_view(
+        target,
+        image::ViewKind::D2,
+        format::Format::Rgba8Srgb,
+        format::Swizzle::NO,
+        image::SubresourceRange {
+            aspects: format::Aspects::COLOR,
+            levels: 0..1,
+            layers: 0..1,
+        },
+    )?;
+    let target_view = &*objects.target_view.get_or_insert(target_view);
+    let framebuffer = s.device.create_framebuffer(
+        render_pass,
+        vec![target_view],
+        image::Extent {
+            width: w,
+            height: h,
+            depth: 1,
+        },
+    )?;
+    let framebuffer = &*objects.framebuffer.get_or_insert(framebuffer);
+
+    let (vertex_buffer, vertex_memory, _) = make_vertex_buffer_with_data(s, &vertices[..])?;
+    objects.vertex_memory = Some(vertex_memory);
+    let vertex_buffer = &*objects.vertex_buffer.get_or_insert(vertex_buffer);
+
+    let whole = command::ImageCopy {
+        src_subresource: image::SubresourceLayers {
+            aspects: format::Aspects::COLOR,
+            level: 0,
+            layers: 0..1,
+        },
+        src_offset: image::Offset { x: 0, y: 0, z: 0 },
+        dst_subresource: image::SubresourceLayers {
+            aspects: format::Aspects::COLOR,
+            level: 0,
+            layers: 0..1,
+        },
+        dst_offset: image::Offset { x: 0, y: 0, z: 0 },
+        extent: image::Extent {
+            width: w,
+            height: h,
+            depth: 1,
+        },
+    };
+    let range = image::SubresourceRange {
+        aspects: format::Aspects::COLOR,
+        levels: 0..1,
+        layers: 0..1,
+    };
+
+    let mut cmd_buffer = s
+        .command_pool
+        .allocate_one(gfx_hal::command::Level::Primary);
+    cmd_buffer.begin_primary(CommandBufferFlags::ONE_TIME_SUBMIT);
+    cmd_buffer.pipeline_barrier(
+        pso::PipelineStage::TOP_OF_PIPE..pso::PipelineStage::TRANSFER,
+        memory::Dependencies::empty(),
+        &[memory::Barrier::Image {
+            states: (image::Access::empty(), image::Layout::Undefined)
+                ..(image::Access::TRANSFER_WRITE, image::Layout::General),
+            target,
+            families: None,
+            range: range.clone(),
+        }],
+    );
+    cmd_buffer.copy_image(
+        &s.strtexs[id].image_buffer[0],
+        image::Layout::General,
+        target,
+        image::Layout::General,
+        once(whole.clone()),
+    );
+    cmd_buffer.pipeline_barrier(
+        pso::PipelineStage::TRANSFER..pso::PipelineStage::COLOR_ATTACHMENT_OUTPUT,
+        memory::Dependencies::empty(),
+        &[memory::Barrier::Image {
+            states: (image::Access::TRANSFER_WRITE, image::Layout::General)
+                ..(
+                    image::Access::COLOR_ATTACHMENT_READ | image::Access::COLOR_ATTACHMENT_WRITE,
+                    image::Layout::General,
+                ),
+            target,
+            families: None,
+            range: range.clone(),
+        }],
+    );
+    cmd_buffer.begin_render_pass(
+        render_pass,
+        framebuffer,
+        extent,
+        std::iter::empty::<command::ClearValue>(),
+        command::SubpassContents::Inline,
+    );
+    cmd_buffer.bind_graphics_pipeline(pipeline);
+    cmd_buffer.push_graphics_constants(
+        pipeline_layout,
+        pso::ShaderStageFlags::VERTEX,
+        0,
+        &(std::mem::transmute::<[f32; 2], [u32; 2]>([w as f32, h as f32])),
+    );
+    let buffers: ArrayVec<[_; 1]> = [(vertex_buffer, 0)].into();
+    cmd_buffer.bind_vertex_buffers(0, buffers);
+    for (vertex_range, texture) in draws {
+        cmd_buffer.bind_graphics_descriptor_sets(
+            pipeline_layout,
+            0,
+            Some(&descriptor_sets[texture]),
+            &[],
+        );
+        cmd_buffer.draw(vertex_range, 0..1);
+    }
+    cmd_buffer.end_render_pass();
+    cmd_buffer.pipeline_barrier(
+        pso::PipelineStage::COLOR_ATTACHMENT_OUTPUT..pso::PipelineStage::TRANSFER,
+        memory::Dependencies::empty(),
+        &[memory::Barrier::Image {
+            states: (
+                image::Access::COLOR_ATTACHMENT_WRITE,
+                image::Layout::General,
+            )..(image::Access::TRANSFER_READ, image::Layout::General),
+            target,
+            families: None,
+            range,
+        }],
+    );
+    for image_buffer in &s.strtexs[id].image_buffer {
+        cmd_buffer.copy_image(
+            target,
+            image::Layout::General,
+            image_buffer,
+            image::Layout::General,
+            once(whole.clone()),
+        );
+    }
+    cmd_buffer.finish();
+    submit_and_wait(s, cmd_buffer)
+}
+
+/// Compute the region that survives a resize
+///
+/// Returns the source offset, destination offset, and extent, or `None` if nothing overlaps.
+fn resize_overlap(
+    anchor: Anchor,
+    old: (u32, u32),
+    new: (u32, u32),
+) -> Option<((u32, u32), (u32, u32), (u32, u32))> {
+    let (fx, fy) = match anchor {
+        Anchor::TopLeft => (0, 0),
+        Anchor::Top => (1, 0),
+        Anchor::TopRight => (2, 0),
+        Anchor::Left => (0, 1),
+        Anchor::Center => (1, 1),
+        Anchor::Right => (2, 1),
+        Anchor::BottomLeft => (0, 2),
+        Anchor::Bottom => (1, 2),
+        Anchor::BottomRight => (2, 2),
+    };
+    // Position of the old top-left corner inside the new texture, per axis
+    let axis = |factor: i64, old: u32, new: u32| {
+        let offset = (i64::from(new) - i64::from(old)) * factor / 2;
+        let src = (-offset).max(0);
+        let dst = offset.max(0);
+        let extent = (i64::from(old) - src).min(i64::from(new) - dst);
+        (src as u32, dst as u32, extent.max(0) as u32)
+    };
+    let (sx, dx, w) = axis(fx, old.0, new.0);
+    let (sy, dy, h) = axis(fy, old.1, new.1);
+    if w == 0 || h == 0 {
+        return None;
+    }
+    Some(((sx, sy), (dx, dy), (w, h)))
+}
+
+fn transition_to_general(s: &mut VxDraw, images: &[<back::Backend as Backend>::Image]) {
+    unsafe {
+        let barrier_fence = s.device.create_fence(false).expect("unable to make fence");
+        // TODO Use a proper command buffer here
+        s.device.wait_idle().unwrap();
+        let buffer = &mut s.command_buffers[s.current_frame];
+        buffer.begin_primary(CommandBufferFlags::EMPTY);
+        for the_image in images {
+            let image_barrier = memory::Barrier::Image {
+                states: (image::Access::empty(), image::Layout::Undefined)
+                    ..(
+                        // image::Access::HOST_READ | image::Access::HOST_WRITE,
+                        image::Access::empty(),
+                        image::Layout::General,
+                    ),
+                target: the_image,
+                families: None,
+                range: image::SubresourceRange {
+                    aspects: format::Aspects::COLOR,
+                    levels: 0..1,
+                    layers: 0..1,
+                },
+            };
+            buffer.pipeline_barrier(
+                pso::PipelineStage::TOP_OF_PIPE..pso::PipelineStage::HOST,
+                memory::Dependencies::empty(),
+                &[image_barrier],
+            );
+        }
+        buffer.finish();
+        s.queue_group.queues[0].submit_without_semaphores(Some(&*buffer), Some(&barrier_fence));
+        s.device
+            .wait_for_fence(&barrier_fence, u64::max_value())
+            .unwrap();
+        s.device.destroy_fence(barrier_fence);
+    }
+}
+
+/// Record the copies of all requested reads that are not yet recorded
+pub(crate) unsafe fn record_reads(
+    cmd_buffer: &mut <back::Backend as Backend>::CommandBuffer,
+    strtex: &mut StreamingTexture,
+    frame: usize,
+) {
+    for read in strtex.reads.iter_mut().filter(|x| x.recorded.is_none()) {
+        cmd_buffer.copy_image_to_buffer(
+            &strtex.image_buffer[frame],
+            image::Layout::General,
+            &read.buffer,
+            once(region_copy(read.start, read.size)),
+        );
+        cmd_buffer.pipeline_barrier(
+            pso::PipelineStage::TRANSFER..pso::PipelineStage::HOST,
+            memory::Dependencies::empty(),
+            &[memory::Barrier::Buffer {
+                states: buffer::Access::TRANSFER_WRITE..buffer::Access::HOST_READ,
+                target: &read.buffer,
+                families: None,
+                range: None..None,
+            }],
+        );
+        read.recorded = Some(frame);
+    }
+}
+
+/// Apply all queued writes of a streaming texture to the image of a frame
+pub(crate) unsafe fn apply_writes(
+    device: &back::Device,
+    strtex: &StreamingTexture,
+    frame: usize,
+) -> Result<(), Error> {
+    let foot = device.get_image_subresource_footprint(
+        &strtex.image_buffer[frame],
+        image::Subresource {
+            aspects: format::Aspects::COLOR,
+            level: 0,
+            layer: 0,
+        },
+    );
+
+    let target = device.map_memory(
+        &strtex.image_memory[frame],
+        0..strtex.image_requirements[frame].size,
+    )?;
+
+    for items in &strtex.circular_writes {
+        for item in items {
+            match item {
+                StreamingTextureWrite::Single((x, y), color) => {
+                    if !(*x < strtex.width && *y < strtex.height) {
+                        continue;
+                    }
+                    let access = foot.row_pitch * u64::from(*y) + u64::from(*x * 4);
+                    std::slice::from_raw_parts_mut(target, (access + 4) as usize)
+                        [access as usize..(access + 4) as usize]
+                        .copy_from_slice(&[color.0, color.1, color.2, color.3]);
+                }
+                StreamingTextureWrite::Block((x, y), (w, h), color) => {
+                    for idx in *y..*y + h {
+                        let pitch = foot.row_pitch as usize;
+                        for x in *x..*x + w {
+                            let idx = (idx as usize * pitch + x as usize * 4) as usize;
+                            std::slice::from_raw_parts_mut(target, idx + 4)[idx..idx + 4]
+                                .copy_from_slice(&[color.0, color.1, color.2, color.3]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    device.unmap_memory(&strtex.image_memory[frame]);
+    Ok(())
+}
+
+/// Apply the queued writes to every frame and clear the queues
+///
+/// Used before immediate copies so that older queued writes don't overwrite newer pixels.
+unsafe fn flush_writes(s: &mut VxDraw, id: usize) -> Result<(), Error> {
+    let strtex = &mut s.strtexs[id];
+    if strtex.circular_writes.iter().all(|x| x.is_empty()) {
+        return Ok(());
+    }
+    for frame in 0..strtex.image_buffer.len() {
+        apply_writes(&s.device, strtex, frame)?;
+    }
+    for circ in &mut strtex.circular_writes {
+        circ.clear();
+    }
+    Ok(())
+}
+
+fn region_copy(start: (u32, u32), wh: (u32, u32)) -> command::BufferImageCopy {
+    command::BufferImageCopy {
+        buffer_offset: 0,
+        buffer_width: wh.0,
+        buffer_height: wh.1,
+        image_layers: image::SubresourceLayers {
+            aspects: format::Aspects::COLOR,
+            level: 0,
+            layers: 0..1,
+        },
+        image_offset: image::Offset {
+            x: start.0 as i32,
+            y: start.1 as i32,
+            z: 0,
+        },
+        image_extent: image::Extent {
+            width: wh.0,
+            height: wh.1,
+            depth: 1,
+        },
+    }
+}
+
+unsafe fn copy_buffer_to_frames(
+    cmd_buffer: &mut <back::Backend as Backend>::CommandBuffer,
+    buffer: &<back::Backend as Backend>::Buffer,
+    strtex: &StreamingTexture,
+    start: (u32, u32),
+    wh: (u32, u32),
+) {
+    for image_buffer in &strtex.image_buffer {
+        cmd_buffer.copy_buffer_to_image(
+            buffer,
+            image_buffer,
+            image::Layout::General,
+            once(region_copy(start, wh)),
+        );
+    }
+}
+
+unsafe fn submit_and_wait(
+    s: &mut VxDraw,
+    cmd_buffer: <back::Backend as Backend>::CommandBuffer,
+) -> Result<(), Error> {
+    let fence = match s.device.create_fence(false) {
+        Ok(fence) => fence,
+        Err(err) => {
+            s.command_pool.free(once(cmd_buffer));
+            return Err(err.into());
+        }
+    };
+    s.queue_group.queues[0].submit_without_semaphores(Some(&cmd_buffer), Some(&fence));
+    let waited = s.device.wait_for_fence(&fence, u64::max_value());
+    s.device.destroy_fence(fence);
+    s.command_pool.free(once(cmd_buffer));
+    waited?;
+    Ok(())
+}
+
+/// Zero the images of a resized texture and copy the overlapping pixels of the old texture
+unsafe fn fill_resized_images(
+    s: &mut VxDraw,
+    id: usize,
+    the_images: &[<back::Backend as Backend>::Image],
+    image_memories: &[<back::Backend as Backend>::Memory],
+    image_requirements: &[memory::Requirements],
+    overlap: Option<((u32, u32), (u32, u32), (u32, u32))>,
+) -> Result<(), Error> {
+    for (image_memory, requirements) in image_memories.iter().zip(image_requirements) {
+        let target = s.device.map_memory(image_memory, 0..requirements.size)?;
+        ptr::write_bytes(target, 0, requirements.size as usize);
+        s.device.unmap_memory(image_memory);
+    }
+
+    if let Some((src, dst, extent)) = overlap {
+        let mut cmd_buffer = s
+            .command_pool
+            .allocate_one(gfx_hal::command::Level::Primary);
+        cmd_buffer.begin_primary(CommandBufferFlags::ONE_TIME_SUBMIT);
+        for the_image in the_images {
+            cmd_buffer.copy_image(
+                &s.strtexs[id].image_buffer[0],
+                image::Layout::General,
+                the_image,
+                image::Layout::General,
+                once(command::ImageCopy {
+                    src_subresource: image::SubresourceLayers {
+                        aspects: format::Aspects::COLOR,
+                        level: 0,
+                        layers: 0..1,
+                    },
+                    src_offset: image::Offset {
+                        x: src.0 as i32,
+                        y: src.1 as i32,
+                        z: 0,
+                    },
+                    dst_subresource: image::SubresourceLayers {
+                        aspects: format::Aspects::COLOR,
+                        level: 0,
+                        layers: 0..1,
+                    },
+                    dst_offset: image::Offset {
+                        x: dst.0 as i32,
+                        y: dst.1 as i32,
+                        z: 0,
+                    },
+                    extent: image::Extent {
+                        width: extent.0,
+                        height: extent.1,
+                        depth: 1,
+                    },
+                }),
+            );
+        }
+        cmd_buffer.finish();
+        submit_and_wait(s, cmd_buffer)?;
+    }
+    Ok(())
+}
+
 // ---
 
 #[cfg(test)]
@@ -2095,6 +3881,281 @@ mod tests {
         assert_eq!(124, green_value);
     }
 
+    #[test]
+    fn set_pixels_from_slice_is_readable_immediately() {
+        let logger = Logger::root(Discard, o!());
+        let event_loop = EventLoop::new_any_thread();
+        let mut vx = VxDraw::new(logger, ShowWindow::Headless1k, &event_loop);
+
+        let mut strtex = vx.strtex();
+        let id = strtex.add_layer(&LayerOptions::new().width(10).height(10));
+        let pixels = (0..2 * 3)
+            .flat_map(|idx| vec![idx as u8, 100, 0, 255])
+            .collect::<Vec<_>>();
+        strtex
+            .set_pixels_from_slice(&id, (4, 5), (2, 3), &pixels[..])
+            .unwrap();
+        strtex
+            .set_pixels_from_slice(&id, (9, 9), (2, 3), &pixels[..])
+            .unwrap();
+        strtex
+            .set_pixels_from_slice(&id, (u32::max_value(), 0), (2, 3), &pixels[..])
+            .unwrap();
+        assert![strtex
+            .set_pixels_from_slice(&id, (0, 0), (2, 2), &pixels[..])
+            .is_err()];
+
+        let mut values = vec![];
+        strtex.read(&id, |arr, pitch| {
+            values.push(arr[4 + 5 * pitch]);
+            values.push(arr[5 + 7 * pitch]);
+            values.push(arr[3 + 5 * pitch]);
+        });
+        assert_eq!((0, 100, 0, 255), values[0]);
+        assert_eq!((5, 100, 0, 255), values[1]);
+        assert_ne!((0, 100, 0, 255), values[2]);
+    }
+
+    #[test]
+    fn copy_region_keeps_queued_writes_in_order() {
+        let logger = Logger::root(Discard, o!());
+        let event_loop = EventLoop::new_any_thread();
+        let mut vx = VxDraw::new(logger, ShowWindow::Headless1k, &event_loop);
+
+        let mut strtex = vx.strtex();
+        let src = strtex.add_layer(&LayerOptions::new().width(10).height(10));
+        let dst = strtex.add_layer(&LayerOptions::new().width(20).height(20));
+        strtex.set_pixels_block(&src, (0, 0), (2, 2), Color::Rgba(0, 200, 0, 255));
+        strtex.set_pixel(&dst, 15, 15, Color::Rgba(255, 0, 0, 255));
+        strtex
+            .copy_region(&src, (0, 0), (2, 2), &dst, (14, 14))
+            .unwrap();
+        strtex
+            .copy_region(&src, (0, 0), (2, 2), &dst, (u32::max_value(), 14))
+            .unwrap();
+
+        let mut values = vec![];
+        vx.draw_frame();
+        vx.strtex().read(&dst, |arr, pitch| {
+            values.push(arr[14 + 14 * pitch]);
+            values.push(arr[15 + 15 * pitch]);
+        });
+        assert_eq!((0, 200, 0, 255), values[0]);
+        assert_eq!((0, 200, 0, 255), values[1]);
+    }
+
+    #[test]
+    fn resize_overlap_follows_the_anchor() {
+        assert_eq![
+            Some(((0, 0), (0, 0), (10, 10))),
+            resize_overlap(Anchor::TopLeft, (10, 10), (20, 30))
+        ];
+        assert_eq![
+            Some(((0, 0), (10, 20), (10, 10))),
+            resize_overlap(Anchor::BottomRight, (10, 10), (20, 30))
+        ];
+        assert_eq![
+            Some(((0, 0), (5, 10), (10, 10))),
+            resize_overlap(Anchor::Center, (10, 10), (20, 30))
+        ];
+        assert_eq![
+            Some(((3, 0), (0, 0), (4, 10))),
+            resize_overlap(Anchor::Top, (10, 10), (4, 10))
+        ];
+        assert_eq![
+            Some(((6, 6), (0, 0), (4, 4))),
+            resize_overlap(Anchor::BottomRight, (10, 10), (4, 4))
+        ];
+    }
+
+    #[test]
+    fn resize_keeps_pixels_and_sprites() {
+        let logger = Logger::root(Discard, o!());
+        let event_loop = EventLoop::new_any_thread();
+        let mut vx = VxDraw::new(logger, ShowWindow::Headless1k, &event_loop);
+
+        let mut strtex = vx.strtex();
+        let id = strtex.add_layer(&LayerOptions::new().width(10).height(10));
+        let sprite = strtex.add(&id, strtex::Sprite::new());
+        strtex.set_pixel(&id, 1, 2, Color::Rgba(0, 123, 0, 255));
+        strtex.resize(&id, (20, 15), Anchor::BottomRight).unwrap();
+        strtex.set_pixel(&id, 19, 14, Color::Rgba(0, 0, 45, 255));
+        strtex.set_translation(&sprite, (0.5, 0.5));
+        assert_eq![1, strtex.sprite_count(&id)];
+
+        let mut values = vec![];
+        vx.draw_frame();
+        vx.strtex().read(&id, |arr, pitch| {
+            values.push(arr[11 + 7 * pitch]);
+            values.push(arr[19 + 14 * pitch]);
+            values.push(arr[pitch]);
+        });
+        assert_eq!((0, 123, 0, 255), values[0]);
+        assert_eq!((0, 0, 45, 255), values[1]);
+        assert_eq!((0, 0, 0, 0), values[2]);
+    }
+
+    #[test]
+    fn flood_fill_stops_at_other_colors() {
+        let (a, b, c) = ((0, 0, 0, 0), (1, 1, 1, 1), (9, 9, 9, 9));
+        #[rustfmt::skip]
+        let mut pixels = vec![
+            a, a, b, a, c,
+            a, b, a, a, c,
+            b, a, a, b, c,
+        ];
+        flood_fill(&mut pixels, (4, 3), 5, (3, 0), (5, 5, 5, 5));
+        let f = (5, 5, 5, 5);
+        #[rustfmt::skip]
+        assert_eq![
+            vec![
+                a, a, b, f, c,
+                a, b, f, f, c,
+                b, f, f, b, c,
+            ],
+            pixels
+        ];
+    }
+
+    #[test]
+    fn paint_canvas_into_layer() {
+        let logger = Logger::root(Discard, o!());
+        let event_loop = EventLoop::new_any_thread();
+        let mut vx = VxDraw::new(logger, ShowWindow::Headless1k, &event_loop);
+
+        let mut strtex = vx.strtex();
+        let id = strtex.add_layer(&LayerOptions::new().width(40).height(40));
+        strtex.set_pixel(&id, 0, 0, Color::Rgba(1, 2, 3, 255));
+        let canvas = Canvas::new()
+            .polygon(
+                &[(0.0, 20.0), (20.0, 20.0), (20.0, 40.0)],
+                Color::Rgba(0, 0, 255, 255),
+            )
+            .line((0.0, 5.0), (40.0, 5.0), 2.0, Color::Rgba(255, 0, 0, 255))
+            .circle((30.0, 30.0), 5.0, Color::Rgba(0, 255, 0, 255))
+            .flood_fill((39, 0), Color::Rgba(100, 100, 100, 255));
+        strtex.paint(&id, &canvas).unwrap();
+
+        let mut values = vec![];
+        strtex.read(&id, |arr, pitch| {
+            values.push(arr[0]);
+            values.push(arr[10 + 5 * pitch]);
+            values.push(arr[30 + 30 * pitch]);
+            values.push(arr[15 + 25 * pitch]);
+            values.push(arr[39]);
+            values.push(arr[39 + 39 * pitch]);
+        });
+        assert_eq!((1, 2, 3, 255), values[0]);
+        assert_eq!((255, 0, 0, 255), values[1]);
+        assert_eq!((0, 255, 0, 255), values[2]);
+        assert_eq!((0, 0, 255, 255), values[3]);
+        assert_eq!((100, 100, 100, 255), values[4]);
+        assert_eq!((0, 0, 0, 0), values[5]);
+    }
+
+    #[test]
+    fn noise_options_push_constants_layout() {
+        let constants = NoiseOptions::new()
+            .noise(NoiseType::Worley)
+            .seed([1.0, 2.0, 3.0])
+            .octaves(100)
+            .mapping(NoiseMapping::Threshold(0.25))
+            .push_constants((4, 5), (6, 7));
+        let float = |idx: usize| f32::from_bits(constants[idx]);
+        assert_eq![[1.0, 2.0, 3.0], [float(0), float(1), float(2)]];
+        assert_eq![
+            [4.0, 5.0, 6.0, 7.0],
+            [float(4), float(5), float(6), float(7)]
+        ];
+        assert_eq![[2.0, 0.5, 0.25], [float(8), float(9), float(10)]];
+        assert_eq![[16, 2, 1], [constants[12], constants[13], constants[14]]];
+    }
+
+    #[test]
+    fn fill_region_with_thresholded_noise() {
+        let logger = Logger::root(Discard, o!());
+        let event_loop = EventLoop::new_any_thread();
+        let mut vx = VxDraw::new(logger, ShowWindow::Headless1k, &event_loop);
+
+        let mut strtex = vx.strtex();
+        let id = strtex.add_layer(&LayerOptions::new().width(64).height(64));
+        strtex.fill_with_noise(
+            &id,
+            &NoiseOptions::new()
+                .noise(NoiseType::Simplex)
+                .mapping(NoiseMapping::Threshold(0.5))
+                .region((16, 16), (32, 32)),
+        );
+        strtex.fill_with_noise(
+            &id,
+            &NoiseOptions::new().region((u32::max_value(), 0), (32, 32)),
+        );
+
+        let mut inside = vec![];
+        let mut outside = vec![];
+        strtex.read(&id, |arr, pitch| {
+            for y in 0..64 {
+                for x in 0..64 {
+                    if x >= 16 && x < 48 && y >= 16 && y < 48 {
+                        inside.push(arr[x + y * pitch]);
+                    } else {
+                        outside.push(arr[x + y * pitch]);
+                    }
+                }
+            }
+        });
+        assert![outside.iter().all(|pixel| *pixel == (0, 0, 0, 0))];
+        assert![inside
+            .iter()
+            .all(|pixel| *pixel == (0, 0, 0, 255) || *pixel == (255, 255, 255, 255))];
+        assert![inside.iter().any(|pixel| *pixel == (255, 255, 255, 255))];
+    }
+
+    #[test]
+    fn request_read_is_ready_after_frames() {
+        let logger = Logger::root(Discard, o!());
+        let event_loop = EventLoop::new_any_thread();
+        let mut vx = VxDraw::new(logger, ShowWindow::Headless1k, &event_loop);
+
+        let mut strtex = vx.strtex();
+        let id = strtex.add_layer(&LayerOptions::new().width(10).height(10));
+        strtex.set_pixel(&id, 3, 2, Color::Rgba(0, 123, 0, 255));
+        assert![strtex.request_read(&id, (5, 5), (6, 1)).is_none()];
+        let ticket = strtex.request_read(&id, (2, 2), (2, 1)).unwrap();
+        assert_eq![ReadStatus::Pending, strtex.poll_read(&ticket).unwrap()];
+
+        for _ in 0..vx.swapconfig.image_count {
+            vx.draw_frame();
+        }
+        let mut strtex = vx.strtex();
+        assert_eq![
+            ReadStatus::Ready(vec![(0, 0, 0, 0), (0, 123, 0, 255)]),
+            strtex.poll_read(&ticket).unwrap()
+        ];
+        assert_eq![ReadStatus::Unknown, strtex.poll_read(&ticket).unwrap()];
+    }
+
+    #[test]
+    fn reads_are_dropped_with_their_layer() {
+        let logger = Logger::root(Discard, o!());
+        let event_loop = EventLoop::new_any_thread();
+        let mut vx = VxDraw::new(logger, ShowWindow::Headless1k, &event_loop);
+
+        let mut strtex = vx.strtex();
+        let id = strtex.add_layer(&LayerOptions::new().width(10).height(10));
+        assert![strtex
+            .request_read(&id, (u32::max_value(), 0), (2, 1))
+            .is_none()];
+        let recorded = strtex.request_read(&id, (0, 0), (2, 1)).unwrap();
+        vx.draw_frame();
+        let mut strtex = vx.strtex();
+        let unrecorded = strtex.request_read(&id, (0, 0), (2, 1)).unwrap();
+        strtex.remove_layer(id);
+        assert_eq![ReadStatus::Unknown, strtex.poll_read(&recorded).unwrap()];
+        assert_eq![ReadStatus::Unknown, strtex.poll_read(&unrecorded).unwrap()];
+        vx.draw_frame();
+    }
+
     #[test]
     fn streaming_texture_weird_pixel_accesses() {
         let logger = Logger::root(Discard, o!());
@@ -93,7 +93,11 @@
 use super::utils::*;
 use crate::{
     blender,
-    data::{DrawType, SData, Text, VxDraw},
+    data::{
+        CharacterCell, DistanceFieldGlyphs, DrawType, SData, Span, Text, TextLayout, TextPlacement,
+        VxDraw,
+    },
+    Color,
 };
 use cgmath::{Matrix4, Rad, Vector4};
 use core::ptr::read;
@@ -114,8 +118,13 @@ use gfx_hal::{
     queue::CommandQueue,
     Backend,
 };
-use glyph_brush::{BrushAction, BrushError, GlyphBrushBuilder};
-use std::{io::Cursor, mem::ManuallyDrop};
+use glyph_brush::{
+    rusttype::{GlyphId, Rect},
+    BrushAction, BrushError, BuiltInLineBreaker, FontId, GlyphBrush, GlyphBrushBuilder,
+    GlyphCruncher, HorizontalAlign, SectionText, VariedSection,
+};
+use slog::{error, Logger};
+use std::{borrow::Cow, collections::HashMap, io::Cursor, mem::ManuallyDrop, ops::Range};
 
 // ---
 
@@ -133,6 +142,7 @@ pub struct LayerOptions {
     vertex_shader: VertexShader,
     fragment_shader: FragmentShader,
     fixed_perspective: Option<Matrix4<f32>>,
+    distance_field: Option<DistanceField>,
 }
 
 impl Default for LayerOptions {
@@ -143,6 +153,7 @@ impl Default for LayerOptions {
             vertex_shader: VertexShader::Standard,
             fragment_shader: FragmentShader::Standard,
             fixed_perspective: None,
+            distance_field: None,
         }
     }
 }
@@ -200,6 +211,107 @@ impl LayerOptions {
         self.fixed_perspective = Some(mat);
         self
     }
+
+    /// Render the glyphs of this layer as signed distance fields
+    ///
+    /// Distance field text stays sharp when it is scaled or zoomed, and supports outlines, glows,
+    /// and drop shadows.
+    pub fn distance_field(mut self, distance_field: DistanceField) -> Self {
+        self.distance_field = Some(distance_field);
+        self
+    }
+}
+
+/// Options for rendering text as signed distance fields
+///
+/// All glyphs are rasterized once at a fixed size and stored as their distance to the outline of
+/// the glyph, up to `spread` texels away. The widths of the effects are given as a fraction of the
+/// spread, since the distance field does not extend further.
+#[derive(Clone, Copy, Debug)]
+pub struct DistanceField {
+    size: f32,
+    spread: u32,
+    outline: (f32, [u8; 4]),
+    glow: (f32, [u8; 4]),
+    shadow: ((f32, f32), [u8; 4]),
+}
+
+impl Default for DistanceField {
+    fn default() -> Self {
+        Self {
+            size: 48.0,
+            spread: 6,
+            outline: (0.0, [0, 0, 0, 0]),
+            glow: (0.0, [0, 0, 0, 0]),
+            shadow: ((0.0, 0.0), [0, 0, 0, 0]),
+        }
+    }
+}
+
+impl DistanceField {
+    /// Same as default
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the font size glyphs are rasterized at (default is 48)
+    pub fn size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Set how many texels around the outline of a glyph its distance field covers (default is 6)
+    pub fn spread(mut self, spread: u32) -> Self {
+        self.spread = spread.max(1);
+        self
+    }
+
+    /// Draw an outline around the glyphs, the width is a fraction of the spread
+    pub fn outline(mut self, width: f32, color: Color) -> Self {
+        let Color::Rgba(r, g, b, a) = color;
+        self.outline = (width, [r, g, b, a]);
+        self
+    }
+
+    /// Draw a glow fading out around the glyphs, the radius is a fraction of the spread
+    pub fn glow(mut self, radius: f32, color: Color) -> Self {
+        let Color::Rgba(r, g, b, a) = color;
+        self.glow = (radius, [r, g, b, a]);
+        self
+    }
+
+    /// Draw a shadow behind the glyphs, the offset is a fraction of the spread
+    pub fn shadow(mut self, offset: (f32, f32), color: Color) -> Self {
+        let Color::Rgba(r, g, b, a) = color;
+        self.shadow = (offset, [r, g, b, a]);
+        self
+    }
+
+    /// Push constants of the distance field fragment shader
+    fn effects(&self) -> [f32; 16] {
+        let channel = |value: u8| f32::from(value) / 255.0;
+        let (outline, glow, shadow) = (self.outline.1, self.glow.1, self.shadow.1);
+        let offset = self.shadow.0;
+        let spread = self.spread as f32;
+        [
+            channel(outline[0]),
+            channel(outline[1]),
+            channel(outline[2]),
+            channel(outline[3]),
+            channel(glow[0]),
+            channel(glow[1]),
+            channel(glow[2]),
+            channel(glow[3]),
+            channel(shadow[0]),
+            channel(shadow[1]),
+            channel(shadow[2]),
+            channel(shadow[3]),
+            self.outline.0,
+            self.glow.0,
+            offset.0 * spread,
+            offset.1 * spread,
+        ]
+    }
 }
 
 /// Specify filter options
@@ -211,6 +323,26 @@ pub enum Filter {
     Linear,
 }
 
+/// Horizontal alignment of the lines within a text block
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Align {
+    /// Align lines to the left edge of the block
+    Left,
+    /// Center each line within the block
+    Center,
+    /// Align lines to the right edge of the block
+    Right,
+}
+
+/// How lines are broken when they exceed the maximum width of a text
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Wrap {
+    /// Break lines between words
+    Word,
+    /// Break lines at any character
+    Anywhere,
+}
+
 /// Options when adding a text
 pub struct TextOptions {
     font_size_x: f32,
@@ -219,6 +351,13 @@ pub struct TextOptions {
     origin: (f32, f32),
     rotation: f32,
     scale: f32,
+    max_width: Option<f32>,
+    wrap: Wrap,
+    align: Align,
+    line_spacing: f32,
+    color: [u8; 4],
+    font: Font,
+    spans: Vec<(Range<usize>, Span)>,
 }
 
 impl Default for TextOptions {
@@ -230,6 +369,13 @@ impl Default for TextOptions {
             origin: (0.0, 0.0),
             rotation: 0.0,
             scale: 1.0,
+            max_width: None,
+            wrap: Wrap::Word,
+            align: Align::Left,
+            line_spacing: 1.0,
+            color: [255, 255, 255, 255],
+            font: Font::default(),
+            spans: vec![],
         }
     }
 }
@@ -295,17 +441,588 @@ impl TextOptions {
     pub fn scale(self, scale: f32) -> Self {
         Self { scale, ..self }
     }
+
+    /// Set the maximum width of the text, longer lines are wrapped
+    ///
+    /// The width is in the same model space units as [Texts::get_width]. By default lines are
+    /// only broken at newlines.
+    pub fn max_width(self, max_width: f32) -> Self {
+        Self {
+            max_width: Some(max_width),
+            ..self
+        }
+    }
+
+    /// Set where lines are broken when they exceed the maximum width (default is [Wrap::Word])
+    pub fn wrap(self, wrap: Wrap) -> Self {
+        Self { wrap, ..self }
+    }
+
+    /// Set the horizontal alignment of the lines (default is [Align::Left])
+    ///
+    /// Lines are aligned within the width of the widest line. To place the text block as a whole,
+    /// see [TextOptions::origin].
+    pub fn align(self, align: Align) -> Self {
+        Self { align, ..self }
+    }
+
+    /// Set the distance between lines as a multiple of the font's line height (default is 1.0)
+    pub fn line_spacing(self, line_spacing: f32) -> Self {
+        Self {
+            line_spacing,
+            ..self
+        }
+    }
+
+    /// Set the color of the text (default is white)
+    pub fn color(self, color: Color) -> Self {
+        let Color::Rgba(r, g, b, a) = color;
+        Self {
+            color: [r, g, b, a],
+            ..self
+        }
+    }
+
+    /// Set the color of a span of the text, given as a byte range of the string
+    ///
+    /// A bound inside a character is moved back to the start of the character. When spans
+    /// overlap, the span set last takes precedence.
+    pub fn span(mut self, range: Range<usize>, color: Color) -> Self {
+        let Color::Rgba(r, g, b, a) = color;
+        self.spans.push((range, Span::Color([r, g, b, a])));
+        self
+    }
+
+    /// Set the font of the text (default is the font the layer was created with)
+    pub fn font(self, font: Font) -> Self {
+        Self { font, ..self }
+    }
+
+    /// Set the font of a span of the text, given as a byte range of the string
+    ///
+    /// A font that does not belong to the layer of the text is replaced by the default font.
+    /// A bound inside a character is moved back to the start of the character. When spans
+    /// overlap, the span set last takes precedence.
+    pub fn font_span(mut self, range: Range<usize>, font: Font) -> Self {
+        self.spans.push((range, Span::Font(font.0)));
+        self
+    }
+
+    fn layout(&self) -> TextLayout {
+        TextLayout {
+            max_width: self.max_width,
+            wrap: self.wrap,
+            align: self.align,
+            line_spacing: self.line_spacing,
+            color: self.color,
+            font: self.font.0,
+        }
+    }
+}
+
+/// Split a text into parts that each have a single color and font
+///
+/// Characters missing from the font of their part are drawn with the first fallback font that
+/// has them.
+fn styled_parts<'a>(
+    text: &'a str,
+    layout: &TextLayout,
+    spans: &[(Range<usize>, Span)],
+    fallbacks: &[usize],
+    has_glyph: impl Fn(usize, char) -> bool,
+) -> Vec<(&'a str, [u8; 4], usize)> {
+    let mut bounds = vec![0, text.len()];
+    for (span, _) in spans {
+        bounds.push(span.start.min(text.len()));
+        bounds.push(span.end.min(text.len()));
+    }
+    bounds.sort();
+    bounds.dedup();
+    let mut parts = vec![];
+    for part in bounds.windows(2) {
+        let covering = spans
+            .iter()
+            .rev()
+            .filter(|(span, _)| span.start <= part[0] && part[1] <= span.end);
+        let color = covering
+            .clone()
+            .find_map(|(_, span)| match span {
+                Span::Color(color) => Some(*color),
+                _ => None,
+            })
+            .unwrap_or(layout.color);
+        let font = covering
+            .clone()
+            .find_map(|(_, span)| match span {
+                Span::Font(font) => Some(*font),
+                _ => None,
+            })
+            .unwrap_or(layout.font);
+
+        let mut begin = part[0];
+        let mut current = None;
+        for (idx, ch) in text[part[0]..part[1]].char_indices() {
+            let ch_font = if ch.is_control() || has_glyph(font, ch) {
+                font
+            } else {
+                fallbacks
+                    .iter()
+                    .cloned()
+                    .find(|fallback| has_glyph(*fallback, ch))
+                    .unwrap_or(font)
+            };
+            match current {
+                Some(current) if current != ch_font => {
+                    parts.push((&text[begin..part[0] + idx], color, current));
+                    begin = part[0] + idx;
+                }
+                _ => {}
+            }
+            current = Some(ch_font);
+        }
+        if let Some(current) = current {
+            parts.push((&text[begin..part[1]], color, current));
+        }
+    }
+    parts
+}
+
+/// Make the styles of a text safe to lay out with a layer that has `font_count` fonts
+///
+/// Span bounds inside a character are moved back to the start of the character, and fonts that
+/// do not belong to the layer are replaced by the default font.
+fn sanitize_styles(
+    log: &Logger,
+    text: &str,
+    font_count: usize,
+    layout: &mut TextLayout,
+    spans: &mut [(Range<usize>, Span)],
+) {
+    if layout.font >= font_count {
+        error!(log, "Font does not belong to the layer, using the default font"; "font" => layout.font);
+        layout.font = 0;
+    }
+    for (range, span) in spans.iter_mut() {
+        let start = char_boundary_at_or_before(text, range.start);
+        let end = char_boundary_at_or_before(text, range.end);
+        if start != range.start.min(text.len()) || end != range.end.min(text.len()) {
+            error!(log, "Span does not lie on character boundaries"; "range" => ?range);
+        }
+        *range = start..end;
+        if let Span::Font(font) = span {
+            if *font >= font_count {
+                error!(log, "Font does not belong to the layer, using the default font"; "font" => *font);
+                *font = 0;
+            }
+        }
+    }
+}
+
+/// Find the closest character boundary at or before a byte index, clamped to the string
+fn char_boundary_at_or_before(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+/// Build the section of a text, aligned lines are positioned to start at the left edge
+fn section<'a>(
+    glyph_brush: &mut GlyphBrush<'static, SData>,
+    text: &'a str,
+    font_size: (f32, f32),
+    layout: &TextLayout,
+    spans: &[(Range<usize>, Span)],
+    fallbacks: &[usize],
+) -> VariedSection<'a> {
+    let line_breaker = match layout.wrap {
+        Wrap::Word => BuiltInLineBreaker::UnicodeLineBreaker,
+        Wrap::Anywhere => BuiltInLineBreaker::AnyCharLineBreaker,
+    };
+    let scale = glyph_brush::rusttype::Scale {
+        x: font_size.0,
+        y: font_size.1,
+    };
+    let parts = styled_parts(text, layout, spans, fallbacks, |font, ch| {
+        glyph_brush.fonts()[font].glyph(ch).id() != GlyphId(0)
+    });
+    let section = VariedSection {
+        text: parts
+            .into_iter()
+            .map(|(text, color, font)| SectionText {
+                text,
+                scale,
+                color: [
+                    f32::from(color[0]) / 255.0,
+                    f32::from(color[1]) / 255.0,
+                    f32::from(color[2]) / 255.0,
+                    f32::from(color[3]) / 255.0,
+                ],
+                font_id: FontId(font),
+            })
+            .collect(),
+        bounds: (
+            layout
+                .max_width
+                .map_or(std::f32::INFINITY, |width| width * PIX_WIDTH_DIVISOR),
+            std::f32::INFINITY,
+        ),
+        layout: glyph_brush::Layout::default_wrap().line_breaker(line_breaker),
+        ..VariedSection::default()
+    };
+    let (h_align, fraction) = match layout.align {
+        Align::Left => return section,
+        Align::Center => (HorizontalAlign::Center, 0.5),
+        Align::Right => (HorizontalAlign::Right, 1.0),
+    };
+    let block_width = glyph_brush
+        .pixel_bounds(Cow::Borrowed(&section))
+        .map_or(0, |bounds| bounds.max.x) as f32;
+    VariedSection {
+        screen_position: (block_width * fraction, 0.0),
+        layout: section.layout.h_align(h_align),
+        ..section
+    }
+}
+
+/// Move the glyphs of every line down by the spacing added to the lines above it
+fn space_lines(
+    glyph_brush: &GlyphBrush<'static, SData>,
+    font_size: (f32, f32),
+    layout: &TextLayout,
+    vertices: &mut [SData],
+) {
+    let line_spacing = layout.line_spacing;
+    let metrics = glyph_brush.fonts()[layout.font].v_metrics(glyph_brush::rusttype::Scale {
+        x: font_size.0,
+        y: font_size.1,
+    });
+    let line_height = metrics.ascent - metrics.descent + metrics.line_gap;
+    for vtx in vertices.iter_mut() {
+        let center = (vtx.topleft.1 + vtx.bottomright.1) as f32 / 2.0;
+        let line = (center / line_height).floor();
+        let shift = (line * line_height * (line_spacing - 1.0)).round() as i32;
+        vtx.topleft.1 += shift;
+        vtx.bottomright.1 += shift;
+    }
+}
+
+/// Cells of the characters of a text, in pixels of the rasterized text before its lines are spaced
+///
+/// Glyphs belong, in order, to the characters that have an outline. A character without a glyph
+/// starts where the character before it ends.
+fn character_cells(
+    glyph_brush: &GlyphBrush<'static, SData>,
+    text: &str,
+    font_size: (f32, f32),
+    layout: &TextLayout,
+    spans: &[(Range<usize>, Span)],
+    fallbacks: &[usize],
+    vertices: &[SData],
+) -> Vec<CharacterCell> {
+    let fonts = glyph_brush.fonts();
+    let scale = glyph_brush::rusttype::Scale {
+        x: font_size.0,
+        y: font_size.1,
+    };
+    let metrics = fonts[layout.font].v_metrics(scale);
+    let line_height = metrics.ascent - metrics.descent + metrics.line_gap;
+    let line_pitch = line_height * layout.line_spacing;
+    let parts = styled_parts(text, layout, spans, fallbacks, |font, ch| {
+        fonts[font].glyph(ch).id() != GlyphId(0)
+    });
+
+    let mut glyphs = vertices.iter().enumerate();
+    let mut cells = Vec::with_capacity(text.chars().count() + 1);
+    let (mut index, mut pen, mut line) = (0, 0.0, 0.0);
+    for (part, _, font) in parts {
+        for ch in part.chars() {
+            let glyph = fonts[font].glyph(ch).scaled(scale);
+            let h_metrics = glyph.h_metrics();
+            let drawn = if ch.is_control() || glyph.exact_bounding_box().is_none() {
+                None
+            } else {
+                glyphs.next()
+            };
+            if let Some((_, vtx)) = drawn {
+                let center = (vtx.topleft.1 + vtx.bottomright.1) as f32 / 2.0;
+                pen = vtx.topleft.0 as f32 - h_metrics.left_side_bearing;
+                line = (center / line_height).floor();
+            }
+            let advance = if ch.is_control() {
+                0.0
+            } else {
+                h_metrics.advance_width
+            };
+            let top = line * line_pitch;
+            cells.push(CharacterCell {
+                index,
+                rect: [pen, top, pen + advance, top + line_height],
+                glyph: drawn.map(|(glyph, _)| glyph),
+            });
+            if ch == '\n' {
+                pen = 0.0;
+                line += 1.0;
+            } else {
+                pen += advance;
+            }
+            index += ch.len_utf8();
+        }
+    }
+    let top = line * line_pitch;
+    cells.push(CharacterCell {
+        index,
+        rect: [pen, top, pen, top + line_height],
+        glyph: None,
+    });
+    cells
+}
+
+/// Corners of a glyph quad in model space and in the glyph texture, relative to the text's origin
+fn glyph_quad(
+    vtx: &SData,
+    origin: (f32, f32),
+    size: (i32, i32),
+    factor: f32,
+) -> ([f32; 8], [f32; 8]) {
+    let muscale = PIX_WIDTH_DIVISOR / factor;
+    let uv_a = vtx.uv_begin;
+    let uv_b = vtx.uv_end;
+    let beg = vtx.topleft;
+    let end = vtx.bottomright;
+    let begf = (beg.0 as f32 / muscale, beg.1 as f32 / muscale);
+    let width = (end.0 - beg.0) as f32 / muscale;
+    let height = (end.1 - beg.1) as f32 / muscale;
+
+    let topleft = (begf.0, begf.1);
+    let topright = (begf.0 + width, begf.1);
+    let bottomleft = (begf.0, begf.1 + height);
+    let bottomright = (begf.0 + width, begf.1 + height);
+
+    let dx = origin.0 * size.0 as f32 / PIX_WIDTH_DIVISOR;
+    let dy = origin.1 * size.1 as f32 / PIX_WIDTH_DIVISOR;
+    (
+        [
+            topleft.0 - dx,
+            topleft.1 - dy,
+            bottomleft.0 - dx,
+            bottomleft.1 - dy,
+            bottomright.0 - dx,
+            bottomright.1 - dy,
+            topright.0 - dx,
+            topright.1 - dy,
+        ],
+        [
+            uv_a.0, uv_a.1, uv_a.0, uv_b.1, uv_b.0, uv_b.1, uv_b.0, uv_a.1,
+        ],
+    )
+}
+
+/// Convert a vertex produced by the glyph brush
+fn glyph_data(vtx: glyph_brush::GlyphVertex) -> SData {
+    let channel = |value: f32| (value * 255.0).round() as u8;
+    SData {
+        uv_begin: (vtx.tex_coords.min.x, vtx.tex_coords.min.y),
+        uv_end: (vtx.tex_coords.max.x, vtx.tex_coords.max.y),
+        topleft: (vtx.pixel_coords.min.x, vtx.pixel_coords.min.y),
+        bottomright: (vtx.pixel_coords.max.x, vtx.pixel_coords.max.y),
+        text: vtx.z as usize,
+        color: [
+            channel(vtx.color[0]),
+            channel(vtx.color[1]),
+            channel(vtx.color[2]),
+            channel(vtx.color[3]),
+        ],
+    }
+}
+
+/// Queue a pending string in the glyph brush, its vertices are marked with `z`
+fn queue_pending(tex: &mut Text, pending: &Pending, z: usize) {
+    let (raster_size, raster_layout, _) = rasterization(
+        tex.distance_field.as_ref(),
+        pending.font_size,
+        pending.layout,
+    );
+    let section = section(
+        &mut tex.glyph_brush,
+        pending.string,
+        raster_size,
+        &raster_layout,
+        pending.spans,
+        &tex.fallbacks,
+    );
+    tex.glyph_brush.queue(VariedSection {
+        z: z as f32,
+        ..section
+    });
+}
+
+/// Check whether two glyph cache entries share any texel
+fn overlaps(a: &Rect<u32>, b: &Rect<u32>) -> bool {
+    a.min.x < b.max.x && b.min.x < a.max.x && a.min.y < b.max.y && b.min.y < a.max.y
+}
+
+/// Font size and layout a text is rasterized with, and the factor scaling it to its actual size
+///
+/// Distance field layers rasterize all glyphs at the same size.
+fn rasterization(
+    distance_field: Option<&DistanceFieldGlyphs>,
+    font_size: (f32, f32),
+    layout: &TextLayout,
+) -> ((f32, f32), TextLayout, f32) {
+    match distance_field {
+        Some(distance_field) => {
+            let factor = font_size.0 / distance_field.size;
+            (
+                (distance_field.size, font_size.1 / factor),
+                TextLayout {
+                    max_width: layout.max_width.map(|width| width / factor),
+                    ..*layout
+                },
+                factor,
+            )
+        }
+        None => (font_size, *layout, 1.0),
+    }
+}
+
+/// Compute the signed distance field of a glyph, with `spread` texels of room on each side
+///
+/// A value of 128 lies on the outline of the glyph, values rise to 255 at `spread` texels inside
+/// and fall to 0 at `spread` texels outside.
+fn signed_distance_field(coverage: &[u8], size: (u32, u32), spread: u32) -> Vec<u8> {
+    let (width, height) = (size.0 as usize, size.1 as usize);
+    let spread = spread as usize;
+    let (w, h) = (width + 2 * spread, height + 2 * spread);
+    let inside = (0..w * h)
+        .map(|idx| {
+            let (x, y) = (idx % w, idx / w);
+            x >= spread
+                && y >= spread
+                && x < width + spread
+                && y < height + spread
+                && coverage[(y - spread) * width + x - spread] >= 128
+        })
+        .collect::<Vec<_>>();
+    let to_inside = squared_distances(&inside, (w, h), true);
+    let to_outside = squared_distances(&inside, (w, h), false);
+    inside
+        .iter()
+        .zip(to_inside.iter().zip(&to_outside))
+        .map(|(this, (to_inside, to_outside))| {
+            let nearest = if *this { to_outside } else { to_inside };
+            // Texel centers are half a texel away from the outline between them
+            let distance = (nearest.sqrt() as f32 - 0.5).min(spread as f32);
+            let signed = if *this { distance } else { -distance };
+            let value = 0.5 + signed / (2 * spread) as f32;
+            (value.clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+        .collect()
+}
+
+/// Squared distance from every texel to the closest texel where `inside` equals `target`
+///
+/// Uses the separable transform by Felzenszwalb and Huttenlocher, which is linear in the number of
+/// texels: columns first, then rows of the column results.
+fn squared_distances(inside: &[bool], (w, h): (usize, usize), target: bool) -> Vec<f64> {
+    const FAR: f64 = 1e20;
+    let mut grid = inside
+        .iter()
+        .map(|x| if *x == target { 0.0 } else { FAR })
+        .collect::<Vec<_>>();
+    let n = w.max(h);
+    let (mut f, mut parabolas, mut bounds) = (vec![0.0; n], vec![0; n], vec![0.0; n + 1]);
+    let mut column = vec![0.0; h];
+    for x in 0..w {
+        for y in 0..h {
+            f[y] = grid[y * w + x];
+        }
+        distance_transform(&f[..h], &mut column, &mut parabolas, &mut bounds);
+        for y in 0..h {
+            grid[y * w + x] = column[y];
+        }
+    }
+    for row in grid.chunks_exact_mut(w) {
+        f[..w].copy_from_slice(row);
+        distance_transform(&f[..w], row, &mut parabolas, &mut bounds);
+    }
+    grid
+}
+
+/// One-dimensional squared distance transform of `f` into `d`
+///
+/// `d[q]` becomes the minimum of `(q - p)^2 + f[p]` over all `p`, found as the lower envelope of
+/// the parabolas rooted at every `p`.
+fn distance_transform(f: &[f64], d: &mut [f64], parabolas: &mut [usize], bounds: &mut [f64]) {
+    let mut k = 0;
+    parabolas[0] = 0;
+    bounds[0] = std::f64::NEG_INFINITY;
+    bounds[1] = std::f64::INFINITY;
+    for q in 1..f.len() {
+        let qf = q as f64;
+        loop {
+            let p = parabolas[k];
+            let pf = p as f64;
+            let intersection = ((f[q] + qf * qf) - (f[p] + pf * pf)) / (2.0 * (qf - pf));
+            if intersection <= bounds[k] {
+                k -= 1;
+            } else {
+                k += 1;
+                parabolas[k] = q;
+                bounds[k] = intersection;
+                bounds[k + 1] = std::f64::INFINITY;
+                break;
+            }
+        }
+    }
+    k = 0;
+    for (q, d) in d.iter_mut().enumerate() {
+        while bounds[k + 1] < q as f64 {
+            k += 1;
+        }
+        let offset = q as f64 - parabolas[k] as f64;
+        *d = offset * offset + f[parabolas[k]];
+    }
+}
+
+/// A string laid out with the options of a text
+struct Pending<'a> {
+    string: &'a str,
+    font_size: (f32, f32),
+    layout: &'a TextLayout,
+    spans: &'a [(Range<usize>, Span)],
+}
+
+/// Glyph quads of a laid out text
+struct Glyphs {
+    positions: Vec<[f32; 8]>,
+    uvs: Vec<[f32; 8]>,
+    colors: Vec<[u8; 16]>,
+    cells: Vec<CharacterCell>,
+    width: i32,
+    height: i32,
 }
 
 /// Handle to a piece of text
+///
+/// The handle becomes stale when the text or its layer is removed, using a stale handle panics in
+/// debug builds and is ignored in release builds.
 pub struct Handle {
     layer: usize,
-    vertices: std::ops::Range<usize>,
     id: usize,
+    generation: u64,
 }
 
 /// Handle to a layer (a single glyph store/font)
-pub struct Layer(usize);
+pub struct Layer(usize, u64);
+
+/// Handle to a font of a text layer
+///
+/// The default font is the one the layer was created with. A font handle is only meaningful for
+/// the layer it was added to.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Font(usize);
 
 /// Accessor object to all text
 pub struct Texts<'a> {
@@ -328,6 +1045,9 @@ impl<'a> Texts<'a> {
 
     /// Set the fixed perspective of a layer. `None` uses the vxdraw perspective.
     pub fn set_perspective(&mut self, layer: &Layer, perspective: Option<Matrix4<f32>>) {
+        if !self.check_layer(layer) {
+            return;
+        }
         self.vx.texts[layer.0].fixed_perspective = perspective;
     }
 
@@ -378,7 +1098,8 @@ impl<'a> Texts<'a> {
                 memory::Properties::DEVICE_LOCAL
                     | memory::Properties::CPU_VISIBLE
                     | memory::Properties::COHERENT,
-            );
+            )
+            .expect("Unable to find memory type id");
             self.vx
                 .device
                 .allocate_memory(memory_type_id, image_requirements.size)
@@ -424,12 +1145,17 @@ impl<'a> Texts<'a> {
         /// Add shader
         const VERTEX_SOURCE_TEXTURE: &[u8] = include_bytes!["../target/spirv/text.vert.spirv"];
         const FRAGMENT_SOURCE_TEXTURE: &[u8] = include_bytes!["../target/spirv/text.frag.spirv"];
+        const FRAGMENT_SOURCE_DISTANCE_FIELD: &[u8] =
+            include_bytes!["../target/spirv/text_sdf.frag.spirv"];
 
         let vertex_source_texture = match options.vertex_shader {
             VertexShader::Standard => pso::read_spirv(Cursor::new(VERTEX_SOURCE_TEXTURE)).unwrap(),
             VertexShader::Spirv(ref data) => pso::read_spirv(Cursor::new(data)).unwrap(),
         };
         let fragment_source_texture = match options.fragment_shader {
+            FragmentShader::Standard if options.distance_field.is_some() => {
+                pso::read_spirv(Cursor::new(FRAGMENT_SOURCE_DISTANCE_FIELD)).unwrap()
+            }
             FragmentShader::Standard => {
                 pso::read_spirv(Cursor::new(FRAGMENT_SOURCE_TEXTURE)).unwrap()
             }
@@ -505,6 +1231,11 @@ impl<'a> Texts<'a> {
                 stride: 1,
                 rate: pso::VertexInputRate::Vertex,
             },
+            pso::VertexBufferDesc {
+                binding: 6,
+                stride: 4,
+                rate: pso::VertexInputRate::Vertex,
+            },
         ];
         let attributes: Vec<pso::AttributeDesc> = vec![
             pso::AttributeDesc {
@@ -555,6 +1286,14 @@ impl<'a> Texts<'a> {
                     offset: 0,
                 },
             },
+            pso::AttributeDesc {
+                location: 6,
+                binding: 6,
+                element: pso::Element {
+                    format: format::Format::Rgba8Unorm,
+                    offset: 0,
+                },
+            },
         ];
 
         let rasterizer = pso::Rasterizer {
@@ -690,6 +1429,9 @@ impl<'a> Texts<'a> {
         // Push constants
         let mut push_constants = Vec::<(pso::ShaderStageFlags, core::ops::Range<u32>)>::new();
         push_constants.push((pso::ShaderStageFlags::VERTEX, 0..64));
+        if options.distance_field.is_some() {
+            push_constants.push((pso::ShaderStageFlags::FRAGMENT, 64..128));
+        }
 
         let pipeline_layout = unsafe {
             self.vx
@@ -733,26 +1475,53 @@ impl<'a> Texts<'a> {
         // Create vertex buffers
         let image_count = self.vx.swapconfig.image_count;
         let posbuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&self.vx.device, &self.vx.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&self.vx.device, &self.vx.adapter)
+                    .expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
         let opacbuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&self.vx.device, &self.vx.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&self.vx.device, &self.vx.adapter)
+                    .expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
         let uvbuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&self.vx.device, &self.vx.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&self.vx.device, &self.vx.adapter)
+                    .expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
         let tranbuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&self.vx.device, &self.vx.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&self.vx.device, &self.vx.adapter)
+                    .expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
         let rotbuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&self.vx.device, &self.vx.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&self.vx.device, &self.vx.adapter)
+                    .expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
         let scalebuf = (0..image_count)
-            .map(|_| super::utils::ResizBuf::new(&self.vx.device, &self.vx.adapter))
+            .map(|_| {
+                super::utils::ResizBuf::new(&self.vx.device, &self.vx.adapter)
+                    .expect("Unable to create buffer")
+            })
+            .collect::<Vec<_>>();
+        let colbuf = (0..image_count)
+            .map(|_| {
+                super::utils::ResizBuf::new(&self.vx.device, &self.vx.adapter)
+                    .expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
 
         let indices = (0..image_count)
-            .map(|_| super::utils::ResizBufIdx4::new(&self.vx.device, &self.vx.adapter))
+            .map(|_| {
+                super::utils::ResizBufIdx4::new(&self.vx.device, &self.vx.adapter)
+                    .expect("Unable to create buffer")
+            })
             .collect::<Vec<_>>();
 
         // Change the image to layout general
@@ -799,16 +1568,32 @@ impl<'a> Texts<'a> {
         let text = Text {
             hidden: false,
             removed: vec![],
+            generation: self.vx.next_generation(),
+            generations: vec![],
             glyph_brush,
 
             texts: vec![],
             font_sizes: vec![],
             origin: vec![],
+            layouts: vec![],
+            spans: vec![],
+            fallbacks: vec![],
 
             width: vec![],
             height: vec![],
+            ranges: vec![],
+            placements: vec![],
+            cells: vec![],
 
             fixed_perspective: options.fixed_perspective,
+            distance_field: options.distance_field.map(|options| DistanceFieldGlyphs {
+                size: options.size,
+                spread: options.spread,
+                effects: options.effects(),
+                slots: HashMap::new(),
+                shelf: (0, 0, 0),
+            }),
+            glyph_rects: vec![],
 
             posbuf_touch: 0,
             opacbuf_touch: 0,
@@ -816,6 +1601,7 @@ impl<'a> Texts<'a> {
             tranbuf_touch: 0,
             rotbuf_touch: 0,
             scalebuf_touch: 0,
+            colbuf_touch: 0,
 
             posbuffer: vec![],
             opacbuffer: vec![],
@@ -823,6 +1609,7 @@ impl<'a> Texts<'a> {
             tranbuffer: vec![],
             rotbuffer: vec![],
             scalebuffer: vec![],
+            colbuffer: vec![],
 
             posbuf,
             opacbuf,
@@ -830,6 +1617,7 @@ impl<'a> Texts<'a> {
             tranbuf,
             rotbuf,
             scalebuf,
+            colbuf,
 
             indices,
 
@@ -850,13 +1638,14 @@ impl<'a> Texts<'a> {
             DrawType::Text { .. } => true,
             _ => false,
         });
+        let generation = text.generation;
         if let Some(prev_layer) = prev_layer {
             match prev_layer {
                 DrawType::Text { id } => {
                     let old_text = std::mem::replace(&mut self.vx.texts[id], text);
                     old_text.destroy(&self.vx.device);
                     self.vx.draw_order.push(DrawType::Text { id });
-                    Layer(id)
+                    Layer(id, generation)
                 }
                 _ => panic!["Got a non-text drawtype, should be impossible!"],
             }
@@ -865,348 +1654,605 @@ impl<'a> Texts<'a> {
             self.vx.draw_order.push(DrawType::Text {
                 id: self.vx.texts.len() - 1,
             });
-            Layer(self.vx.texts.len() - 1)
+            Layer(self.vx.texts.len() - 1, generation)
         }
     }
 
     /// Remove a layer
     pub fn remove_layer(&mut self, layer: Layer) {
+        if !self.check_layer(&layer) {
+            return;
+        }
         let s = &mut *self.vx;
-        let mut index = None;
-        for (idx, x) in s.draw_order.iter().enumerate() {
-            match x {
-                DrawType::Text { id } if *id == layer.0 => {
-                    index = Some(idx);
-                    break;
-                }
-                _ => {}
+        s.texts[layer.0].generation = 0;
+        let draw_type = s.take_from_draw_order(|x| match x {
+            DrawType::Text { id } => *id == layer.0,
+            _ => false,
+        });
+        if let Some(draw_type) = draw_type {
+            s.layer_holes.push(draw_type);
+        }
+    }
+
+    /// Check if a text handle still refers to a text, that is, neither the text nor its layer has
+    /// been removed
+    pub fn is_alive(&self, handle: &Handle) -> bool {
+        let tex = &self.vx.texts[handle.layer];
+        tex.generation != 0 && tex.generations.get(handle.id) == Some(&handle.generation)
+    }
+
+    fn check(&self, handle: &Handle) -> bool {
+        let tex = &self.vx.texts[handle.layer];
+        let current = if tex.generation == 0 {
+            0
+        } else {
+            tex.generations.get(handle.id).cloned().unwrap_or(0)
+        };
+        is_current(&self.vx.log, current, handle.generation)
+    }
+
+    fn check_layer(&self, layer: &Layer) -> bool {
+        is_current(&self.vx.log, self.vx.texts[layer.0].generation, layer.1)
+    }
+
+    fn vertices(&self, handle: &Handle) -> std::ops::Range<usize> {
+        self.vx.texts[handle.layer].ranges[handle.id].clone()
+    }
+
+    /// Add a font to a text layer, for use with [TextOptions::font] or as a fallback
+    ///
+    /// Adding to a removed layer returns a font that no text can use.
+    pub fn add_font(&mut self, layer: &Layer, font: &'static [u8]) -> Font {
+        if !self.check_layer(layer) {
+            return Font(usize::max_value());
+        }
+        Font(self.vx.texts[layer.0].glyph_brush.add_font_bytes(font).0)
+    }
+
+    /// Set the fonts searched, in order, for characters missing from the font of a text
+    ///
+    /// All texts of the layer are laid out again, which resets per-glyph changes.
+    pub fn set_fallback_fonts(&mut self, layer: &Layer, fonts: &[Font]) {
+        if !self.check_layer(layer) {
+            return;
+        }
+        let font_count = self.vx.texts[layer.0].glyph_brush.fonts().len();
+        if fonts.iter().any(|font| font.0 >= font_count) {
+            error!(
+                self.vx.log,
+                "Ignoring fallback fonts that do not belong to the layer"
+            );
+        }
+        self.vx.texts[layer.0].fallbacks = fonts
+            .iter()
+            .map(|font| font.0)
+            .filter(|font| *font < font_count)
+            .collect();
+        for id in 0..self.vx.texts[layer.0].texts.len() {
+            let string = self.vx.texts[layer.0].texts[id].clone();
+            let handle = Handle {
+                layer: layer.0,
+                id,
+                generation: self.vx.texts[layer.0].generations[id],
+            };
+            if handle.generation != 0 {
+                self.relayout(&handle, &string);
             }
         }
-        if let Some(idx) = index {
-            let draw_type = s.draw_order.remove(idx);
-            s.layer_holes.push(draw_type);
+    }
+
+    /// Change the outline, glow, and shadow of a distance field layer
+    ///
+    /// The size and spread of the given options are ignored, they are fixed when the layer is
+    /// created.
+    pub fn set_distance_field_effects(&mut self, layer: &Layer, effects: DistanceField) {
+        if !self.check_layer(layer) {
+            return;
+        }
+        let distance_field = self.vx.texts[layer.0]
+            .distance_field
+            .as_mut()
+            .expect("Layer does not render distance fields");
+        distance_field.effects = DistanceField {
+            spread: distance_field.spread,
+            ..effects
         }
+        .effects();
     }
 
     /// Add text to this layer
+    ///
+    /// The slot of a removed text is reused if there is one. Adding to a removed layer returns a
+    /// handle that is already stale.
     pub fn add(&mut self, layer: &Layer, string: &str, opts: TextOptions) -> Handle {
-        let section = glyph_brush::Section {
-            text: string,
-            scale: glyph_brush::rusttype::Scale {
-                x: opts.font_size_x,
-                y: opts.font_size_y,
+        if !self.check_layer(layer) {
+            return Handle {
+                layer: layer.0,
+                id: 0,
+                generation: 0,
+            };
+        }
+        let font_size = (opts.font_size_x, opts.font_size_y);
+        let mut layout = opts.layout();
+        let mut spans = opts.spans;
+        let font_count = self.vx.texts[layer.0].glyph_brush.fonts().len();
+        sanitize_styles(&self.vx.log, string, font_count, &mut layout, &mut spans);
+        let glyphs = self.lay_out(
+            layer,
+            None,
+            &Pending {
+                string,
+                font_size,
+                layout: &layout,
+                spans: &spans,
             },
-            ..glyph_brush::Section::default()
+            opts.origin,
+        );
+        let placement = TextPlacement {
+            translation: opts.translation,
+            rotation: opts.rotation,
+            scale: opts.scale,
+            opacity: 255,
         };
-        self.vx.texts[layer.0].glyph_brush.queue(section);
-        self.vx.texts[layer.0].posbuf_touch = self.vx.swapconfig.image_count;
-        self.vx.texts[layer.0].opacbuf_touch = self.vx.swapconfig.image_count;
-        self.vx.texts[layer.0].uvbuf_touch = self.vx.swapconfig.image_count;
-        self.vx.texts[layer.0].tranbuf_touch = self.vx.swapconfig.image_count;
-        self.vx.texts[layer.0].rotbuf_touch = self.vx.swapconfig.image_count;
-        self.vx.texts[layer.0].scalebuf_touch = self.vx.swapconfig.image_count;
-        self.vx.wait_for_fences();
-        let prev_begin = self.vx.texts[layer.0].posbuffer.len();
-        let mut count = 0usize;
-        let mut tex_values = vec![];
 
+        let generation = self.vx.next_generation();
+        let tex = &mut self.vx.texts[layer.0];
+        let id = match tex.removed.pop() {
+            Some(id) => {
+                tex.font_sizes[id] = font_size;
+                tex.origin[id] = opts.origin;
+                tex.layouts[id] = layout;
+                tex.spans[id] = spans;
+                tex.placements[id] = placement;
+                tex.generations[id] = generation;
+                id
+            }
+            None => {
+                let end = tex.posbuffer.len();
+                tex.texts.push(String::new());
+                tex.font_sizes.push(font_size);
+                tex.origin.push(opts.origin);
+                tex.layouts.push(layout);
+                tex.spans.push(spans);
+                tex.width.push(0);
+                tex.height.push(0);
+                tex.ranges.push(end..end);
+                tex.placements.push(placement);
+                tex.cells.push(vec![]);
+                tex.generations.push(generation);
+                tex.texts.len() - 1
+            }
+        };
+        self.replace_glyphs(layer.0, id, string, glyphs);
+        Handle {
+            layer: layer.0,
+            id,
+            generation,
+        }
+    }
+
+    /// Remove a text
+    ///
+    /// The glyphs of the text are removed right away, the glyphs of the texts after it move down
+    /// to fill the space. [Texts::add] reuses the slot of the text.
+    pub fn remove(&mut self, handle: Handle) {
+        if !self.check(&handle) {
+            return;
+        }
+        let nothing = Glyphs {
+            positions: vec![],
+            uvs: vec![],
+            colors: vec![],
+            cells: vec![],
+            width: 0,
+            height: 0,
+        };
+        self.replace_glyphs(handle.layer, handle.id, "", nothing);
+        let tex = &mut self.vx.texts[handle.layer];
+        tex.spans[handle.id].clear();
+        tex.generations[handle.id] = 0;
+        tex.removed.push(handle.id);
+    }
+
+    /// Replace the string of a text, laying it out again in place
+    ///
+    /// The translation, rotation, scale, opacity, and color set on the text as a whole are kept,
+    /// per-glyph changes and colored spans are not. Font spans are kept if their bounds lie on
+    /// character boundaries of the new string, bounds past its end are clamped. The layout
+    /// options given when the text was added are reused.
+    pub fn set_text(&mut self, handle: &Handle, string: &str) {
+        if !self.check(handle) {
+            return;
+        }
+        self.vx.texts[handle.layer].spans[handle.id].retain(|(range, span)| {
+            matches!(span, Span::Font(_))
+                && string.is_char_boundary(range.start.min(string.len()))
+                && string.is_char_boundary(range.end.min(string.len()))
+        });
+        self.relayout(handle, string);
+    }
+
+    /// Lay out a text again with its stored options and replace its glyphs
+    fn relayout(&mut self, handle: &Handle, string: &str) {
+        let (font_size, layout, spans, origin) = {
+            let s = &mut *self.vx;
+            let tex = &mut s.texts[handle.layer];
+            let font_count = tex.glyph_brush.fonts().len();
+            sanitize_styles(
+                &s.log,
+                string,
+                font_count,
+                &mut tex.layouts[handle.id],
+                &mut tex.spans[handle.id],
+            );
+            (
+                tex.font_sizes[handle.id],
+                tex.layouts[handle.id],
+                tex.spans[handle.id].clone(),
+                tex.origin[handle.id],
+            )
+        };
+        let layer = Layer(handle.layer, self.vx.texts[handle.layer].generation);
+        let glyphs = self.lay_out(
+            &layer,
+            Some(handle.id),
+            &Pending {
+                string,
+                font_size,
+                layout: &layout,
+                spans: &spans,
+            },
+            origin,
+        );
+        self.replace_glyphs(handle.layer, handle.id, string, glyphs);
+    }
+
+    /// Splice the glyphs of a text into the vertex buffers, moving the texts after it
+    fn replace_glyphs(&mut self, layer: usize, id: usize, string: &str, glyphs: Glyphs) {
+        let image_count = self.vx.swapconfig.image_count;
+        let tex = &mut self.vx.texts[layer];
+        let placement = tex.placements[id];
+        let range = tex.ranges[id].clone();
+        let count = glyphs.positions.len();
+        tex.posbuffer.splice(range.clone(), glyphs.positions);
+        tex.uvbuffer.splice(range.clone(), glyphs.uvs);
+        tex.opacbuffer
+            .splice(range.clone(), placement.opacities(count));
+        tex.tranbuffer
+            .splice(range.clone(), placement.translations(count));
+        tex.rotbuffer
+            .splice(range.clone(), placement.rotations(count));
+        tex.scalebuffer
+            .splice(range.clone(), placement.scales(count));
+        tex.colbuffer.splice(range.clone(), glyphs.colors);
+        tex.posbuf_touch = image_count;
+        tex.opacbuf_touch = image_count;
+        tex.uvbuf_touch = image_count;
+        tex.tranbuf_touch = image_count;
+        tex.rotbuf_touch = image_count;
+        tex.scalebuf_touch = image_count;
+        tex.colbuf_touch = image_count;
+
+        tex.ranges[id] = range.start..range.start + count;
+        for later in tex.ranges[id + 1..].iter_mut() {
+            *later = later.start + count - range.len()..later.end + count - range.len();
+        }
+        tex.texts[id] = string.to_string();
+        tex.width[id] = glyphs.width;
+        tex.height[id] = glyphs.height;
+        tex.cells[id] = glyphs.cells;
+    }
+
+    /// Lay out a string and write its glyphs into the glyph texture
+    ///
+    /// The text with id `skip` is being replaced by the string.
+    fn lay_out(
+        &mut self,
+        layer: &Layer,
+        skip: Option<usize>,
+        pending: &Pending,
+        origin: (f32, f32),
+    ) -> Glyphs {
         let mut top = 0;
         let mut bottom = 0;
         let mut left = 0;
         let mut right = 0;
 
-        let mut resized = false;
+        let mut vertices = self.cache_glyphs(layer, skip, pending);
+        let tex = &self.vx.texts[layer.0];
+        let (raster_size, raster_layout, factor) = rasterization(
+            tex.distance_field.as_ref(),
+            pending.font_size,
+            pending.layout,
+        );
+        let mut cells = character_cells(
+            &tex.glyph_brush,
+            pending.string,
+            raster_size,
+            &raster_layout,
+            pending.spans,
+            &tex.fallbacks,
+            &vertices,
+        );
+        space_lines(&tex.glyph_brush, raster_size, &raster_layout, &mut vertices);
+        for vtx in vertices.iter() {
+            top = top.min(vtx.topleft.0);
+            left = left.min(vtx.topleft.1);
+            bottom = bottom.max(vtx.bottomright.1);
+            right = right.max(vtx.bottomright.0);
+        }
+        let width = ((right - left) as f32 * factor).round() as i32;
+        let height = ((bottom - top) as f32 * factor).round() as i32;
+        let muscale = PIX_WIDTH_DIVISOR / factor;
+        let dx = origin.0 * width as f32 / PIX_WIDTH_DIVISOR;
+        let dy = origin.1 * height as f32 / PIX_WIDTH_DIVISOR;
+        for cell in cells.iter_mut() {
+            let [left, top, right, bottom] = cell.rect;
+            cell.rect = [
+                left / muscale - dx,
+                top / muscale - dy,
+                right / muscale - dx,
+                bottom / muscale - dy,
+            ];
+        }
+        if let Some(ref distance_field) = tex.distance_field {
+            distance_field.expand(tex.glyph_brush.texture_dimensions(), &mut vertices);
+        }
+        let (positions, uvs) = vertices
+            .iter()
+            .map(|vtx| glyph_quad(vtx, origin, (width, height), factor))
+            .unzip();
+        let colors = vertices
+            .iter()
+            .map(|vtx| {
+                let [r, g, b, a] = vtx.color;
+                [r, g, b, a, r, g, b, a, r, g, b, a, r, g, b, a]
+            })
+            .collect();
+
+        Glyphs {
+            positions,
+            uvs,
+            colors,
+            cells,
+            width,
+            height,
+        }
+    }
 
-        match self.vx.texts[layer.0].glyph_brush.process_queued(
-            |rect, tex_data| {
-                tex_values.push((rect, tex_data.to_owned()));
-            },
-            |vtx| SData {
-                uv_begin: (vtx.tex_coords.min.x, vtx.tex_coords.min.y),
-                uv_end: (vtx.tex_coords.max.x, vtx.tex_coords.max.y),
-                topleft: (vtx.pixel_coords.min.x, vtx.pixel_coords.min.y),
-                bottomright: (vtx.pixel_coords.max.x, vtx.pixel_coords.max.y),
-            },
-        ) {
-            Ok(BrushAction::Draw(vertices)) => {
-                assert_eq!(0, count);
-                count = vertices.len();
-                self.vx.texts[layer.0].texts.push(string.to_string());
-                self.vx.texts[layer.0]
-                    .font_sizes
-                    .push((opts.font_size_x, opts.font_size_y));
-                self.vx.texts[layer.0]
-                    .origin
-                    .push((opts.origin.0, opts.origin.1));
-                for vtx in vertices.iter() {
-                    top = top.min(vtx.topleft.0);
-                    left = left.min(vtx.topleft.1);
-                    bottom = bottom.max(vtx.bottomright.1);
-                    right = right.max(vtx.bottomright.0);
-                }
-                for vtx in vertices.iter() {
-                    let muscale = PIX_WIDTH_DIVISOR;
-                    let uv_b = vtx.uv_begin;
-                    let uv_e = vtx.uv_end;
-                    let beg = vtx.topleft;
-                    let end = vtx.bottomright;
-                    let begf = (beg.0 as f32 / muscale, beg.1 as f32 / muscale);
-                    let width = (end.0 - beg.0) as f32 / muscale;
-                    let height = (end.1 - beg.1) as f32 / muscale;
-                    let uv_a = uv_b;
-                    let uv_b = uv_e;
-
-                    let topleft = (begf.0, begf.1);
-                    let topleft_uv = uv_a;
-
-                    let topright = (begf.0 + width, begf.1);
-                    let topright_uv = (uv_b.0, uv_a.1);
-
-                    let bottomleft = (begf.0, begf.1 + height);
-                    let bottomleft_uv = (uv_a.0, uv_b.1);
-
-                    let bottomright = (begf.0 + width, begf.1 + height);
-                    let bottomright_uv = (uv_b.0, uv_b.1);
-                    let tex = &mut self.vx.texts[layer.0];
-                    tex.posbuffer.push([
-                        topleft.0,
-                        topleft.1,
-                        bottomleft.0,
-                        bottomleft.1,
-                        bottomright.0,
-                        bottomright.1,
-                        topright.0,
-                        topright.1,
-                    ]);
-                    tex.opacbuffer.push([255, 255, 255, 255]);
-                    tex.tranbuffer.push([
-                        opts.translation.0,
-                        opts.translation.1,
-                        opts.translation.0,
-                        opts.translation.1,
-                        opts.translation.0,
-                        opts.translation.1,
-                        opts.translation.0,
-                        opts.translation.1,
-                    ]);
-                    tex.rotbuffer.push([opts.rotation; 4]);
-                    tex.scalebuffer.push([opts.scale; 4]);
-                    tex.uvbuffer.push([
-                        topleft_uv.0,
-                        topleft_uv.1,
-                        bottomleft_uv.0,
-                        bottomleft_uv.1,
-                        bottomright_uv.0,
-                        bottomright_uv.1,
-                        topright_uv.0,
-                        topright_uv.1,
-                    ]);
+    /// Write glyphs rasterized by the glyph brush into the glyph texture
+    ///
+    /// Distance field layers store the distance field of each glyph in a slot of its own. Returns
+    /// false when there is no room left for a slot.
+    fn upload_glyphs(
+        &mut self,
+        layer: &Layer,
+        glyphs: Vec<(glyph_brush::rusttype::Rect<u32>, Vec<u8>)>,
+    ) -> bool {
+        if glyphs.is_empty() {
+            return true;
+        }
+        let tex = &mut self.vx.texts[layer.0];
+        let texture = tex.glyph_brush.texture_dimensions();
+        // Space between distance field slots is sampled by shadows, so it must not hold garbage
+        let fresh_slots = tex
+            .distance_field
+            .as_ref()
+            .map_or(false, |distance_field| distance_field.slots.is_empty());
+        let rects = glyphs.iter().map(|(rect, _)| *rect).collect::<Vec<_>>();
+        let mut writes = vec![];
+        for (rect, coverage) in glyphs {
+            let (width, height) = (rect.max.x - rect.min.x, rect.max.y - rect.min.y);
+            match tex.distance_field {
+                Some(ref mut distance_field) => {
+                    let spread = distance_field.spread;
+                    let size = (width + 2 * spread, height + 2 * spread);
+                    let slot = match distance_field.allocate(size, texture) {
+                        Some(slot) => slot,
+                        None => return false,
+                    };
+                    distance_field.slots.insert((rect.min.x, rect.min.y), slot);
+                    let field = signed_distance_field(&coverage, (width, height), spread);
+                    writes.push((slot, size.0, field));
                 }
-            }
-            Ok(BrushAction::ReDraw) => {}
-            Err(BrushError::TextureTooSmall { suggested }) => {
-                self.resize_internal_texture(&layer, suggested);
-                resized = true;
+                None => writes.push(((rect.min.x, rect.min.y), width, coverage)),
             }
         }
 
-        if resized {
-            self.recompute_text(layer);
-            return self.add(layer, string, opts);
-        }
-
-        let width = right - left;
-        let height = bottom - top;
-        self.vx.texts[layer.0].width.push(width);
-        self.vx.texts[layer.0].height.push(height);
-        for idx in prev_begin..prev_begin + count {
-            let pos = &mut self.vx.texts[layer.0].posbuffer[idx];
-            pos[0] -= opts.origin.0 * width as f32 / PIX_WIDTH_DIVISOR;
-            pos[1] -= opts.origin.1 * height as f32 / PIX_WIDTH_DIVISOR;
-            pos[2] -= opts.origin.0 * width as f32 / PIX_WIDTH_DIVISOR;
-            pos[3] -= opts.origin.1 * height as f32 / PIX_WIDTH_DIVISOR;
-            pos[4] -= opts.origin.0 * width as f32 / PIX_WIDTH_DIVISOR;
-            pos[5] -= opts.origin.1 * height as f32 / PIX_WIDTH_DIVISOR;
-            pos[6] -= opts.origin.0 * width as f32 / PIX_WIDTH_DIVISOR;
-            pos[7] -= opts.origin.1 * height as f32 / PIX_WIDTH_DIVISOR;
-        }
+        unsafe {
+            let foot = self.vx.device.get_image_subresource_footprint(
+                &tex.image_buffer,
+                image::Subresource {
+                    aspects: format::Aspects::COLOR,
+                    level: 0,
+                    layer: 0,
+                },
+            );
+            let target = self
+                .vx
+                .device
+                .map_memory(&tex.image_memory, 0..tex.image_requirements.size)
+                .expect("unable to acquire mapping writer");
+            let target =
+                std::slice::from_raw_parts_mut(target, tex.image_requirements.size as usize);
+            if fresh_slots {
+                for pixel in target.chunks_mut(4) {
+                    pixel.copy_from_slice(&[255, 255, 255, 0]);
+                }
+            }
 
-        for (rect, tex_data) in tex_values {
-            unsafe {
-                let foot = self.vx.device.get_image_subresource_footprint(
-                    &self.vx.texts[layer.0].image_buffer,
-                    image::Subresource {
-                        aspects: format::Aspects::COLOR,
-                        level: 0,
-                        layer: 0,
-                    },
-                );
-                let target = self
-                    .vx
-                    .device
-                    .map_memory(
-                        &self.vx.texts[layer.0].image_memory,
-                        0..self.vx.texts[layer.0].image_requirements.size,
-                    )
-                    .expect("unable to acquire mapping writer");
-
-                let width = rect.max.x - rect.min.x;
-                for (idx, alpha) in tex_data.iter().enumerate() {
+            for ((left, top), width, alphas) in writes {
+                for (idx, alpha) in alphas.iter().enumerate() {
                     let idx = idx as u32;
-                    let x = rect.min.x + idx % width;
-                    let y = rect.min.y + idx / width;
+                    let x = left + idx % width;
+                    let y = top + idx / width;
                     let access = foot.row_pitch * u64::from(y) + u64::from(x * 4);
-                    std::slice::from_raw_parts_mut(
-                        target,
-                        self.vx.texts[layer.0].image_requirements.size as usize,
-                    )[access as usize..(access + 4) as usize]
+                    target[access as usize..(access + 4) as usize]
                         .copy_from_slice(&[255, 255, 255, *alpha]);
                 }
-                self.vx
-                    .device
-                    .unmap_memory(&self.vx.texts[layer.0].image_memory);
             }
+            self.vx.device.unmap_memory(&tex.image_memory);
         }
-        Handle {
-            layer: layer.0,
-            vertices: prev_begin..prev_begin + count,
-            id: self.vx.texts[layer.0].width.len() - 1,
-        }
-    }
-
-    fn recompute_text(&mut self, layer: &Layer) {
-        let this_layer = &mut self.vx.texts[layer.0];
-        let mut count = 0;
-        for (idx, text) in this_layer.texts.iter().enumerate() {
-            let font_size = this_layer.font_sizes[idx];
-            let width = this_layer.width[idx];
-            let height = this_layer.height[idx];
-            let origin = this_layer.origin[idx];
-            let section = glyph_brush::Section {
-                text: &text,
-                scale: glyph_brush::rusttype::Scale {
-                    x: font_size.0,
-                    y: font_size.1,
-                },
-                ..glyph_brush::Section::default()
-            };
-            let mut tex_values = vec![];
+        for rect in rects {
+            tex.glyph_rects.retain(|old| !overlaps(old, &rect));
+            tex.glyph_rects.push(rect);
+        }
+        true
+    }
 
-            let _just_clear_the_cache = this_layer.glyph_brush.process_queued(
-                |rect, tex_data| {
-                    tex_values.push((rect, tex_data.to_owned()));
-                },
-                |_| SData::default(),
+    /// Lay out a pending string and cache its glyphs
+    ///
+    /// Only the pending string is queued, the quads of the other texts are left alone. If the
+    /// glyph cache evicted glyphs to make room, which shows as an upload over an earlier cache
+    /// entry, or the glyph texture has to grow, all texts are cached again with
+    /// [Texts::recache_glyphs]. Returns the vertices of the pending string, before its lines are
+    /// spaced.
+    fn cache_glyphs(
+        &mut self,
+        layer: &Layer,
+        skip: Option<usize>,
+        pending: &Pending,
+    ) -> Vec<SData> {
+        let tex = &mut self.vx.texts[layer.0];
+        queue_pending(tex, pending, tex.texts.len());
+        let mut tex_values = vec![];
+        match tex.glyph_brush.process_queued(
+            |rect, tex_data| {
+                tex_values.push((rect, tex_data.to_owned()));
+            },
+            glyph_data,
+        ) {
+            Ok(BrushAction::Draw(vertices)) => {
+                let evicted = tex_values
+                    .iter()
+                    .any(|(rect, _)| tex.glyph_rects.iter().any(|old| overlaps(old, rect)));
+                if evicted {
+                    // Evicted glyphs may still be drawn by the frames in flight
+                    self.vx.wait_for_fences_or_log();
+                    if !self.upload_glyphs(layer, tex_values) {
+                        self.grow_internal_texture(layer);
+                    }
+                } else if self.upload_glyphs(layer, tex_values) {
+                    return vertices;
+                } else {
+                    self.vx.wait_for_fences_or_log();
+                    self.grow_internal_texture(layer);
+                }
+            }
+            Ok(BrushAction::ReDraw) => return vec![],
+            Err(BrushError::TextureTooSmall { suggested }) => {
+                self.vx.wait_for_fences_or_log();
+                self.resize_internal_texture(layer, suggested);
+            }
+        }
+        self.recache_glyphs(layer, skip, pending)
+    }
+
+    /// Lay out all texts of a layer together with a pending string and cache their glyphs
+    ///
+    /// Every text is queued at once, so the glyph cache never evicts glyphs that are still drawn,
+    /// and every text is uploaded again when the glyph texture is enlarged. As the glyph cache may
+    /// move glyphs around, the quads of the texts are rewritten; a text whose glyph count changed
+    /// is left alone, it is about to be laid out again. The text with id `skip` is left out.
+    /// Returns the vertices of the pending string, before its lines are spaced.
+    fn recache_glyphs(
+        &mut self,
+        layer: &Layer,
+        skip: Option<usize>,
+        pending: &Pending,
+    ) -> Vec<SData> {
+        let tex = &mut self.vx.texts[layer.0];
+        let count = tex.texts.len();
+        for id in (0..count).filter(|id| Some(*id) != skip) {
+            let (raster_size, raster_layout, _) = rasterization(
+                tex.distance_field.as_ref(),
+                tex.font_sizes[id],
+                &tex.layouts[id],
             );
+            let section = section(
+                &mut tex.glyph_brush,
+                &tex.texts[id],
+                raster_size,
+                &raster_layout,
+                &tex.spans[id],
+                &tex.fallbacks,
+            );
+            tex.glyph_brush.queue(VariedSection {
+                z: id as f32,
+                ..section
+            });
+        }
+        queue_pending(tex, pending, count);
+        self.vx.wait_for_fences_or_log();
 
-            this_layer.glyph_brush.queue(section);
-            let mut resized = None;
-            match this_layer.glyph_brush.process_queued(
+        let mut vertices = loop {
+            let mut tex_values = vec![];
+            match self.vx.texts[layer.0].glyph_brush.process_queued(
                 |rect, tex_data| {
                     tex_values.push((rect, tex_data.to_owned()));
                 },
-                |vtx| SData {
-                    uv_begin: (vtx.tex_coords.min.x, vtx.tex_coords.min.y),
-                    uv_end: (vtx.tex_coords.max.x, vtx.tex_coords.max.y),
-                    topleft: (vtx.pixel_coords.min.x, vtx.pixel_coords.min.y),
-                    bottomright: (vtx.pixel_coords.max.x, vtx.pixel_coords.max.y),
-                },
+                glyph_data,
             ) {
                 Ok(BrushAction::Draw(vertices)) => {
-                    for vtx in vertices.iter() {
-                        let muscale = PIX_WIDTH_DIVISOR;
-                        let uv_b = vtx.uv_begin;
-                        let uv_e = vtx.uv_end;
-                        let beg = vtx.topleft;
-                        let end = vtx.bottomright;
-                        let begf = (beg.0 as f32 / muscale, beg.1 as f32 / muscale);
-                        let width2 = (end.0 - beg.0) as f32 / muscale;
-                        let height2 = (end.1 - beg.1) as f32 / muscale;
-                        let uv_a = uv_b;
-                        let uv_b = uv_e;
-
-                        let topleft = (begf.0, begf.1);
-                        let topleft_uv = uv_a;
-
-                        let topright = (begf.0 + width2, begf.1);
-                        let topright_uv = (uv_b.0, uv_a.1);
-
-                        let bottomleft = (begf.0, begf.1 + height2);
-                        let bottomleft_uv = (uv_a.0, uv_b.1);
-
-                        let bottomright = (begf.0 + width2, begf.1 + height2);
-                        let bottomright_uv = (uv_b.0, uv_b.1);
-
-                        this_layer.posbuffer[count].copy_from_slice(&[
-                            topleft.0 - origin.0 * width as f32 / PIX_WIDTH_DIVISOR,
-                            topleft.1 - origin.1 * height as f32 / PIX_WIDTH_DIVISOR,
-                            bottomleft.0 - origin.0 * width as f32 / PIX_WIDTH_DIVISOR,
-                            bottomleft.1 - origin.1 * height as f32 / PIX_WIDTH_DIVISOR,
-                            bottomright.0 - origin.0 * width as f32 / PIX_WIDTH_DIVISOR,
-                            bottomright.1 - origin.1 * height as f32 / PIX_WIDTH_DIVISOR,
-                            topright.0 - origin.0 * width as f32 / PIX_WIDTH_DIVISOR,
-                            topright.1 - origin.1 * height as f32 / PIX_WIDTH_DIVISOR,
-                        ]);
-                        this_layer.uvbuffer[count].copy_from_slice(&[
-                            topleft_uv.0,
-                            topleft_uv.1,
-                            bottomleft_uv.0,
-                            bottomleft_uv.1,
-                            bottomright_uv.0,
-                            bottomright_uv.1,
-                            topright_uv.0,
-                            topright_uv.1,
-                        ]);
-                        count += 1;
+                    if self.upload_glyphs(layer, tex_values) {
+                        break vertices;
                     }
+                    self.grow_internal_texture(layer);
+                    return self.recache_glyphs(layer, skip, pending);
                 }
-                Ok(BrushAction::ReDraw) => {}
+                Ok(BrushAction::ReDraw) => break vec![],
+                // The queue is kept when the glyph brush fails
                 Err(BrushError::TextureTooSmall { suggested }) => {
-                    resized = Some(suggested);
+                    self.resize_internal_texture(layer, suggested);
                 }
             }
+        };
 
-            for (rect, tex_data) in tex_values {
-                unsafe {
-                    let foot = self.vx.device.get_image_subresource_footprint(
-                        &this_layer.image_buffer,
-                        image::Subresource {
-                            aspects: format::Aspects::COLOR,
-                            level: 0,
-                            layer: 0,
-                        },
-                    );
-                    let target = self
-                        .vx
-                        .device
-                        .map_memory(
-                            &this_layer.image_memory,
-                            0..this_layer.image_requirements.size,
-                        )
-                        .expect("unable to acquire mapping writer");
-
-                    let width = rect.max.x - rect.min.x;
-                    for (idx, alpha) in tex_data.iter().enumerate() {
-                        let idx = idx as u32;
-                        let x = rect.min.x + idx % width;
-                        let y = rect.min.y + idx / width;
-                        let access = foot.row_pitch * u64::from(y) + u64::from(x * 4);
-                        std::slice::from_raw_parts_mut(
-                            target,
-                            this_layer.image_requirements.size as usize,
-                        )[access as usize..(access + 4) as usize]
-                            .copy_from_slice(&[255, 255, 255, *alpha]);
-                    }
-                    self.vx.device.unmap_memory(&this_layer.image_memory);
-                }
+        let image_count = self.vx.swapconfig.image_count;
+        let tex = &mut self.vx.texts[layer.0];
+        tex.posbuf_touch = image_count;
+        tex.uvbuf_touch = image_count;
+        let mut pending_vertices = vec![];
+        let mut begin = 0;
+        while begin < vertices.len() {
+            let id = vertices[begin].text;
+            let end = begin
+                + vertices[begin..]
+                    .iter()
+                    .take_while(|vtx| vtx.text == id)
+                    .count();
+            let glyphs = &mut vertices[begin..end];
+            begin = end;
+            if id == count {
+                pending_vertices = glyphs.to_vec();
+                continue;
             }
-
-            if let Some(suggested) = resized {
-                self.resize_internal_texture(layer, suggested);
-                self.recompute_text(layer);
-                return;
+            if glyphs.len() != tex.ranges[id].len() {
+                continue;
+            }
+            let (raster_size, raster_layout, factor) = rasterization(
+                tex.distance_field.as_ref(),
+                tex.font_sizes[id],
+                &tex.layouts[id],
+            );
+            space_lines(&tex.glyph_brush, raster_size, &raster_layout, glyphs);
+            if let Some(ref distance_field) = tex.distance_field {
+                distance_field.expand(tex.glyph_brush.texture_dimensions(), glyphs);
+            }
+            let size = (tex.width[id], tex.height[id]);
+            for (idx, vtx) in tex.ranges[id].clone().zip(glyphs.iter()) {
+                let (pos, uv) = glyph_quad(vtx, tex.origin[id], size, factor);
+                tex.posbuffer[idx] = pos;
+                tex.uvbuffer[idx] = uv;
             }
         }
+        pending_vertices
+    }
+
+    /// Double the size of the glyph texture
+    fn grow_internal_texture(&mut self, layer: &Layer) {
+        let (width, height) = self.vx.texts[layer.0].glyph_brush.texture_dimensions();
+        self.resize_internal_texture(layer, (width * 2, height * 2));
     }
 
     fn resize_internal_texture(&mut self, layer: &Layer, suggested: (u32, u32)) {
@@ -1246,7 +2292,8 @@ impl<'a> Texts<'a> {
                 memory::Properties::DEVICE_LOCAL
                     | memory::Properties::CPU_VISIBLE
                     | memory::Properties::COHERENT,
-            );
+            )
+            .expect("Unable to find memory type id");
             self.vx
                 .device
                 .allocate_memory(memory_type_id, image_requirements.size)
@@ -1344,6 +2391,10 @@ impl<'a> Texts<'a> {
         self.vx.texts[layer.0]
             .glyph_brush
             .resize_texture(suggested.0, suggested.1);
+        if let Some(ref mut distance_field) = self.vx.texts[layer.0].distance_field {
+            distance_field.clear();
+        }
+        self.vx.texts[layer.0].glyph_rects.clear();
     }
 
     /// Get the width of the text in native -1..1 coordinates
@@ -1368,8 +2419,8 @@ impl<'a> Texts<'a> {
     /// The perspective used during rendering does not factor into the model size.
     pub fn get_model_size(&self, handle: &Handle) -> (f32, f32) {
         let (w, h) = (self.get_width(handle), self.get_height(handle));
-        let scale = self.vx.texts[handle.layer].scalebuffer[handle.vertices.start][0];
-        let rotation = self.vx.texts[handle.layer].rotbuffer[handle.vertices.start][0];
+        let placement = self.vx.texts[handle.layer].placements[handle.id];
+        let (scale, rotation) = (placement.scale, placement.rotation);
 
         let size = Vector4::new(w, h, 0.0, 0.0);
         let angle = Matrix4::from_angle_z(Rad(rotation));
@@ -1393,8 +2444,8 @@ impl<'a> Texts<'a> {
     /// layer.
     pub fn get_world_size(&self, handle: &Handle) -> (f32, f32) {
         let (w, h) = (self.get_width(handle), self.get_height(handle));
-        let scale = self.vx.texts[handle.layer].scalebuffer[handle.vertices.start][0];
-        let rotation = self.vx.texts[handle.layer].rotbuffer[handle.vertices.start][0];
+        let placement = self.vx.texts[handle.layer].placements[handle.id];
+        let (scale, rotation) = (placement.scale, placement.rotation);
 
         let size = Vector4::new(w, h, 0.0, 0.0);
         let angle = Matrix4::from_angle_z(Rad(rotation));
@@ -1419,15 +2470,92 @@ impl<'a> Texts<'a> {
 
     /// Get the amount of glyphs for this handle
     pub fn get_glyph_count(&self, handle: &Handle) -> usize {
-        handle.vertices.end - handle.vertices.start
+        self.vertices(handle).len()
+    }
+
+    /// Find the character of a text under a point in world space
+    ///
+    /// World space is the space texts are translated in, see
+    /// [crate::camera::Camera2D::screen_to_world] to get there from window pixels. Returns the byte
+    /// index of the character in the string of the text, or `None` when no character is under the
+    /// point.
+    pub fn hit_test(&self, handle: &Handle, point: (f32, f32)) -> Option<usize> {
+        if !self.check(handle) {
+            return None;
+        }
+        self.vx.texts[handle.layer].cells[handle.id]
+            .iter()
+            .find(|cell| {
+                let ((x, y), rotation, scale) = self.cell_transform(handle, cell);
+                let (sin, cos) = rotation.sin_cos();
+                let (dx, dy) = (point.0 - x, point.1 - y);
+                let (x, y) = ((cos * dx - sin * dy) / scale, (sin * dx + cos * dy) / scale);
+                let [left, top, right, bottom] = cell.rect;
+                left <= x && x < right && top <= y && y < bottom
+            })
+            .map(|cell| cell.index)
+    }
+
+    /// Get the corners of the cell of the character at a byte index, in world space
+    ///
+    /// The cell spans the line of the character and its advance, a caret before the character goes
+    /// on its left edge. The length of the string gives the empty cell after the last character.
+    /// The corners are in the order top left, bottom left, bottom right, top right. Returns `None`
+    /// when the index does not start a character.
+    pub fn caret_rect(&self, handle: &Handle, index: usize) -> Option<[(f32, f32); 4]> {
+        if !self.check(handle) {
+            return None;
+        }
+        let cell = self.vx.texts[handle.layer].cells[handle.id]
+            .iter()
+            .find(|cell| cell.index == index)?;
+        let ((x, y), rotation, scale) = self.cell_transform(handle, cell);
+        let (sin, cos) = rotation.sin_cos();
+        let world = |(cx, cy): (f32, f32)| {
+            (
+                (cos * cx + sin * cy) * scale + x,
+                (cos * cy - sin * cx) * scale + y,
+            )
+        };
+        let [left, top, right, bottom] = cell.rect;
+        Some([
+            world((left, top)),
+            world((left, bottom)),
+            world((right, bottom)),
+            world((right, top)),
+        ])
+    }
+
+    /// Translation, rotation, and scale of a character cell, those of its glyph if it has one
+    fn cell_transform(&self, handle: &Handle, cell: &CharacterCell) -> ((f32, f32), f32, f32) {
+        let tex = &self.vx.texts[handle.layer];
+        match cell.glyph {
+            Some(glyph) => {
+                let vertex = tex.ranges[handle.id].start + glyph;
+                let translation = tex.tranbuffer[vertex];
+                (
+                    (translation[0], translation[1]),
+                    tex.rotbuffer[vertex][0],
+                    tex.scalebuffer[vertex][0],
+                )
+            }
+            None => {
+                let placement = &tex.placements[handle.id];
+                (placement.translation, placement.rotation, placement.scale)
+            }
+        }
     }
 
     // ---
 
     /// Set the scale of the text segment
     pub fn set_translation(&mut self, handle: &Handle, translation: (f32, f32)) {
+        if !self.check(handle) {
+            return;
+        }
+        self.vx.texts[handle.layer].placements[handle.id].translation = translation;
         self.vx.texts[handle.layer].tranbuf_touch = self.vx.swapconfig.image_count;
-        for idx in handle.vertices.start..handle.vertices.end {
+        for idx in self.vertices(handle) {
             self.vx.texts[handle.layer].tranbuffer[idx].copy_from_slice(&[
                 translation.0,
                 translation.1,
@@ -1443,49 +2571,127 @@ impl<'a> Texts<'a> {
 
     /// Set the scale of the text segment
     pub fn set_scale(&mut self, handle: &Handle, scale: f32) {
+        if !self.check(handle) {
+            return;
+        }
+        self.vx.texts[handle.layer].placements[handle.id].scale = scale;
         self.vx.texts[handle.layer].scalebuf_touch = self.vx.swapconfig.image_count;
-        for idx in handle.vertices.start..handle.vertices.end {
+        for idx in self.vertices(handle) {
             self.vx.texts[handle.layer].scalebuffer[idx].copy_from_slice(&[scale; 4]);
         }
     }
 
     /// Set the opacity of a text segment
     pub fn set_opacity(&mut self, handle: &Handle, opacity: u8) {
+        if !self.check(handle) {
+            return;
+        }
+        self.vx.texts[handle.layer].placements[handle.id].opacity = opacity;
         self.vx.texts[handle.layer].opacbuf_touch = self.vx.swapconfig.image_count;
-        for idx in handle.vertices.start..handle.vertices.end {
+        for idx in self.vertices(handle) {
             self.vx.texts[handle.layer].opacbuffer[idx].copy_from_slice(&[opacity; 4]);
         }
     }
 
     /// Set the rotation of the text segment as a whole
     pub fn set_rotation<T: Copy + Into<Rad<f32>>>(&mut self, handle: &Handle, angle: T) {
+        if !self.check(handle) {
+            return;
+        }
+        self.vx.texts[handle.layer].placements[handle.id].rotation = angle.into().0;
         self.vx.texts[handle.layer].rotbuf_touch = self.vx.swapconfig.image_count;
-        for idx in handle.vertices.start..handle.vertices.end {
+        for idx in self.vertices(handle) {
             self.vx.texts[handle.layer].rotbuffer[idx].copy_from_slice(&[angle.into().0; 4]);
         }
     }
 
+    /// Set the color of the text segment as a whole, this removes all colored spans
+    pub fn set_color(&mut self, handle: &Handle, rgba: Color) {
+        if !self.check(handle) {
+            return;
+        }
+        let Color::Rgba(r, g, b, a) = rgba;
+        self.vx.texts[handle.layer].layouts[handle.id].color = [r, g, b, a];
+        self.vx.texts[handle.layer].spans[handle.id]
+            .retain(|(_, span)| !matches!(span, Span::Color(_)));
+        self.vx.texts[handle.layer].colbuf_touch = self.vx.swapconfig.image_count;
+        for idx in self.vertices(handle) {
+            self.vx.texts[handle.layer].colbuffer[idx]
+                .copy_from_slice(&[r, g, b, a, r, g, b, a, r, g, b, a, r, g, b, a]);
+        }
+    }
+
+    /// Set the color of a span of the text, given as a byte range of its string
+    ///
+    /// A bound inside a character is moved back to the start of the character. The text is laid
+    /// out again, which resets per-glyph changes. When spans overlap, the span set last takes
+    /// precedence.
+    pub fn set_color_span(&mut self, handle: &Handle, range: Range<usize>, rgba: Color) {
+        if !self.check(handle) {
+            return;
+        }
+        let Color::Rgba(r, g, b, a) = rgba;
+        let tex = &mut self.vx.texts[handle.layer];
+        tex.spans[handle.id].push((range, Span::Color([r, g, b, a])));
+        let string = tex.texts[handle.id].clone();
+        self.relayout(handle, &string);
+    }
+
+    /// Set the font of a span of the text, given as a byte range of its string
+    ///
+    /// A bound inside a character is moved back to the start of the character. The text is laid
+    /// out again, which resets per-glyph changes. When spans overlap, the span set last takes
+    /// precedence.
+    pub fn set_font_span(&mut self, handle: &Handle, range: Range<usize>, font: Font) {
+        if !self.check(handle) {
+            return;
+        }
+        let tex = &mut self.vx.texts[handle.layer];
+        tex.spans[handle.id].push((range, Span::Font(font.0)));
+        let string = tex.texts[handle.id].clone();
+        self.relayout(handle, &string);
+    }
+
     // ---
 
     /// Set the opacity on a per-glyph basis. Glyphs are enumerated as they would in a string
     pub fn set_opacity_glyphs(&mut self, handle: &Handle, mut delta: impl FnMut(usize) -> u8) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.texts[handle.layer].opacbuf_touch = self.vx.swapconfig.image_count;
-        for idx in handle.vertices.start..handle.vertices.end {
-            let delta = delta(idx - handle.vertices.start);
+        for idx in self.vertices(handle) {
+            let delta = delta(idx - self.vertices(handle).start);
             self.vx.texts[handle.layer].opacbuffer[idx]
                 .copy_from_slice(&[delta, delta, delta, delta]);
         }
     }
 
+    /// Set the color on a per-glyph basis. Glyphs are enumerated as they would in a string
+    pub fn set_color_glyphs(&mut self, handle: &Handle, mut delta: impl FnMut(usize) -> Color) {
+        if !self.check(handle) {
+            return;
+        }
+        self.vx.texts[handle.layer].colbuf_touch = self.vx.swapconfig.image_count;
+        for idx in self.vertices(handle) {
+            let Color::Rgba(r, g, b, a) = delta(idx - self.vertices(handle).start);
+            self.vx.texts[handle.layer].colbuffer[idx]
+                .copy_from_slice(&[r, g, b, a, r, g, b, a, r, g, b, a, r, g, b, a]);
+        }
+    }
+
     /// Set the rotation of the text segment as a whole
     pub fn set_translation_glyphs(
         &mut self,
         handle: &Handle,
         mut delta: impl FnMut(usize) -> (f32, f32),
     ) {
+        if !self.check(handle) {
+            return;
+        }
         self.vx.texts[handle.layer].tranbuf_touch = self.vx.swapconfig.image_count;
-        for idx in handle.vertices.start..handle.vertices.end {
-            let delta = delta(idx - handle.vertices.start);
+        for idx in self.vertices(handle) {
+            let delta = delta(idx - self.vertices(handle).start);
             self.vx.texts[handle.layer].tranbuffer[idx].copy_from_slice(&[
                 delta.0, delta.1, delta.0, delta.1, delta.0, delta.1, delta.0, delta.1,
             ]);
@@ -1852,6 +3058,505 @@ mod tests {
         }
     }
 
+    #[test]
+    fn wrapped_text_reports_wrapped_size() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let layer = vx.text().add_layer(DEJAVU, text::LayerOptions::new());
+        let string = "A dialog box with enough words to need a few lines";
+
+        let single = vx
+            .text()
+            .add(&layer, string, text::TextOptions::new().font_size(32.0));
+        let wrapped = vx.text().add(
+            &layer,
+            string,
+            text::TextOptions::new().font_size(32.0).max_width(0.5),
+        );
+        let anywhere = vx.text().add(
+            &layer,
+            string,
+            text::TextOptions::new()
+                .font_size(32.0)
+                .max_width(0.5)
+                .wrap(text::Wrap::Anywhere),
+        );
+
+        assert![vx.text().get_width(&single) > 0.5];
+        for handle in &[&wrapped, &anywhere] {
+            assert![vx.text().get_width(handle) <= 0.5];
+            assert![vx.text().get_height(handle) > 2.0 * vx.text().get_height(&single)];
+        }
+        let (world_width, world_height) = vx.text().get_world_size(&wrapped);
+        assert_eq![vx.text().get_width(&wrapped), world_width];
+        assert_eq![vx.text().get_height(&wrapped), world_height];
+        vx.draw_frame();
+    }
+
+    #[test]
+    fn aligned_lines_stay_within_the_block() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let layer = vx.text().add_layer(DEJAVU, text::LayerOptions::new());
+        let string = "The first line is long\nshort";
+        let mut add = |align| {
+            let handle = vx.text().add(
+                &layer,
+                string,
+                text::TextOptions::new().font_size(32.0).align(align),
+            );
+            let width = vx.text().get_width(&handle);
+            let first_of_second_line =
+                vx.texts[0].posbuffer[vx.texts[0].ranges[handle.id].end - 5][0];
+            (width, first_of_second_line)
+        };
+
+        let left = add(text::Align::Left);
+        let center = add(text::Align::Center);
+        let right = add(text::Align::Right);
+
+        assert![(left.0 - center.0).abs() < 0.01];
+        assert![(left.0 - right.0).abs() < 0.01];
+        assert![left.1 < center.1];
+        assert![center.1 < right.1];
+        assert![right.1 < right.0];
+    }
+
+    #[test]
+    fn line_spacing_moves_lines_apart() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let layer = vx.text().add_layer(DEJAVU, text::LayerOptions::new());
+
+        let normal = vx.text().add(&layer, "One\nTwo", text::TextOptions::new());
+        let spaced = vx.text().add(
+            &layer,
+            "One\nTwo",
+            text::TextOptions::new().line_spacing(2.0),
+        );
+
+        let normal = vx.text().get_height(&normal);
+        let spaced = vx.text().get_height(&spaced);
+        assert![spaced > normal * 1.4];
+    }
+
+    #[test]
+    fn set_text_resizes_in_place() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let layer = vx.text().add_layer(DEJAVU, text::LayerOptions::new());
+
+        let first = vx.text().add(&layer, "abc", text::TextOptions::new());
+        let second = vx.text().add(
+            &layer,
+            "xyz",
+            text::TextOptions::new().translation((0.5, -0.25)),
+        );
+        vx.text().set_opacity(&second, 128);
+
+        vx.text().set_text(&first, "abcdefgh");
+        assert_eq![8, vx.text().get_glyph_count(&first)];
+        assert_eq![8..11, vx.texts[0].ranges[second.id]];
+        assert_eq![11, vx.texts[0].posbuffer.len()];
+        assert_eq![[128; 4], vx.texts[0].opacbuffer[8]];
+        assert_eq![0.5, vx.texts[0].tranbuffer[8][0]];
+
+        let wide = vx.text().get_width(&first);
+        vx.text().set_text(&first, "a");
+        assert_eq![1, vx.text().get_glyph_count(&first)];
+        assert_eq![1..4, vx.texts[0].ranges[second.id]];
+        assert_eq![4, vx.texts[0].opacbuffer.len()];
+        assert![vx.text().get_width(&first) < wide];
+
+        vx.text().set_text(&second, "");
+        assert_eq![0, vx.text().get_glyph_count(&second)];
+        vx.draw_frame();
+    }
+
+    #[test]
+    fn removed_texts_give_back_their_glyphs_and_slot() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let layer = vx.text().add_layer(DEJAVU, text::LayerOptions::new());
+
+        let first = vx.text().add(&layer, "abc", text::TextOptions::new());
+        let second = vx.text().add(&layer, "defg", text::TextOptions::new());
+        let third = vx.text().add(
+            &layer,
+            "hi",
+            text::TextOptions::new().translation((0.5, 0.0)),
+        );
+        vx.text().remove(second);
+        assert_eq![5, vx.texts[0].posbuffer.len()];
+        assert_eq![3..5, vx.texts[0].ranges[third.id]];
+        assert_eq![0.5, vx.texts[0].tranbuffer[3][0]];
+
+        let fourth = vx.text().add(&layer, "xy", text::TextOptions::new());
+        assert_eq![1, fourth.id];
+        assert_eq![3..5, vx.texts[0].ranges[fourth.id]];
+        assert_eq![5..7, vx.texts[0].ranges[third.id]];
+        assert_eq![7, vx.texts[0].colbuffer.len()];
+        assert![vx.text().is_alive(&first)];
+        assert![vx.text().is_alive(&fourth)];
+        vx.draw_frame();
+    }
+
+    #[test]
+    fn growing_the_glyph_texture_moves_earlier_texts() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let layer = vx.text().add_layer(DEJAVU, text::LayerOptions::new());
+        let options = || text::TextOptions::new().font_size(40.0);
+
+        let small = vx.text().add(&layer, "Small text", options());
+        vx.text().add(
+            &layer,
+            "Big letters",
+            text::TextOptions::new().font_size(300.0),
+        );
+        assert![vx.text().get_texture_dimensions(&layer).0 > 256];
+
+        let again = vx.text().add(&layer, "Small text", options());
+        let tex = &vx.texts[0];
+        assert_eq![
+            tex.uvbuffer[tex.ranges[small.id].clone()],
+            tex.uvbuffer[tex.ranges[again.id].clone()]
+        ];
+        vx.draw_frame();
+    }
+
+    #[test]
+    fn adding_text_leaves_earlier_quads_alone() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let layer = vx.text().add_layer(DEJAVU, text::LayerOptions::new());
+
+        let first = vx.text().add(&layer, "Hello", text::TextOptions::new());
+        let range = vx.texts[0].ranges[first.id].clone();
+        for uv in &mut vx.texts[0].uvbuffer[range.clone()] {
+            *uv = [0.5; 8];
+        }
+        vx.text()
+            .add(&layer, "Hello there", text::TextOptions::new());
+        assert![vx.texts[0].uvbuffer[range].iter().all(|uv| *uv == [0.5; 8])];
+    }
+
+    #[test]
+    fn glyph_cache_entries_overlap_when_sharing_texels() {
+        let rect = |min: (u32, u32), max: (u32, u32)| Rect {
+            min: glyph_brush::rusttype::point(min.0, min.1),
+            max: glyph_brush::rusttype::point(max.0, max.1),
+        };
+        assert![overlaps(&rect((0, 0), (2, 2)), &rect((1, 1), (3, 3)))];
+        assert![!overlaps(&rect((0, 0), (2, 2)), &rect((2, 0), (4, 2)))];
+        assert![!overlaps(&rect((0, 0), (2, 2)), &rect((0, 2), (2, 4)))];
+    }
+
+    #[test]
+    fn hit_test_finds_characters_in_their_cells() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let layer = vx.text().add_layer(DEJAVU, text::LayerOptions::new());
+        let center = |corners: [(f32, f32); 4]| {
+            (
+                (corners[0].0 + corners[2].0) / 2.0,
+                (corners[0].1 + corners[2].1) / 2.0,
+            )
+        };
+
+        let turned = vx.text().add(
+            &layer,
+            "ab c\nd",
+            text::TextOptions::new()
+                .font_size(40.0)
+                .translation((0.25, -0.5))
+                .rotation(0.3),
+        );
+        for &index in &[0, 1, 2, 3, 5] {
+            let cell = vx.text().caret_rect(&turned, index).unwrap();
+            assert_eq![Some(index), vx.text().hit_test(&turned, center(cell))];
+        }
+        assert![vx.text().caret_rect(&turned, 6).is_some()];
+        assert![vx.text().caret_rect(&turned, 7).is_none()];
+        assert_eq![None, vx.text().hit_test(&turned, (-0.9, 0.9))];
+
+        let plain = vx.text().add(
+            &layer,
+            "ab\nd",
+            text::TextOptions::new().translation((-0.5, 0.5)),
+        );
+        let glyph = vx.texts[0].posbuffer[vx.texts[0].ranges[plain.id].start + 1];
+        let glyph_center = (
+            (glyph[0] + glyph[4]) / 2.0 - 0.5,
+            (glyph[1] + glyph[5]) / 2.0 + 0.5,
+        );
+        assert_eq![Some(1), vx.text().hit_test(&plain, glyph_center)];
+
+        let first_line = vx.text().caret_rect(&plain, 0).unwrap();
+        let second_line = vx.text().caret_rect(&plain, 3).unwrap();
+        assert![(first_line[1].1 - second_line[0].1).abs() < 1e-5];
+        assert![(first_line[0].0 - second_line[0].0).abs() < 0.01];
+    }
+
+    #[test]
+    fn overlapping_spans_split_the_text() {
+        let layout = text::TextOptions::new().layout();
+        let white = [255, 255, 255, 255];
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let parts = |text, spans: &[(Range<usize>, Span)]| {
+            styled_parts(text, &layout, spans, &[], |_, _| true)
+        };
+        assert_eq![vec![("abc", white, 0)], parts("abc", &[])];
+        assert_eq![
+            vec![
+                ("a", white, 0),
+                ("bc", red, 0),
+                ("d", blue, 0),
+                ("e", red, 0)
+            ],
+            parts(
+                "abcde",
+                &[(1..5, Span::Color(red)), (3..4, Span::Color(blue))]
+            )
+        ];
+        assert_eq![
+            vec![("a", white, 0), ("b", white, 1), ("c", red, 1)],
+            parts("abc", &[(2..10, Span::Color(red)), (1..3, Span::Font(1))])
+        ];
+    }
+
+    #[test]
+    fn styles_are_kept_within_the_string_and_layer() {
+        let logger = Logger::root(Discard, o!());
+        let mut layout = text::TextOptions::new().font(text::Font(3)).layout();
+        let mut spans = vec![
+            (1..2, Span::Color([255, 0, 0, 255])),
+            (0..9, Span::Font(2)),
+            (0..1, Span::Font(1)),
+        ];
+        sanitize_styles(&logger, "aé", 2, &mut layout, &mut spans);
+        assert_eq![0, layout.font];
+        assert_eq![1..1, spans[0].0];
+        assert_eq![(0..3, Span::Font(0)), spans[1]];
+        assert_eq![(0..1, Span::Font(1)), spans[2]];
+        styled_parts("aé", &layout, &spans, &[], |_, _| true);
+    }
+
+    #[test]
+    fn missing_glyphs_use_the_first_fallback_that_has_them() {
+        let layout = text::TextOptions::new().layout();
+        let white = [255, 255, 255, 255];
+        let has_glyph = |font, ch: char| match font {
+            0 => ('a'..='w').contains(&ch),
+            1 => ch != 'x',
+            _ => true,
+        };
+        assert_eq![
+            vec![
+                ("ab", white, 0),
+                ("12", white, 1),
+                ("x", white, 2),
+                ("\nc", white, 0)
+            ],
+            styled_parts("ab12x\nc", &layout, &[], &[1, 2], has_glyph)
+        ];
+    }
+
+    #[test]
+    fn texts_select_added_fonts() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let layer = vx.text().add_layer(DEJAVU, text::LayerOptions::new());
+        let second = vx.text().add_font(&layer, DEJAVU);
+        assert_eq![text::Font(1), second];
+
+        let plain = vx.text().add(&layer, "Hello", text::TextOptions::new());
+        let handle = vx.text().add(
+            &layer,
+            "Hello",
+            text::TextOptions::new()
+                .font(second)
+                .font_span(0..1, text::Font::default()),
+        );
+        assert_eq![vx.text().get_width(&plain), vx.text().get_width(&handle)];
+
+        vx.text().set_fallback_fonts(&layer, &[second]);
+        assert_eq![5, vx.text().get_glyph_count(&plain)];
+        assert_eq![5..10, vx.texts[0].ranges[handle.id]];
+        vx.draw_frame();
+    }
+
+    #[test]
+    fn set_text_keeps_font_spans_on_character_boundaries() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let layer = vx.text().add_layer(DEJAVU, text::LayerOptions::new());
+        let second = vx.text().add_font(&layer, DEJAVU);
+        let handle = vx.text().add(
+            &layer,
+            "abcdef",
+            text::TextOptions::new()
+                .font_span(0..1, second)
+                .font_span(1..2, second)
+                .font_span(3..6, second)
+                .span(0..1, Color::Rgba(255, 0, 0, 255)),
+        );
+
+        // The second span ends inside the umlaut, the last one is clamped to the new string
+        vx.text().set_text(&handle, "aüb");
+        assert_eq![
+            vec![(0..1, Span::Font(1)), (3..4, Span::Font(1))],
+            vx.texts[0].spans[handle.id]
+        ];
+        assert_eq![3, vx.text().get_glyph_count(&handle)];
+        vx.draw_frame();
+    }
+
+    #[test]
+    fn foreign_fonts_and_split_characters_do_not_panic() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let layer = vx.text().add_layer(DEJAVU, text::LayerOptions::new());
+        let handle = vx.text().add(
+            &layer,
+            "Grüße",
+            text::TextOptions::new()
+                .font(text::Font(7))
+                .span(3..4, Color::Rgba(255, 0, 0, 255)),
+        );
+        vx.text()
+            .set_font_span(&handle, 1..5, text::Font(usize::max_value()));
+        vx.text().set_fallback_fonts(&layer, &[text::Font(9)]);
+        assert_eq![5, vx.text().get_glyph_count(&handle)];
+        vx.draw_frame();
+    }
+
+    #[test]
+    fn distance_field_falls_off_from_the_outline() {
+        let coverage = [0, 255, 255, 0, 255, 255];
+        let field = signed_distance_field(&coverage, (3, 2), 2);
+        assert_eq![7 * 6, field.len()];
+        let row = &field[2 * 7..3 * 7];
+        assert_eq![0, row[0]];
+        assert![row[2] < 128 && row[3] > 128];
+        assert![row.windows(2).take(3).all(|pair| pair[0] < pair[1])];
+        assert_eq![0, field[0]];
+    }
+
+    #[test]
+    fn distance_field_matches_brute_force() {
+        let (width, height, spread) = (9i64, 7i64, 3i64);
+        let coverage = (0..width * height)
+            .map(|idx| if (idx * 7 + idx / 5) % 3 == 0 { 255 } else { 0 })
+            .collect::<Vec<u8>>();
+        let inside = |x: i64, y: i64| {
+            x >= 0 && y >= 0 && x < width && y < height && coverage[(y * width + x) as usize] >= 128
+        };
+        let mut expected = vec![];
+        for y in -spread..height + spread {
+            for x in -spread..width + spread {
+                let this = inside(x, y);
+                let mut nearest = (spread as f32 + 0.5).powi(2);
+                for dy in -spread..=spread {
+                    for dx in -spread..=spread {
+                        if inside(x + dx, y + dy) != this {
+                            nearest = nearest.min((dx * dx + dy * dy) as f32);
+                        }
+                    }
+                }
+                let distance = (nearest.sqrt() - 0.5).min(spread as f32);
+                let signed = if this { distance } else { -distance };
+                let value = 0.5 + signed / (2 * spread) as f32;
+                expected.push((value.clamp(0.0, 1.0) * 255.0).round() as u8);
+            }
+        }
+        assert_eq![
+            expected,
+            signed_distance_field(&coverage, (width as u32, height as u32), spread as u32)
+        ];
+        assert![signed_distance_field(&[0; 4], (2, 2), 2)
+            .iter()
+            .all(|value| *value == 0)];
+    }
+
+    #[test]
+    fn distance_field_slots_fill_rows() {
+        let mut glyphs = DistanceFieldGlyphs {
+            size: 48.0,
+            spread: 4,
+            effects: [0.0; 16],
+            slots: HashMap::new(),
+            shelf: (0, 0, 0),
+        };
+        assert_eq![Some((0, 0)), glyphs.allocate((10, 20), (32, 64))];
+        assert_eq![Some((11, 0)), glyphs.allocate((10, 5), (32, 64))];
+        assert_eq![Some((0, 21)), glyphs.allocate((15, 15), (32, 64))];
+        assert_eq![None, glyphs.allocate((10, 50), (32, 64))];
+        glyphs.clear();
+        assert_eq![Some((0, 0)), glyphs.allocate((32, 64), (32, 64))];
+    }
+
+    #[test]
+    fn distance_field_text_matches_plain_text_size() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let plain = vx.text().add_layer(DEJAVU, text::LayerOptions::new());
+        let sdf = vx.text().add_layer(
+            DEJAVU,
+            text::LayerOptions::new().distance_field(
+                text::DistanceField::new()
+                    .outline(0.3, Color::Rgba(0, 0, 0, 255))
+                    .shadow((0.5, 0.5), Color::Rgba(0, 0, 0, 128)),
+            ),
+        );
+
+        let options = || text::TextOptions::new().font_size(24.0);
+        let plain = vx.text().add(&plain, "Distance", options());
+        let handle = vx.text().add(&sdf, "Distance", options());
+        let (plain, field) = (vx.text().get_width(&plain), vx.text().get_width(&handle));
+        assert![(plain - field).abs() < plain * 0.1, "{} {}", plain, field];
+        assert_eq![8, vx.text().get_glyph_count(&handle)];
+
+        vx.text().set_scale(&handle, 4.0);
+        vx.text().set_distance_field_effects(
+            &sdf,
+            text::DistanceField::new().glow(1.0, Color::Rgba(255, 255, 0, 255)),
+        );
+        vx.draw_frame();
+    }
+
+    #[test]
+    fn colored_spans_and_glyphs() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (1000, 1000));
+        let layer = vx.text().add_layer(DEJAVU, text::LayerOptions::new());
+
+        let handle = vx.text().add(
+            &layer,
+            "abcd",
+            text::TextOptions::new()
+                .color(Color::Rgba(0, 255, 0, 255))
+                .span(2..4, Color::Rgba(255, 0, 0, 255)),
+        );
+        assert_eq![[0, 255, 0, 255], vx.texts[0].colbuffer[1][12..16]];
+        assert_eq![[255, 0, 0, 255], vx.texts[0].colbuffer[2][0..4]];
+
+        vx.text()
+            .set_color_span(&handle, 0..1, Color::Rgba(0, 0, 255, 255));
+        assert_eq![[0, 0, 255, 255], vx.texts[0].colbuffer[0][4..8]];
+        assert_eq![[255, 0, 0, 255], vx.texts[0].colbuffer[3][8..12]];
+
+        vx.text()
+            .set_color_glyphs(&handle, |idx| Color::Rgba(idx as u8, 0, 0, 255));
+        assert_eq![[3, 0, 0, 255], vx.texts[0].colbuffer[3][0..4]];
+
+        vx.text().set_color(&handle, Color::Rgba(1, 2, 3, 4));
+        vx.text().set_text(&handle, "abcde");
+        assert_eq![[1, 2, 3, 4], vx.texts[0].colbuffer[4][0..4]];
+        vx.draw_frame();
+    }
+
     #[bench]
     fn text_flag(b: &mut Bencher) {
         let logger = Logger::root(Discard, o!());
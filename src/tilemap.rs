@@ -0,0 +1,920 @@
+//! Methods and types to control tile maps
+//!
+//! A tile map is a grid of tiles drawn from a single tileset texture. The tileset is cut into
+//! equally sized tiles that are numbered row by row starting at 0, and every cell of the map shows
+//! one of these tiles or nothing. The entire map is a single layer: only the parts of the map that
+//! are visible in the current perspective are drawn, and only the parts whose tiles changed are
+//! uploaded to the GPU.
+//!
+//! # Example #
+//! ```
+//! use vxdraw::{dyntex::ImgData, tilemap::LayerOptions, void_logger, VxDraw};
+//! let mut vx = VxDraw::new_offscreen(void_logger(), (200, 100));
+//!
+//! // A tileset of two 1x1 pixel tiles, red and blue
+//! let tileset = ImgData::RawBytes {
+//!     width: 2,
+//!     height: 1,
+//!     bytes: &[255, 0, 0, 255, 0, 0, 255, 255],
+//! };
+//! let map = vx
+//!     .tilemap()
+//!     .add_layer(&tileset, &LayerOptions::new((64, 64), (1, 1)));
+//!
+//! vx.tilemap().set_tile(&map, (0, 0), Some(0));
+//! vx.tilemap().set_tile(&map, (1, 0), Some(1));
+//! assert_eq![Some(1), vx.tilemap().get_tile(&map, (1, 0))];
+//!
+//! // Make every red tile blink blue
+//! vx.tilemap().set_animation(&map, 0, &[0, 1], 0.5);
+//! vx.tilemap().tick(0.5);
+//! vx.draw_frame();
+//! ```
+use super::{blender, dyntex, utils::*};
+use crate::data::{DrawType, Tilemap, VxDraw};
+use cgmath::{prelude::*, Matrix4};
+#[cfg(feature = "dx12")]
+use gfx_backend_dx12 as back;
+#[cfg(feature = "gl")]
+use gfx_backend_gl as back;
+#[cfg(feature = "metal")]
+use gfx_backend_metal as back;
+#[cfg(feature = "vulkan")]
+use gfx_backend_vulkan as back;
+use gfx_hal::{
+    device::Device,
+    format, image, pass,
+    pso::{self, DescriptorPool, Primitive},
+    Backend,
+};
+use std::{collections::HashMap, io::Cursor, mem::ManuallyDrop, ops::Range};
+
+// ---
+
+/// Width and height of a chunk in tiles, chunks are the unit of uploading and culling
+const CHUNK: usize = 16;
+
+/// Handle to a tile map layer
+pub struct Layer(usize, u64);
+
+impl Layerable for Layer {
+    fn get_layer(&self, vx: &VxDraw) -> Option<LayerPosition> {
+        vx.find_in_draw_order(|ord| match ord {
+            DrawType::Tilemap { id } => *id == self.0,
+            _ => false,
+        })
+    }
+}
+
+/// Options for creating a tile map layer
+#[derive(Clone)]
+pub struct LayerOptions {
+    /// Width and height of the map in tiles
+    map_size: (usize, usize),
+    /// Width and height of a single tile in the tileset, in pixels
+    tile_pixels: (u32, u32),
+    /// Width and height of a single tile in the world
+    tile_size: (f32, f32),
+    /// World position of the top left corner of the map
+    translation: (f32, f32),
+    /// Specify filtering mode for sampling the tileset (default is [dyntex::Filter::Nearest])
+    filtering: dyntex::Filter,
+    /// Blending mode for this layer
+    blend: blender::Blender,
+    /// Fix the perspective, this ignores the perspective sent into draw for this layer
+    fixed_perspective: Option<Matrix4<f32>>,
+}
+
+impl LayerOptions {
+    /// Create options for a map of `map_size` tiles, cutting the tileset into tiles of
+    /// `tile_pixels` pixels
+    ///
+    /// By default every tile is 0.1 by 0.1 in size and the map starts at the top left corner of
+    /// the default perspective.
+    pub fn new(map_size: (usize, usize), tile_pixels: (u32, u32)) -> Self {
+        Self {
+            map_size,
+            tile_pixels,
+            tile_size: (0.1, 0.1),
+            translation: (-1.0, -1.0),
+            filtering: dyntex::Filter::Nearest,
+            blend: blender::Blender::default(),
+            fixed_perspective: None,
+        }
+    }
+
+    /// Set the size of a single tile in the world
+    pub fn tile_size(mut self, size: (f32, f32)) -> Self {
+        self.tile_size = size;
+        self
+    }
+
+    /// Set the world position of the top left corner of the map
+    pub fn translation(mut self, translation: (f32, f32)) -> Self {
+        self.translation = translation;
+        self
+    }
+
+    /// Set the filtering mode for sampling the tileset
+    pub fn filter(mut self, filter: dyntex::Filter) -> Self {
+        self.filtering = filter;
+        self
+    }
+
+    /// Set the blender of this layer (see [blender])
+    pub fn blend(mut self, blend_setter: impl Fn(blender::Blender) -> blender::Blender) -> Self {
+        self.blend = blend_setter(self.blend);
+        self
+    }
+
+    /// Set a fixed perspective for this layer
+    pub fn fixed_perspective(mut self, mat: Matrix4<f32>) -> Self {
+        self.fixed_perspective = Some(mat);
+        self
+    }
+}
+
+// ---
+
+/// Accessor object to all tile maps
+///
+/// A tile map is a grid of tiles from a tileset texture. See [crate::tilemap] for examples.
+pub struct Tilemaps<'a> {
+    vx: &'a mut VxDraw,
+}
+
+impl<'a> Tilemaps<'a> {
+    /// Prepare to edit tile maps
+    ///
+    /// You're not supposed to use this function directly (although you can).
+    /// The recommended way of spawning a tile map is via [VxDraw::tilemap()].
+    pub(crate) fn new(s: &'a mut VxDraw) -> Self {
+        Self { vx: s }
+    }
+
+    /// Add a tile map layer using the given image as its tileset
+    ///
+    /// All tiles of the new map are empty. The tileset is cut into tiles of the size given in the
+    /// options, tiles are numbered from the top left of the tileset, row by row.
+    pub fn add_layer(&mut self, tileset: &dyntex::ImgData, options: &LayerOptions) -> Layer {
+        let (img_width, img_height, img) = tileset.decode();
+        assert![
+            options.tile_pixels.0 > 0 && options.tile_pixels.1 > 0,
+            "Tiles must be at least one pixel in size"
+        ];
+        let s = &mut *self.vx;
+        let (texture_image_buffer, texture_image_memory, image_view) =
+            dyntex::upload_texture(s, img_width, img_height, &img[..]);
+
+        let sampler = unsafe {
+            s.device
+                .create_sampler(&image::SamplerDesc::new(
+                    match options.filtering {
+                        dyntex::Filter::Nearest => image::Filter::Nearest,
+                        dyntex::Filter::Linear => image::Filter::Linear,
+                    },
+                    image::WrapMode::Clamp,
+                ))
+                .expect("Couldn't create the sampler!")
+        };
+
+        const VERTEX_SOURCE: &[u8] = include_bytes!["../target/spirv/tilemap.vert.spirv"];
+
+        const FRAGMENT_SOURCE: &[u8] = include_bytes!["../target/spirv/tilemap.frag.spirv"];
+
+        let vertex_source = pso::read_spirv(Cursor::new(VERTEX_SOURCE)).unwrap();
+        let fragment_source = pso::read_spirv(Cursor::new(FRAGMENT_SOURCE)).unwrap();
+
+        let vs_module = { unsafe { s.device.create_shader_module(&vertex_source) }.unwrap() };
+        let fs_module = { unsafe { s.device.create_shader_module(&fragment_source) }.unwrap() };
+
+        // Describe the shaders
+        const ENTRY_NAME: &str = "main";
+        let vs_module: <back::Backend as Backend>::ShaderModule = vs_module;
+        let (vs_entry, fs_entry) = (
+            pso::EntryPoint {
+                entry: ENTRY_NAME,
+                module: &vs_module,
+                specialization: pso::Specialization::default(),
+            },
+            pso::EntryPoint {
+                entry: ENTRY_NAME,
+                module: &fs_module,
+                specialization: pso::Specialization::default(),
+            },
+        );
+        let shader_entries = pso::GraphicsShaderSet {
+            vertex: vs_entry,
+            hull: None,
+            domain: None,
+            geometry: None,
+            fragment: Some(fs_entry),
+        };
+        let input_assembler = pso::InputAssemblerDesc::new(Primitive::TriangleList);
+
+        let vertex_buffers: Vec<pso::VertexBufferDesc> = vec![
+            pso::VertexBufferDesc {
+                binding: 0,
+                stride: 8,
+                rate: pso::VertexInputRate::Vertex,
+            },
+            pso::VertexBufferDesc {
+                binding: 1,
+                stride: 8,
+                rate: pso::VertexInputRate::Vertex,
+            },
+        ];
+        let attributes: Vec<pso::AttributeDesc> = vec![
+            pso::AttributeDesc {
+                location: 0,
+                binding: 0,
+                element: pso::Element {
+                    format: format::Format::Rg32Sfloat,
+                    offset: 0,
+                },
+            },
+            pso::AttributeDesc {
+                location: 1,
+                binding: 1,
+                element: pso::Element {
+                    format: format::Format::Rg32Sfloat,
+                    offset: 0,
+                },
+            },
+        ];
+
+        let rasterizer = pso::Rasterizer {
+            depth_clamping: false,
+            polygon_mode: pso::PolygonMode::Fill,
+            cull_face: pso::Face::NONE,
+            front_face: pso::FrontFace::Clockwise,
+            depth_bias: None,
+            conservative: false,
+        };
+
+        let depth_stencil = pso::DepthStencilDesc {
+            depth: None,
+            depth_bounds: false,
+            stencil: None,
+        };
+        let blender = options.blend.clone().into_gfx_blender();
+        let render_pass = {
+            let attachment = pass::Attachment {
+                format: Some(s.format),
+                samples: 1,
+                ops: pass::AttachmentOps::new(
+                    pass::AttachmentLoadOp::Clear,
+                    pass::AttachmentStoreOp::Store,
+                ),
+                stencil_ops: pass::AttachmentOps::DONT_CARE,
+                layouts: image::Layout::Undefined..image::Layout::Present,
+            };
+            let depth = pass::Attachment {
+                format: Some(format::Format::D32Sfloat),
+                samples: 1,
+                ops: pass::AttachmentOps::new(
+                    pass::AttachmentLoadOp::Clear,
+                    pass::AttachmentStoreOp::Store,
+                ),
+                stencil_ops: pass::AttachmentOps::DONT_CARE,
+                layouts: image::Layout::Undefined..image::Layout::DepthStencilAttachmentOptimal,
+            };
+
+            let subpass = pass::SubpassDesc {
+                colors: &[(0, image::Layout::ColorAttachmentOptimal)],
+                depth_stencil: Some(&(1, image::Layout::DepthStencilAttachmentOptimal)),
+                inputs: &[],
+                resolves: &[],
+                preserves: &[],
+            };
+
+            unsafe {
+                s.device
+                    .create_render_pass(&[attachment, depth], &[subpass], &[])
+            }
+            .expect("Can't create render pass")
+        };
+        let baked_states = pso::BakedStates {
+            viewport: None,
+            scissor: None,
+            blend_color: None,
+            depth_bounds: None,
+        };
+        let mut bindings = Vec::<pso::DescriptorSetLayoutBinding>::new();
+        bindings.push(pso::DescriptorSetLayoutBinding {
+            binding: 0,
+            ty: pso::DescriptorType::SampledImage,
+            count: 1,
+            stage_flags: pso::ShaderStageFlags::FRAGMENT,
+            immutable_samplers: false,
+        });
+        bindings.push(pso::DescriptorSetLayoutBinding {
+            binding: 1,
+            ty: pso::DescriptorType::Sampler,
+            count: 1,
+            stage_flags: pso::ShaderStageFlags::FRAGMENT,
+            immutable_samplers: false,
+        });
+        let immutable_samplers = Vec::<<back::Backend as Backend>::Sampler>::new();
+        let descriptor_set_layouts: Vec<<back::Backend as Backend>::DescriptorSetLayout> =
+            vec![unsafe {
+                s.device
+                    .create_descriptor_set_layout(bindings, immutable_samplers)
+                    .expect("Couldn't make a DescriptorSetLayout")
+            }];
+
+        let mut descriptor_pool = unsafe {
+            s.device
+                .create_descriptor_pool(
+                    1, // sets
+                    &[
+                        pso::DescriptorRangeDesc {
+                            ty: pso::DescriptorType::SampledImage,
+                            count: 1,
+                        },
+                        pso::DescriptorRangeDesc {
+                            ty: pso::DescriptorType::Sampler,
+                            count: 1,
+                        },
+                    ],
+                    pso::DescriptorPoolCreateFlags::empty(),
+                )
+                .expect("Couldn't create a descriptor pool!")
+        };
+
+        let descriptor_set = unsafe {
+            descriptor_pool
+                .allocate_set(&descriptor_set_layouts[0])
+                .expect("Couldn't make a Descriptor Set!")
+        };
+
+        unsafe {
+            s.device.write_descriptor_sets(vec![
+                pso::DescriptorSetWrite {
+                    set: &descriptor_set,
+                    binding: 0,
+                    array_offset: 0,
+                    descriptors: Some(pso::Descriptor::Image(
+                        &image_view,
+                        image::Layout::ShaderReadOnlyOptimal,
+                    )),
+                },
+                pso::DescriptorSetWrite {
+                    set: &descriptor_set,
+                    binding: 1,
+                    array_offset: 0,
+                    descriptors: Some(pso::Descriptor::Sampler(&sampler)),
+                },
+            ]);
+        }
+
+        let mut push_constants = Vec::<(pso::ShaderStageFlags, core::ops::Range<u32>)>::new();
+        push_constants.push((pso::ShaderStageFlags::VERTEX, 0..64));
+        let pipeline_layout = unsafe {
+            s.device
+                .create_pipeline_layout(&descriptor_set_layouts, push_constants)
+                .expect("Couldn't create a pipeline layout")
+        };
+
+        let pipeline_desc = pso::GraphicsPipelineDesc {
+            shaders: shader_entries,
+            rasterizer,
+            vertex_buffers,
+            attributes,
+            input_assembler,
+            blender,
+            depth_stencil,
+            multisampling: None,
+            baked_states,
+            layout: &pipeline_layout,
+            subpass: pass::Subpass {
+                index: 0,
+                main_pass: &render_pass,
+            },
+            flags: pso::PipelineCreationFlags::empty(),
+            parent: pso::BasePipeline::None,
+        };
+
+        let pipeline = unsafe {
+            s.device
+                .create_graphics_pipeline(&pipeline_desc, None)
+                .expect("Couldn't create a graphics pipeline!")
+        };
+
+        unsafe {
+            s.device.destroy_shader_module(vs_module);
+            s.device.destroy_shader_module(fs_module);
+        }
+
+        let chunks = (
+            options.map_size.0.div_ceil(CHUNK),
+            options.map_size.1.div_ceil(CHUNK),
+        );
+        let quads = chunks.0 * chunks.1 * CHUNK * CHUNK;
+
+        let image_count = s.swapconfig.image_count;
+        let posbuf = (0..image_count)
+            .map(|_| {
+                super::utils::ResizBuf::with_capacity(
+                    &s.device,
+                    &s.adapter,
+                    quads.max(1) * std::mem::size_of::<[f32; 8]>(),
+                )
+                .expect("Unable to create buffer")
+            })
+            .collect::<Vec<_>>();
+        let uvbuf = (0..image_count)
+            .map(|_| {
+                super::utils::ResizBuf::with_capacity(
+                    &s.device,
+                    &s.adapter,
+                    quads.max(1) * std::mem::size_of::<[f32; 8]>(),
+                )
+                .expect("Unable to create buffer")
+            })
+            .collect::<Vec<_>>();
+
+        let indices = (0..image_count)
+            .map(|_| {
+                super::utils::ResizBufIdx4::with_capacity(&s.device, &s.adapter, quads.max(1))
+                    .expect("Unable to create buffer")
+            })
+            .collect::<Vec<_>>();
+
+        let tilemap = Tilemap {
+            hidden: false,
+            generation: s.next_generation(),
+            fixed_perspective: options.fixed_perspective,
+
+            size: options.map_size,
+            chunks,
+            tile_pixels: options.tile_pixels,
+            tileset_size: (img_width as u32, img_height as u32),
+            tile_size: options.tile_size,
+            translation: options.translation,
+            tiles: vec![None; options.map_size.0 * options.map_size.1],
+            animations: HashMap::new(),
+            time: 0.0,
+
+            chunk_touch: vec![image_count; chunks.0 * chunks.1],
+
+            posbuffer: vec![[0.0; 8]; quads],
+            uvbuffer: vec![[0.0; 8]; quads],
+
+            posbuf,
+            uvbuf,
+            indices,
+
+            texture_image_buffer: ManuallyDrop::new(texture_image_buffer),
+            texture_image_memory: ManuallyDrop::new(texture_image_memory),
+            image_view: ManuallyDrop::new(image_view),
+            sampler: ManuallyDrop::new(sampler),
+            descriptor_pool: ManuallyDrop::new(descriptor_pool),
+
+            descriptor_set: ManuallyDrop::new(descriptor_set),
+            descriptor_set_layouts,
+            pipeline: ManuallyDrop::new(pipeline),
+            pipeline_layout: ManuallyDrop::new(pipeline_layout),
+            render_pass: ManuallyDrop::new(render_pass),
+        };
+
+        let prev_layer = s.layer_holes.find_available(|x| match x {
+            DrawType::Tilemap { .. } => true,
+            _ => false,
+        });
+
+        let generation = tilemap.generation;
+        if let Some(prev_layer) = prev_layer {
+            match prev_layer {
+                DrawType::Tilemap { id } => {
+                    let old_tilemap = std::mem::replace(&mut s.tilemaps[id], tilemap);
+                    old_tilemap.destroy(&s.device);
+                    s.draw_order.push(DrawType::Tilemap { id });
+                    Layer(id, generation)
+                }
+                _ => panic!["Got a non-tilemap drawtype, should be impossible!"],
+            }
+        } else {
+            s.tilemaps.push(tilemap);
+            s.draw_order.push(DrawType::Tilemap {
+                id: s.tilemaps.len() - 1,
+            });
+            Layer(s.tilemaps.len() - 1, generation)
+        }
+    }
+
+    /// Remove a layer
+    ///
+    /// Removes the tile map and its tileset from memory, the handle becomes stale.
+    pub fn remove_layer(&mut self, layer: Layer) {
+        if !self.check_layer(&layer) {
+            return;
+        }
+        let s = &mut *self.vx;
+        s.tilemaps[layer.0].generation = 0;
+        let draw_type = s.take_from_draw_order(|x| match x {
+            DrawType::Tilemap { id } => *id == layer.0,
+            _ => false,
+        });
+        if let Some(draw_type) = draw_type {
+            s.layer_holes.push(draw_type);
+        }
+    }
+
+    /// Set the fixed perspective of a layer. `None` uses the vxdraw perspective.
+    pub fn set_perspective(&mut self, layer: &Layer, perspective: Option<Matrix4<f32>>) {
+        if !self.check_layer(layer) {
+            return;
+        }
+        self.vx.tilemaps[layer.0].fixed_perspective = perspective;
+    }
+
+    /// Query the amount of layers of this type there are
+    pub fn layer_count(&self) -> usize {
+        self.vx.tilemaps.len()
+    }
+
+    /// Disable drawing of the tile map
+    pub fn hide(&mut self, layer: &Layer) {
+        if !self.check_layer(layer) {
+            return;
+        }
+        self.vx.tilemaps[layer.0].hidden = true;
+    }
+
+    /// Enable drawing of the tile map
+    pub fn show(&mut self, layer: &Layer) {
+        if !self.check_layer(layer) {
+            return;
+        }
+        self.vx.tilemaps[layer.0].hidden = false;
+    }
+
+    // ---
+
+    /// Set the tileset index shown at a position of the map, `None` leaves the position empty
+    ///
+    /// Only the chunk containing the tile is uploaded again.
+    pub fn set_tile(&mut self, layer: &Layer, position: (usize, usize), tile: Option<u32>) {
+        if !self.check_layer(layer) {
+            return;
+        }
+        let image_count = self.vx.swapconfig.image_count;
+        let tilemap = &mut self.vx.tilemaps[layer.0];
+        assert![
+            position.0 < tilemap.size.0 && position.1 < tilemap.size.1,
+            "Position is outside the tile map"
+        ];
+        if let Some(tile) = tile {
+            assert![
+                tile < tile_count(tilemap),
+                "Tile index is outside the tileset"
+            ];
+        }
+        tilemap.tiles[position.1 * tilemap.size.0 + position.0] = tile;
+        write_tile(tilemap, position, image_count);
+    }
+
+    /// Get the tileset index at a position of the map
+    ///
+    /// Animated tiles return the index they were set to, not their current frame. Positions
+    /// outside the map are empty.
+    pub fn get_tile(&self, layer: &Layer, position: (usize, usize)) -> Option<u32> {
+        if !self.check_layer(layer) {
+            return None;
+        }
+        let tilemap = &self.vx.tilemaps[layer.0];
+        if position.0 >= tilemap.size.0 || position.1 >= tilemap.size.1 {
+            return None;
+        }
+        tilemap.tiles[position.1 * tilemap.size.0 + position.0]
+    }
+
+    /// Animate every tile of the map showing `tile`
+    ///
+    /// The tiles cycle through `frames`, showing each for `frame_duration` seconds, as
+    /// [Tilemaps::tick] advances time. An empty list of frames stops the animation.
+    pub fn set_animation(&mut self, layer: &Layer, tile: u32, frames: &[u32], frame_duration: f32) {
+        if !self.check_layer(layer) {
+            return;
+        }
+        let image_count = self.vx.swapconfig.image_count;
+        let tilemap = &mut self.vx.tilemaps[layer.0];
+        let count = tile_count(tilemap);
+        assert![
+            frames.iter().all(|frame| *frame < count),
+            "Animation frame is outside the tileset"
+        ];
+        if frames.is_empty() {
+            tilemap.animations.remove(&tile);
+        } else {
+            assert![frame_duration > 0.0, "Frame duration must be positive"];
+            tilemap
+                .animations
+                .insert(tile, (frames.to_vec(), frame_duration));
+        }
+        write_tiles_showing(tilemap, &[tile], image_count);
+    }
+
+    /// Advance the animated tiles of all tile maps by `dt` seconds
+    ///
+    /// Only chunks containing tiles that changed frame are uploaded again.
+    pub fn tick(&mut self, dt: f32) {
+        let image_count = self.vx.swapconfig.image_count;
+        for tilemap in self.vx.tilemaps.iter_mut() {
+            if tilemap.generation == 0 || tilemap.animations.is_empty() {
+                continue;
+            }
+            let before = tilemap.time;
+            tilemap.time += dt;
+            let changed = tilemap
+                .animations
+                .iter()
+                .filter(|(_, (frames, duration))| {
+                    frame_at(frames, *duration, before) != frame_at(frames, *duration, tilemap.time)
+                })
+                .map(|(tile, _)| *tile)
+                .collect::<Vec<_>>();
+            if !changed.is_empty() {
+                write_tiles_showing(tilemap, &changed, image_count);
+            }
+        }
+    }
+
+    // ---
+
+    fn check_layer(&self, layer: &Layer) -> bool {
+        is_current(&self.vx.log, self.vx.tilemaps[layer.0].generation, layer.1)
+    }
+}
+
+// ---
+
+/// Amount of tiles in the tileset
+fn tile_count(tilemap: &Tilemap) -> u32 {
+    (tilemap.tileset_size.0 / tilemap.tile_pixels.0)
+        * (tilemap.tileset_size.1 / tilemap.tile_pixels.1)
+}
+
+/// Frame of an animation shown at a point in time
+fn frame_at(frames: &[u32], duration: f32, time: f32) -> u32 {
+    frames[(time / duration) as usize % frames.len()]
+}
+
+/// Index of the quad of a map position in the vertex buffers
+fn quad_index(chunks_x: usize, position: (usize, usize)) -> usize {
+    let chunk = (position.1 / CHUNK) * chunks_x + position.0 / CHUNK;
+    chunk * CHUNK * CHUNK + (position.1 % CHUNK) * CHUNK + position.0 % CHUNK
+}
+
+/// UV coordinates of the corners of a tile in the tileset
+fn tile_uv(tilemap: &Tilemap, tile: u32) -> [f32; 8] {
+    let columns = tilemap.tileset_size.0 / tilemap.tile_pixels.0;
+    let (width, height) = (
+        tilemap.tile_pixels.0 as f32 / tilemap.tileset_size.0 as f32,
+        tilemap.tile_pixels.1 as f32 / tilemap.tileset_size.1 as f32,
+    );
+    let (left, top) = (
+        (tile % columns) as f32 * width,
+        (tile / columns) as f32 * height,
+    );
+    let (right, bottom) = (left + width, top + height);
+    [left, top, left, bottom, right, bottom, right, top]
+}
+
+/// Write the vertices of a map position and mark its chunk for upload
+fn write_tile(tilemap: &mut Tilemap, position: (usize, usize), image_count: u32) {
+    let index = quad_index(tilemap.chunks.0, position);
+    match tilemap.tiles[position.1 * tilemap.size.0 + position.0] {
+        Some(tile) => {
+            let shown = match tilemap.animations.get(&tile) {
+                Some((frames, duration)) => frame_at(frames, *duration, tilemap.time),
+                None => tile,
+            };
+            let (left, top) = (
+                tilemap.translation.0 + position.0 as f32 * tilemap.tile_size.0,
+                tilemap.translation.1 + position.1 as f32 * tilemap.tile_size.1,
+            );
+            let (right, bottom) = (left + tilemap.tile_size.0, top + tilemap.tile_size.1);
+            tilemap.posbuffer[index] = [left, top, left, bottom, right, bottom, right, top];
+            tilemap.uvbuffer[index] = tile_uv(tilemap, shown);
+        }
+        None => {
+            tilemap.posbuffer[index] = [0.0; 8];
+            tilemap.uvbuffer[index] = [0.0; 8];
+        }
+    }
+    let chunk = (position.1 / CHUNK) * tilemap.chunks.0 + position.0 / CHUNK;
+    tilemap.chunk_touch[chunk] = image_count;
+}
+
+/// Rewrite every map position set to one of the given tiles
+fn write_tiles_showing(tilemap: &mut Tilemap, tiles: &[u32], image_count: u32) {
+    for y in 0..tilemap.size.1 {
+        for x in 0..tilemap.size.0 {
+            match tilemap.tiles[y * tilemap.size.0 + x] {
+                Some(tile) if tiles.contains(&tile) => write_tile(tilemap, (x, y), image_count),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Quad ranges of the chunks that need to be uploaded this frame
+///
+/// Each touched chunk is counted down once, adjacent chunks are merged into a single range.
+pub(crate) fn take_dirty_chunks(chunk_touch: &mut [u32]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = vec![];
+    for (chunk, touch) in chunk_touch.iter_mut().enumerate() {
+        if *touch == 0 {
+            continue;
+        }
+        *touch -= 1;
+        push_range(
+            &mut ranges,
+            chunk * CHUNK * CHUNK..(chunk + 1) * CHUNK * CHUNK,
+        );
+    }
+    ranges
+}
+
+/// Quad ranges of the chunks that are visible with the given perspective
+///
+/// The corners of the screen are mapped back into the world to find the visible area, every row
+/// of visible chunks is a single range. If the perspective can not be inverted the whole map is
+/// considered visible.
+pub(crate) fn visible_quads(tilemap: &Tilemap, view: &Matrix4<f32>) -> Vec<Range<usize>> {
+    let quads = tilemap.chunks.0 * tilemap.chunks.1 * CHUNK * CHUNK;
+    let inverse = match view.invert() {
+        Some(inverse) => inverse,
+        None => return std::iter::once(0..quads).collect(),
+    };
+    let (mut min, mut max) = ((f32::MAX, f32::MAX), (f32::MIN, f32::MIN));
+    for &(x, y) in &[(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+        let corner = inverse * cgmath::vec4(x, y, 0.0, 1.0);
+        let corner = (corner.x / corner.w, corner.y / corner.w);
+        min = (min.0.min(corner.0), min.1.min(corner.1));
+        max = (max.0.max(corner.0), max.1.max(corner.1));
+    }
+    let chunk_size = (
+        tilemap.tile_size.0 * CHUNK as f32,
+        tilemap.tile_size.1 * CHUNK as f32,
+    );
+    let span = |min: f32, max: f32, origin: f32, size: f32, count: usize| {
+        let (a, b) = ((min - origin) / size, (max - origin) / size);
+        let (a, b) = (a.min(b), a.max(b));
+        let clamp = |x: f32| x.max(0.0).min(count as f32) as usize;
+        clamp(a.floor())..clamp(b.ceil())
+    };
+    let columns = span(
+        min.0,
+        max.0,
+        tilemap.translation.0,
+        chunk_size.0,
+        tilemap.chunks.0,
+    );
+    let rows = span(
+        min.1,
+        max.1,
+        tilemap.translation.1,
+        chunk_size.1,
+        tilemap.chunks.1,
+    );
+    let mut ranges = vec![];
+    if columns.start >= columns.end {
+        return ranges;
+    }
+    for row in rows {
+        let first = row * tilemap.chunks.0;
+        push_range(
+            &mut ranges,
+            (first + columns.start) * CHUNK * CHUNK..(first + columns.end) * CHUNK * CHUNK,
+        );
+    }
+    ranges
+}
+
+/// Append a range, extending the last range if the two are adjacent
+fn push_range(ranges: &mut Vec<Range<usize>>, range: Range<usize>) {
+    match ranges.last_mut() {
+        Some(last) if last.end == range.start => last.end = range.end,
+        _ => ranges.push(range),
+    }
+}
+
+// ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use slog::{Discard, Logger};
+
+    static TILESET: &dyntex::ImgData = &dyntex::ImgData::RawBytes {
+        width: 2,
+        height: 2,
+        bytes: &[
+            255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 255,
+        ],
+    };
+
+    #[test]
+    fn setting_tiles_marks_their_chunk() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (100, 100));
+        let layer = vx
+            .tilemap()
+            .add_layer(TILESET, &LayerOptions::new((40, 20), (1, 1)));
+
+        let tilemap = &mut vx.tilemaps[layer.0];
+        assert_eq![(3, 2), tilemap.chunks];
+        assert_eq![
+            vec![0..6 * 256],
+            take_dirty_chunks(&mut tilemap.chunk_touch)
+        ];
+        for touch in tilemap.chunk_touch.iter_mut() {
+            *touch = 0;
+        }
+
+        vx.tilemap().set_tile(&layer, (17, 3), Some(3));
+        assert_eq![Some(3), vx.tilemap().get_tile(&layer, (17, 3))];
+        assert_eq![None, vx.tilemap().get_tile(&layer, (16, 3))];
+        assert_eq![None, vx.tilemap().get_tile(&layer, (40, 0))];
+
+        let tilemap = &mut vx.tilemaps[layer.0];
+        let index = 256 + 3 * 16 + 1;
+        assert_eq![index, quad_index(tilemap.chunks.0, (17, 3))];
+        assert_eq![
+            [0.5, 0.5, 0.5, 1.0, 1.0, 1.0, 1.0, 0.5],
+            tilemap.uvbuffer[index]
+        ];
+        assert![(tilemap.posbuffer[index][0] - 0.7).abs() < 1e-6];
+        assert_eq![vec![256..512], take_dirty_chunks(&mut tilemap.chunk_touch)];
+
+        vx.tilemap().set_tile(&layer, (17, 3), None);
+        assert_eq![[0.0; 8], vx.tilemaps[layer.0].posbuffer[index]];
+        vx.draw_frame();
+    }
+
+    #[test]
+    fn only_visible_chunks_are_drawn() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (100, 100));
+        let layer = vx
+            .tilemap()
+            .add_layer(TILESET, &LayerOptions::new((64, 64), (1, 1)));
+        let tilemap = &vx.tilemaps[layer.0];
+
+        // Chunks are 1.6 wide, the default view shows the first two chunks of the first two rows
+        assert_eq![
+            vec![0..512, 1024..1536],
+            visible_quads(tilemap, &Matrix4::identity())
+        ];
+        assert_eq![
+            vec![0..256],
+            visible_quads(tilemap, &Matrix4::from_scale(2.0))
+        ];
+        assert_eq![
+            vec![0..64 * 64],
+            visible_quads(tilemap, &Matrix4::from_scale(0.1))
+        ];
+        assert![visible_quads(
+            tilemap,
+            &Matrix4::from_translation(cgmath::vec3(10.0, 0.0, 0.0))
+        )
+        .is_empty()];
+        assert_eq![
+            vec![0..64 * 64],
+            visible_quads(tilemap, &Matrix4::from_scale(0.0))
+        ];
+    }
+
+    #[test]
+    fn animated_tiles_change_with_time() {
+        let logger = Logger::root(Discard, o!());
+        let mut vx = VxDraw::new_offscreen(logger, (100, 100));
+        let layer = vx
+            .tilemap()
+            .add_layer(TILESET, &LayerOptions::new((4, 4), (1, 1)));
+        vx.tilemap().set_tile(&layer, (2, 1), Some(0));
+        vx.tilemap().set_animation(&layer, 0, &[0, 1, 2], 0.5);
+
+        let index = quad_index(1, (2, 1));
+        for touch in vx.tilemaps[layer.0].chunk_touch.iter_mut() {
+            *touch = 0;
+        }
+        vx.tilemap().tick(0.4);
+        assert_eq![0, vx.tilemaps[layer.0].chunk_touch[0]];
+
+        vx.tilemap().tick(0.2);
+        let tilemap = &vx.tilemaps[layer.0];
+        assert_eq![tile_uv(tilemap, 1), tilemap.uvbuffer[index]];
+        assert_ne![0, tilemap.chunk_touch[0]];
+        assert_eq![Some(0), vx.tilemap().get_tile(&layer, (2, 1))];
+
+        vx.tilemap().set_animation(&layer, 0, &[], 0.0);
+        let tilemap = &vx.tilemaps[layer.0];
+        assert_eq![tile_uv(tilemap, 0), tilemap.uvbuffer[index]];
+        vx.draw_frame();
+    }
+}
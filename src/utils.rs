@@ -1,5 +1,6 @@
 //! Various utilities and helpers for vxdraw
 use crate::data::VxDraw;
+use crate::error::Error;
 #[cfg(feature = "dx12")]
 use gfx_backend_dx12 as back;
 #[cfg(feature = "gl")]
@@ -30,8 +31,65 @@ use std::mem::ManuallyDrop;
 
 /// Trait for describing layers and their ordering
 pub trait Layerable {
-    /// Get the layer number from this layer
-    fn get_layer(&self, vx: &VxDraw) -> usize;
+    /// Find where this layer is drawn, `None` if the layer has been removed
+    fn get_layer(&self, vx: &VxDraw) -> Option<LayerPosition>;
+}
+
+/// Position of a layer in the main draw order or in the draw order of a render texture
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LayerPosition {
+    /// Render texture the layer is attached to, `None` when it is drawn to the screen
+    pub(crate) rentex: Option<usize>,
+    /// Index of the layer in the draw order
+    pub(crate) index: usize,
+}
+
+/// Check that a handle still refers to the object it was created for
+///
+/// `current` is the generation stored with the object, 0 once it has been removed. Using a stale
+/// handle panics in debug builds. In release builds the error is logged and `false` is returned so
+/// the caller can ignore the operation.
+pub(crate) fn is_current(log: &slog::Logger, current: u64, handle: u64) -> bool {
+    if current != 0 && current == handle {
+        return true;
+    }
+    if cfg!(debug_assertions) {
+        panic!["Stale handle: the object it refers to has been removed"];
+    }
+    error!(log, "Ignoring operation on a stale handle"; "handle" => handle, "current" => current);
+    false
+}
+
+/// Plan the compaction of a buffer of `len` slots of which `holes` are unused
+///
+/// Returns the moves `(from, to)` that fill the lowest holes with the highest live slots, and the
+/// new length of the buffer once the trailing slots are truncated.
+pub(crate) fn compaction_moves(len: usize, holes: &[usize]) -> (Vec<(usize, usize)>, usize) {
+    let mut holes = holes.to_vec();
+    holes.sort_unstable();
+    holes.dedup();
+    let mut moves = vec![];
+    let mut len = len;
+    let mut front = 0;
+    while front < holes.len() {
+        if holes[holes.len() - 1] == len - 1 {
+            holes.pop();
+        } else {
+            moves.push((len - 1, holes[front]));
+            front += 1;
+        }
+        len -= 1;
+    }
+    (moves, len)
+}
+
+/// Take the first `width` values of every primitive's per-vertex data
+///
+/// Used by instanced layers to upload one record per primitive instead of one per vertex.
+pub(crate) fn per_instance<T: Copy, A: AsRef<[T]>>(data: &[A], width: usize) -> Vec<T> {
+    data.iter()
+        .flat_map(|x| x.as_ref()[..width].iter().cloned())
+        .collect()
 }
 
 /// Find the memory type id that satisfies the requirements and the memory properties for the given
@@ -40,7 +98,7 @@ pub(crate) fn find_memory_type_id<B: gfx_hal::Backend>(
     adap: &Adapter<B>,
     reqs: memory::Requirements,
     prop: memory::Properties,
-) -> MemoryTypeId {
+) -> Result<MemoryTypeId, Error> {
     adap.physical_device
         .memory_properties()
         .memory_types
@@ -50,45 +108,40 @@ pub(crate) fn find_memory_type_id<B: gfx_hal::Backend>(
             reqs.type_mask & (1 << id) != 0 && memory_type.properties.contains(prop)
         })
         .map(|(id, _)| MemoryTypeId(id))
-        .expect("Unable to find memory type id")
+        .ok_or(Error::NoMemoryType)
 }
 
 pub(crate) fn make_vertex_buffer_with_data(
     s: &mut VxDraw,
     data: &[f32],
-) -> (
-    <back::Backend as Backend>::Buffer,
-    <back::Backend as Backend>::Memory,
-    memory::Requirements,
-) {
+) -> Result<
+    (
+        <back::Backend as Backend>::Buffer,
+        <back::Backend as Backend>::Memory,
+        memory::Requirements,
+    ),
+    Error,
+> {
     let device = &s.device;
     let (buffer, memory, requirements) = unsafe {
         let buffer_size: u64 = (std::mem::size_of::<f32>() * data.len()) as u64;
-        let mut buffer = device
-            .create_buffer(buffer_size, b::Usage::VERTEX)
-            .expect("cant make bf");
+        let mut buffer = device.create_buffer(buffer_size, b::Usage::VERTEX)?;
         let requirements = device.get_buffer_requirements(&buffer);
         let memory_type_id = find_memory_type_id(
             &s.adapter,
             requirements,
             Properties::CPU_VISIBLE | Properties::COHERENT,
-        );
-        let memory = device
-            .allocate_memory(memory_type_id, requirements.size)
-            .expect("Couldn't allocate vertex buffer memory");
-        device
-            .bind_buffer_memory(&memory, 0, &mut buffer)
-            .expect("Couldn't bind the buffer memory!");
+        )?;
+        let memory = device.allocate_memory(memory_type_id, requirements.size)?;
+        device.bind_buffer_memory(&memory, 0, &mut buffer)?;
         (buffer, memory, requirements)
     };
     unsafe {
-        let data_target = device
-            .map_memory(&memory, 0..requirements.size)
-            .expect("Failed to acquire a memory writer!");
+        let data_target = device.map_memory(&memory, 0..requirements.size)?;
         std::ptr::copy_nonoverlapping(data.as_ptr() as *const u8, data_target, data.len() * 4);
         device.unmap_memory(&memory);
     }
-    (buffer, memory, requirements)
+    Ok((buffer, memory, requirements))
 }
 
 /// A more opinionated resizable buffer
@@ -100,7 +153,10 @@ pub(crate) struct ResizBufIdx4 {
 }
 
 impl ResizBufIdx4 {
-    pub(crate) fn new(device: &back::Device, adapter: &Adapter<back::Backend>) -> Self {
+    pub(crate) fn new(
+        device: &back::Device,
+        adapter: &Adapter<back::Backend>,
+    ) -> Result<Self, Error> {
         Self::with_capacity(device, adapter, 1)
     }
 
@@ -108,30 +164,22 @@ impl ResizBufIdx4 {
         device: &back::Device,
         adapter: &Adapter<back::Backend>,
         capacity: usize,
-    ) -> Self {
+    ) -> Result<Self, Error> {
         let (buffer, memory, requirements) = unsafe {
             let buffer_size: u64 = (capacity * 6 * std::mem::size_of::<u32>()) as u64;
-            let mut buffer = device
-                .create_buffer(buffer_size, b::Usage::INDEX)
-                .expect("cant make bf");
+            let mut buffer = device.create_buffer(buffer_size, b::Usage::INDEX)?;
             let requirements = device.get_buffer_requirements(&buffer);
             let memory_type_id = find_memory_type_id(
                 adapter,
                 requirements,
                 Properties::COHERENT | Properties::CPU_VISIBLE | Properties::DEVICE_LOCAL,
-            );
-            let memory = device
-                .allocate_memory(memory_type_id, requirements.size)
-                .expect("Couldn't allocate vertex buffer memory");
-            device
-                .bind_buffer_memory(&memory, 0, &mut buffer)
-                .expect("Couldn't bind the buffer memory!");
+            )?;
+            let memory = device.allocate_memory(memory_type_id, requirements.size)?;
+            device.bind_buffer_memory(&memory, 0, &mut buffer)?;
             (buffer, memory, requirements)
         };
         unsafe {
-            let data_target = device
-                .map_memory(&memory, 0..requirements.size)
-                .expect("Failed to acquire a memory writer!");
+            let data_target = device.map_memory(&memory, 0..requirements.size)?;
             let data_target = std::mem::transmute::<_, *mut u32>(data_target);
             for index in 0..capacity {
                 let ver = (index * 6) as u32;
@@ -142,23 +190,29 @@ impl ResizBufIdx4 {
             }
             device.unmap_memory(&memory);
         }
-        Self {
+        Ok(Self {
             buffer: ManuallyDrop::new(buffer),
             memory: ManuallyDrop::new(memory),
             capacity,
-        }
+        })
     }
 
     pub(crate) fn buffer(&self) -> &<back::Backend as Backend>::Buffer {
         &self.buffer
     }
 
-    fn resize(&mut self, device: &back::Device, adapter: &Adapter<back::Backend>, capacity: usize) {
-        let mut new_resizbuf = Self::with_capacity(device, adapter, capacity);
+    fn resize(
+        &mut self,
+        device: &back::Device,
+        adapter: &Adapter<back::Backend>,
+        capacity: usize,
+    ) -> Result<(), Error> {
+        let mut new_resizbuf = Self::with_capacity(device, adapter, capacity)?;
         std::mem::swap(&mut self.buffer, &mut new_resizbuf.buffer);
         std::mem::swap(&mut self.memory, &mut new_resizbuf.memory);
         std::mem::swap(&mut self.capacity, &mut new_resizbuf.capacity);
         new_resizbuf.destroy(device);
+        Ok(())
     }
 
     pub(crate) fn ensure_capacity(
@@ -166,16 +220,17 @@ impl ResizBufIdx4 {
         device: &back::Device,
         adapter: &Adapter<back::Backend>,
         capacity: usize,
-    ) {
+    ) -> Result<(), Error> {
         static SHRINK_TRESHOLD: usize = 2;
 
         let capacity = capacity.max(1);
 
         if self.capacity >= capacity * SHRINK_TRESHOLD {
-            self.resize(device, adapter, (self.capacity / 2).max(capacity));
+            self.resize(device, adapter, (self.capacity / 2).max(capacity))
         } else if self.capacity >= capacity {
+            Ok(())
         } else {
-            self.resize(device, adapter, (self.capacity * 2).max(capacity));
+            self.resize(device, adapter, (self.capacity * 2).max(capacity))
         }
     }
 
@@ -197,7 +252,10 @@ pub(crate) struct ResizBuf {
 }
 
 impl ResizBuf {
-    pub(crate) fn new(device: &back::Device, adapter: &Adapter<back::Backend>) -> Self {
+    pub(crate) fn new(
+        device: &back::Device,
+        adapter: &Adapter<back::Backend>,
+    ) -> Result<Self, Error> {
         Self::with_capacity(device, adapter, 1)
     }
 
@@ -205,32 +263,26 @@ impl ResizBuf {
         device: &back::Device,
         adapter: &Adapter<back::Backend>,
         capacity_in_bytes: usize,
-    ) -> Self {
+    ) -> Result<Self, Error> {
         let (buffer, memory, requirements) = unsafe {
             let buffer_size: u64 = capacity_in_bytes as u64;
-            let mut buffer = device
-                .create_buffer(buffer_size, b::Usage::VERTEX)
-                .expect("cant make bf");
+            let mut buffer = device.create_buffer(buffer_size, b::Usage::VERTEX)?;
             let requirements = device.get_buffer_requirements(&buffer);
             let memory_type_id = find_memory_type_id(
                 adapter,
                 requirements,
                 Properties::COHERENT | Properties::CPU_VISIBLE | Properties::DEVICE_LOCAL,
-            );
-            let memory = device
-                .allocate_memory(memory_type_id, requirements.size)
-                .expect("Couldn't allocate vertex buffer memory");
-            device
-                .bind_buffer_memory(&memory, 0, &mut buffer)
-                .expect("Couldn't bind the buffer memory!");
+            )?;
+            let memory = device.allocate_memory(memory_type_id, requirements.size)?;
+            device.bind_buffer_memory(&memory, 0, &mut buffer)?;
             (buffer, memory, requirements)
         };
-        Self {
+        Ok(Self {
             buffer: ManuallyDrop::new(buffer),
             memory: ManuallyDrop::new(memory),
             requirements,
             capacity_in_bytes,
-        }
+        })
     }
 
     pub(crate) fn buffer(&self) -> &<back::Backend as Backend>::Buffer {
@@ -242,10 +294,11 @@ impl ResizBuf {
         device: &back::Device,
         adapter: &Adapter<back::Backend>,
         capacity_in_bytes: usize,
-    ) {
-        let mut new_resizbuf = Self::with_capacity(device, adapter, capacity_in_bytes);
+    ) -> Result<(), Error> {
+        let mut new_resizbuf = Self::with_capacity(device, adapter, capacity_in_bytes)?;
         std::mem::swap(self, &mut new_resizbuf);
         new_resizbuf.destroy(device);
+        Ok(())
     }
 
     pub(crate) fn copy_from_slice_and_maybe_resize<T: Copy>(
@@ -253,7 +306,7 @@ impl ResizBuf {
         device: &back::Device,
         adapter: &Adapter<back::Backend>,
         slice: &[T],
-    ) {
+    ) -> Result<(), Error> {
         static SHRINK_TRESHOLD: u64 = 2;
 
         let bytes_in_slice = (slice.len() * std::mem::size_of::<T>()).max(1) as u64;
@@ -263,13 +316,11 @@ impl ResizBuf {
                 device,
                 adapter,
                 (self.capacity_in_bytes as usize / 2).max(bytes_in_slice as usize),
-            );
-            self.copy_from_slice_and_maybe_resize(device, adapter, slice);
+            )?;
+            self.copy_from_slice_and_maybe_resize(device, adapter, slice)
         } else if self.capacity_in_bytes as u64 >= bytes_in_slice {
             unsafe {
-                let data_target = device
-                    .map_memory(&self.memory, 0..self.requirements.size)
-                    .expect("Failed to acquire a memory writer!");
+                let data_target = device.map_memory(&self.memory, 0..self.requirements.size)?;
                 let data_target = data_target as *mut T;
                 std::slice::from_raw_parts_mut(
                     data_target,
@@ -278,16 +329,39 @@ impl ResizBuf {
                     .copy_from_slice(slice);
                 device.unmap_memory(&self.memory);
             }
+            Ok(())
         } else {
             self.resize(
                 device,
                 adapter,
                 (self.capacity_in_bytes as usize * 2).max(bytes_in_slice as usize),
-            );
-            self.copy_from_slice_and_maybe_resize(device, adapter, slice);
+            )?;
+            self.copy_from_slice_and_maybe_resize(device, adapter, slice)
         }
     }
 
+    /// Copy parts of a slice to the same offsets in the buffer, which must fit the entire slice
+    pub(crate) fn copy_ranges<T: Copy>(
+        &mut self,
+        device: &back::Device,
+        slice: &[T],
+        ranges: &[std::ops::Range<usize>],
+    ) -> Result<(), Error> {
+        debug_assert![slice.len() * std::mem::size_of::<T>() <= self.capacity_in_bytes];
+        if ranges.is_empty() {
+            return Ok(());
+        }
+        unsafe {
+            let data_target = device.map_memory(&self.memory, 0..self.requirements.size)?;
+            let target = std::slice::from_raw_parts_mut(data_target as *mut T, slice.len());
+            for range in ranges {
+                target[range.clone()].copy_from_slice(&slice[range.clone()]);
+            }
+            device.unmap_memory(&self.memory);
+        }
+        Ok(())
+    }
+
     pub(crate) fn destroy(&mut self, device: &back::Device) {
         use core::ptr::read;
         unsafe {
@@ -300,42 +374,57 @@ impl ResizBuf {
 pub(crate) fn make_transfer_buffer_of_size(
     s: &mut VxDraw,
     size: u64,
-) -> (
-    <back::Backend as Backend>::Buffer,
-    <back::Backend as Backend>::Memory,
-    memory::Requirements,
-) {
+) -> Result<
+    (
+        <back::Backend as Backend>::Buffer,
+        <back::Backend as Backend>::Memory,
+        memory::Requirements,
+    ),
+    Error,
+> {
     let device = &s.device;
     let (buffer, memory, requirements) = unsafe {
-        let mut buffer = device
-            .create_buffer(size, b::Usage::TRANSFER_DST)
-            .expect("cant make bf");
+        let mut buffer =
+            device.create_buffer(size, b::Usage::TRANSFER_SRC | b::Usage::TRANSFER_DST)?;
         let requirements = device.get_buffer_requirements(&buffer);
-        let memory_type_id = find_memory_type_id(
+        let memory = match find_memory_type_id(
             &s.adapter,
             requirements,
             Properties::CPU_VISIBLE | Properties::COHERENT,
-        );
-        let memory = device
-            .allocate_memory(memory_type_id, requirements.size)
-            .expect("Couldn't allocate vertex buffer memory");
-        device
-            .bind_buffer_memory(&memory, 0, &mut buffer)
-            .expect("Couldn't bind the buffer memory!");
+        )
+        .and_then(|id| {
+            device
+                .allocate_memory(id, requirements.size)
+                .map_err(Error::from)
+        }) {
+            Ok(memory) => memory,
+            Err(err) => {
+                device.destroy_buffer(buffer);
+                return Err(err);
+            }
+        };
+        if let Err(err) = device.bind_buffer_memory(&memory, 0, &mut buffer) {
+            device.destroy_buffer(buffer);
+            device.free_memory(memory);
+            return Err(err.into());
+        }
         (buffer, memory, requirements)
     };
-    (buffer, memory, requirements)
+    Ok((buffer, memory, requirements))
 }
 
 pub(crate) fn make_transfer_img_of_size(
     s: &mut VxDraw,
     w: u32,
     h: u32,
-) -> (
-    <back::Backend as Backend>::Image,
-    <back::Backend as Backend>::Memory,
-    memory::Requirements,
-) {
+) -> Result<
+    (
+        <back::Backend as Backend>::Image,
+        <back::Backend as Backend>::Memory,
+        memory::Requirements,
+    ),
+    Error,
+> {
     let device = &s.device;
     let (buffer, memory, requirements) = unsafe {
         if s.adapter
@@ -351,7 +440,7 @@ pub(crate) fn make_transfer_img_of_size(
         {
             const MSG: &str = "Device does not support VK_FORMAT_R8G8B8A8_UNORM transfer image";
             error!(s.log, "{}", MSG);
-            panic!(MSG);
+            return Err(Error::Unsupported(MSG));
         }
         if !s
             .adapter
@@ -363,33 +452,41 @@ pub(crate) fn make_transfer_img_of_size(
             const MSG: &str =
                 "Device does not support VK_FORMAT_R8G8B8A8_UNORM as blit destination";
             error!(s.log, "{}", MSG);
-            panic!(MSG);
+            return Err(Error::Unsupported(MSG));
         }
-        let mut buffer = device
-            .create_image(
-                i::Kind::D2(w, h, 1, 1),
-                1,
-                f::Format::Rgba8Unorm,
-                i::Tiling::Linear,
-                i::Usage::TRANSFER_DST | i::Usage::TRANSFER_SRC,
-                i::ViewCapabilities::empty(),
-            )
-            .expect("cant make bf");
+        let mut buffer = device.create_image(
+            i::Kind::D2(w, h, 1, 1),
+            1,
+            f::Format::Rgba8Unorm,
+            i::Tiling::Linear,
+            i::Usage::TRANSFER_DST | i::Usage::TRANSFER_SRC,
+            i::ViewCapabilities::empty(),
+        )?;
         let requirements = device.get_image_requirements(&buffer);
-        let memory_type_id = find_memory_type_id(
+        let memory = match find_memory_type_id(
             &s.adapter,
             requirements,
             Properties::CPU_VISIBLE | Properties::COHERENT,
-        );
-        let memory = device
-            .allocate_memory(memory_type_id, requirements.size)
-            .expect("Couldn't allocate image buffer memory");
-        device
-            .bind_image_memory(&memory, 0, &mut buffer)
-            .expect("Couldn't bind the buffer memory!");
+        )
+        .and_then(|id| {
+            device
+                .allocate_memory(id, requirements.size)
+                .map_err(Error::from)
+        }) {
+            Ok(memory) => memory,
+            Err(err) => {
+                device.destroy_image(buffer);
+                return Err(err);
+            }
+        };
+        if let Err(err) = device.bind_image_memory(&memory, 0, &mut buffer) {
+            device.destroy_image(buffer);
+            device.free_memory(memory);
+            return Err(err.into());
+        }
         (buffer, memory, requirements)
     };
-    (buffer, memory, requirements)
+    Ok((buffer, memory, requirements))
 }
 
 #[cfg(test)]
@@ -412,7 +509,8 @@ pub(crate) fn make_vertex_buffer_with_data_on_gpu(
             &s.adapter,
             requirements,
             Properties::CPU_VISIBLE | Properties::COHERENT,
-        );
+        )
+        .expect("Unable to find memory type id");
         let memory = device
             .allocate_memory(memory_type_id, requirements.size)
             .expect("Couldn't allocate vertex buffer memory");
@@ -436,7 +534,8 @@ pub(crate) fn make_vertex_buffer_with_data_on_gpu(
             .expect("cant make bf");
         let requirements = device.get_buffer_requirements(&buffer);
         let memory_type_id =
-            find_memory_type_id(&s.adapter, requirements, Properties::DEVICE_LOCAL);
+            find_memory_type_id(&s.adapter, requirements, Properties::DEVICE_LOCAL)
+                .expect("Unable to find memory type id");
         let memory = device
             .allocate_memory(memory_type_id, requirements.size)
             .expect("Couldn't allocate vertex buffer memory");
@@ -512,13 +611,48 @@ pub(crate) fn copy_image_to_rgb(
     s: &mut VxDraw,
     image_index: gfx_hal::window::SwapImageIndex,
     output: &mut Vec<u8>,
-) {
+) -> Result<(), Error> {
     let width = s.swapconfig.extent.width;
     let height = s.swapconfig.extent.height;
 
-    let (buffer, memory, requirements) =
-        make_transfer_buffer_of_size(s, u64::from(width * height * 4));
-    let (imgbuf, imgmem, _imgreq) = make_transfer_img_of_size(s, width, height);
+    let size = u64::from(width) * u64::from(height) * 4;
+    let (buffer, memory, requirements) = make_transfer_buffer_of_size(s, size)?;
+    let (imgbuf, imgmem, _imgreq) = match make_transfer_img_of_size(s, width, height) {
+        Ok(img) => img,
+        Err(err) => {
+            unsafe {
+                s.device.destroy_buffer(buffer);
+                s.device.free_memory(memory);
+            }
+            return Err(err);
+        }
+    };
+    let result = blit_to_transfer_buffer(s, image_index, &imgbuf, &buffer).and_then(|()| unsafe {
+        let reader = s.device.map_memory(&memory, 0..requirements.size)?;
+        assert!(size <= requirements.size);
+        output.extend_from_slice(std::slice::from_raw_parts(reader, size as usize));
+        s.device.unmap_memory(&memory);
+        Ok(())
+    });
+    unsafe {
+        s.device.destroy_buffer(buffer);
+        s.device.free_memory(memory);
+        s.device.destroy_image(imgbuf);
+        s.device.free_memory(imgmem);
+    }
+    result
+}
+
+/// Blit a swapchain image into a linear image and copy that into a buffer, waiting until done
+fn blit_to_transfer_buffer(
+    s: &mut VxDraw,
+    image_index: gfx_hal::window::SwapImageIndex,
+    imgbuf: &<back::Backend as Backend>::Image,
+    buffer: &<back::Backend as Backend>::Buffer,
+) -> Result<(), Error> {
+    let width = s.swapconfig.extent.width;
+    let height = s.swapconfig.extent.height;
+    let color_layout = s.color_layout();
     let images = &s.images;
     unsafe {
         // s.device
@@ -531,7 +665,7 @@ pub(crate) fn copy_image_to_rgb(
         let mut cmd_buffer = s.command_pool.allocate_one(command::Level::Primary);
         cmd_buffer.begin_primary(CommandBufferFlags::ONE_TIME_SUBMIT);
         let image_barrier = m::Barrier::Image {
-            states: (i::Access::empty(), i::Layout::Present)
+            states: (i::Access::empty(), color_layout)
                 ..(i::Access::TRANSFER_READ, i::Layout::TransferSrcOptimal),
             target: &images[image_index as usize],
             families: None,
@@ -544,7 +678,7 @@ pub(crate) fn copy_image_to_rgb(
         let dstbarrier = m::Barrier::Image {
             states: (i::Access::empty(), i::Layout::Undefined)
                 ..(i::Access::TRANSFER_WRITE, i::Layout::TransferDstOptimal),
-            target: &imgbuf,
+            target: imgbuf,
             families: None,
             range: i::SubresourceRange {
                 aspects: f::Aspects::COLOR,
@@ -560,7 +694,7 @@ pub(crate) fn copy_image_to_rgb(
         cmd_buffer.blit_image(
             &images[image_index as usize],
             i::Layout::TransferSrcOptimal,
-            &imgbuf,
+            imgbuf,
             i::Layout::TransferDstOptimal,
             i::Filter::Nearest,
             once(command::ImageBlit {
@@ -589,7 +723,7 @@ pub(crate) fn copy_image_to_rgb(
         let image_barrier = m::Barrier::Image {
             states: (i::Access::TRANSFER_WRITE, i::Layout::TransferDstOptimal)
                 ..(i::Access::TRANSFER_READ, i::Layout::TransferSrcOptimal),
-            target: &imgbuf,
+            target: imgbuf,
             families: None,
             range: i::SubresourceRange {
                 aspects: f::Aspects::COLOR,
@@ -603,9 +737,9 @@ pub(crate) fn copy_image_to_rgb(
             &[image_barrier],
         );
         cmd_buffer.copy_image_to_buffer(
-            &imgbuf,
+            imgbuf,
             i::Layout::TransferSrcOptimal,
-            &buffer,
+            buffer,
             once(command::BufferImageCopy {
                 buffer_offset: 0,
                 buffer_width: width,
@@ -625,7 +759,7 @@ pub(crate) fn copy_image_to_rgb(
         );
         let image_barrier = m::Barrier::Image {
             states: (i::Access::TRANSFER_READ, i::Layout::TransferSrcOptimal)
-                ..(i::Access::empty(), i::Layout::Present),
+                ..(i::Access::empty(), color_layout),
             target: &images[image_index as usize],
             families: None,
             range: i::SubresourceRange {
@@ -641,44 +775,34 @@ pub(crate) fn copy_image_to_rgb(
         );
         cmd_buffer.finish();
         let the_command_queue = &mut s.queue_group.queues[0];
-        let fence = s
-            .device
-            .create_fence(false)
-            .expect("Unable to create fence");
+        let fence = match s.device.create_fence(false) {
+            Ok(fence) => fence,
+            Err(err) => {
+                s.command_pool.free(once(cmd_buffer));
+                return Err(err.into());
+            }
+        };
 
-        let present_wait_semaphore = &s.present_wait_semaphores[s.current_frame];
+        // Offscreen rendering never presents, so nobody waits for the semaphore
+        let present_wait_semaphore = if s.swapchain.is_some() {
+            Some(&s.present_wait_semaphores[s.current_frame])
+        } else {
+            None
+        };
         the_command_queue.submit(
             Submission {
                 command_buffers: once(&cmd_buffer),
                 wait_semaphores: None,
-                signal_semaphores: Some(present_wait_semaphore),
+                signal_semaphores: present_wait_semaphore,
             },
             Some(&fence),
         );
-        s.device
-            .wait_for_fence(&fence, u64::max_value())
-            .expect("unable to wait for fence");
+        let waited = s.device.wait_for_fence(&fence, u64::max_value());
         s.device.destroy_fence(fence);
         s.command_pool.free(once(cmd_buffer));
+        waited?;
     }
-    unsafe {
-        let reader = s
-            .device
-            .map_memory(&memory, 0..requirements.size as u64)
-            .expect("Unable to open reader");
-        assert!(u64::from(4 * width * height) <= requirements.size);
-        output.extend(
-            std::slice::from_raw_parts(reader, (4 * width * height) as usize)
-                .iter()
-                .take((4 * width * height) as usize)
-                .cloned(),
-        );
-        s.device.unmap_memory(&memory);
-        s.device.destroy_buffer(buffer);
-        s.device.free_memory(memory);
-        s.device.destroy_image(imgbuf);
-        s.device.free_memory(imgmem);
-    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -824,6 +948,13 @@ pub(crate) fn assert_swapchain_eq(vx: &mut VxDraw, name: &str, rgb: Vec<u8>) {
     }
 }
 
+/// Get the RGBA of a pixel in a frame from [VxDraw::draw_frame_copy_framebuffer]
+#[cfg(test)]
+pub(crate) fn pixel(vx: &VxDraw, img: &[u8], (x, y): (u32, u32)) -> [u8; 4] {
+    let idx = ((y * vx.swapconfig.extent.width + x) * 4) as usize;
+    [img[idx], img[idx + 1], img[idx + 2], img[idx + 3]]
+}
+
 #[cfg(test)]
 pub(crate) fn add_windmills(vx: &mut VxDraw, rand_rotat: bool) -> Vec<super::debtri::Handle> {
     use cgmath::Rad;